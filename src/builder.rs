@@ -0,0 +1,223 @@
+use crate::{
+    lexer::Region,
+    parser::{
+        AssignmentOperator, BinaryOperationOperator, Block, DefinedFunction, Expression,
+        ExpressionValue, FunctionSource, IfClause, Program, UnaryOperator, UpdateOperator,
+    },
+};
+
+pub type Expr = Expression;
+
+// constructors for building programs without going through source code,
+// every expression gets a synthetic region
+impl Expression {
+    pub fn new(value: ExpressionValue) -> Self {
+        Expression {
+            region: Region::synthetic(),
+            value,
+        }
+    }
+
+    pub fn int(value: i64) -> Self {
+        Self::new(ExpressionValue::Int(value))
+    }
+
+    pub fn float(value: f64) -> Self {
+        Self::new(ExpressionValue::Float(value))
+    }
+
+    pub fn string(value: &str) -> Self {
+        Self::new(ExpressionValue::String(value.to_string()))
+    }
+
+    pub fn bool(value: bool) -> Self {
+        Self::new(ExpressionValue::Bool(value))
+    }
+
+    pub fn null() -> Self {
+        Self::new(ExpressionValue::Null)
+    }
+
+    pub fn list(expressions: Vec<Expression>) -> Self {
+        Self::new(ExpressionValue::List(expressions))
+    }
+
+    pub fn map(entries: Vec<(Expression, Expression)>) -> Self {
+        Self::new(ExpressionValue::Map(entries))
+    }
+
+    pub fn block(block: Block) -> Self {
+        Self::new(ExpressionValue::Block(block))
+    }
+
+    pub fn identifier(id: &str) -> Self {
+        Self::new(ExpressionValue::Identifier(id.to_string(), None))
+    }
+
+    pub fn binary(left: Expression, operator: BinaryOperationOperator, right: Expression) -> Self {
+        Self::new(ExpressionValue::Binary {
+            left: Box::new(left),
+            operator,
+            right: Box::new(right),
+        })
+    }
+
+    pub fn unary(operator: UnaryOperator, operand: Expression) -> Self {
+        Self::new(ExpressionValue::Unary {
+            operator,
+            operand: Box::new(operand),
+        })
+    }
+
+    pub fn declare(id: &str, expression: Expression) -> Self {
+        Self::new(ExpressionValue::VariableDeclaration {
+            identifier: id.to_string(),
+            slot: None,
+            expression: Box::new(expression),
+        })
+    }
+
+    pub fn assign(id: &str, operator: AssignmentOperator, expression: Expression) -> Self {
+        Self::new(ExpressionValue::Assign {
+            identifier: id.to_string(),
+            slot: None,
+            operator,
+            expression: Box::new(expression),
+        })
+    }
+
+    pub fn index_assign(
+        id: &str,
+        indices: Vec<Expression>,
+        operator: AssignmentOperator,
+        expression: Expression,
+    ) -> Self {
+        Self::new(ExpressionValue::IndexAssign {
+            identifier: id.to_string(),
+            slot: None,
+            indices,
+            operator,
+            expression: Box::new(expression),
+        })
+    }
+
+    pub fn update(id: &str, operator: UpdateOperator) -> Self {
+        Self::new(ExpressionValue::Update {
+            identifier: id.to_string(),
+            slot: None,
+            operator,
+        })
+    }
+
+    pub fn function(parameters: &[&str], body: Block) -> Self {
+        Self::new(ExpressionValue::Function(DefinedFunction {
+            parameters: parameters.iter().map(|v| v.to_string()).collect(),
+            body,
+            source: Box::new(FunctionSource::synthetic()),
+            pure: None,
+        }))
+    }
+
+    pub fn call(id: &str, arguments: Vec<Expression>) -> Self {
+        Self::new(ExpressionValue::Call {
+            identifier: id.to_string(),
+            slot: None,
+            arguments,
+            invariant: None,
+        })
+    }
+
+    pub fn index(expression: Expression, index: Expression) -> Self {
+        Self::new(ExpressionValue::Index {
+            expression: Box::new(expression),
+            index: Box::new(index),
+        })
+    }
+
+    pub fn member(expression: Expression, member: &str) -> Self {
+        Self::new(ExpressionValue::Member {
+            expression: Box::new(expression),
+            member: member.to_string(),
+        })
+    }
+
+    pub fn member_call(expression: Expression, member: &str, arguments: Vec<Expression>) -> Self {
+        Self::new(ExpressionValue::MemberCall {
+            expression: Box::new(expression),
+            member: member.to_string(),
+            arguments,
+            invariant: None,
+        })
+    }
+
+    // each clause is a test and the body that runs when the test is true
+    pub fn if_else(clauses: Vec<(Expression, Block)>, else_block: Option<Block>) -> Self {
+        Self::new(ExpressionValue::If {
+            clauses: clauses
+                .into_iter()
+                .map(|(test, body)| IfClause {
+                    test: Box::new(test),
+                    body,
+                })
+                .collect(),
+            else_block,
+        })
+    }
+
+    pub fn while_loop(test: Expression, body: Block) -> Self {
+        Self::new(ExpressionValue::Loop {
+            init: None,
+            test: Some(Box::new(test)),
+            update: None,
+            body,
+        })
+    }
+
+    pub fn for_loop(init: Expression, test: Expression, update: Expression, body: Block) -> Self {
+        Self::new(ExpressionValue::Loop {
+            init: Some(Box::new(init)),
+            test: Some(Box::new(test)),
+            update: Some(Box::new(update)),
+            body,
+        })
+    }
+
+    pub fn infinite_loop(body: Block) -> Self {
+        Self::new(ExpressionValue::Loop {
+            init: None,
+            test: None,
+            update: None,
+            body,
+        })
+    }
+
+    pub fn for_in(id: &str, iterable: Expression, body: Block) -> Self {
+        Self::new(ExpressionValue::ForIn {
+            identifier: id.to_string(),
+            iterable: Box::new(iterable),
+            body,
+        })
+    }
+
+    pub fn throw(expression: Expression) -> Self {
+        Self::new(ExpressionValue::Throw(Box::new(expression)))
+    }
+
+    pub fn import(path: &str) -> Self {
+        Self::new(ExpressionValue::Import(path.to_string()))
+    }
+
+    pub fn continue_loop() -> Self {
+        Self::new(ExpressionValue::Continue)
+    }
+
+    pub fn break_loop() -> Self {
+        Self::new(ExpressionValue::Break)
+    }
+}
+
+impl Program {
+    pub fn new(ast: Vec<Expression>) -> Self {
+        Program { ast }
+    }
+}
@@ -1,23 +1,91 @@
-use crate::value::{ControlFlowValue, Exception, Value};
-use std::{io, thread, time::Duration};
+use crate::{
+    header::FEATURES,
+    interpreter::{
+        euclidean_modulo, exponent, fits_in_memory, int_pow, is_greater_than, is_less_than,
+        multiply, plus, Interpreter,
+    },
+    printer,
+    value::{ControlFlowValue, Exception, Function, List, Value, WeakValue},
+};
+use sha2::{Digest, Sha256};
+use signal_hook::consts::{SIGINT, SIGTERM};
+use std::{
+    cell::{Ref, RefCell},
+    cmp,
+    collections::{BTreeMap, BTreeSet, HashSet},
+    fs,
+    io::{self, BufRead, BufReader},
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
 
-fn expect_num_of_argumets(arguments: &Vec<Value>, num: usize) -> Result<(), ControlFlowValue> {
+fn expect_num_of_argumets(arguments: &[Value], num: usize) -> Result<(), ControlFlowValue> {
     if arguments.len() != num {
         Err(ControlFlowValue::Exception(
             Exception::WrongNumberOfArguments,
+            None,
         ))
     } else {
         Ok(())
     }
 }
 
-pub fn print_ln(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+// the fill character of the padding builtins, a space when it is left out
+fn pad_arguments(arguments: &[Value]) -> Result<(&str, usize, char), ControlFlowValue> {
+    if !(2..=3).contains(&arguments.len()) {
+        return Err(ControlFlowValue::Exception(
+            Exception::WrongNumberOfArguments,
+            None,
+        ));
+    }
+
+    let string = arguments[0].into_str()?;
+    let width = (*arguments[1].into_int()?).max(0) as usize;
+    let fill = match arguments.get(2) {
+        Some(fill) => {
+            let mut chars = fill.into_str()?.chars();
+            match (chars.next(), chars.next()) {
+                (Some(fill), None) => fill,
+                _ => {
+                    return Err(ControlFlowValue::Exception(
+                        Exception::ValueIsWrongType,
+                        None,
+                    ))
+                }
+            }
+        }
+        None => ' ',
+    };
+    // at most width fill characters are added to the string
+    fits_in_memory(
+        width
+            .checked_mul(fill.len_utf8())
+            .and_then(|bytes| bytes.checked_add(string.len())),
+    )?;
+    Ok((string, width, fill))
+}
+
+// errors of the interpreter's Io and of the file system
+pub(crate) fn io_error(err: std::io::Error) -> ControlFlowValue {
+    ControlFlowValue::Exception(Exception::Io(err.to_string()), None)
+}
+
+fn file_error(path: &str) -> impl Fn(std::io::Error) -> ControlFlowValue + '_ {
+    move |err| ControlFlowValue::Exception(Exception::Io(format!("{}: {}", path, err)), None)
+}
+
+pub fn print_ln(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Value>,
+) -> Result<Value, ControlFlowValue> {
     let mut result = String::new();
     for arg in arguments.iter() {
         result.push_str(format!("{}", arg).as_str())
     }
+    result.push('\n');
 
-    println!("{}", result);
+    interpreter.io().write(&result).map_err(io_error)?;
     Ok(Value::Null)
 }
 
@@ -26,28 +94,2050 @@ pub fn to_string(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
     Ok(Value::String(format!("{}", arguments.first().unwrap())))
 }
 
-pub fn read_ln(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+// the type of a value as a string, builtins and defined functions are both "function"
+pub fn type_of(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 1)?;
+    Ok(Value::String(arguments[0].type_name().to_string()))
+}
+
+// a value the way printLn shows it, or with pretty the source which evaluates to
+// it like the formatter writes it, so the code of a function can be read.
+// values without a source are shown the way printLn shows them either way
+pub fn inspect(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    if !(1..=2).contains(&arguments.len()) {
+        return Err(ControlFlowValue::Exception(
+            Exception::WrongNumberOfArguments,
+            None,
+        ));
+    }
+    let pretty = match arguments.get(1) {
+        Some(pretty) => *pretty.into_bool()?,
+        None => false,
+    };
+
+    let source = pretty.then(|| printer::value(&arguments[0])).flatten();
+    Ok(Value::String(
+        source.unwrap_or_else(|| arguments[0].to_string()),
+    ))
+}
+
+// the code of a defined function, builtins have none
+pub fn source(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 1)?;
+    match &arguments[0] {
+        Value::Function(Function::Defined(closure)) => {
+            Ok(Value::String(printer::function_source(&closure.function)))
+        }
+        Value::Function(Function::Builtin(_)) => Ok(Value::Null),
+        _ => Err(ControlFlowValue::Exception(
+            Exception::ValueIsWrongType,
+            None,
+        )),
+    }
+}
+
+fn not_a_number(name: &str, text: &str, kind: &str) -> ControlFlowValue {
+    ControlFlowValue::Exception(
+        Exception::Custom(format!("{}: \"{}\" is not {}", name, text, kind)),
+        None,
+    )
+}
+
+// the text may be surrounded by whitespace, like the lines readLn returns. the
+// radix is 10 when it is left out and can be anything from 2 to 36
+pub fn parse_int(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    if !(1..=2).contains(&arguments.len()) {
+        return Err(ControlFlowValue::Exception(
+            Exception::WrongNumberOfArguments,
+            None,
+        ));
+    }
+    let text = arguments[0].into_str()?;
+    let radix = match arguments.get(1) {
+        Some(radix) => match *radix.into_int()? {
+            radix @ 2..=36 => radix as u32,
+            radix => {
+                return Err(ControlFlowValue::Exception(
+                    Exception::Custom(format!(
+                        "parseInt: the radix has to be from 2 to 36, not {}",
+                        radix
+                    )),
+                    None,
+                ))
+            }
+        },
+        None => 10,
+    };
+
+    i64::from_str_radix(text.trim(), radix)
+        .map(Value::Int)
+        .map_err(|_| not_a_number("parseInt", text, "an int"))
+}
+
+// ints are accepted as well, the words inf and nan which rust understands aren't
+pub fn parse_float(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 1)?;
+    let text = arguments[0].into_str()?;
+
+    match text.trim().parse::<f64>() {
+        Ok(value) if value.is_finite() || text.contains(|c: char| c.is_ascii_digit()) => {
+            Ok(Value::Float(value))
+        }
+        _ => Err(not_a_number("parseFloat", text, "a number")),
+    }
+}
+
+pub fn read_ln(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Value>,
+) -> Result<Value, ControlFlowValue> {
     expect_num_of_argumets(&arguments, 0)?;
-    let mut input = String::new();
-    io::stdin()
-        .read_line(&mut input)
-        .map_err(|err| ControlFlowValue::Exception(Exception::Custom(err.to_string())))?;
+    let input = interpreter.io().read_line().map_err(io_error)?;
 
     Ok(Value::String(input.trim().to_string()))
 }
 
+// y or yes is true and n or no false, an empty answer is no. a person is asked
+// again after any other answer, other input is no as well
+pub fn confirm(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Value>,
+) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 1)?;
+    let prompt = format!("{} [y/N] ", arguments[0].into_str()?);
+
+    loop {
+        interpreter.io().write(&prompt).map_err(io_error)?;
+        let answer = interpreter.io().read_line().map_err(io_error)?;
+        match answer.trim().to_lowercase().as_str() {
+            "y" | "yes" => return Ok(Value::Bool(true)),
+            "n" | "no" | "" => return Ok(Value::Bool(false)),
+            _ if interpreter.io().is_interactive() => continue,
+            _ => return Ok(Value::Bool(false)),
+        }
+    }
+}
+
+// the options are numbered from 1, the answer is either the number or the
+// option itself. the chosen element of the list is returned
+pub fn select(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Value>,
+) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 2)?;
+    let prompt = arguments[0].into_str()?;
+    let options = arguments[1].into_list()?;
+    if options.is_empty() {
+        return Err(ControlFlowValue::Exception(Exception::EmptyList, None));
+    }
+
+    let mut menu = format!("{}\n", prompt);
+    for (i, option) in options.iter().enumerate() {
+        menu += &format!("  {}) {}\n", i + 1, option);
+    }
+    interpreter.io().write(&menu).map_err(io_error)?;
+
+    loop {
+        interpreter.io().write("> ").map_err(io_error)?;
+        let answer = interpreter.io().read_line().map_err(io_error)?;
+        let answer = answer.trim();
+        let chosen = match answer.parse::<usize>() {
+            Ok(number) => number.checked_sub(1).and_then(|i| options.get(i)),
+            Err(_) => options.iter().find(|option| option.to_string() == answer),
+        };
+        match chosen {
+            Some(option) => return Ok(option.clone()),
+            None if !answer.is_empty() && interpreter.io().is_interactive() => continue,
+            None => {
+                return Err(ControlFlowValue::Exception(
+                    Exception::Custom(format!("select: \"{}\" is not one of the options", answer)),
+                    None,
+                ))
+            }
+        }
+    }
+}
+
+// reads a line without showing it on the terminal
+pub fn password(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Value>,
+) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 1)?;
+    let prompt = arguments[0].into_str()?;
+
+    interpreter.io().write(prompt).map_err(io_error)?;
+    let input = interpreter.io().read_password().map_err(io_error)?;
+    Ok(Value::String(input))
+}
+
 pub fn len(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
     expect_num_of_argumets(&arguments, 1)?;
 
-    Ok(Value::Int(
-        arguments.first().unwrap().into_list()?.len() as i64
+    Ok(Value::Int(match &arguments[0] {
+        Value::Map(map) => map.len(),
+        value => value.into_list()?.len(),
+    } as i64))
+}
+
+pub fn sleep(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Value>,
+) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 1)?;
+
+    let duration = milliseconds(&arguments[0])?;
+    interpreter.sleep(duration);
+
+    Ok(Value::Null)
+}
+
+// milliseconds since 1970, the virtual clock of deterministic interpreters
+// starts at 1970
+pub fn time_millis(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Value>,
+) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 0)?;
+    let time = if interpreter.is_deterministic() {
+        interpreter.elapsed()
+    } else {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+    };
+    Ok(Value::Int(time.as_millis() as i64))
+}
+
+// seconds since the interpreter was created as a float, unlike timeMillis it
+// never jumps when the system time is changed, so it is meant for measuring
+pub fn clock(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Value>,
+) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 0)?;
+    Ok(Value::Float(interpreter.elapsed().as_secs_f64()))
+}
+
+// a float from 0 up to but not including 1
+pub fn random(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Value>,
+) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 0)?;
+    Ok(Value::Float(interpreter.random().next_float()))
+}
+
+// an int from min to max, both included
+pub fn random_int(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Value>,
+) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 2)?;
+    let (min, max) = (*arguments[0].into_int()?, *arguments[1].into_int()?);
+    if min > max {
+        return Err(ControlFlowValue::Exception(
+            Exception::Custom("randomInt: min is greater than max".to_string()),
+            None,
+        ));
+    }
+    Ok(Value::Int(interpreter.random().next_int(min, max)))
+}
+
+// after seeding, random and randomInt return the same numbers on every run
+pub fn seed_random(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Value>,
+) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 1)?;
+    interpreter.seed_random(*arguments[0].into_int()? as u64);
+    Ok(Value::Null)
+}
+
+// functions given to builtins which call them later are checked right away
+fn expect_function(value: &Value) -> Result<(), ControlFlowValue> {
+    match value {
+        Value::Function(_) => Ok(()),
+        _ => Err(ControlFlowValue::Exception(
+            Exception::ValueIsWrongType,
+            None,
+        )),
+    }
+}
+
+fn milliseconds(value: &Value) -> Result<Duration, ControlFlowValue> {
+    let millis = u64::try_from(*value.into_int()?)
+        .map_err(|_| ControlFlowValue::Exception(Exception::ValueIsWrongType, None))?;
+    Ok(Duration::from_millis(millis))
+}
+
+// the function is called once by runEventLoop after the delay in milliseconds
+pub fn set_timeout(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Value>,
+) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 2)?;
+    expect_function(&arguments[0])?;
+    let delay = milliseconds(&arguments[1])?;
+    let id = interpreter.add_timer(arguments[0].clone(), delay, None);
+    Ok(Value::Int(id))
+}
+
+// the function is called by runEventLoop every interval milliseconds until the
+// timer is cleared
+pub fn set_interval(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Value>,
+) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 2)?;
+    expect_function(&arguments[0])?;
+    let interval = milliseconds(&arguments[1])?;
+    let id = interpreter.add_timer(arguments[0].clone(), interval, Some(interval));
+    Ok(Value::Int(id))
+}
+
+// true when the timer was still pending
+pub fn clear_timer(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Value>,
+) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 1)?;
+    Ok(Value::Bool(
+        interpreter.clear_timer(*arguments[0].into_int()?),
     ))
 }
 
-pub fn sleep(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+// sleeps until the next timer is due and calls its function, until no timers
+// are left. the functions can add and clear timers themselves
+pub fn run_event_loop(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Value>,
+) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 0)?;
+    while let Some(function) = interpreter.next_timer() {
+        interpreter.call_function(&function, vec![])?;
+    }
+    Ok(Value::Null)
+}
+
+// the number and approximate size of the lists, strings, maps and environments
+// the program can still reach
+pub fn memory_stats(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Value>,
+) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 0)?;
+
+    Ok(interpreter.memory_stats().to_value())
+}
+
+// the arguments given to the script on the command line after --
+pub fn args(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Value>,
+) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 0)?;
+
+    Ok(Value::List(
+        interpreter
+            .args()
+            .iter()
+            .map(|arg| Value::String(arg.clone()))
+            .collect(),
+    ))
+}
+
+// stop the program, the olang binary exits with the given status or 0
+pub fn exit(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    let code = match arguments.as_slice() {
+        [] => 0,
+        [code] => i32::try_from(*code.into_int()?)
+            .map_err(|_| ControlFlowValue::Exception(Exception::ValueIsWrongType, None))?,
+        _ => {
+            return Err(ControlFlowValue::Exception(
+                Exception::WrongNumberOfArguments,
+                None,
+            ))
+        }
+    };
+    Err(ControlFlowValue::Exit(code))
+}
+
+// the function is called without arguments at the next step of the evaluation
+// after the signal arrived. a function for INT replaces the Interrupted
+// exception of Ctrl-C, so the program can finish its work and exit
+pub fn on_signal(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Value>,
+) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 2)?;
+    let name = arguments[0].into_str()?;
+    let signal = match name {
+        "INT" => SIGINT,
+        "TERM" => SIGTERM,
+        _ => {
+            return Err(ControlFlowValue::Exception(
+                Exception::Custom(format!(
+                    "onSignal: unknown signal \"{}\", use INT or TERM",
+                    name
+                )),
+                None,
+            ))
+        }
+    };
+    expect_function(&arguments[1])?;
+    interpreter
+        .on_signal(name, signal, arguments[1].clone())
+        .map_err(io_error)?;
+    Ok(Value::Null)
+}
+
+// a reference to a list or native which doesn't keep it alive, deref gives
+// the value back as long as something else still holds it
+pub fn weak_ref(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
     expect_num_of_argumets(&arguments, 1)?;
+    WeakValue::new(&arguments[0])
+        .map(Value::native)
+        .ok_or(ControlFlowValue::Exception(
+            Exception::ValueIsWrongType,
+            None,
+        ))
+}
+
+// the value of a weakRef, or null once it is gone
+pub fn deref(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 1)?;
+    let weak = arguments[0].into_native::<WeakValue>()?;
+    Ok(weak.upgrade().unwrap_or(Value::Null))
+}
+
+pub fn freeze(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 1)?;
+    Ok(arguments[0].freeze())
+}
 
-    thread::sleep(Duration::from_millis(*arguments[0].into_int()? as u64));
+// only lists can be frozen, other values are never changed in place
+pub fn is_frozen(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 1)?;
+    Ok(Value::Bool(
+        matches!(&arguments[0], Value::List(list) if list.is_frozen()),
+    ))
+}
+
+pub fn olang_version(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 0)?;
+    Ok(Value::String(env!("CARGO_PKG_VERSION").to_string()))
+}
+
+// features this version of olang doesn't know about are missing as well
+pub fn has_feature(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 1)?;
+    let name = arguments[0].into_str()?;
+    Ok(Value::Bool(FEATURES.contains(&(name, true))))
+}
+
+// the operating system, like linux, macos or windows
+pub fn platform(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 0)?;
+    Ok(Value::String(std::env::consts::OS.to_string()))
+}
+
+// run a program with a list of arguments and wait for it to finish. nothing
+// goes through a shell, so the arguments need no quoting. the program gets the
+// environment variables loaded by dotenvLoad and no standard input, its output
+// is returned with the exit status, which is null when a signal killed it
+#[cfg(feature = "exec")]
+pub fn exec(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Value>,
+) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 2)?;
+    let command = arguments[0].into_str()?;
+    let args = arguments[1]
+        .into_list()?
+        .iter()
+        .map(|arg| arg.into_str().map(str::to_string))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let output = std::process::Command::new(command)
+        .args(args)
+        .envs(interpreter.env_vars())
+        .stdin(std::process::Stdio::null())
+        .output()
+        .map_err(file_error(command))?;
+    Ok(Value::Map(BTreeMap::from([
+        (
+            "stdout".to_string(),
+            Value::String(String::from_utf8_lossy(&output.stdout).into_owned()),
+        ),
+        (
+            "stderr".to_string(),
+            Value::String(String::from_utf8_lossy(&output.stderr).into_owned()),
+        ),
+        (
+            "status".to_string(),
+            output
+                .status
+                .code()
+                .map_or(Value::Null, |code| Value::Int(code as i64)),
+        ),
+    ])))
+}
 
+// an environment variable, or null when it isn't set
+pub fn get_env(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Value>,
+) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 1)?;
+
+    Ok(match interpreter.env_var(arguments[0].into_str()?) {
+        Some(value) => Value::String(value),
+        None => Value::Null,
+    })
+}
+
+fn parse_error(builtin: &str, line: usize, message: &str) -> ControlFlowValue {
+    ControlFlowValue::Exception(
+        Exception::Custom(format!("{}: line {} {}", builtin, line + 1, message)),
+        None,
+    )
+}
+
+// a value in matching single or double quotes without them
+fn unquote(value: &str) -> &str {
+    for quote in ['"', '\''] {
+        if let Some(inner) = value
+            .strip_prefix(quote)
+            .and_then(|value| value.strip_suffix(quote))
+        {
+            return inner;
+        }
+    }
+    value
+}
+
+// keys before the first [section] are top level entries of the map, every
+// section is a map of its own. the values are strings, lines starting with ;
+// or # are comments
+pub fn ini_parse(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 1)?;
+
+    let mut result = BTreeMap::new();
+    let mut section: Option<String> = None;
+    for (i, line) in arguments[0].into_str()?.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line
+            .strip_prefix('[')
+            .and_then(|line| line.strip_suffix(']'))
+        {
+            let name = name.trim().to_string();
+            if !matches!(result.get(&name), Some(Value::Map(_))) {
+                result.insert(name.clone(), Value::Map(BTreeMap::new()));
+            }
+            section = Some(name);
+            continue;
+        }
+
+        let (key, value) = line.split_once('=').ok_or_else(|| {
+            parse_error(
+                "iniParse",
+                i,
+                "is neither a key = value pair nor a [section]",
+            )
+        })?;
+        let entries = match &section {
+            Some(name) => match result.get_mut(name) {
+                Some(Value::Map(entries)) => entries,
+                _ => unreachable!("sections are declared as maps"),
+            },
+            None => &mut result,
+        };
+        entries.insert(
+            key.trim().to_string(),
+            Value::String(unquote(value.trim()).to_string()),
+        );
+    }
+
+    Ok(Value::Map(result))
+}
+
+// binary formats work on lists of bytes, ints from 0 to 255
+fn bytes(value: &Value) -> Result<Vec<u8>, ControlFlowValue> {
+    match value {
+        Value::List(list) => list
+            .borrow()
+            .iter()
+            .map(|v| match v {
+                Value::Int(b) => u8::try_from(*b).ok(),
+                _ => None,
+            })
+            .collect::<Option<_>>()
+            .ok_or(ControlFlowValue::Exception(
+                Exception::ValueIsWrongType,
+                None,
+            )),
+        _ => Err(ControlFlowValue::Exception(
+            Exception::ValueIsWrongType,
+            None,
+        )),
+    }
+}
+
+fn bytes_value(bytes: Vec<u8>) -> Value {
+    Value::list(bytes.into_iter().map(|b| Value::Int(b as i64)).collect())
+}
+
+fn encoding_error(builtin: &str, err: impl ToString) -> ControlFlowValue {
+    ControlFlowValue::Exception(
+        Exception::Custom(format!("{}: {}", builtin, err.to_string())),
+        None,
+    )
+}
+
+pub fn msgpack_encode(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 1)?;
+    rmp_serde::to_vec(&arguments[0])
+        .map(bytes_value)
+        .map_err(|err| encoding_error("msgpackEncode", err))
+}
+
+pub fn msgpack_decode(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 1)?;
+    rmp_serde::from_slice(&bytes(&arguments[0])?)
+        .map_err(|err| encoding_error("msgpackDecode", err))
+}
+
+pub fn cbor_encode(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 1)?;
+    let mut output = vec![];
+    ciborium::into_writer(&arguments[0], &mut output)
+        .map_err(|err| encoding_error("cborEncode", err))?;
+    Ok(bytes_value(output))
+}
+
+pub fn cbor_decode(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 1)?;
+    ciborium::from_reader(bytes(&arguments[0])?.as_slice())
+        .map_err(|err| encoding_error("cborDecode", err))
+}
+
+// numbers without a fraction or exponent become ints, the others floats
+pub fn json_parse(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 1)?;
+    serde_json::from_str(arguments[0].into_str()?).map_err(|err| encoding_error("jsonParse", err))
+}
+
+// infinite floats and NaN have no json representation and become null. json
+// can't refer back to a list, lists containing themselves are rejected
+pub fn json_stringify(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 1)?;
+    if arguments[0].contains_itself() {
+        return Err(encoding_error(
+            "jsonStringify",
+            "lists containing themselves can't be written as json",
+        ));
+    }
+    serde_json::to_string(&arguments[0])
+        .map(Value::String)
+        .map_err(|err| encoding_error("jsonStringify", err))
+}
+
+// checksums are computed over the utf-8 bytes of strings or over lists of bytes
+fn checksum_input(value: &Value) -> Result<Vec<u8>, ControlFlowValue> {
+    match value {
+        Value::String(v) => Ok(v.as_bytes().to_vec()),
+        v => bytes(v),
+    }
+}
+
+pub fn crc32(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 1)?;
+    let checksum = crc32fast::hash(&checksum_input(&arguments[0])?);
+    Ok(Value::Int(checksum as i64))
+}
+
+pub fn adler32(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 1)?;
+    let mut adler = adler2::Adler32::new();
+    adler.write_slice(&checksum_input(&arguments[0])?);
+    Ok(Value::Int(adler.checksum() as i64))
+}
+
+// the hex digest of a file, it is read in chunks so large files don't have to
+// fit into memory
+pub fn file_sha256(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 1)?;
+    let path = arguments[0].into_str()?;
+    let mut file = fs::File::open(path).map_err(file_error(path))?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher).map_err(file_error(path))?;
+    let digest: String = hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    Ok(Value::String(digest))
+}
+
+fn temp_builder() -> tempfile::Builder<'static, 'static> {
+    let mut builder = tempfile::Builder::new();
+    builder.prefix("olang-");
+    builder
+}
+
+fn path_value(path: &Path) -> Value {
+    Value::String(path.to_string_lossy().into_owned())
+}
+
+// the path of a new empty file, it is deleted when the program ends
+pub fn temp_file(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Value>,
+) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 0)?;
+    let file = temp_builder()
+        .tempfile()
+        .map_err(io_error)?
+        .into_temp_path();
+    let path = path_value(&file);
+    interpreter.keep_temp_file(file);
+    Ok(path)
+}
+
+// the path of a new empty directory, it is deleted with everything inside of
+// it when the program ends
+pub fn temp_dir(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Value>,
+) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 0)?;
+    let directory = temp_builder().tempdir().map_err(io_error)?;
+    let path = path_value(directory.path());
+    interpreter.keep_temp_dir(directory);
+    Ok(path)
+}
+
+// call the function with the path of a new directory and delete the directory
+// once the function returns, also when it raises an exception
+pub fn with_temp_dir(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Value>,
+) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 1)?;
+    let directory = temp_builder().tempdir().map_err(io_error)?;
+    let result = interpreter.call_function(&arguments[0], vec![path_value(directory.path())]);
+    drop(directory);
+    result
+}
+
+pub fn file_size(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 1)?;
+    let path = arguments[0].into_str()?;
+    let metadata = fs::metadata(path).map_err(file_error(path))?;
+    Ok(Value::Int(metadata.len() as i64))
+}
+
+// milliseconds since the unix epoch, like the other times of the builtins
+pub fn file_modified_time(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 1)?;
+    let path = arguments[0].into_str()?;
+    let modified = fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .map_err(file_error(path))?;
+    let millis = match modified.duration_since(UNIX_EPOCH) {
+        Ok(since) => since.as_millis() as i64,
+        Err(err) => -(err.duration().as_millis() as i64),
+    };
+    Ok(Value::Int(millis))
+}
+
+// false for paths that don't exist or can't be read
+pub fn is_dir(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 1)?;
+    Ok(Value::Bool(Path::new(arguments[0].into_str()?).is_dir()))
+}
+
+pub fn is_file(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 1)?;
+    Ok(Value::Bool(Path::new(arguments[0].into_str()?).is_file()))
+}
+
+// the mode is an int or a string of octal digits like "755"
+pub fn chmod(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 2)?;
+    let path = arguments[0].into_str()?;
+    let mode = match &arguments[1] {
+        Value::Int(mode) => u32::try_from(*mode).ok(),
+        Value::String(mode) => u32::from_str_radix(mode, 8).ok(),
+        _ => None,
+    }
+    .ok_or(ControlFlowValue::Exception(
+        Exception::ValueIsWrongType,
+        None,
+    ))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(mode)).map_err(file_error(path))?;
+    }
+    // other systems only know whether a file is read only
+    #[cfg(not(unix))]
+    {
+        let mut permissions = fs::metadata(path).map_err(file_error(path))?.permissions();
+        permissions.set_readonly(mode & 0o222 == 0);
+        fs::set_permissions(path, permissions).map_err(file_error(path))?;
+    }
+    Ok(Value::Null)
+}
+
+pub fn copy_file(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 2)?;
+    let from = arguments[0].into_str()?;
+    fs::copy(from, arguments[1].into_str()?).map_err(file_error(from))?;
+    Ok(Value::Null)
+}
+
+pub fn rename_file(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 2)?;
+    let from = arguments[0].into_str()?;
+    fs::rename(from, arguments[1].into_str()?).map_err(file_error(from))?;
     Ok(Value::Null)
 }
+
+pub fn read_lines(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 1)?;
+    let path = arguments[0].into_str()?;
+    let content = fs::read_to_string(path).map_err(file_error(path))?;
+    Ok(Value::List(
+        content
+            .lines()
+            .map(|line| Value::String(line.to_string()))
+            .collect(),
+    ))
+}
+
+// the handle returned by openLines, every iteration of a for loop over it reads
+// the next line of the file, so only one line is in memory at a time. the
+// lines read by one loop are gone for the next one
+pub(crate) struct LineReader {
+    path: String,
+    lines: RefCell<io::Lines<BufReader<fs::File>>>,
+}
+
+impl LineReader {
+    pub(crate) fn next_line(&self) -> Option<Result<Value, ControlFlowValue>> {
+        let line = self.lines.borrow_mut().next()?;
+        Some(line.map(Value::String).map_err(file_error(&self.path)))
+    }
+}
+
+pub fn open_lines(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 1)?;
+    let path = arguments[0].into_str()?;
+    let file = fs::File::open(path).map_err(file_error(path))?;
+    Ok(Value::native(LineReader {
+        path: path.to_string(),
+        lines: RefCell::new(BufReader::new(file).lines()),
+    }))
+}
+
+// the handle returned by stdinLines, a for loop over it reads the input one
+// line per iteration, so programs can filter input larger than the memory
+pub(crate) struct StdinLines;
+
+pub fn stdin_lines(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 0)?;
+    Ok(Value::native(StdinLines))
+}
+
+// the variables of a .env file, KEY=value lines which may start with export.
+// double quoted values can contain \n escapes, a # after whitespace starts a
+// comment in unquoted values
+fn parse_dotenv(source: &str) -> Result<Vec<(String, String)>, ControlFlowValue> {
+    let mut variables = vec![];
+    for (i, line) in source.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| parse_error("dotenvLoad", i, "is not a KEY=value pair"))?;
+
+        let value = value.trim();
+        let value = if value.starts_with('"') || value.starts_with('\'') {
+            let unquoted = unquote(value);
+            if unquoted.len() == value.len() {
+                return Err(parse_error(
+                    "dotenvLoad",
+                    i,
+                    "has a value without a closing quote",
+                ));
+            }
+            if value.starts_with('"') {
+                unquoted.replace("\\n", "\n").replace("\\\"", "\"")
+            } else {
+                unquoted.to_string()
+            }
+        } else {
+            match value.find(" #") {
+                Some(comment) => value[..comment].trim_end().to_string(),
+                None => value.to_string(),
+            }
+        };
+        variables.push((key.trim().to_string(), value));
+    }
+    Ok(variables)
+}
+
+// load the variables of a .env file so getEnv returns them, variables which
+// are already set keep their value. returns the variables of the file
+pub fn dotenv_load(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Value>,
+) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 1)?;
+
+    let path = arguments[0].into_str()?;
+    let source = fs::read_to_string(path).map_err(file_error(path))?;
+    let mut loaded = BTreeMap::new();
+    for (key, value) in parse_dotenv(&source)? {
+        if interpreter.env_var(&key).is_none() {
+            interpreter.set_env_var(&key, &value);
+        }
+        loaded.insert(key, Value::String(value));
+    }
+    Ok(Value::Map(loaded))
+}
+
+// pairs the elements of two lists, the result is as long as the shorter list
+pub fn zip(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 2)?;
+
+    Ok(Value::List(
+        arguments[0]
+            .into_list()?
+            .iter()
+            .zip(arguments[1].into_list()?.iter())
+            .map(|(left, right)| Value::list(vec![left.clone(), right.clone()]))
+            .collect(),
+    ))
+}
+
+pub fn enumerate(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 1)?;
+
+    Ok(Value::List(
+        arguments[0]
+            .into_list()?
+            .iter()
+            .enumerate()
+            .map(|(i, value)| Value::list(vec![Value::Int(i as i64), value.clone()]))
+            .collect(),
+    ))
+}
+
+// removes one level of nesting, elements that are not lists are kept as they are
+pub fn flatten(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 1)?;
+
+    let mut result = vec![];
+    for value in arguments[0].into_list()?.iter() {
+        match value {
+            Value::List(list) => result.extend(list.borrow().iter().cloned()),
+            value => result.push(value.clone()),
+        }
+    }
+    Ok(Value::list(result))
+}
+
+// keeps the first occurrence of every value, values can't be hashed because of
+// floats so this compares every pair. 1 and 1.0 are different values here
+pub fn unique(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 1)?;
+
+    let mut result: Vec<Value> = vec![];
+    for value in arguments[0].into_list()?.iter() {
+        if !result.contains(value) {
+            result.push(value.clone());
+        }
+    }
+    Ok(Value::list(result))
+}
+
+// a stable merge sort which stops at the first error of the comparison. the
+// sort of the standard library can't return errors and may panic when a
+// comparison function of the program isn't consistent
+fn merge_sort(
+    mut items: Vec<Value>,
+    compare: &mut impl FnMut(&Value, &Value) -> Result<cmp::Ordering, ControlFlowValue>,
+) -> Result<Vec<Value>, ControlFlowValue> {
+    if items.len() <= 1 {
+        return Ok(items);
+    }
+    let right = items.split_off(items.len() / 2);
+    let mut left = merge_sort(items, compare)?.into_iter().peekable();
+    let mut right = merge_sort(right, compare)?.into_iter().peekable();
+
+    let mut sorted = Vec::with_capacity(left.len() + right.len());
+    while let (Some(l), Some(r)) = (left.peek(), right.peek()) {
+        // an element of the right half only goes first when it is smaller,
+        // so equal elements keep their order
+        let item = if compare(r, l)? == cmp::Ordering::Less {
+            right.next()
+        } else {
+            left.next()
+        };
+        sorted.extend(item);
+    }
+    sorted.extend(left);
+    sorted.extend(right);
+    Ok(sorted)
+}
+
+// a new list with the numbers or strings in ascending order, strings are
+// compared by their characters. other values and lists mixing strings and
+// numbers can't be sorted
+pub fn sort(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 1)?;
+    let items = arguments[0].into_list()?.clone();
+
+    let sorted = if items.iter().all(|item| matches!(item, Value::String(_))) {
+        merge_sort(items, &mut |a, b| Ok(a.into_str()?.cmp(b.into_str()?)))?
+    } else {
+        merge_sort(items, &mut |a, b| {
+            Ok(if is_less_than(a.clone(), b.clone())? {
+                cmp::Ordering::Less
+            } else if is_less_than(b.clone(), a.clone())? {
+                cmp::Ordering::Greater
+            } else {
+                cmp::Ordering::Equal
+            })
+        })?
+    };
+    Ok(Value::list(sorted))
+}
+
+// the function compares two elements, it returns a negative int when the first goes before the second, a positive one when it
+// goes after and 0 when their order doesn't matter
+pub fn sort_by(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Value>,
+) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 2)?;
+    let items = arguments[0].into_list()?.clone();
+
+    let sorted = merge_sort(items, &mut |a, b| {
+        let order = interpreter.call_function(&arguments[1], vec![a.clone(), b.clone()])?;
+        Ok(order.into_int()?.cmp(&0))
+    })?;
+    Ok(Value::list(sorted))
+}
+
+pub fn reverse(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 1)?;
+    Ok(Value::List(
+        arguments[0].into_list()?.iter().rev().cloned().collect(),
+    ))
+}
+
+// a list of n elements which are all the fill value, a list as the fill value
+// is shared by every element like any other list
+pub fn new_list(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 2)?;
+    let length = usize::try_from(*arguments[0].into_int()?)
+        .map_err(|_| ControlFlowValue::Exception(Exception::ValueIsWrongType, None))?;
+    // every element is a copy, strings copy their text as well
+    let element = std::mem::size_of::<Value>()
+        + match &arguments[1] {
+            Value::String(fill) => fill.len(),
+            _ => 0,
+        };
+    fits_in_memory(element.checked_mul(length))?;
+    Ok(Value::list(vec![arguments[1].clone(); length]))
+}
+
+// the list of the first argument, the builtins below change it in place
+fn list_argument(arguments: &[Value]) -> Result<&List, ControlFlowValue> {
+    match arguments.first() {
+        Some(Value::List(list)) => Ok(list),
+        _ => Err(ControlFlowValue::Exception(
+            Exception::ValueIsWrongType,
+            None,
+        )),
+    }
+}
+
+fn position(index: &Value, len: usize) -> Result<usize, ControlFlowValue> {
+    let index = *index.into_int()?;
+    usize::try_from(index)
+        .ok()
+        .filter(|index| *index < len)
+        .ok_or(ControlFlowValue::Exception(
+            Exception::IndexOutOfRange,
+            None,
+        ))
+}
+
+pub fn push(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 2)?;
+    list_argument(&arguments)?
+        .borrow_mut()?
+        .push(arguments[1].clone());
+    Ok(Value::Null)
+}
+
+// appends the elements of the other list, unlike + it doesn't copy the list
+pub fn extend(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 2)?;
+    let list = list_argument(&arguments)?;
+    // the other list is copied first since it can be the same list
+    let items = arguments[1].into_list()?.clone();
+    list.borrow_mut()?.extend(items);
+    Ok(Value::Null)
+}
+
+// removes the last element and returns it
+pub fn pop(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 1)?;
+    list_argument(&arguments)?
+        .borrow_mut()?
+        .pop()
+        .ok_or(ControlFlowValue::Exception(Exception::EmptyList, None))
+}
+
+// the element is inserted before the index, the length of the list appends it
+pub fn insert(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 3)?;
+    let list = list_argument(&arguments)?;
+    let len = list.borrow().len();
+    let index = position(&arguments[1], len + 1)?;
+    list.borrow_mut()?.insert(index, arguments[2].clone());
+    Ok(Value::Null)
+}
+
+// removes the element at the index and returns it
+pub fn remove_at(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 2)?;
+    let list = list_argument(&arguments)?;
+    let len = list.borrow().len();
+    let index = position(&arguments[1], len)?;
+    Ok(list.borrow_mut()?.remove(index))
+}
+
+pub fn clear(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 1)?;
+    list_argument(&arguments)?.borrow_mut()?.clear();
+    Ok(Value::Null)
+}
+
+// the elements of a list that may only contain ints and floats
+fn numbers(value: &Value) -> Result<Ref<'_, Vec<Value>>, ControlFlowValue> {
+    let list = value.into_list()?;
+    for value in list.iter() {
+        value.into_float()?;
+    }
+    Ok(list)
+}
+
+fn non_empty_numbers(value: &Value) -> Result<Ref<'_, Vec<Value>>, ControlFlowValue> {
+    let list = numbers(value)?;
+    if list.is_empty() {
+        return Err(ControlFlowValue::Exception(Exception::EmptyList, None));
+    }
+    Ok(list)
+}
+
+pub fn sum(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 1)?;
+
+    numbers(&arguments[0])?
+        .iter()
+        .try_fold(Value::Int(0), |sum, value| plus(sum, value.clone()))
+}
+
+pub fn product(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 1)?;
+
+    numbers(&arguments[0])?
+        .iter()
+        .try_fold(Value::Int(1), |product, value| {
+            multiply(product, value.clone())
+        })
+}
+
+pub fn min_of(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 1)?;
+
+    let list = non_empty_numbers(&arguments[0])?;
+    let mut min = &list[0];
+    for value in &list[1..] {
+        if is_less_than(value.clone(), min.clone())? {
+            min = value;
+        }
+    }
+    Ok(min.clone())
+}
+
+pub fn max_of(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 1)?;
+
+    let list = non_empty_numbers(&arguments[0])?;
+    let mut max = &list[0];
+    for value in &list[1..] {
+        if is_greater_than(value.clone(), max.clone())? {
+            max = value;
+        }
+    }
+    Ok(max.clone())
+}
+
+pub fn modulo(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 2)?;
+
+    euclidean_modulo(arguments[0].clone(), arguments[1].clone())
+}
+
+// the value limited to the range from the lower to the upper bound, ints stay ints
+pub fn clamp(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 3)?;
+
+    let (value, low, high) = (&arguments[0], &arguments[1], &arguments[2]);
+    value.into_float()?;
+    if is_greater_than(low.clone(), high.clone())? {
+        return Err(ControlFlowValue::Exception(
+            Exception::Custom("clamp: the lower bound is greater than the upper bound".to_string()),
+            None,
+        ));
+    }
+    Ok(if is_less_than(value.clone(), low.clone())? {
+        low.clone()
+    } else if is_greater_than(value.clone(), high.clone())? {
+        high.clone()
+    } else {
+        value.clone()
+    })
+}
+
+// the value t of the way from a to b, t isn't limited to the range from 0 to 1
+pub fn lerp(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 3)?;
+
+    let a = arguments[0].into_float()?;
+    let b = arguments[1].into_float()?;
+    let t = arguments[2].into_float()?;
+    Ok(Value::Float(a + (b - a) * t))
+}
+
+// -1, 0 or 1, floats keep the sign of zero
+pub fn sign(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 1)?;
+
+    match &arguments[0] {
+        Value::Int(v) => Ok(Value::Int(v.signum())),
+        Value::Float(v) if *v == 0.0 || v.is_nan() => Ok(Value::Float(*v)),
+        Value::Float(v) => Ok(Value::Float(v.signum())),
+        _ => Err(ControlFlowValue::Exception(
+            Exception::ValueIsWrongType,
+            None,
+        )),
+    }
+}
+
+// the distance between two numbers
+pub fn abs_diff(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 2)?;
+
+    match (&arguments[0], &arguments[1]) {
+        (Value::Int(a), Value::Int(b)) => i64::try_from(a.abs_diff(*b))
+            .map(Value::Int)
+            .map_err(|_| ControlFlowValue::Exception(Exception::IntegerOverflowed, None)),
+        (a, b) => Ok(Value::Float((a.into_float()? - b.into_float()?).abs())),
+    }
+}
+
+pub fn abs(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 1)?;
+
+    match &arguments[0] {
+        Value::Int(v) => match v.checked_abs() {
+            Some(v) => Ok(Value::Int(v)),
+            None => Err(ControlFlowValue::Exception(
+                Exception::IntegerOverflowed,
+                None,
+            )),
+        },
+        Value::Float(v) => Ok(Value::Float(v.abs())),
+        _ => Err(ControlFlowValue::Exception(
+            Exception::ValueIsWrongType,
+            None,
+        )),
+    }
+}
+
+// the smallest of the arguments, minOf does the same for a list
+pub fn min(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    if arguments.is_empty() {
+        return Err(ControlFlowValue::Exception(
+            Exception::WrongNumberOfArguments,
+            None,
+        ));
+    }
+    min_of(vec![Value::list(arguments)])
+}
+
+pub fn max(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    if arguments.is_empty() {
+        return Err(ControlFlowValue::Exception(
+            Exception::WrongNumberOfArguments,
+            None,
+        ));
+    }
+    max_of(vec![Value::list(arguments)])
+}
+
+// the square root of a negative number is NaN
+pub fn sqrt(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 1)?;
+    Ok(Value::Float(arguments[0].into_float()?.sqrt()))
+}
+
+// floats are rounded to ints, floats too large for an int give the largest or
+// smallest int
+fn round_with(arguments: Vec<Value>, round: fn(f64) -> f64) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 1)?;
+
+    match &arguments[0] {
+        Value::Int(v) => Ok(Value::Int(*v)),
+        Value::Float(v) => Ok(Value::Int(round(*v) as i64)),
+        _ => Err(ControlFlowValue::Exception(
+            Exception::ValueIsWrongType,
+            None,
+        )),
+    }
+}
+
+pub fn floor(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    round_with(arguments, f64::floor)
+}
+
+pub fn ceil(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    round_with(arguments, f64::ceil)
+}
+
+// halfway cases are rounded away from zero
+pub fn round(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    round_with(arguments, f64::round)
+}
+
+// like `**`, but negative bases work and negative exponents give a float
+pub fn pow(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 2)?;
+
+    match (&arguments[0], &arguments[1]) {
+        (Value::Int(base), Value::Int(exponent)) if *exponent < 0 => {
+            Ok(Value::Float((*base as f64).powf(*exponent as f64)))
+        }
+        (Value::Int(base), Value::Int(exponent)) => Ok(Value::Int(int_pow(*base, *exponent)?)),
+        _ => {
+            let mut arguments = arguments.into_iter();
+            exponent(arguments.next().unwrap(), arguments.next().unwrap())
+        }
+    }
+}
+
+// the average is always a float, even for a list of ints. the numbers are added
+// up as floats, so ints whose sum doesn't fit in 64 bits still have an average
+pub fn average(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 1)?;
+
+    let list = non_empty_numbers(&arguments[0])?;
+    let total = list.iter().map(Value::into_float).sum::<Result<f64, _>>()?;
+    Ok(Value::Float(total / list.len() as f64))
+}
+
+// keys of the second map replace the same keys of the first one
+pub fn merge(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 2)?;
+
+    let mut result = arguments[0].into_map()?.clone();
+    result.extend(arguments[1].into_map()?.clone());
+    Ok(Value::Map(result))
+}
+
+pub fn get_or(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 3)?;
+
+    Ok(arguments[0]
+        .into_map()?
+        .get(arguments[1].into_str()?)
+        .unwrap_or(&arguments[2])
+        .clone())
+}
+
+// the key value pairs of a map as a list of lists, sorted by key
+pub fn entries(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 1)?;
+
+    Ok(Value::List(
+        arguments[0]
+            .into_map()?
+            .iter()
+            .map(|(key, value)| Value::list(vec![Value::String(key.clone()), value.clone()]))
+            .collect(),
+    ))
+}
+
+// the inverse of entries, later pairs replace earlier pairs with the same key
+pub fn from_entries(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 1)?;
+
+    let mut result = BTreeMap::new();
+    for entry in arguments[0].into_list()?.iter() {
+        match entry.into_list()?.as_slice() {
+            [key, value] => result.insert(key.into_str()?.to_string(), value.clone()),
+            _ => {
+                return Err(ControlFlowValue::Exception(
+                    Exception::ValueIsWrongType,
+                    None,
+                ))
+            }
+        };
+    }
+    Ok(Value::Map(result))
+}
+
+fn padding(fill: char, count: usize) -> String {
+    fill.to_string().repeat(count)
+}
+
+// strings that are already at least as wide as the width are returned unchanged
+pub fn pad_left(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    let (string, width, fill) = pad_arguments(&arguments)?;
+    let missing = width.saturating_sub(string.chars().count());
+
+    Ok(Value::String(padding(fill, missing) + string))
+}
+
+pub fn pad_right(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    let (string, width, fill) = pad_arguments(&arguments)?;
+    let missing = width.saturating_sub(string.chars().count());
+
+    Ok(Value::String(string.to_string() + &padding(fill, missing)))
+}
+
+// when the padding can't be split evenly the extra character goes to the right
+pub fn center(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    let (string, width, fill) = pad_arguments(&arguments)?;
+    let missing = width.saturating_sub(string.chars().count());
+
+    Ok(Value::String(
+        padding(fill, missing / 2) + string + &padding(fill, missing - missing / 2),
+    ))
+}
+
+pub fn repeat(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 2)?;
+    arguments[0].into_str()?;
+
+    let mut arguments = arguments.into_iter();
+    multiply(arguments.next().unwrap(), arguments.next().unwrap())
+}
+
+enum ScanPart<'a> {
+    Text(&'a str),
+    Str,
+    Int,
+    Float,
+}
+
+fn scan_error(message: String) -> ControlFlowValue {
+    ControlFlowValue::Exception(Exception::Custom(format!("scan: {}", message)), None)
+}
+
+fn scan_pattern(pattern: &str) -> Result<Vec<ScanPart<'_>>, ControlFlowValue> {
+    let mut parts = vec![];
+    let mut rest = pattern;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..]
+            .find('}')
+            .map(|end| start + end)
+            .ok_or_else(|| scan_error(format!("{} has no closing }}", &rest[start..])))?;
+        if start > 0 {
+            parts.push(ScanPart::Text(&rest[..start]));
+        }
+        parts.push(match &rest[start + 1..end] {
+            "str" => ScanPart::Str,
+            "int" => ScanPart::Int,
+            "float" => ScanPart::Float,
+            other => {
+                return Err(scan_error(format!(
+                    "unknown placeholder {{{}}}, use {{str}}, {{int}} or {{float}}",
+                    other
+                )))
+            }
+        });
+        rest = &rest[end + 1..];
+    }
+    if !rest.is_empty() {
+        parts.push(ScanPart::Text(rest));
+    }
+    Ok(parts)
+}
+
+// the length of the number at the start of the text, digits with an optional
+// sign, floats can also have a fraction and an exponent
+fn number_length(text: &str, float: bool) -> usize {
+    let bytes = text.as_bytes();
+    let digits = |from: usize| {
+        from + bytes[from.min(bytes.len())..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count()
+    };
+    let start = usize::from(matches!(bytes.first(), Some(b'+' | b'-')));
+    let mut end = digits(start);
+    if end == start {
+        return 0;
+    }
+    if float {
+        if bytes.get(end) == Some(&b'.') && digits(end + 1) > end + 1 {
+            end = digits(end + 1);
+        }
+        if matches!(bytes.get(end), Some(b'e' | b'E')) {
+            let exponent = end + 1 + usize::from(matches!(bytes.get(end + 1), Some(b'+' | b'-')));
+            if digits(exponent) > exponent {
+                end = digits(exponent);
+            }
+        }
+    }
+    end
+}
+
+// numbers are as long as possible and strings as short as possible, a string
+// only grows when the rest of the pattern doesn't match after it
+fn scan_from(parts: &[ScanPart], text: &str, values: &mut Vec<Value>) -> bool {
+    let Some((part, rest)) = parts.split_first() else {
+        return text.is_empty();
+    };
+    let value = match part {
+        ScanPart::Text(literal) => {
+            return text
+                .strip_prefix(literal)
+                .is_some_and(|text| scan_from(rest, text, values))
+        }
+        ScanPart::Str => {
+            let ends = text.char_indices().map(|(i, _)| i).chain([text.len()]);
+            for end in ends {
+                values.push(Value::String(text[..end].to_string()));
+                if scan_from(rest, &text[end..], values) {
+                    return true;
+                }
+                values.pop();
+            }
+            return false;
+        }
+        ScanPart::Int => {
+            let length = number_length(text, false);
+            text[..length].parse().ok().map(|v| (Value::Int(v), length))
+        }
+        ScanPart::Float => {
+            let length = number_length(text, true);
+            text[..length]
+                .parse()
+                .ok()
+                .map(|v| (Value::Float(v), length))
+        }
+    };
+
+    let Some((value, length)) = value else {
+        return false;
+    };
+    values.push(value);
+    if scan_from(rest, &text[length..], values) {
+        return true;
+    }
+    values.pop();
+    false
+}
+
+// the values of the {str}, {int} and {float} placeholders in the pattern, the
+// rest of the pattern has to match the text exactly. null when it doesn't
+pub fn scan(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 2)?;
+    let parts = scan_pattern(arguments[0].into_str()?)?;
+
+    let mut values = vec![];
+    Ok(
+        if scan_from(&parts, arguments[1].into_str()?, &mut values) {
+            Value::list(values)
+        } else {
+            Value::Null
+        },
+    )
+}
+
+fn ordering_value(ordering: cmp::Ordering) -> Value {
+    Value::Int(ordering as i64)
+}
+
+// strings are compared the way most languages sort them, not by the numbers of
+// their characters. letters are compared without their accents first, so "ä"
+// goes between "a" and "b", and only then with them. the case is ignored,
+// including letters written differently in upper case, so "Straße" and
+// "STRASSE" are equal. there is no tailoring for a single language, like
+// Swedish sorting "ä" after "z"
+fn compare_ignoring_case(a: &str, b: &str) -> cmp::Ordering {
+    // upper case first turns letters like ß into their upper case spelling
+    let folded = |s: &str| {
+        s.nfd()
+            .flat_map(char::to_uppercase)
+            .flat_map(char::to_lowercase)
+            .collect::<Vec<_>>()
+    };
+    let (a, b) = (folded(a), folded(b));
+    let letters = |s: &[char]| {
+        s.iter()
+            .filter(|c| !is_combining_mark(**c))
+            .copied()
+            .collect::<Vec<_>>()
+    };
+    letters(&a).cmp(&letters(&b)).then(a.cmp(&b))
+}
+
+// -1, 0 or 1 like the functions of sortBy expect, strings which only differ in
+// case are equal and keep their order when sorting
+pub fn compare_ignore_case(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 2)?;
+    let (a, b) = (arguments[0].into_str()?, arguments[1].into_str()?);
+    Ok(ordering_value(compare_ignoring_case(a, b)))
+}
+
+fn format_error(message: String) -> ControlFlowValue {
+    ControlFlowValue::Exception(Exception::Custom(format!("format: {}", message)), None)
+}
+
+// format(text arguments...) replaces every {} with the next argument and {0},
+// {1} with the argument at that position, like toString would write them.
+// {{ and }} stand for the braces themselves
+pub fn format(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    let Some((text, arguments)) = arguments.split_first() else {
+        return Err(ControlFlowValue::Exception(
+            Exception::WrongNumberOfArguments,
+            None,
+        ));
+    };
+
+    let mut result = String::new();
+    let mut next = 0;
+    let mut chars = text.into_str()?.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.as_str().starts_with('{') => {
+                chars.next();
+                result.push('{');
+            }
+            '{' => {
+                let rest = chars.as_str();
+                let end = rest
+                    .find('}')
+                    .ok_or_else(|| format_error(format!("{{{} has no closing }}", rest)))?;
+                let index = match &rest[..end] {
+                    "" => {
+                        next += 1;
+                        next - 1
+                    }
+                    position => position.parse().map_err(|_| {
+                        format_error(format!(
+                            "unknown placeholder {{{}}}, use {{}} or the position of an argument like {{0}}",
+                            position
+                        ))
+                    })?,
+                };
+                let argument = arguments.get(index).ok_or_else(|| {
+                    format_error(format!(
+                        "there is no argument {} for the placeholder, only {} were given",
+                        index,
+                        arguments.len()
+                    ))
+                })?;
+                result.push_str(&argument.to_string());
+                chars = rest[end + 1..].chars();
+            }
+            '}' if chars.as_str().starts_with('}') => {
+                chars.next();
+                result.push('}');
+            }
+            '}' => return Err(format_error("a } has to be written as }}".to_string())),
+            c => result.push(c),
+        }
+    }
+    Ok(Value::String(result))
+}
+
+// the text split into runs of digits and runs of other characters, the other
+// characters are compared together since "ß" is the same as "ss"
+fn natural_chunks(text: &str) -> Vec<&str> {
+    let mut chunks = vec![];
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        let digit = c.is_ascii_digit();
+        let length = rest
+            .find(|c: char| c.is_ascii_digit() != digit)
+            .unwrap_or(rest.len());
+        chunks.push(&rest[..length]);
+        rest = &rest[length..];
+    }
+    chunks
+}
+
+// numbers in the strings are compared by their value, so "file2" goes before
+// "file10". everything else is compared like compareIgnoreCase does, with
+// leading zeros and case only deciding between otherwise equal strings
+pub fn natural_compare(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 2)?;
+    let (a, b) = (arguments[0].into_str()?, arguments[1].into_str()?);
+
+    let is_number = |chunk: &str| chunk.starts_with(|c: char| c.is_ascii_digit());
+    let mut ordering = cmp::Ordering::Equal;
+    for (x, y) in natural_chunks(a).into_iter().zip(natural_chunks(b)) {
+        ordering = if is_number(x) && is_number(y) {
+            let (x, y) = (x.trim_start_matches('0'), y.trim_start_matches('0'));
+            x.len().cmp(&y.len()).then(x.cmp(y))
+        } else {
+            compare_ignoring_case(x, y)
+        };
+        if ordering.is_ne() {
+            break;
+        }
+    }
+    let ordering = ordering
+        .then(natural_chunks(a).len().cmp(&natural_chunks(b).len()))
+        .then(a.cmp(b));
+    Ok(ordering_value(ordering))
+}
+
+// the cells of a table, rows are either lists or maps. the columns of map rows
+// are the headers, or all keys in the order they first appear without headers
+fn table_cells(
+    rows: &[Value],
+    headers: Option<&Vec<Value>>,
+) -> Result<(Vec<String>, Vec<Vec<Value>>), ControlFlowValue> {
+    let mut columns: Vec<String> = match headers {
+        Some(headers) => headers.iter().map(|header| header.to_string()).collect(),
+        None => vec![],
+    };
+
+    if rows.iter().all(|row| matches!(row, Value::Map(_))) && !rows.is_empty() {
+        if headers.is_none() {
+            for row in rows {
+                for key in row.into_map()?.keys() {
+                    if !columns.contains(key) {
+                        columns.push(key.clone());
+                    }
+                }
+            }
+        }
+        let cells = rows
+            .iter()
+            .map(|row| {
+                let row = row.into_map().unwrap();
+                columns
+                    .iter()
+                    .map(|column| row.get(column).cloned().unwrap_or(Value::Null))
+                    .collect()
+            })
+            .collect();
+        return Ok((columns, cells));
+    }
+
+    let mut cells = vec![];
+    for row in rows {
+        cells.push(row.into_list()?.clone());
+    }
+    Ok((columns, cells))
+}
+
+// an ascii table with a column for every cell, numbers are aligned to the right
+pub fn format_table(
+    rows: &[Value],
+    headers: Option<&Vec<Value>>,
+) -> Result<String, ControlFlowValue> {
+    let (columns, cells) = table_cells(rows, headers)?;
+    let cells: Vec<Vec<(String, bool)>> = cells
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|value| match value {
+                    Value::Int(_) | Value::Float(_) => (value.to_string(), true),
+                    // missing cells of map rows are left empty
+                    Value::Null => (String::new(), false),
+                    value => (value.to_string(), false),
+                })
+                .collect()
+        })
+        .collect();
+
+    let header: Vec<_> = columns.into_iter().map(|column| (column, false)).collect();
+
+    let count = cells.iter().chain([&header]).map(Vec::len).max().unwrap();
+    if count == 0 {
+        return Ok(String::new());
+    }
+    let mut widths = vec![0; count];
+    for row in cells.iter().chain([&header]) {
+        for (i, (cell, _)) in row.iter().enumerate() {
+            widths[i] = widths[i].max(cell.chars().count());
+        }
+    }
+
+    let separator = widths.iter().fold(String::from("+"), |line, width| {
+        line + &"-".repeat(width + 2) + "+"
+    }) + "\n";
+    let line = |row: &[(String, bool)]| {
+        let mut line = String::from("|");
+        for (i, width) in widths.iter().enumerate() {
+            let (cell, right) = row
+                .get(i)
+                .map(|(cell, right)| (cell.as_str(), *right))
+                .unwrap_or(("", false));
+            let padding = " ".repeat(width - cell.chars().count());
+            if right {
+                line += &format!(" {}{} |", padding, cell);
+            } else {
+                line += &format!(" {}{} |", cell, padding);
+            }
+        }
+        line + "\n"
+    };
+
+    let mut table = separator.clone();
+    if !header.is_empty() {
+        table += &line(&header);
+        table += &separator;
+    }
+    for row in &cells {
+        table += &line(row);
+    }
+    if !cells.is_empty() {
+        table += &separator;
+    }
+    Ok(table)
+}
+
+// tablePrint(rows) or tablePrint(rows headers)
+pub fn table_print(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Value>,
+) -> Result<Value, ControlFlowValue> {
+    let headers = match arguments.len() {
+        1 => None,
+        2 => Some(arguments[1].into_list()?),
+        _ => {
+            return Err(ControlFlowValue::Exception(
+                Exception::WrongNumberOfArguments,
+                None,
+            ))
+        }
+    };
+
+    let table = format_table(&arguments[0].into_list()?, headers.as_deref())?;
+    interpreter.io().write(&table).map_err(io_error)?;
+    Ok(Value::Null)
+}
+
+// the number of differences assertEqual reports before leaving out the rest
+const MAX_DIFFERENCES: usize = 20;
+
+// strings are quoted so "1" and 1 can be told apart
+fn show(value: &Value) -> String {
+    match value {
+        Value::String(s) => format!("{:?}", s),
+        value => value.to_string(),
+    }
+}
+
+// collects the paths at which two values differ, lists and maps are compared
+// element by element so only the parts that differ are reported. every pair of
+// lists is only compared once, lists can contain themselves
+fn diff(
+    path: &str,
+    left: &Value,
+    right: &Value,
+    differences: &mut Vec<String>,
+    lists: &mut HashSet<(*const (), *const ())>,
+) {
+    match (left, right) {
+        (Value::List(left), Value::List(right))
+            if left.id() == right.id() || !lists.insert((left.id(), right.id())) => {}
+        (Value::List(left), Value::List(right)) => {
+            let (left, right) = (left.borrow(), right.borrow());
+            for i in 0..left.len().max(right.len()) {
+                let path = format!("{}[{}]", path, i);
+                diff_element(&path, left.get(i), right.get(i), differences, lists);
+            }
+        }
+        (Value::Map(left), Value::Map(right)) => {
+            let keys: BTreeSet<&String> = left.keys().chain(right.keys()).collect();
+            for key in keys {
+                let path = format!("{}[{:?}]", path, key);
+                diff_element(&path, left.get(key), right.get(key), differences, lists);
+            }
+        }
+        (left, right) if left != right => {
+            let path = if path.is_empty() { "value" } else { path };
+            differences.push(format!("{}: {} != {}", path, show(left), show(right)))
+        }
+        _ => {}
+    }
+}
+
+fn diff_element(
+    path: &str,
+    left: Option<&Value>,
+    right: Option<&Value>,
+    differences: &mut Vec<String>,
+    lists: &mut HashSet<(*const (), *const ())>,
+) {
+    match (left, right) {
+        (Some(left), Some(right)) => diff(path, left, right, differences, lists),
+        (Some(left), None) => {
+            differences.push(format!("{}: {} is missing on the right", path, show(left)))
+        }
+        (None, Some(right)) => {
+            differences.push(format!("{}: {} is missing on the left", path, show(right)))
+        }
+        (None, None) => {}
+    }
+}
+
+// assertEqual(left right) raises an exception describing every difference
+pub fn assert_equal(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    expect_num_of_argumets(&arguments, 2)?;
+    let mut differences = vec![];
+    diff(
+        "",
+        &arguments[0],
+        &arguments[1],
+        &mut differences,
+        &mut HashSet::new(),
+    );
+    if differences.is_empty() {
+        return Ok(Value::Null);
+    }
+
+    let mut message = String::new();
+    for difference in differences.iter().take(MAX_DIFFERENCES) {
+        message.push_str("\n    ");
+        message.push_str(difference);
+    }
+    if differences.len() > MAX_DIFFERENCES {
+        message.push_str(&format!(
+            "\n    and {} more differences",
+            differences.len() - MAX_DIFFERENCES
+        ));
+    }
+    Err(ControlFlowValue::Exception(
+        Exception::AssertionFailed(message),
+        None,
+    ))
+}
+
+// assert(condition) or assert(condition message), the message says what was
+// expected to be true
+pub fn assert(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    if !(1..=2).contains(&arguments.len()) {
+        return Err(ControlFlowValue::Exception(
+            Exception::WrongNumberOfArguments,
+            None,
+        ));
+    }
+    let message = match arguments.get(1) {
+        Some(message) => format!(" {}", message.into_str()?),
+        None => String::new(),
+    };
+    match arguments[0] {
+        Value::Bool(true) => Ok(Value::Null),
+        Value::Bool(false) => Err(ControlFlowValue::Exception(
+            Exception::AssertionFailed(message),
+            None,
+        )),
+        _ => Err(ControlFlowValue::Exception(
+            Exception::ValueIsWrongType,
+            None,
+        )),
+    }
+}
+
+// the total time benchmark aims for when the number of runs is not given
+const BENCHMARK_TARGET: Duration = Duration::from_millis(200);
+const BENCHMARK_MAX_RUNS: u128 = 1_000_000;
+
+// benchmark(function) or benchmark(function runs), the timings are in milliseconds
+pub fn benchmark(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Value>,
+) -> Result<Value, ControlFlowValue> {
+    let runs = match arguments.len() {
+        1 => None,
+        2 => match *arguments[1].into_int()? {
+            runs if runs > 0 => Some(runs as u128),
+            _ => {
+                return Err(ControlFlowValue::Exception(
+                    Exception::Custom(
+                        "benchmark: the number of runs has to be positive".to_string(),
+                    ),
+                    None,
+                ))
+            }
+        },
+        _ => {
+            return Err(ControlFlowValue::Exception(
+                Exception::WrongNumberOfArguments,
+                None,
+            ))
+        }
+    };
+    let function = &arguments[0];
+    if !matches!(function, Value::Function(_)) {
+        return Err(ControlFlowValue::Exception(
+            Exception::ValueIsWrongType,
+            None,
+        ));
+    }
+
+    let mut time_call = || -> Result<Duration, ControlFlowValue> {
+        let start = interpreter.elapsed();
+        interpreter.call_function(function, vec![])?;
+        Ok(interpreter.elapsed() - start)
+    };
+
+    // the first call also warms up and estimates how many runs fit into the target
+    let first = time_call()?;
+    let runs = runs.unwrap_or_else(|| {
+        (BENCHMARK_TARGET.as_nanos() / first.as_nanos().max(1)).clamp(1, BENCHMARK_MAX_RUNS)
+    });
+
+    // the timings are summarized as they come in, so many runs don't need memory
+    let (mut min, mut max, mut total) = (Duration::MAX, Duration::ZERO, Duration::ZERO);
+    for _ in 0..runs {
+        let timing = time_call()?;
+        min = min.min(timing);
+        max = max.max(timing);
+        total = total.saturating_add(timing);
+    }
+
+    let milliseconds = |duration: Duration| Value::Float(duration.as_secs_f64() * 1000.0);
+    Ok(Value::Map(BTreeMap::from([
+        ("runs".to_string(), Value::Int(runs as i64)),
+        ("min".to_string(), milliseconds(min)),
+        ("mean".to_string(), milliseconds(total.div_f64(runs as f64))),
+        ("max".to_string(), milliseconds(max)),
+    ])))
+}
@@ -0,0 +1,100 @@
+use thiserror::Error;
+
+// options that can be set process wide through environment variables, the
+// command line flags are applied on top of them
+#[derive(Debug, Clone)]
+pub struct Config {
+    // OLANG_STACK_LIMIT, the maximum depth of nested function calls
+    pub stack_limit: Option<usize>,
+    // OLANG_FUEL, the maximum number of expressions a program may evaluate
+    pub fuel: Option<u64>,
+    // OLANG_NO_COLOR, any non-empty value disables colored output
+    pub color: bool,
+    // OLANG_STDLIB=0 starts programs without any builtins
+    pub stdlib: bool,
+    // OLANG_DETERMINISTIC=1 makes builtins give the same results on every run
+    pub deterministic: bool,
+    // OLANG_FLAT_BUILTINS=1 declares the builtins of the namespaces like math
+    // globally as well, for programs written before there were namespaces
+    pub flat_builtins: bool,
+}
+
+// the variables read by from_vars
+pub const VARIABLES: &[&str] = &[
+    "OLANG_STACK_LIMIT",
+    "OLANG_FUEL",
+    "OLANG_NO_COLOR",
+    "OLANG_STDLIB",
+    "OLANG_DETERMINISTIC",
+    "OLANG_FLAT_BUILTINS",
+];
+
+#[derive(Error, Debug)]
+#[error("environment variable {name} has to be {expected}, found \"{value}\"")]
+pub struct ConfigError {
+    name: String,
+    value: String,
+    expected: &'static str,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            stack_limit: None,
+            fuel: None,
+            color: true,
+            stdlib: true,
+            deterministic: false,
+            flat_builtins: false,
+        }
+    }
+}
+
+fn switch(value: &str) -> Option<bool> {
+    match value {
+        "0" => Some(false),
+        "1" => Some(true),
+        _ => None,
+    }
+}
+
+impl Config {
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Self::from_vars(std::env::vars())
+    }
+
+    // variables that don't start with OLANG_ are ignored
+    pub fn from_vars(
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<Self, ConfigError> {
+        let mut config = Config::default();
+
+        for (name, value) in vars {
+            let invalid = |expected| ConfigError {
+                name: name.clone(),
+                value: value.clone(),
+                expected,
+            };
+
+            match name.as_str() {
+                "OLANG_STACK_LIMIT" => {
+                    config.stack_limit = Some(value.parse().map_err(|_| invalid("a number"))?)
+                }
+                "OLANG_FUEL" => config.fuel = Some(value.parse().map_err(|_| invalid("a number"))?),
+                "OLANG_NO_COLOR" => config.color = value.is_empty(),
+                "OLANG_STDLIB" => {
+                    config.stdlib = switch(&value).ok_or_else(|| invalid("0 or 1"))?
+                }
+                "OLANG_DETERMINISTIC" => {
+                    config.deterministic = switch(&value).ok_or_else(|| invalid("0 or 1"))?
+                }
+                "OLANG_FLAT_BUILTINS" => {
+                    config.flat_builtins = switch(&value).ok_or_else(|| invalid("0 or 1"))?
+                }
+                _ => {}
+            }
+        }
+
+        Ok(config)
+    }
+}
@@ -0,0 +1,81 @@
+use crate::interpreter::ErrorReport;
+use strum::{Display, EnumString, VariantNames};
+
+// how errors and dumps are printed, json is meant for editors and other tools
+#[derive(Debug, Clone, Copy, Display, EnumString, VariantNames)]
+#[strum(serialize_all = "lowercase")]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+struct Style {
+    color: bool,
+}
+
+impl Style {
+    fn paint(&self, code: &str, text: &str) -> String {
+        if self.color {
+            format!("{}{}{}", code, text, RESET)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+// render an error for the terminal, with the source line it points at and the
+// region underlined when the report has them
+//
+// error[OL0011]: 2:1 -> 2:9 Unhandled exception: ValueIsWrongType
+//   |
+// 2 | x + "ab"
+//   | ^^^^^^^^
+pub fn render(report: &ErrorReport, color: bool) -> String {
+    let style = Style { color };
+    let mut output = format!(
+        "{}{}\n",
+        style.paint(RED, &format!("error[{}]", report.code)),
+        style.paint(BOLD, &format!(": {}", report.message))
+    );
+
+    if let (Some(region), Some(line)) = (&report.region, &report.line) {
+        let row = region.start.row.to_string();
+        let gutter = " ".repeat(row.len());
+        let start = region.start.col.max(1);
+        // the end of single character regions is the character itself, the end
+        // of longer ones is the character after them
+        let end = if region.end.row == region.start.row {
+            region.end.col.max(start + 1)
+        } else {
+            line.chars().count() + 1
+        };
+
+        output += &format!("{} {}\n", gutter, style.paint(BLUE, "|"));
+        output += &format!(
+            "{} {} {}\n",
+            style.paint(BLUE, &row),
+            style.paint(BLUE, "|"),
+            line
+        );
+        output += &format!(
+            "{} {} {}{}\n",
+            gutter,
+            style.paint(BLUE, "|"),
+            " ".repeat(start - 1),
+            style.paint(RED, &"^".repeat(end.saturating_sub(start).max(1)))
+        );
+    }
+
+    output + &format!("For more information try `olang explain {}`\n", report.code)
+}
+
+// the code, message and region of an error as a single line of json
+pub fn render_json(report: &ErrorReport) -> String {
+    // serializing a report can't fail
+    serde_json::to_string(report).unwrap() + "\n"
+}
@@ -0,0 +1,100 @@
+use crate::{
+    config::{self, Config},
+    header::FEATURES,
+    interpreter::DEFAULT_MAX_RECURSION,
+};
+use std::path::Path;
+
+fn path_state(path: Option<&Path>) -> String {
+    match path {
+        Some(path) if path.exists() => format!("{}", path.display()),
+        Some(path) => format!("{} (does not exist)", path.display()),
+        None => "none, HOME is not set".to_string(),
+    }
+}
+
+fn names(features: impl Iterator<Item = &'static str>) -> String {
+    let names: Vec<_> = features.collect();
+    if names.is_empty() {
+        "none".to_string()
+    } else {
+        names.join(", ")
+    }
+}
+
+// the report of `olang doctor`, everything that makes olang behave differently
+// on another machine: how it was built, the files it reads on startup and the
+// settings the environment variables changed. vars are the variables of the
+// process, the ones starting with OLANG_ are listed
+pub fn report(
+    config: &Config,
+    vars: impl IntoIterator<Item = (String, String)>,
+    prelude: Option<&Path>,
+    history: Option<&Path>,
+) -> String {
+    let mut report = String::new();
+    let mut line = |text: String| {
+        report.push_str(&text);
+        report.push('\n');
+    };
+
+    line(format!("olang {}", env!("CARGO_PKG_VERSION")));
+    line(format!(
+        "features: {}",
+        names(FEATURES.iter().filter(|(_, on)| *on).map(|(name, _)| *name))
+    ));
+    line(format!(
+        "features left out: {}",
+        names(
+            FEATURES
+                .iter()
+                .filter(|(_, on)| !*on)
+                .map(|(name, _)| *name)
+        )
+    ));
+
+    line(String::new());
+    line(format!("prelude: {}", path_state(prelude)));
+    line(format!("history: {}", path_state(history)));
+
+    line(String::new());
+    line("settings:".to_string());
+    line(format!(
+        "    stack limit: {}",
+        config.stack_limit.unwrap_or(DEFAULT_MAX_RECURSION)
+    ));
+    line(format!(
+        "    fuel: {}",
+        config
+            .fuel
+            .map_or("unlimited".to_string(), |fuel| fuel.to_string())
+    ));
+    line(format!("    standard library: {}", config.stdlib));
+    line(format!("    flat builtins: {}", config.flat_builtins));
+    line(format!("    deterministic: {}", config.deterministic));
+    line(format!("    color: {}", config.color));
+    line(
+        "    --expr and sandboxed interpreters: builtins with side effects are forbidden"
+            .to_string(),
+    );
+
+    line(String::new());
+    let mut vars: Vec<_> = vars
+        .into_iter()
+        .filter(|(name, _)| name.starts_with("OLANG_"))
+        .collect();
+    vars.sort();
+    if vars.is_empty() {
+        line("environment: no OLANG_ variables are set".to_string());
+    } else {
+        line("environment:".to_string());
+        for (name, value) in vars {
+            let mut text = format!("    {}={}", name, value);
+            if !config::VARIABLES.contains(&name.as_str()) {
+                text.push_str(" (unknown, ignored)");
+            }
+            line(text);
+        }
+    }
+    report
+}
@@ -1,68 +1,267 @@
 use crate::builtin::*;
-use crate::value::{ControlFlowValue, Exception, Function, Value};
-use std::collections::HashMap;
+use crate::interpreter::Interpreter;
+use crate::memory::MemoryStats;
+use crate::parser::Slot;
+use crate::value::{Builtin, BuiltinFunction, ControlFlowValue, Exception, Function, Value};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap, HashSet},
+    mem::size_of,
+    rc::Rc,
+};
+
+pub type ScopeRef = Rc<RefCell<Scope>>;
+
+// scopes form a chain through their parents, functions keep a reference to the
+// scope they were defined in so they can still use it after it was left.
+// values are stored by slot index, declarations with a slot from the resolver
+// can leave earlier slots empty until their own declaration runs
+#[derive(Debug, Default)]
+pub struct Scope {
+    pub(crate) slots: HashMap<String, usize>,
+    pub(crate) values: Vec<Option<Value>>,
+    pub(crate) parent: Option<ScopeRef>,
+}
+
+impl Scope {
+    fn child(parent: ScopeRef) -> ScopeRef {
+        Rc::new(RefCell::new(Scope {
+            parent: Some(parent),
+            ..Scope::default()
+        }))
+    }
+
+    fn get(&self, id: &str) -> Option<&Value> {
+        self.slots
+            .get(id)
+            .and_then(|&index| self.values[index].as_ref())
+    }
+
+    fn get_mut(&mut self, id: &str) -> Option<&mut Value> {
+        self.slots
+            .get(id)
+            .and_then(|&index| self.values[index].as_mut())
+    }
+
+    fn bindings(&self) -> HashMap<String, Value> {
+        self.slots
+            .keys()
+            .filter_map(|id| Some((id.clone(), self.get(id)?.clone())))
+            .collect()
+    }
+
+    fn declare(&mut self, id: String, index: Option<usize>, value: Value) {
+        let index = index
+            .or_else(|| self.slots.get(&id).copied())
+            .unwrap_or(self.values.len());
+        if index >= self.values.len() {
+            self.values.resize(index + 1, None);
+        }
+        self.values[index] = Some(value);
+        self.slots.insert(id, index);
+    }
+}
 
 #[derive(Debug)]
 pub struct Environment {
-    scopes: Vec<HashMap<String, Value>>,
+    global: ScopeRef,
+    current: ScopeRef,
+    // the scopes of the functions waiting for a call to return
+    callers: Vec<ScopeRef>,
 }
 
 impl Environment {
     pub fn new() -> Self {
+        let global = ScopeRef::default();
         Environment {
-            scopes: vec![HashMap::new()],
+            current: global.clone(),
+            global,
+            callers: vec![],
         }
     }
 
-    // remove a scope to the environment
-    pub fn pop(&mut self) -> &mut Self {
-        self.scopes.pop();
-        self
+    // add a scope to the environment, returns the previous scope which has to be
+    // passed to restore when the new scope is left
+    pub fn push(&mut self) -> ScopeRef {
+        let scope = Scope::child(self.current.clone());
+        std::mem::replace(&mut self.current, scope)
     }
-    // add a scope to the environment
-    pub fn push(&mut self) -> &mut Self {
-        self.scopes.push(HashMap::new());
-        self
+
+    // add a scope below the given parent instead of the current scope, this is
+    // used to run the body of a function in the scope it was defined in. the
+    // scope of the caller is restored by leave
+    pub fn enter(&mut self, parent: ScopeRef) {
+        let caller = std::mem::replace(&mut self.current, Scope::child(parent));
+        self.callers.push(caller);
+    }
+
+    pub fn leave(&mut self) {
+        if let Some(caller) = self.callers.pop() {
+            self.current = caller;
+        }
+    }
+
+    pub fn restore(&mut self, scope: ScopeRef) {
+        self.current = scope;
+    }
+
+    pub fn current(&self) -> ScopeRef {
+        self.current.clone()
     }
 
     pub fn get(&self, id: &str) -> Option<Value> {
-        for value in self.scopes.iter().rev() {
-            match value.get(id) {
-                Some(v) => {
-                    return Some(v.clone());
-                }
-                _ => {}
+        let mut scope = Some(self.current.clone());
+        while let Some(current) = scope {
+            let current = current.borrow();
+            if let Some(v) = current.get(id) {
+                return Some(v.clone());
             }
+            scope = current.parent.clone();
         }
 
         None
     }
 
+    // the scope the given number of levels above the current one
+    fn ancestor(&self, depth: usize) -> Option<ScopeRef> {
+        let mut scope = self.current.clone();
+        for _ in 0..depth {
+            let parent = scope.borrow().parent.clone()?;
+            scope = parent;
+        }
+        Some(scope)
+    }
+
+    fn get_slot(&self, slot: Slot) -> Option<Value> {
+        let scope = self.ancestor(slot.depth)?;
+        let scope = scope.borrow();
+        scope.values.get(slot.index).cloned().flatten()
+    }
+
+    // look up a variable through its resolved slot, variables without a slot or
+    // whose declaration hasn't run yet are looked up by name
+    pub fn get_resolved(&self, id: &str, slot: Option<Slot>) -> Result<Value, ControlFlowValue> {
+        match slot.and_then(|slot| self.get_slot(slot)) {
+            Some(value) => Ok(value),
+            None => self.get_or_undeclared(id),
+        }
+    }
+
+    pub fn assign_resolved(
+        &mut self,
+        id: &str,
+        slot: Option<Slot>,
+        value: Value,
+    ) -> Result<(), ControlFlowValue> {
+        if let Some(slot) = slot {
+            if let Some(scope) = self.ancestor(slot.depth) {
+                if let Some(Some(v)) = scope.borrow_mut().values.get_mut(slot.index) {
+                    *v = value.stored_in(&scope);
+                    return Ok(());
+                }
+            }
+        }
+        self.assign(id, value)
+    }
+
     pub fn get_or_undeclared(&self, id: &str) -> Result<Value, ControlFlowValue> {
-        self.get(id)
-            .ok_or(ControlFlowValue::Exception(Exception::UndeclaredIdentifier))
+        self.get(id).ok_or(ControlFlowValue::Exception(
+            Exception::UndeclaredIdentifier,
+            None,
+        ))
     }
 
     pub fn assign(&mut self, id: &str, value: Value) -> Result<(), ControlFlowValue> {
-        let mut success = false;
-
-        for scope in self.scopes.iter_mut().rev() {
-            if scope.get(id).is_some() {
-                scope.insert(id.to_string(), value);
-                success = true;
-                break;
+        let mut scope = Some(self.current.clone());
+        while let Some(current) = scope {
+            let mut borrowed = current.borrow_mut();
+            if let Some(v) = borrowed.get_mut(id) {
+                *v = value.stored_in(&current);
+                return Ok(());
             }
+            scope = borrowed.parent.clone();
         }
 
-        if success {
-            Ok(())
-        } else {
-            Err(ControlFlowValue::Exception(Exception::UndeclaredIdentifier))
+        Err(ControlFlowValue::Exception(
+            Exception::UndeclaredIdentifier,
+            None,
+        ))
+    }
+
+    // the bindings of the outermost scope, including the builtins
+    pub(crate) fn global(&self) -> ScopeRef {
+        self.global.clone()
+    }
+
+    // start over with another global scope, like the one of a snapshot
+    pub(crate) fn replace_global(&mut self, global: ScopeRef) {
+        self.current = global.clone();
+        self.global = global;
+        self.callers.clear();
+    }
+
+    pub fn top_level(&self) -> HashMap<String, Value> {
+        self.global.borrow().bindings()
+    }
+
+    // evaluate an imported file in a scope of its own below the global scope, so
+    // it can use the builtins but not the variables of the program importing it
+    pub fn enter_module(&mut self) {
+        self.enter(self.global.clone());
+    }
+
+    // leave the scope of an imported file, returns the variables it declared
+    pub fn leave_module(&mut self) -> BTreeMap<String, Value> {
+        let exports = self.current.borrow().bindings().into_iter().collect();
+        self.leave();
+        exports
+    }
+
+    // count everything reachable from the current scope and the scopes of the
+    // callers, every scope is only counted once even when closures share it
+    pub fn memory_stats(&self) -> MemoryStats {
+        let mut stats = MemoryStats::default();
+        let mut seen = HashSet::new();
+        let mut lists = HashSet::new();
+        let mut pending: Vec<ScopeRef> = self.callers.clone();
+        pending.push(self.current.clone());
+
+        while let Some(scope) = pending.pop() {
+            if !seen.insert(Rc::as_ptr(&scope)) {
+                continue;
+            }
+            let scope = scope.borrow();
+            stats.environments += 1;
+            stats.environment_bytes += size_of::<RefCell<Scope>>()
+                + scope.values.capacity() * size_of::<Option<Value>>()
+                + scope
+                    .slots
+                    .keys()
+                    .map(|id| size_of::<(String, usize)>() + id.capacity())
+                    .sum::<usize>();
+            for value in scope.values.iter().flatten() {
+                stats.add_value(value, &mut pending, &mut lists);
+            }
+            pending.extend(scope.parent.clone());
         }
+
+        stats
     }
 
     pub fn declare(&mut self, id: String, value: Value) -> &mut Self {
-        self.scopes.last_mut().unwrap().insert(id, value);
+        self.declare_resolved(id, None, value)
+    }
+
+    // declare a variable in the slot the resolver picked for it, or in a new
+    // slot when it wasn't resolved
+    pub fn declare_resolved(
+        &mut self,
+        id: String,
+        index: Option<usize>,
+        value: Value,
+    ) -> &mut Self {
+        let value = value.stored_in(&self.current);
+        self.current.borrow_mut().declare(id, index, value);
         self
     }
 
@@ -70,20 +269,282 @@ impl Environment {
         &mut self,
         id: String,
         function: fn(Vec<Value>) -> Result<Value, ControlFlowValue>,
+        side_effects: bool,
     ) -> &mut Self {
-        self.declare(id, Value::Function(Function::Builtin(function)));
+        self.declare_builtin_function(id, simple(function), side_effects)
+    }
+
+    fn declare_interpreter_builtin(
+        &mut self,
+        id: String,
+        function: fn(&mut Interpreter, Vec<Value>) -> Result<Value, ControlFlowValue>,
+        side_effects: bool,
+    ) -> &mut Self {
+        self.declare_builtin_function(id, with_interpreter(function), side_effects)
+    }
+
+    pub fn declare_builtin_function(
+        &mut self,
+        id: String,
+        function: BuiltinFunction,
+        side_effects: bool,
+    ) -> &mut Self {
+        self.declare(id.clone(), builtin(id, function, side_effects))
+    }
+
+    // declare a map holding the builtins, programs call them with `name.member()`.
+    // in flat mode every member is declared on its own as well
+    fn declare_namespace(
+        &mut self,
+        name: &str,
+        members: Vec<(&str, BuiltinFunction, bool)>,
+        flat: bool,
+    ) -> &mut Self {
+        let mut namespace = BTreeMap::new();
+        for (member, function, side_effects) in members {
+            if flat {
+                self.declare_builtin_function(member.to_string(), function.clone(), side_effects);
+            }
+            namespace.insert(
+                member.to_string(),
+                builtin(format!("{}.{}", name, member), function, side_effects),
+            );
+        }
+        self.declare(name.to_string(), Value::Map(namespace))
+    }
+
+    // values like math.PI, they are only declared in their namespace so they
+    // are never taken for variables of the program
+    fn declare_constants(&mut self, name: &str, constants: Vec<(&str, Value)>) -> &mut Self {
+        if let Some(Value::Map(mut namespace)) = self.get(name) {
+            for (constant, value) in constants {
+                namespace.insert(constant.to_string(), value);
+            }
+            self.declare(name.to_string(), Value::Map(namespace));
+        }
         self
     }
+
+    // the standard library, the builtins everyone needs are always global and
+    // the rest are grouped into namespaces like math and str
+    pub fn with_builtins(flat: bool) -> Self {
+        let mut env = Environment::new();
+        env.declare_interpreter_builtin("printLn".to_string(), print_ln, true)
+            .declare_interpreter_builtin("readLn".to_string(), read_ln, true)
+            .declare_builtin("toString".to_string(), to_string, false)
+            .declare_builtin("len".to_string(), len, false)
+            .declare_interpreter_builtin("sleep".to_string(), sleep, true)
+            .declare_builtin("typeof".to_string(), type_of, false)
+            .declare_builtin("inspect".to_string(), inspect, false)
+            .declare_builtin("source".to_string(), source, false)
+            .declare_builtin("parseInt".to_string(), parse_int, false)
+            .declare_builtin("parseFloat".to_string(), parse_float, false)
+            .declare_builtin("assert".to_string(), assert, false)
+            .declare_builtin("assertEqual".to_string(), assert_equal, false)
+            .declare_builtin("assertEq".to_string(), assert_equal, false)
+            .declare_namespace(
+                "io",
+                vec![
+                    ("printLn", with_interpreter(print_ln), true),
+                    ("readLn", with_interpreter(read_ln), true),
+                    ("tablePrint", with_interpreter(table_print), true),
+                    ("confirm", with_interpreter(confirm), true),
+                    ("select", with_interpreter(select), true),
+                    ("password", with_interpreter(password), true),
+                    ("stdinLines", simple(stdin_lines), true),
+                ],
+                flat,
+            )
+            .declare_namespace(
+                "list",
+                vec![
+                    ("zip", simple(zip), false),
+                    ("enumerate", simple(enumerate), false),
+                    ("flatten", simple(flatten), false),
+                    ("unique", simple(unique), false),
+                    ("sort", simple(sort), false),
+                    ("sortBy", with_interpreter(sort_by), false),
+                    ("reverse", simple(reverse), false),
+                    ("newList", simple(new_list), false),
+                    ("push", simple(push), false),
+                    ("extend", simple(extend), false),
+                    ("pop", simple(pop), false),
+                    ("insert", simple(insert), false),
+                    ("removeAt", simple(remove_at), false),
+                    ("clear", simple(clear), false),
+                ],
+                flat,
+            )
+            .declare_namespace(
+                "math",
+                vec![
+                    ("sum", simple(sum), false),
+                    ("product", simple(product), false),
+                    ("minOf", simple(min_of), false),
+                    ("maxOf", simple(max_of), false),
+                    ("average", simple(average), false),
+                    ("mod", simple(modulo), false),
+                    ("clamp", simple(clamp), false),
+                    ("lerp", simple(lerp), false),
+                    ("sign", simple(sign), false),
+                    ("absDiff", simple(abs_diff), false),
+                    ("abs", simple(abs), false),
+                    ("min", simple(min), false),
+                    ("max", simple(max), false),
+                    ("sqrt", simple(sqrt), false),
+                    ("floor", simple(floor), false),
+                    ("ceil", simple(ceil), false),
+                    ("round", simple(round), false),
+                    ("pow", simple(pow), false),
+                    ("random", with_interpreter(random), false),
+                    ("randomInt", with_interpreter(random_int), false),
+                    ("seedRandom", with_interpreter(seed_random), false),
+                ],
+                flat,
+            )
+            .declare_constants(
+                "math",
+                vec![
+                    ("PI", Value::Float(std::f64::consts::PI)),
+                    ("E", Value::Float(std::f64::consts::E)),
+                ],
+            )
+            .declare_namespace(
+                "dict",
+                vec![
+                    ("merge", simple(merge), false),
+                    ("getOr", simple(get_or), false),
+                    ("entries", simple(entries), false),
+                    ("fromEntries", simple(from_entries), false),
+                ],
+                flat,
+            )
+            .declare_namespace(
+                "str",
+                vec![
+                    ("padLeft", simple(pad_left), false),
+                    ("padRight", simple(pad_right), false),
+                    ("center", simple(center), false),
+                    ("repeat", simple(repeat), false),
+                    ("scan", simple(scan), false),
+                    ("naturalCompare", simple(natural_compare), false),
+                    ("compareIgnoreCase", simple(compare_ignore_case), false),
+                    ("format", simple(format), false),
+                ],
+                flat,
+            )
+            .declare_namespace(
+                "time",
+                vec![
+                    ("sleep", with_interpreter(sleep), true),
+                    ("timeMillis", with_interpreter(time_millis), false),
+                    ("clock", with_interpreter(clock), false),
+                    ("benchmark", with_interpreter(benchmark), true),
+                    ("setTimeout", with_interpreter(set_timeout), true),
+                    ("setInterval", with_interpreter(set_interval), true),
+                    ("clearTimer", with_interpreter(clear_timer), true),
+                    ("runEventLoop", with_interpreter(run_event_loop), true),
+                ],
+                flat,
+            )
+            .declare_namespace("sys", sys_builtins(), flat)
+            .declare_namespace(
+                "fs",
+                vec![
+                    ("tempFile", with_interpreter(temp_file), true),
+                    ("tempDir", with_interpreter(temp_dir), true),
+                    ("withTempDir", with_interpreter(with_temp_dir), true),
+                    ("fileSize", simple(file_size), true),
+                    ("fileModifiedTime", simple(file_modified_time), true),
+                    ("isDir", simple(is_dir), true),
+                    ("isFile", simple(is_file), true),
+                    ("chmod", simple(chmod), true),
+                    ("copyFile", simple(copy_file), true),
+                    ("renameFile", simple(rename_file), true),
+                    ("readLines", simple(read_lines), true),
+                    ("openLines", simple(open_lines), true),
+                ],
+                flat,
+            )
+            .declare_namespace("data", data_builtins(), flat)
+            .declare_namespace(
+                "hash",
+                vec![
+                    ("crc32", simple(crc32), false),
+                    ("adler32", simple(adler32), false),
+                    ("fileSha256", simple(file_sha256), true),
+                ],
+                flat,
+            );
+        env
+    }
 }
 
+// running other programs can be left out of olang builds which embed it
+fn sys_builtins() -> Vec<(&'static str, BuiltinFunction, bool)> {
+    #[allow(unused_mut)]
+    let mut members = vec![
+        ("memoryStats", with_interpreter(memory_stats), false),
+        ("args", with_interpreter(args), false),
+        ("exit", simple(exit), false),
+        ("getEnv", with_interpreter(get_env), true),
+        ("dotenvLoad", with_interpreter(dotenv_load), true),
+        ("onSignal", with_interpreter(on_signal), true),
+        ("weakRef", simple(weak_ref), false),
+        ("deref", simple(deref), false),
+        ("freeze", simple(freeze), false),
+        ("isFrozen", simple(is_frozen), false),
+        ("olangVersion", simple(olang_version), false),
+        ("hasFeature", simple(has_feature), false),
+        ("platform", simple(platform), false),
+    ];
+    #[cfg(feature = "exec")]
+    members.push(("exec", with_interpreter(exec), true));
+    members
+}
+
+// the parsers of the optional formats are only there when olang is built
+// with their feature
+fn data_builtins() -> Vec<(&'static str, BuiltinFunction, bool)> {
+    #[allow(unused_mut)]
+    let mut members = vec![
+        ("iniParse", simple(ini_parse), false),
+        ("jsonParse", simple(json_parse), false),
+        ("jsonStringify", simple(json_stringify), false),
+        ("msgpackEncode", simple(msgpack_encode), false),
+        ("msgpackDecode", simple(msgpack_decode), false),
+        ("cborEncode", simple(cbor_encode), false),
+        ("cborDecode", simple(cbor_decode), false),
+    ];
+    #[cfg(feature = "xml")]
+    members.push(("xmlParse", simple(crate::markup::xml_parse), false));
+    #[cfg(feature = "html")]
+    members.push(("htmlQuery", simple(crate::markup::html_query), false));
+    members
+}
+
+fn simple(function: fn(Vec<Value>) -> Result<Value, ControlFlowValue>) -> BuiltinFunction {
+    BuiltinFunction::Simple(Rc::new(function))
+}
+
+fn with_interpreter(
+    function: fn(&mut Interpreter, Vec<Value>) -> Result<Value, ControlFlowValue>,
+) -> BuiltinFunction {
+    BuiltinFunction::Interpreter(Rc::new(function))
+}
+
+fn builtin(name: String, function: BuiltinFunction, side_effects: bool) -> Value {
+    Value::Function(Function::Builtin(Builtin {
+        name,
+        function,
+        side_effects,
+    }))
+}
+
+// the builtins of the namespaces are only declared inside of them, flat mode
+// is for programs written before there were namespaces
 impl Default for Environment {
     fn default() -> Self {
-        let mut env = Environment::new();
-        env.declare_builtin("printLn".to_string(), print_ln)
-            .declare_builtin("readLn".to_string(), read_ln)
-            .declare_builtin("toString".to_string(), to_string)
-            .declare_builtin("len".to_string(), len)
-            .declare_builtin("sleep".to_string(), sleep);
-        env
+        Environment::with_builtins(false)
     }
 }
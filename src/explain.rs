@@ -0,0 +1,499 @@
+use phf::phf_map;
+
+static EXPLANATIONS: phf::Map<&'static str, &'static str> = phf_map! {
+    "OL0001" => r#"The lexer found a character that does not start any token.
+
+Erroneous code example:
+
+    var price = 10 $ 2
+
+Only letters, digits, string quotes, comments and the operators of the
+language may appear in source code. Remove the character or replace it with
+the operator you meant:
+
+    var price = 10 * 2
+"#,
+    "OL0002" => r#"The lexer expected a digit while reading an int literal.
+
+This error is raised when an int literal contains something that can not be
+read as a base 10 digit. Make sure int literals only consist of the digits
+0 to 9:
+
+    var answer = 42
+"#,
+    "OL0003" => r#"The parser expected a specific token but found another one.
+
+Erroneous code example:
+
+    var = 10
+
+A variable declaration has to be followed by the name of the variable. The
+error message names the expected token and the one that was found instead:
+
+    var count = 10
+"#,
+    "OL0004" => r#"The parser found a token that can not appear at this position.
+
+Erroneous code example:
+
+    var result = 10 +
+
+The binary `+` operator is missing its right hand side, so the parser runs
+into the end of the file. Complete the expression:
+
+    var result = 10 + 5
+"#,
+    "OL0005" => r#"The `continue` keyword was used outside of a loop.
+
+Erroneous code example:
+
+    if true {
+        continue
+    }
+
+`continue` skips to the next iteration of the enclosing `while`, `for` or
+`loop` and has no meaning anywhere else. Move it inside a loop body:
+
+    for var i = 0 i < 10 i++ {
+        if i < 5 {
+            continue
+        }
+    }
+"#,
+    "OL0006" => r#"The `break` keyword was used outside of a loop.
+
+Erroneous code example:
+
+    if true {
+        break
+    }
+
+`break` exits the enclosing `while`, `for` or `loop` and has no meaning
+anywhere else. Move it inside a loop body:
+
+    loop {
+        break
+    }
+"#,
+    "OL0007" => r#"A function was called with the wrong number of arguments.
+
+Erroneous code example:
+
+    var add = fun(a b) { a + b }
+    add(1)
+
+The number of arguments in a call has to match the number of parameters of
+the function:
+
+    add(1 2)
+"#,
+    "OL0008" => r#"A return was nested inside another return.
+
+This exception is reserved for the interpreter and can not be raised by any
+program today.
+"#,
+    "OL0009" => r#"An identifier was used before it was declared.
+
+Erroneous code example:
+
+    count = 10
+
+Variables have to be declared with `var` before they can be read or
+assigned to:
+
+    var count = 0
+    count = 10
+
+Most builtins are only declared in their namespace, `sum` is called as
+`math.sum`. Programs using the names without the namespace run with
+`--flat-builtins`.
+"#,
+    "OL0010" => r#"A value that is not a function was called.
+
+Erroneous code example:
+
+    var name = "olang"
+    name()
+
+Only function values can be called. Check that the identifier refers to the
+function you meant to call.
+"#,
+    "OL0011" => r#"A value had the wrong type for the operation.
+
+Erroneous code example:
+
+    var total = 10 + "5"
+
+Operators and builtin functions only accept values of certain types. Convert
+the value first or use an operation that supports its type:
+
+    var total = 10 + 5
+"#,
+    "OL0012" => r#"An exponentiation overflowed or used a negative operand.
+
+Erroneous code example:
+
+    var power = 2**-2
+
+Exponentiation only works on non-negative ints and the result has to fit
+into a 64 bit int. Keep the base and the exponent positive and small enough:
+
+    var power = 2**2
+"#,
+    "OL0013" => r#"A list was indexed outside of its bounds.
+
+Erroneous code example:
+
+    var list = [1 2 3]
+    list !! 3
+
+Lists are indexed from 0, so the last valid index is one less than the
+length of the list:
+
+    list !! (len(list) - 1)
+"#,
+    "OL0014" => r#"A builtin function raised a custom exception.
+
+The message of the exception describes what went wrong, for example an IO
+error raised by `readLn()` when standard input is closed.
+"#,
+    "OL0015" => r#"A builtin with side effects was called in expression only evaluation.
+
+Erroneous code example:
+
+    olang --expr 'printLn("hello")'
+
+Expression only evaluation is meant for configuration and rules, so builtins
+that perform IO or block the interpreter like `printLn`, `readLn` and `sleep`
+are forbidden. Return the value instead of printing it:
+
+    olang --expr '"hello"'
+"#,
+    "OL0016" => r#"The evaluation was interrupted.
+
+This exception is raised at the next evaluated expression after the user
+pressed Ctrl-C. Pressing Ctrl-C a second time before the interpreter reaches
+that point terminates the process immediately.
+"#,
+    "OL0017" => r#"A string literal is missing its closing quote.
+
+Erroneous code example:
+
+    printLn("Hello)
+
+The error points at the opening quote of the string. Without the closing
+quote the rest of the file would be read as part of the string. Add the
+missing quote:
+
+    printLn("Hello")
+"#,
+    "OL0018" => r#"A block comment is missing its closing `]#`.
+
+Erroneous code example:
+
+    #[ the comment starts here
+    printLn("Hello")
+
+The error points at the `#[` that opens the comment. Without the closing
+`]#` the rest of the file would be ignored. Close the comment:
+
+    #[ the comment starts here ]#
+    printLn("Hello")
+"#,
+    "OL0019" => r#"A map was indexed with a key it does not contain.
+
+Erroneous code example:
+
+    var ages = {"alice": 31}
+    ages !! "bob"
+
+Reading a key that was never set raises this exception. Set the key before
+reading it:
+
+    ages !! "bob" = 27
+    ages !! "bob"
+"#,
+    "OL0020" => r#"A builtin that needs at least one element was given an empty list.
+
+Erroneous code example:
+
+    var scores = []
+    math.average(scores)
+
+`minOf`, `maxOf` and `average` have no meaningful result for an empty list.
+Check the length of the list first:
+
+    if len(scores) > 0 {
+        average(scores)
+    }
+"#,
+    "OL0021" => r#"A program raised its own exception with `throw`.
+
+Erroneous code example:
+
+    var withdraw = fun(amount) {
+        if amount > 100 {
+            throw "amount is over the limit"
+        }
+        amount
+    }
+    withdraw(500)
+
+`throw` raises an exception carrying any value, which stops the program when
+nothing handles it. The error message shows the thrown value. Make sure the
+condition that leads to the `throw` can not happen, or pass valid input:
+
+    withdraw(50)
+"#,
+    "OL0022" => r#"Functions were nested deeper than the recursion limit.
+
+Erroneous code example:
+
+    var countdown = fun(n) {
+        countdown(n - 1)
+    }
+    countdown(10)
+
+Every call that has not returned yet counts towards the limit, which is 1000
+by default. Usually a recursive function is missing the case where it stops
+calling itself:
+
+    var countdown = fun(n) {
+        if n > 0 {
+            countdown(n - 1)
+        }
+    }
+
+Programs which really need deeper recursion can raise the limit with
+`--max-recursion` or the OLANG_STACK_LIMIT environment variable.
+"#,
+    "OL0023" => r#"The program evaluated more steps than its fuel allows.
+
+Erroneous code example:
+
+    var i = 0
+    while i < 10 {
+        printLn(i)
+    }
+
+Every expression and every loop iteration uses one unit of fuel. Interpreters
+that run untrusted programs set a budget with the OLANG_FUEL environment
+variable or `Interpreter::with_fuel`, so a program that never stops is ended
+instead of hanging. Here `i` is never increased, so the loop runs forever:
+
+    var i = 0
+    while i < 10 {
+        printLn(i)
+        i++
+    }
+"#,
+    "OL0024" => r#"The program ran longer than its timeout.
+
+Erroneous code example:
+
+    var fib = fun(n) {
+        if n < 2 {
+            n
+        } else {
+            fib(n - 1) + fib(n - 2)
+        }
+    }
+    fib(40)
+
+A timeout is set with `--timeout` on the command line or by hosts with
+`Interpreter::eval_with_timeout`, the program is stopped once it runs longer.
+Check that the program doesn't loop forever, use a faster algorithm or raise
+the timeout:
+
+    var fib = fun(n) {
+        var a = 0
+        var b = 1
+        for var i = 0 i < n i++ {
+            var next = a + b
+            a = b
+            b = next
+        }
+        a
+    }
+    fib(40)
+"#,
+    "OL0025" => r#"The two values passed to `assertEqual` were not equal, or the condition
+passed to `assert` was false.
+
+Erroneous code example:
+
+    var count = fun(words) {
+        var counts = {:}
+        for word in words {
+            counts !! word = getOr(counts word 0)
+        }
+        counts
+    }
+    assertEqual(count(["a" "b" "a"]) {"a": 2 "b": 1})
+
+The error message lists every place where the values differ. Elements of lists
+and maps are compared one by one, here `["a"]: 0 != 2` is reported for the key
+"a" of the two maps. Fix the code under test or the expected value:
+
+    var count = fun(words) {
+        var counts = {:}
+        for word in words {
+            counts !! word = getOr(counts word 0) + 1
+        }
+        counts
+    }
+    assertEqual(count(["a" "b" "a"]) {"a": 2 "b": 1})
+
+`assertEq` is another name for `assertEqual`. `assert(condition message)`
+reports its message when the condition is false:
+
+    assert(len(words) > 0 "there are words to count")
+"#,
+    "OL0026" => r#"An int literal is too large to be stored in an int.
+
+Erroneous code example:
+
+    var population = 12345678901234567890
+
+Ints are 64 bits wide and range from -9223372036854775808 to
+9223372036854775807, the error points at the literal outside of that range.
+Use a float literal for numbers that don't have to be exact:
+
+    var population = 12345678901234567890.0
+"#,
+    "OL0027" => r#"An int was divided by zero.
+
+Erroneous code example:
+
+    var perPerson = fun(total people) { total / people }
+    perPerson(10 0)
+
+`/`, `%` and `mod` raise this exception when both operands are ints and the
+divisor is 0. Dividing floats by zero gives infinity or NaN instead. Check the
+divisor first:
+
+    var perPerson = fun(total people) {
+        if people == 0 { 0 } else { total / people }
+    }
+"#,
+    "OL0028" => r#"An imported file could not be read or evaluated.
+
+Erroneous code example:
+
+    # shapes.olang
+    var geometry = import geometry
+
+    # geometry.olang
+    var shapes = import shapes
+
+The message names the file and the reason: the file doesn't exist, it has a
+syntax error, its evaluation raised an exception or, as here, the files import
+each other. Paths are relative to the importing file. Move what both files need
+into a third file which imports neither of them:
+
+    # shapes.olang
+    var units = import units
+
+    # geometry.olang
+    var units = import units
+"#,
+    "OL0029" => r#"The program called `exit`.
+
+Example:
+
+    if len(sys.args()) == 0 {
+        printLn("usage: greet NAME")
+        exit(64)
+    }
+
+`exit` stops the program wherever it is called, the olang binary then exits
+with the given status, 0 when none is given. Hosts that embed the interpreter
+receive this as an error carrying the status, so they can decide what to do.
+"#,
+    "OL0030" => r#"Reading or writing a file or the terminal failed.
+
+Erroneous code example:
+
+    var size = fs.fileSize("report.csv")
+
+The message names the file and the reason the operating system gave, like a
+file that doesn't exist or missing permissions. Check the path first when the
+file may be missing:
+
+    var size = if fs.isFile("report.csv") { fs.fileSize("report.csv") } else { 0 }
+"#,
+    "OL0031" => r#"A frozen list was changed.
+
+Erroneous code example:
+
+    var config = sys.freeze({"hosts": ["a" "b"]})
+    list.push(config.hosts "c")
+
+`freeze` returns a copy of a value in which no list can be changed, so it can
+be given to code which shouldn't modify it. Build a new list instead, `+`
+never changes the list it is applied to:
+
+    var hosts = config.hosts + ["c"]
+"#,
+    "OL0033" => r#"Expressions are nested too deeply.
+
+Erroneous code example:
+
+    # generated with 5001 opening and closing parentheses
+    var x = ((((((((((((((((((((((((((((((( ... 1 ... )))))))))))))))))))))))))))))))
+
+The parser gives up after 5000 levels of parentheses, blocks, lists,
+arguments and the like, deeper input would overflow the stack of the program
+holding it. Every operator in a chain like `1 + 2 + 3` counts as a level too,
+since it is evaluated as `((1 + 2) + 3)`. Code this deep is usually generated,
+split it up with variables or functions:
+
+    var inner = (((1)))
+    var x = ((inner))
+"#,
+    "OL0034" => r#"A string or list would be too large to be stored.
+
+Erroneous code example:
+
+    var line = "-" * 9223372036854775807
+
+Repeating a string, padding it or creating a list with a length this large
+needs more memory than there is. The length is usually computed wrong, check
+it before using it:
+
+    var line = "-" * math.min(width 200)
+"#,
+    "OL0035" => r#"The result of an int operation doesn't fit into 64 bits.
+
+Erroneous code example:
+
+    var smallest = 0 - 9223372036854775807 - 1
+    printLn(-smallest)
+
+Ints go from -9223372036854775808 to 9223372036854775807, the negation of the
+smallest int is one larger than the largest. `+`, `-`, `*`, `/` and `%` raise
+the same way when their result is out of this range instead of wrapping
+around. Use a float when the numbers get this large:
+
+    printLn(-(smallest * 1.0))
+"#,
+    "OL0032" => r#"The script header asks for something this interpreter doesn't have.
+
+Erroneous code example:
+
+    #! olang: version >= 9.0, features = [exec]
+    var result = sys.exec(["make"])
+
+A `#! olang:` comment before the code of a script lists what the script needs,
+separated by commas: a version of olang compared with `>=`, `<=`, `>`, `<` or
+`==`, and the optional features it uses. The script doesn't run when the
+header isn't met, install a newer olang or one built with the features, or fix
+the header when it is written wrong. `olang doctor` shows the version and the
+features of the interpreter.
+"#,
+};
+
+/// Returns the extended description of an error code, ignoring its case
+pub fn explain(code: &str) -> Option<&'static str> {
+    EXPLANATIONS.get(code.to_uppercase().as_str()).copied()
+}
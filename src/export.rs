@@ -0,0 +1,49 @@
+use crate::{
+    config::Config,
+    interpreter::{ErrorReport, Interpreter},
+};
+use anyhow::{anyhow, Result};
+use strum::{Display, EnumString, VariantNames};
+
+#[derive(Debug, Clone, Copy, Display, EnumString, VariantNames)]
+#[strum(serialize_all = "lowercase")]
+pub enum ExportFormat {
+    Json,
+    Toml,
+}
+
+// evaluate the source in a sandbox and serialize either all of its top level
+// variables or only the variable named by `value`
+pub fn export(
+    source: &str,
+    value: Option<&str>,
+    format: ExportFormat,
+    config: &Config,
+) -> Result<String> {
+    let mut interpreter = Interpreter::with_config(config);
+    interpreter
+        .sandbox()
+        .eval_str(source)
+        .map_err(|err| ErrorReport::from(err).with_source(source))?;
+
+    let output = match value {
+        Some(id) => {
+            let value = interpreter
+                .get_variable(id)
+                .ok_or_else(|| anyhow!("the program does not declare \"{}\"", id))?;
+            match format {
+                ExportFormat::Json => serde_json::to_string_pretty(&value)?,
+                ExportFormat::Toml => toml::to_string_pretty(&value)?,
+            }
+        }
+        None => {
+            let variables = interpreter.variables();
+            match format {
+                ExportFormat::Json => serde_json::to_string_pretty(&variables)?,
+                ExportFormat::Toml => toml::to_string_pretty(&variables)?,
+            }
+        }
+    };
+
+    Ok(output)
+}
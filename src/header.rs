@@ -0,0 +1,166 @@
+use crate::lexer::{Location, Region};
+use std::cmp::Ordering;
+use thiserror::Error;
+
+// the optional parts of olang and whether this build has them
+pub(crate) const FEATURES: &[(&str, bool)] = &[
+    ("xml", cfg!(feature = "xml")),
+    ("html", cfg!(feature = "html")),
+    ("exec", cfg!(feature = "exec")),
+];
+
+// a comment among the first lines of a script which states what the script
+// needs from the interpreter running it, so it fails before it starts instead
+// of running into an exception halfway through
+//
+//     #!/usr/bin/env olang
+//     #! olang: version >= 0.3, features = [xml exec]
+#[derive(Error, Debug)]
+pub enum HeaderError {
+    #[error("{region} invalid script header: {message}")]
+    Invalid { region: Region, message: String },
+    #[error("{region} {message}")]
+    Unsatisfied { region: Region, message: String },
+}
+
+impl HeaderError {
+    pub fn region(&self) -> Region {
+        match self {
+            HeaderError::Invalid { region, .. } | HeaderError::Unsatisfied { region, .. } => {
+                region.clone()
+            }
+        }
+    }
+}
+
+const PREFIX: &str = "olang:";
+
+// a version like 0.3 or 1.2.0, missing parts are 0
+fn version(text: &str) -> Option<[u64; 3]> {
+    let mut version = [0; 3];
+    let mut parts = text.split('.');
+    for part in version.iter_mut() {
+        if let Some(text) = parts.next() {
+            *part = text.parse().ok()?;
+        }
+    }
+    parts.next().is_none().then_some(version)
+}
+
+// the requirements are separated by commas, except for the ones inside of the
+// brackets of the features
+fn requirements(text: &str) -> Vec<&str> {
+    let mut requirements = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '[' => depth += 1,
+            ']' => depth -= 1,
+            ',' if depth == 0 => {
+                requirements.push(text[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    requirements.push(text[start..].trim());
+    requirements.retain(|requirement| !requirement.is_empty());
+    requirements
+}
+
+fn check_requirement(requirement: &str) -> Result<Option<String>, String> {
+    if let Some(rest) = requirement.strip_prefix("version") {
+        let rest = rest.trim_start();
+        let operators: [(&str, &[Ordering]); 6] = [
+            (">=", &[Ordering::Greater, Ordering::Equal]),
+            ("<=", &[Ordering::Less, Ordering::Equal]),
+            ("==", &[Ordering::Equal]),
+            (">", &[Ordering::Greater]),
+            ("<", &[Ordering::Less]),
+            ("=", &[Ordering::Equal]),
+        ];
+        let (operator, allowed, text) = operators
+            .iter()
+            .find_map(|(operator, allowed)| {
+                Some((*operator, *allowed, rest.strip_prefix(operator)?.trim()))
+            })
+            .ok_or_else(|| format!("`{}` needs one of >=, <=, >, <, ==", requirement))?;
+        let required = version(text)
+            .ok_or_else(|| format!("`{}` is not a version like 0.3 or 1.2.0", text))?;
+        let current = env!("CARGO_PKG_VERSION");
+        let ordering = version(current).unwrap_or_default().cmp(&required);
+        return Ok((!allowed.contains(&ordering)).then(|| {
+            format!(
+                "the script needs olang {} {}, this is olang {}",
+                operator, text, current
+            )
+        }));
+    }
+
+    if let Some(rest) = requirement.strip_prefix("features") {
+        let list = rest
+            .trim_start()
+            .strip_prefix('=')
+            .map(str::trim)
+            .and_then(|rest| rest.strip_prefix('['))
+            .and_then(|rest| rest.strip_suffix(']'))
+            .ok_or_else(|| format!("`{}` has to look like features = [xml]", requirement))?;
+        let missing: Vec<_> = list
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|feature| !feature.is_empty())
+            .filter(|feature| !FEATURES.contains(&(feature, true)))
+            .collect();
+        return Ok((!missing.is_empty()).then(|| {
+            format!(
+                "the script needs the features {} which this build of olang doesn't have",
+                missing.join(", ")
+            )
+        }));
+    }
+
+    Err(format!(
+        "unknown requirement `{}`, use version or features",
+        requirement
+    ))
+}
+
+// only the comments at the start of the source are looked at, the header has
+// to come before the code
+pub fn check(source: &str) -> Result<(), HeaderError> {
+    for (row, line) in source.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        if !trimmed.starts_with('#') || trimmed.starts_with("#[") {
+            break;
+        }
+        let Some(requirements) = trimmed
+            .strip_prefix("#!")
+            .map(str::trim_start)
+            .and_then(|rest| rest.strip_prefix(PREFIX))
+        else {
+            continue;
+        };
+
+        let region = Region {
+            start: Location {
+                row: row + 1,
+                col: 1,
+            },
+            end: Location {
+                row: row + 1,
+                col: line.chars().count() + 1,
+            },
+        };
+        for requirement in self::requirements(requirements) {
+            match check_requirement(requirement) {
+                Ok(None) => {}
+                Ok(Some(message)) => return Err(HeaderError::Unsatisfied { region, message }),
+                Err(message) => return Err(HeaderError::Invalid { region, message }),
+            }
+        }
+    }
+    Ok(())
+}
@@ -1,36 +1,262 @@
 use crate::{
+    builtin::{io_error, LineReader, StdinLines},
+    config::Config,
     environment::Environment,
-    lexer::LexerError,
+    io::{Io, StdIo},
+    lexer::{LexerError, Region},
+    memory::MemoryStats,
     parser::{
         AssignmentOperator, BinaryOperationOperator, Block, Expression, ExpressionValue, IfClause,
-        Parser, ParserError, UpdateOperator,
+        Parser, ParserError, Program, Slot, UnaryOperator, UpdateOperator,
     },
-    value::{ControlFlowValue, Exception, Function, Value},
+    random::Random,
+    resolver,
+    snapshot::{self, SnapshotError},
+    value::{BuiltinFunction, Closure, ControlFlowValue, Exception, Function, Value},
 };
+use serde::Serialize;
+use signal_hook::SigId;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    ffi::c_int,
+    fs, io, iter, mem,
+    num::NonZeroU64,
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+use tempfile::{TempDir, TempPath};
 use thiserror::Error;
 
 pub struct Interpreter {
     environment: Environment,
+    side_effects_allowed: bool,
+    expression_side_effects: bool,
+    interrupted: Arc<AtomicBool>,
+    io: Box<dyn Io>,
+    call_depth: usize,
+    max_recursion: usize,
+    // the number of steps left, every expression and loop iteration is a step
+    fuel: Option<u64>,
+    deadline: Option<Instant>,
+    // steps until the clock is read again
+    steps_until_deadline_check: u32,
+    clock: Clock,
+    modules: Modules,
+    // the command line arguments of the script
+    args: Vec<String>,
+    // environment variables loaded by the program, the process environment
+    // isn't changed so other interpreters don't see them
+    env_vars: HashMap<String, String>,
+    // temporary files and directories created by the program, they are
+    // deleted when the interpreter is dropped
+    temp_files: Vec<TempPath>,
+    temp_dirs: Vec<TempDir>,
+    signal_handlers: Vec<SignalHandler>,
+    timers: Timers,
+    random: Random,
+    // results of pure functions by their id and arguments, only used when
+    // memoize is set, see purity
+    memoize: bool,
+    memo: HashMap<(NonZeroU64, String), Value>,
+    // results of loop invariant calls by their id, one map for every loop
+    // which is running, the innermost last
+    invariants: Vec<HashMap<NonZeroU64, Value>>,
+}
+
+// the items of a for loop, see eval_for_in
+type Items = Box<dyn Iterator<Item = Result<Value, ControlFlowValue>>>;
+
+// the functions of setTimeout and setInterval, they are called by runEventLoop
+#[derive(Default)]
+struct Timers {
+    next_id: i64,
+    pending: Vec<Timer>,
+}
+
+struct Timer {
+    id: i64,
+    // the time of the clock of the interpreter when the function is called next
+    due: Duration,
+    // intervals are scheduled again after every call, timeouts are removed
+    interval: Option<Duration>,
+    function: Value,
+}
+
+// a function registered with onSignal, the signal only sets the flag and the
+// function is called at the next step, where the evaluation can be resumed
+struct SignalHandler {
+    name: String,
+    id: SigId,
+    pending: Arc<AtomicBool>,
+    function: Value,
+}
+
+impl Drop for SignalHandler {
+    fn drop(&mut self) {
+        signal_hook::low_level::unregister(self.id);
+    }
+}
+
+// every imported file is evaluated once, importing it again gives the same
+// namespace until the file is changed
+#[derive(Default)]
+struct Modules {
+    cache: HashMap<PathBuf, Module>,
+    // the files being evaluated, the innermost last. imports are relative to it
+    importing: Vec<PathBuf>,
+    // every file the programs imported, also the ones which failed to evaluate
+    files: BTreeSet<PathBuf>,
+}
+
+// a file is evaluated again when its modification time differs from the one it
+// had when it was evaluated, files whose time can't be read are never reused
+#[derive(Clone)]
+pub(crate) struct Module {
+    pub(crate) modified: Option<SystemTime>,
+    pub(crate) namespace: Value,
+}
+
+fn modified(file: &Path) -> Option<SystemTime> {
+    fs::metadata(file)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+// the time seen by the builtins, deterministic interpreters use a virtual clock
+// which starts at 0 and only moves when the program sleeps
+enum Clock {
+    Real(Instant),
+    Virtual(Duration),
+}
+
+impl Clock {
+    fn new(deterministic: bool) -> Self {
+        if deterministic {
+            Clock::Virtual(Duration::ZERO)
+        } else {
+            Clock::Real(Instant::now())
+        }
+    }
+}
+
+// deterministic interpreters draw the same numbers on every run
+fn random_generator(deterministic: bool) -> Random {
+    if deterministic {
+        Random::from_seed(0)
+    } else {
+        Random::from_time()
+    }
+}
+
+// reading the clock on every step would slow down the evaluation noticeably
+const DEADLINE_CHECK_INTERVAL: u32 = 1024;
+
+// every nested call takes a few kilobytes of the native stack, about 20KB in
+// debug builds, so 1000 calls need about 20MB
+pub const DEFAULT_MAX_RECURSION: usize = 1000;
+
+// the parser, the passes over the tree and the interpreter recurse for every
+// nested expression and call. they continue on a newly allocated stack segment
+// once less than the red zone is left, so how deep they can go does not depend
+// on the stack size of the thread running them
+const STACK_RED_ZONE: usize = 128 * 1024;
+const STACK_SEGMENT: usize = 8 * 1024 * 1024;
+
+pub(crate) fn grow_stack<R>(f: impl FnOnce() -> R) -> R {
+    stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT, f)
 }
 
+pub const EXIT_SYNTAX_ERROR: i32 = 2;
+pub const EXIT_RUNTIME_ERROR: i32 = 3;
+
 #[derive(Error, Debug)]
 pub enum EvalError {
-    #[error("Unhandled exception: {0}")]
-    UnhandledException(Exception),
+    #[error("{}Unhandled exception: {}", location(.1), .0)]
+    UnhandledException(Exception, Option<Region>),
     #[error("\"continue\" keyword used outside of loop")]
     ContinueOutsideLoop,
     #[error("\"break\" keyword used outside of loop")]
     BreakOutsideLoop,
+    #[error("the program ran out of fuel")]
+    FuelExhausted,
+    #[error("the program did not finish in time")]
+    TimedOut,
+    #[error("the program exited with status {0}")]
+    Exit(i32),
     #[error(transparent)]
     Parser(#[from] ParserError),
     #[error(transparent)]
     Lexer(#[from] LexerError),
 }
 
+// runtime errors are prefixed with their region like parser errors are
+fn location(region: &Option<Region>) -> String {
+    match region {
+        Some(region) => format!("{} ", region),
+        None => String::new(),
+    }
+}
+
 impl EvalError {
+    // where in the source the error happened, for an unhandled exception this
+    // is the innermost expression it was raised in
+    pub fn region(&self) -> Option<Region> {
+        match self {
+            Self::UnhandledException(_, region) => region.clone(),
+            Self::Parser(err) => Some(err.region()),
+            Self::Lexer(err) => Some(err.region()),
+            _ => None,
+        }
+    }
+
+    pub fn is_unexpected_end(&self) -> bool {
+        match self {
+            Self::Parser(err) => err.is_unexpected_end(),
+            _ => false,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::UnhandledException(exception, _) => exception.code(),
+            Self::ContinueOutsideLoop => "OL0005",
+            Self::BreakOutsideLoop => "OL0006",
+            Self::FuelExhausted => "OL0023",
+            Self::TimedOut => "OL0024",
+            Self::Exit(_) => "OL0029",
+            Self::Parser(err) => err.code(),
+            Self::Lexer(err) => err.code(),
+        }
+    }
+
+    // the status the olang binary exits with, programs that couldn't be parsed
+    // and programs that failed while running can be told apart from errors of
+    // the command line itself, which exit with 1
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::Exit(code) => *code,
+            Self::Parser(_) | Self::Lexer(_) => EXIT_SYNTAX_ERROR,
+            _ => EXIT_RUNTIME_ERROR,
+        }
+    }
+
+    // the value of an exception raised with the throw keyword
+    pub fn thrown_value(&self) -> Option<&Value> {
+        match self {
+            Self::UnhandledException(Exception::Thrown(value), _) => Some(value),
+            _ => None,
+        }
+    }
+
     pub fn unwrap_exception(&self) -> &Exception {
         match self {
-            Self::UnhandledException(v) => v,
+            Self::UnhandledException(v, _) => v,
             _ => {
                 panic!("called `EvalError::unwrap_exception()` on something else than a `UnhandledException` error")
             }
@@ -38,58 +264,415 @@ impl EvalError {
     }
 }
 
-fn plus(left: Value, right: Value) -> Result<Value, ControlFlowValue> {
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// control flow that reaches the top level of a program
+impl From<ControlFlowValue> for EvalError {
+    fn from(value: ControlFlowValue) -> Self {
+        match value {
+            ControlFlowValue::Exception(e, region) => EvalError::UnhandledException(e, region),
+            ControlFlowValue::Continue => EvalError::ContinueOutsideLoop,
+            ControlFlowValue::Break => EvalError::BreakOutsideLoop,
+            ControlFlowValue::FuelExhausted => EvalError::FuelExhausted,
+            ControlFlowValue::TimedOut => EvalError::TimedOut,
+            ControlFlowValue::Exit(code) => EvalError::Exit(code),
+        }
+    }
+}
+
+// olang values are bound to the thread of their interpreter, so an EvalError
+// can't be sent to another thread or wrapped in an anyhow::Error. this keeps
+// everything that is reported to the user
+#[derive(Error, Debug, Serialize)]
+#[error("{message}")]
+pub struct ErrorReport {
+    pub code: &'static str,
+    pub message: String,
+    pub region: Option<Region>,
+    // the source line the region starts on, shown below the message
+    #[serde(skip)]
+    pub line: Option<String>,
+    #[serde(skip)]
+    pub exit_code: i32,
+}
+
+impl ErrorReport {
+    // keep the line the error points at, so it can still be shown once the
+    // source is gone
+    pub fn with_source(mut self, source: &str) -> Self {
+        self.line = self
+            .region
+            .as_ref()
+            .and_then(|region| source.lines().nth(region.start.row.checked_sub(1)?))
+            .map(str::to_string);
+        self
+    }
+}
+
+impl From<EvalError> for ErrorReport {
+    fn from(err: EvalError) -> Self {
+        ErrorReport {
+            code: err.code(),
+            message: err.to_string(),
+            region: err.region(),
+            line: None,
+            exit_code: err.exit_code(),
+        }
+    }
+}
+
+// the operands of an arithmetic operation, ints are promoted to floats when
+// they are mixed with a float
+enum Numbers {
+    Ints(i64, i64),
+    Floats(f64, f64),
+}
+
+fn numbers(left: &Value, right: &Value) -> Result<Numbers, ControlFlowValue> {
+    Ok(match (left, right) {
+        (Value::Int(left), Value::Int(right)) => Numbers::Ints(*left, *right),
+        _ => Numbers::Floats(left.into_float()?, right.into_float()?),
+    })
+}
+
+// the result of int arithmetic, a result that doesn't fit in 64 bits raises
+// instead of wrapping around
+fn checked_int(result: Option<i64>) -> Result<Value, ControlFlowValue> {
+    result.map(Value::Int).ok_or(ControlFlowValue::Exception(
+        Exception::IntegerOverflowed,
+        None,
+    ))
+}
+
+pub(crate) fn plus(left: Value, right: Value) -> Result<Value, ControlFlowValue> {
     Ok(match left {
-        Value::Int(left) => Value::Int(left + right.into_int()?),
+        Value::Int(_) | Value::Float(_) => match numbers(&left, &right)? {
+            Numbers::Ints(left, right) => checked_int(left.checked_add(right))?,
+            Numbers::Floats(left, right) => Value::Float(left + right),
+        },
         Value::String(left) => Value::String(left + right.into_str()?),
-        Value::List(mut left) => {
-            left.push(right);
-            Value::List(left)
+        // a list is concatenated with another list, any other value is appended
+        Value::List(left) => {
+            let mut list = left.to_vec();
+            match right {
+                Value::List(right) => list.extend(right.borrow().iter().cloned()),
+                right => list.push(right),
+            }
+            Value::list(list)
+        }
+        _ => {
+            return Err(ControlFlowValue::Exception(
+                Exception::ValueIsWrongType,
+                None,
+            ))
         }
-        _ => return Err(ControlFlowValue::Exception(Exception::ValueIsWrongType)),
     })
 }
+
+// the result of a binary operation on evaluated operands, also used by the
+// optimizer to fold constants
+pub(crate) fn binary_operation(
+    operator: &BinaryOperationOperator,
+    left: Value,
+    right: Value,
+) -> Result<Value, ControlFlowValue> {
+    // FIXME: utilize the Eq trait instead of this garbage
+    Ok(match operator {
+        BinaryOperationOperator::Plus => plus(left, right)?,
+        BinaryOperationOperator::Minus => minus(left, right)?,
+        BinaryOperationOperator::Multiply => multiply(left, right)?,
+        BinaryOperationOperator::Divide => divide(left, right)?,
+        BinaryOperationOperator::Modulus => modulo(left, right)?,
+        BinaryOperationOperator::Exponentiation => exponent(left, right)?,
+        BinaryOperationOperator::IsEqual => Value::Bool(is_equal(left, right)),
+        BinaryOperationOperator::IsNotEqual => Value::Bool(is_not_equal(left, right)),
+        BinaryOperationOperator::IsLessThan => Value::Bool(is_less_than(left, right)?),
+        BinaryOperationOperator::IsLessThanOrEqual => {
+            Value::Bool(is_less_than_or_equal(left, right)?)
+        }
+        BinaryOperationOperator::IsGreaterThan => Value::Bool(is_greater_than(left, right)?),
+        BinaryOperationOperator::IsGreaterThanOrEqual => {
+            Value::Bool(is_greater_than_or_equal(left, right)?)
+        }
+        BinaryOperationOperator::LogicalAnd => Value::Bool(logical_and(left, right)?),
+        BinaryOperationOperator::LogicalOr => Value::Bool(logical_or(left, right)?),
+    })
+}
+
+// values without lists, functions and native values, every copy of them is
+// independent of the others
+fn is_copied(value: &Value) -> bool {
+    match value {
+        Value::List(_) | Value::Function(_) | Value::Native(_) => false,
+        Value::Map(map) => map.values().all(is_copied),
+        _ => true,
+    }
+}
+
+fn contains_function(value: &Value) -> bool {
+    contains_function_in(value, &mut HashSet::new())
+}
+
+// lists can contain themselves, every list is only looked at once
+fn contains_function_in(value: &Value, lists: &mut HashSet<*const ()>) -> bool {
+    match value {
+        Value::Function(_) => true,
+        Value::List(list) => {
+            lists.insert(list.id()) && list.borrow().iter().any(|v| contains_function_in(v, lists))
+        }
+        Value::Map(map) => map.values().any(|v| contains_function_in(v, lists)),
+        _ => false,
+    }
+}
+
+pub(crate) fn unary_operation(
+    operator: &UnaryOperator,
+    value: Value,
+) -> Result<Value, ControlFlowValue> {
+    Ok(match operator {
+        UnaryOperator::Negate => match value {
+            Value::Int(v) => checked_int(v.checked_neg())?,
+            Value::Float(v) => Value::Float(-v),
+            _ => {
+                return Err(ControlFlowValue::Exception(
+                    Exception::ValueIsWrongType,
+                    None,
+                ))
+            }
+        },
+        UnaryOperator::Not => Value::Bool(!*value.into_bool()?),
+    })
+}
+
+fn apply_assignment(
+    operator: &AssignmentOperator,
+    current: Value,
+    value: Value,
+) -> Result<Value, ControlFlowValue> {
+    match operator {
+        AssignmentOperator::Set => Ok(value),
+        // when nothing but the variable and current hold the list nobody can
+        // tell a new list from the old one, appending in place keeps building
+        // a list with += from copying it on every iteration
+        AssignmentOperator::Plus => match current {
+            Value::List(list) if list.references() == 2 && !list.is_frozen() => {
+                let items = match value {
+                    Value::List(right) => right.to_vec(),
+                    value => vec![value],
+                };
+                list.borrow_mut()?.extend(items);
+                Ok(Value::List(list))
+            }
+            current => plus(current, value),
+        },
+        AssignmentOperator::Minus => minus(current, value),
+        AssignmentOperator::Multiply => multiply(current, value),
+        AssignmentOperator::Divide => divide(current, value),
+        AssignmentOperator::Modulo => modulo(current, value),
+    }
+}
+
+// the element of a list or map which an index assignment writes to
+fn element(container: &Value, index: &Value) -> Result<Value, ControlFlowValue> {
+    match container {
+        Value::List(list) => list
+            .borrow()
+            .get(*index.into_int()? as usize)
+            .cloned()
+            .ok_or(ControlFlowValue::Exception(
+                Exception::IndexOutOfRange,
+                None,
+            )),
+        Value::Map(map) => map
+            .get(index.into_str()?)
+            .cloned()
+            .ok_or(ControlFlowValue::Exception(Exception::KeyNotFound, None)),
+        _ => Err(ControlFlowValue::Exception(
+            Exception::ValueIsWrongType,
+            None,
+        )),
+    }
+}
+
+// lists are changed in place so every binding sees the new element, maps are
+// values and the caller stores the changed map again
+fn set_element(container: &mut Value, index: &Value, value: Value) -> Result<(), ControlFlowValue> {
+    match container {
+        Value::List(list) => {
+            *list
+                .borrow_mut()?
+                .get_mut(*index.into_int()? as usize)
+                .ok_or(ControlFlowValue::Exception(
+                    Exception::IndexOutOfRange,
+                    None,
+                ))? = value
+        }
+        Value::Map(map) => {
+            map.insert(index.into_str()?.to_string(), value);
+        }
+        _ => {
+            return Err(ControlFlowValue::Exception(
+                Exception::ValueIsWrongType,
+                None,
+            ))
+        }
+    }
+    Ok(())
+}
+
+// setting a key that is missing from a map inserts it, every other index has to
+// exist. the element is taken out while the rest of the indices are assigned, so
+// a list containing itself is never borrowed twice
+fn assign_index(
+    container: &mut Value,
+    indices: &[Value],
+    operator: &AssignmentOperator,
+    value: Value,
+) -> Result<(), ControlFlowValue> {
+    let (index, rest) = indices
+        .split_first()
+        .expect("index assignment without indices");
+
+    let element = match (&*container, operator) {
+        (Value::Map(_), AssignmentOperator::Set) if rest.is_empty() => value,
+        _ if rest.is_empty() => apply_assignment(operator, element(container, index)?, value)?,
+        _ => {
+            let mut element = element(container, index)?;
+            assign_index(&mut element, rest, operator, value)?;
+            element
+        }
+    };
+    set_element(container, index, element)
+}
+
 fn minus(left: Value, right: Value) -> Result<Value, ControlFlowValue> {
-    Ok(Value::Int(left.into_int()? - right.into_int()?))
+    Ok(match numbers(&left, &right)? {
+        Numbers::Ints(left, right) => checked_int(left.checked_sub(right))?,
+        Numbers::Floats(left, right) => Value::Float(left - right),
+    })
+}
+// strings and lists whose size in bytes is computed from the input of a
+// program are checked first, a size that overflowed or doesn't fit into memory
+// raises an exception instead of aborting the process
+pub(crate) fn fits_in_memory(bytes: Option<usize>) -> Result<(), ControlFlowValue> {
+    let too_large = || ControlFlowValue::Exception(Exception::ResultTooLarge, None);
+    Vec::<u8>::new()
+        .try_reserve_exact(bytes.ok_or_else(too_large)?)
+        .map_err(|_| too_large())
 }
-fn multiply(left: Value, right: Value) -> Result<Value, ControlFlowValue> {
-    Ok(Value::Int(left.into_int()? * right.into_int()?))
+
+pub(crate) fn multiply(left: Value, right: Value) -> Result<Value, ControlFlowValue> {
+    if let Value::String(left) = &left {
+        let count = usize::try_from(*right.into_int()?)
+            .map_err(|_| ControlFlowValue::Exception(Exception::ValueIsWrongType, None))?;
+        fits_in_memory(left.len().checked_mul(count))?;
+        return Ok(Value::String(left.repeat(count)));
+    }
+
+    Ok(match numbers(&left, &right)? {
+        Numbers::Ints(left, right) => checked_int(left.checked_mul(right))?,
+        Numbers::Floats(left, right) => Value::Float(left * right),
+    })
 }
+fn non_zero(divisor: i64) -> Result<i64, ControlFlowValue> {
+    if divisor == 0 {
+        Err(ControlFlowValue::Exception(Exception::DivisionByZero, None))
+    } else {
+        Ok(divisor)
+    }
+}
+// int division rounds towards zero
 fn divide(left: Value, right: Value) -> Result<Value, ControlFlowValue> {
-    Ok(Value::Int(left.into_int()? / right.into_int()?))
+    Ok(match numbers(&left, &right)? {
+        Numbers::Ints(left, right) => checked_int(left.checked_div(non_zero(right)?))?,
+        Numbers::Floats(left, right) => Value::Float(left / right),
+    })
 }
+// the remainder of the division rounding towards zero, it has the sign of the
+// dividend, so -7 % 3 is -1. mod gives the euclidean remainder instead
 fn modulo(left: Value, right: Value) -> Result<Value, ControlFlowValue> {
-    Ok(Value::Int(left.into_int()? % right.into_int()?))
-}
-fn exponent(base: Value, exponent: Value) -> Result<Value, ControlFlowValue> {
-    let base_int = *base.into_int()?;
-    let exponent_int = *exponent.into_int()?;
-    Ok(match (base_int as u64).checked_pow(exponent_int as u32) {
-        Some(v) => Value::Int(v as i64),
-        None => {
+    Ok(match numbers(&left, &right)? {
+        Numbers::Ints(left, right) => checked_int(left.checked_rem(non_zero(right)?))?,
+        Numbers::Floats(left, right) => Value::Float(left % right),
+    })
+}
+// the remainder is never negative, so mod(-7 3) is 2
+pub(crate) fn euclidean_modulo(left: Value, right: Value) -> Result<Value, ControlFlowValue> {
+    Ok(match numbers(&left, &right)? {
+        Numbers::Ints(left, right) => checked_int(left.checked_rem_euclid(non_zero(right)?))?,
+        Numbers::Floats(left, right) => Value::Float(left.rem_euclid(right)),
+    })
+}
+
+// an int to the power of a non-negative int, a result that doesn't fit into an
+// int raises. exponents above u32::MAX only work for the bases whose powers
+// never grow
+pub(crate) fn int_pow(base: i64, exponent: i64) -> Result<i64, ControlFlowValue> {
+    let overflowed = ControlFlowValue::Exception(Exception::ExponentiationOverflowed, None);
+    if exponent < 0 {
+        return Err(overflowed);
+    }
+    match u32::try_from(exponent) {
+        Ok(exponent) => base.checked_pow(exponent),
+        Err(_) => match base {
+            0 | 1 => Some(base),
+            -1 => Some(if exponent % 2 == 0 { 1 } else { -1 }),
+            _ => None,
+        },
+    }
+    .ok_or(overflowed)
+}
+
+// exponentiation of ints only works on non-negative operands
+pub(crate) fn exponent(base: Value, exponent: Value) -> Result<Value, ControlFlowValue> {
+    Ok(match numbers(&base, &exponent)? {
+        Numbers::Ints(base, _) if base < 0 => {
             return Err(ControlFlowValue::Exception(
                 Exception::ExponentiationOverflowed,
+                None,
             ))
         }
+        Numbers::Ints(base, exponent) => Value::Int(int_pow(base, exponent)?),
+        Numbers::Floats(base, exponent) => Value::Float(base.powf(exponent)),
     })
 }
 fn is_equal(left: Value, right: Value) -> bool {
-    left == right
+    // ints and floats with the same value are equal
+    match numbers(&left, &right) {
+        Ok(Numbers::Floats(left, right)) => left == right,
+        _ => left == right,
+    }
 }
 fn is_not_equal(left: Value, right: Value) -> bool {
-    left != right
+    !is_equal(left, right)
 }
-fn is_less_than(left: Value, right: Value) -> Result<bool, ControlFlowValue> {
-    Ok(left.into_int()? < right.into_int()?)
+pub(crate) fn is_less_than(left: Value, right: Value) -> Result<bool, ControlFlowValue> {
+    Ok(match numbers(&left, &right)? {
+        Numbers::Ints(left, right) => left < right,
+        Numbers::Floats(left, right) => left < right,
+    })
 }
 fn is_less_than_or_equal(left: Value, right: Value) -> Result<bool, ControlFlowValue> {
-    Ok(left.into_int()? <= right.into_int()?)
+    Ok(match numbers(&left, &right)? {
+        Numbers::Ints(left, right) => left <= right,
+        Numbers::Floats(left, right) => left <= right,
+    })
 }
-fn is_greater_than(left: Value, right: Value) -> Result<bool, ControlFlowValue> {
-    Ok(left.into_int()? > right.into_int()?)
+pub(crate) fn is_greater_than(left: Value, right: Value) -> Result<bool, ControlFlowValue> {
+    Ok(match numbers(&left, &right)? {
+        Numbers::Ints(left, right) => left > right,
+        Numbers::Floats(left, right) => left > right,
+    })
 }
 fn is_greater_than_or_equal(left: Value, right: Value) -> Result<bool, ControlFlowValue> {
-    Ok(left.into_int()? >= right.into_int()?)
+    Ok(match numbers(&left, &right)? {
+        Numbers::Ints(left, right) => left >= right,
+        Numbers::Floats(left, right) => left >= right,
+    })
 }
 fn logical_and(left: Value, right: Value) -> Result<bool, ControlFlowValue> {
     Ok(*left.into_bool()? && *right.into_bool()?)
@@ -98,37 +681,104 @@ fn logical_or(left: Value, right: Value) -> Result<bool, ControlFlowValue> {
     Ok(*left.into_bool()? || *right.into_bool()?)
 }
 
+fn import_failed(message: String) -> ControlFlowValue {
+    ControlFlowValue::Exception(Exception::ImportFailed(message), None)
+}
+
 impl Interpreter {
+    // evaluate a file into a map of the variables it declares
+    fn eval_import(&mut self, path: &str) -> Result<Value, ControlFlowValue> {
+        if !self.side_effects_allowed {
+            return Err(ControlFlowValue::Exception(
+                Exception::SideEffectForbidden,
+                None,
+            ));
+        }
+
+        let relative = match self.modules.importing.last().and_then(|file| file.parent()) {
+            Some(directory) => directory.join(path),
+            None => PathBuf::from(path),
+        };
+        let file = relative
+            .canonicalize()
+            .map_err(|err| import_failed(format!("{}: {}", relative.display(), err)))?;
+        self.modules.files.insert(file.clone());
+        let modified = modified(&file);
+        match self.modules.cache.get(&file) {
+            Some(module) if module.modified.is_some() && module.modified == modified => {
+                return Ok(module.namespace.clone())
+            }
+            _ => {}
+        }
+        if self.modules.importing.contains(&file) {
+            let cycle: Vec<_> = self
+                .modules
+                .importing
+                .iter()
+                .skip_while(|importing| **importing != file)
+                .chain([&file])
+                .map(|importing| importing.display().to_string())
+                .collect();
+            return Err(import_failed(format!("cycle {}", cycle.join(" -> "))));
+        }
+
+        let source = fs::read_to_string(&file)
+            .map_err(|err| import_failed(format!("{}: {}", file.display(), err)))?;
+        let program = crate::parse(&source)
+            .map_err(|err| import_failed(format!("{}: {}", file.display(), err)))?;
+        let program = resolver::resolve(program);
+
+        self.modules.importing.push(file.clone());
+        self.environment.enter_module();
+        let result = program
+            .ast
+            .iter()
+            .try_for_each(|expression| self.eval_expression(expression).map(drop));
+        let namespace = Value::Map(self.environment.leave_module());
+        self.modules.importing.pop();
+
+        match result {
+            Ok(()) => {}
+            // the limits of the interpreter apply to the whole program
+            Err(
+                err @ (ControlFlowValue::FuelExhausted
+                | ControlFlowValue::TimedOut
+                | ControlFlowValue::Exit(_)
+                | ControlFlowValue::Exception(Exception::Interrupted, _)),
+            ) => return Err(err),
+            // the innermost failed import is the interesting one
+            Err(ControlFlowValue::Exception(Exception::ImportFailed(message), _)) => {
+                return Err(import_failed(message))
+            }
+            Err(err) => {
+                return Err(import_failed(format!(
+                    "{}: {}",
+                    file.display(),
+                    EvalError::from(err)
+                )))
+            }
+        }
+
+        self.modules.cache.insert(
+            file,
+            Module {
+                modified,
+                namespace: namespace.clone(),
+            },
+        );
+        Ok(namespace)
+    }
+
     fn eval_binary(
         &mut self,
-        left_expression: &Box<Expression>,
+        left_expression: &Expression,
         operator: &BinaryOperationOperator,
-        right_expression: &Box<Expression>,
+        right_expression: &Expression,
     ) -> Result<Value, ControlFlowValue> {
         let left = self.eval_expression(left_expression)?;
         let right = self.eval_expression(right_expression)?;
 
-        // FIXME: utilize the Eq trait instead of this garbage
-        Ok(match operator {
-            BinaryOperationOperator::Plus => plus(left, right)?,
-            BinaryOperationOperator::Minus => minus(left, right)?,
-            BinaryOperationOperator::Multiply => multiply(left, right)?,
-            BinaryOperationOperator::Divide => divide(left, right)?,
-            BinaryOperationOperator::Modulus => modulo(left, right)?,
-            BinaryOperationOperator::Exponentiation => exponent(left, right)?,
-            BinaryOperationOperator::IsEqual => Value::Bool(is_equal(left, right)),
-            BinaryOperationOperator::IsNotEqual => Value::Bool(is_not_equal(left, right)),
-            BinaryOperationOperator::IsLessThan => Value::Bool(is_less_than(left, right)?),
-            BinaryOperationOperator::IsLessThanOrEqual => {
-                Value::Bool(is_less_than_or_equal(left, right)?)
-            }
-            BinaryOperationOperator::IsGreaterThan => Value::Bool(is_greater_than(left, right)?),
-            BinaryOperationOperator::IsGreaterThanOrEqual => {
-                Value::Bool(is_greater_than_or_equal(left, right)?)
-            }
-            BinaryOperationOperator::LogicalAnd => Value::Bool(logical_and(left, right)?),
-            BinaryOperationOperator::LogicalOr => Value::Bool(logical_or(left, right)?),
-        })
+        binary_operation(operator, left, right)
     }
 
     fn eval_block(
@@ -137,7 +787,10 @@ impl Interpreter {
         block: &Block,
     ) -> Result<Value, ControlFlowValue> {
         if private_environment {
-            self.environment.push();
+            let outer = self.environment.push();
+            let result = self.eval_block(false, block);
+            self.environment.restore(outer);
+            return result;
         }
 
         let mut result = Value::Null;
@@ -145,72 +798,149 @@ impl Interpreter {
             result = self.eval_expression(expression)?;
         }
 
-        if private_environment {
-            self.environment.pop();
-        }
-
         Ok(result)
     }
 
-    fn eval_identifier(&mut self, id: &str) -> Result<Value, ControlFlowValue> {
-        self.environment.get_or_undeclared(id)
+    fn eval_call(
+        &mut self,
+        id: &str,
+        slot: Option<Slot>,
+        arguments: &[Expression],
+        invariant: Option<NonZeroU64>,
+    ) -> Result<Value, ControlFlowValue> {
+        let function = self.environment.get_resolved(id, slot)?;
+        self.eval_call_value(function, arguments, invariant)
+    }
+
+    fn eval_member_call(
+        &mut self,
+        expression: &Expression,
+        member: &str,
+        arguments: &[Expression],
+        invariant: Option<NonZeroU64>,
+    ) -> Result<Value, ControlFlowValue> {
+        let function = self.eval_member(expression, member)?;
+        self.eval_call_value(function, arguments, invariant)
     }
 
-    fn eval_call(
+    fn eval_call_value(
         &mut self,
-        id: &String,
-        arguments: &Vec<Expression>,
+        function: Value,
+        arguments: &[Expression],
+        invariant: Option<NonZeroU64>,
     ) -> Result<Value, ControlFlowValue> {
-        let function_value = match self.environment.get(id) {
-            Some(v) => v,
-            _ => return Err(ControlFlowValue::Exception(Exception::UndeclaredIdentifier)),
-        };
+        if !matches!(function, Value::Function(_)) {
+            return Err(ControlFlowValue::Exception(
+                Exception::CalledValueIsNotFunction,
+                None,
+            ));
+        }
+
+        let mut evaluated_arguments = vec![];
+        for argument in arguments.iter() {
+            evaluated_arguments.push(self.eval_expression(argument)?)
+        }
+
+        // a call which gives the same result in every iteration is only made
+        // once while the loop runs, as with memo only for copied values
+        let invariant =
+            invariant.filter(|_| self.memoize && evaluated_arguments.iter().all(is_copied));
+        if let Some(result) = invariant.and_then(|id| self.invariants.last()?.get(&id)) {
+            return Ok(result.clone());
+        }
 
-        match function_value {
-            Value::Function(function) => {
-                let mut evaluated_arguments = vec![];
-                for argument in arguments.iter() {
-                    evaluated_arguments.push(self.eval_expression(argument)?)
+        let result = self.call_function(&function, evaluated_arguments);
+        if let (Some(id), Ok(result), Some(results)) =
+            (invariant, &result, self.invariants.last_mut())
+        {
+            if is_copied(result) {
+                results.insert(id, result.clone());
+            }
+        }
+        result
+    }
+
+    // call a function value with arguments that are already evaluated, this is
+    // also used by builtins which take functions as arguments
+    pub fn call_function(
+        &mut self,
+        function: &Value,
+        arguments: Vec<Value>,
+    ) -> Result<Value, ControlFlowValue> {
+        match function {
+            Value::Function(Function::Builtin(builtin)) => {
+                if builtin.side_effects && !self.side_effects_allowed {
+                    return Err(ControlFlowValue::Exception(
+                        Exception::SideEffectForbidden,
+                        None,
+                    ));
+                }
+                match &builtin.function {
+                    BuiltinFunction::Simple(function) => function(arguments),
+                    BuiltinFunction::Interpreter(function) => function(self, arguments),
+                }
+            }
+            Value::Function(Function::Defined(closure)) => {
+                if closure.function.parameters.len() != arguments.len() {
+                    return Err(ControlFlowValue::Exception(
+                        Exception::WrongNumberOfArguments,
+                        None,
+                    ));
                 }
 
-                match function {
-                    Function::Builtin(function) => function(evaluated_arguments),
-                    Function::Defined(defined) => {
-                        self.environment.push();
+                if self.call_depth >= self.max_recursion {
+                    return Err(ControlFlowValue::Exception(
+                        Exception::RecursionLimitExceeded,
+                        None,
+                    ));
+                }
 
-                        if defined.parameters.len() != arguments.len() {
-                            return Err(ControlFlowValue::Exception(
-                                Exception::WrongNumberOfArguments,
-                            ));
+                // lists could be changed after the call, so only calls with
+                // values that are copied are remembered
+                let key = match closure.function.pure {
+                    Some(id) if self.memoize && arguments.iter().all(is_copied) => {
+                        let key = (id, format!("{:?}", arguments));
+                        if let Some(result) = self.memo.get(&key) {
+                            return Ok(result.clone());
                         }
+                        Some(key)
+                    }
+                    _ => None,
+                };
 
-                        for (i, parameter) in defined.parameters.iter().enumerate() {
-                            self.environment
-                                .declare(parameter.clone(), evaluated_arguments[i].clone());
-                        }
+                self.environment.enter(closure.scope());
+                for (parameter, argument) in closure.function.parameters.iter().zip(arguments) {
+                    self.environment.declare(parameter.clone(), argument);
+                }
 
-                        let result = self.eval_block(false, &defined.body);
+                self.call_depth += 1;
+                let result = grow_stack(|| self.eval_block(false, &closure.function.body));
+                self.call_depth -= 1;
 
-                        self.environment.pop();
+                self.environment.leave();
 
-                        result
+                if let (Some(key), Ok(result)) = (key, &result) {
+                    if is_copied(result) {
+                        self.memo.insert(key, result.clone());
                     }
                 }
+                result
             }
             _ => Err(ControlFlowValue::Exception(
                 Exception::CalledValueIsNotFunction,
+                None,
             )),
         }
     }
 
-    fn eval_list(&mut self, expressions: &Vec<Expression>) -> Result<Value, ControlFlowValue> {
+    fn eval_list(&mut self, expressions: &[Expression]) -> Result<Value, ControlFlowValue> {
         let mut values = vec![];
 
         for expression in expressions {
             values.push(self.eval_expression(expression)?);
         }
 
-        Ok(Value::List(values))
+        Ok(Value::list(values))
     }
 
     fn eval_index(
@@ -218,22 +948,60 @@ impl Interpreter {
         expression: &Expression,
         index: &Expression,
     ) -> Result<Value, ControlFlowValue> {
-        let mut value = self.eval_expression(expression)?;
-        value = value
-            .into_list()?
-            .get(*self.eval_expression(index)?.into_int()? as usize)
-            .ok_or(ControlFlowValue::Exception(Exception::IndexOutOfRange))?
-            .clone();
-        Ok(value)
+        let value = self.eval_expression(expression)?;
+        let index = self.eval_expression(index)?;
+        match value {
+            Value::Map(map) => map
+                .get(index.into_str()?)
+                .cloned()
+                .ok_or(ControlFlowValue::Exception(Exception::KeyNotFound, None)),
+            _ => value
+                .into_list()?
+                .get(*index.into_int()? as usize)
+                .cloned()
+                .ok_or(ControlFlowValue::Exception(
+                    Exception::IndexOutOfRange,
+                    None,
+                )),
+        }
+    }
+
+    // members are the keys of maps, the builtin namespaces are maps too
+    fn eval_member(
+        &mut self,
+        expression: &Expression,
+        member: &str,
+    ) -> Result<Value, ControlFlowValue> {
+        self.eval_expression(expression)?
+            .into_map()?
+            .get(member)
+            .cloned()
+            .ok_or(ControlFlowValue::Exception(Exception::KeyNotFound, None))
+    }
+
+    fn eval_map(
+        &mut self,
+        entries: &[(Expression, Expression)],
+    ) -> Result<Value, ControlFlowValue> {
+        let mut map = BTreeMap::new();
+
+        for (key, value) in entries {
+            let key = self.eval_expression(key)?.into_str()?.to_string();
+            map.insert(key, self.eval_expression(value)?);
+        }
+
+        Ok(Value::Map(map))
     }
 
     fn eval_declare_variable(
         &mut self,
-        id: &String,
-        expression: &Box<Expression>,
+        id: &str,
+        slot: Option<usize>,
+        expression: &Expression,
     ) -> Result<Value, ControlFlowValue> {
         let value = self.eval_expression(expression)?;
-        self.environment.declare(id.clone(), value);
+        self.environment
+            .declare_resolved(id.to_string(), slot, value);
         Ok(Value::Null)
     }
 
@@ -256,7 +1024,7 @@ impl Interpreter {
 
         if run_else_block {
             if let Some(block) = else_block {
-                result = self.eval_block(true, &block)?;
+                result = self.eval_block(true, block)?;
             }
         }
 
@@ -266,63 +1034,62 @@ impl Interpreter {
     fn eval_assign(
         &mut self,
         id: &str,
+        slot: Option<Slot>,
         operator: &AssignmentOperator,
-        expression: &Box<Expression>,
+        expression: &Expression,
     ) -> Result<Value, ControlFlowValue> {
         let value = self.eval_expression(expression)?;
+        let value = match operator {
+            AssignmentOperator::Set => value,
+            _ => apply_assignment(operator, self.environment.get_resolved(id, slot)?, value)?,
+        };
+        self.environment.assign_resolved(id, slot, value)?;
 
-        match operator {
-            AssignmentOperator::Set => {
-                self.environment.assign(id, value)?;
-            }
-            AssignmentOperator::Plus => {
-                self.environment
-                    .assign(id, plus(self.environment.get_or_undeclared(id)?, value)?)?;
-            }
-            AssignmentOperator::Minus => {
-                self.environment
-                    .assign(id, minus(self.environment.get_or_undeclared(id)?, value)?)?;
-            }
-            AssignmentOperator::Multiply => {
-                self.environment.assign(
-                    id,
-                    multiply(self.environment.get_or_undeclared(id)?, value)?,
-                )?;
-            }
-            AssignmentOperator::Divide => {
-                self.environment
-                    .assign(id, divide(self.environment.get_or_undeclared(id)?, value)?)?;
-            }
-            AssignmentOperator::Modulo => {
-                self.environment
-                    .assign(id, modulo(self.environment.get_or_undeclared(id)?, value)?)?;
-            }
+        Ok(Value::Null)
+    }
+
+    fn eval_index_assign(
+        &mut self,
+        id: &str,
+        slot: Option<Slot>,
+        indices: &[Expression],
+        operator: &AssignmentOperator,
+        expression: &Expression,
+    ) -> Result<Value, ControlFlowValue> {
+        let mut container = self.environment.get_resolved(id, slot)?;
+        let mut keys = vec![];
+        for index in indices {
+            keys.push(self.eval_expression(index)?);
         }
+        let value = self.eval_expression(expression)?;
+
+        assign_index(&mut container, &keys, operator, value)?;
+        self.environment.assign_resolved(id, slot, container)?;
 
         Ok(Value::Null)
     }
 
+    fn eval_unary(
+        &mut self,
+        operator: &UnaryOperator,
+        operand: &Expression,
+    ) -> Result<Value, ControlFlowValue> {
+        let value = self.eval_expression(operand)?;
+        unary_operation(operator, value)
+    }
+
     fn eval_update(
         &mut self,
         identifier: &str,
+        slot: Option<Slot>,
         operator: &UpdateOperator,
     ) -> Result<Value, ControlFlowValue> {
-        match operator {
-            UpdateOperator::Increment => self.environment.assign(
-                identifier,
-                plus(
-                    self.environment.get_or_undeclared(identifier)?,
-                    Value::Int(1),
-                )?,
-            ),
-            UpdateOperator::Decremet => self.environment.assign(
-                identifier,
-                minus(
-                    self.environment.get_or_undeclared(identifier)?,
-                    Value::Int(1),
-                )?,
-            ),
-        }?;
+        let current = self.environment.get_resolved(identifier, slot)?;
+        let value = match operator {
+            UpdateOperator::Increment => plus(current, Value::Int(1))?,
+            UpdateOperator::Decremet => minus(current, Value::Int(1))?,
+        };
+        self.environment.assign_resolved(identifier, slot, value)?;
 
         Ok(Value::Null)
     }
@@ -334,15 +1101,49 @@ impl Interpreter {
         update: &Option<Box<Expression>>,
         body: &Block,
     ) -> Result<Value, ControlFlowValue> {
-        let mut result = Value::Null;
-
-        self.environment.push();
+        let outer = self.environment.push();
+        let result = self.eval_loop_in_scope(init, test, update, body);
+        self.environment.restore(outer);
+        result
+    }
 
+    fn eval_loop_in_scope(
+        &mut self,
+        init: &Option<Box<Expression>>,
+        test: &Option<Box<Expression>>,
+        update: &Option<Box<Expression>>,
+        body: &Block,
+    ) -> Result<Value, ControlFlowValue> {
         if let Some(init) = init {
             self.eval_expression(init)?;
         }
 
+        self.in_loop(|interpreter| interpreter.eval_iterations(test, update, body))
+    }
+
+    // the results of loop invariant calls are kept until the loop is left
+    fn in_loop(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<Value, ControlFlowValue>,
+    ) -> Result<Value, ControlFlowValue> {
+        self.invariants.push(HashMap::new());
+        let result = f(self);
+        self.invariants.pop();
+        result
+    }
+
+    fn eval_iterations(
+        &mut self,
+        test: &Option<Box<Expression>>,
+        update: &Option<Box<Expression>>,
+        body: &Block,
+    ) -> Result<Value, ControlFlowValue> {
+        let mut result = Value::Null;
+
         loop {
+            // an empty loop body never evaluates an expression
+            self.step()?;
+
             if let Some(test) = test {
                 if !*self.eval_expression(test)?.into_bool()? {
                     break;
@@ -363,14 +1164,136 @@ impl Interpreter {
             }
         }
 
-        self.environment.pop();
+        Ok(result)
+    }
+
+    // iterates the elements of a list or the characters of a string, every
+    // iteration gets its own scope so closures capture the current item
+    fn eval_for_in(
+        &mut self,
+        id: &str,
+        iterable: &Expression,
+        body: &Block,
+    ) -> Result<Value, ControlFlowValue> {
+        // none for stdinLines, whose lines are read through the io of the
+        // interpreter, which an iterator can't hold on to
+        let items: Option<Items> = match self.eval_expression(iterable)? {
+            // changes the body makes to the list don't change the iterations
+            Value::List(list) => Some(Box::new(list.to_vec().into_iter().map(Ok))),
+            Value::String(string) => Some(Box::new(
+                string
+                    .chars()
+                    .map(|c| Ok(Value::String(c.to_string())))
+                    .collect::<Vec<_>>()
+                    .into_iter(),
+            )),
+            // the lines of openLines are read one at a time
+            Value::Native(native) if native.downcast_ref::<LineReader>().is_some() => {
+                Some(Box::new(iter::from_fn(move || {
+                    native.downcast_ref::<LineReader>()?.next_line()
+                })))
+            }
+            Value::Native(native) if native.downcast_ref::<StdinLines>().is_some() => None,
+            _ => {
+                return Err(ControlFlowValue::Exception(
+                    Exception::ValueIsWrongType,
+                    None,
+                ))
+            }
+        };
+
+        self.in_loop(|interpreter| interpreter.eval_items(id, items, body))
+    }
+
+    fn eval_items(
+        &mut self,
+        id: &str,
+        mut items: Option<Items>,
+        body: &Block,
+    ) -> Result<Value, ControlFlowValue> {
+        let mut result = Value::Null;
+        loop {
+            let item = match &mut items {
+                Some(items) => items.next(),
+                None => self
+                    .io
+                    .next_line()
+                    .transpose()
+                    .map(|line| line.map(Value::String).map_err(io_error)),
+            };
+            let Some(item) = item else { break };
+            self.step()?;
+            let item = item?;
+
+            let outer = self.environment.push();
+            self.environment.declare(id.to_string(), item);
+            let value = self.eval_block(false, body);
+            self.environment.restore(outer);
+
+            match value {
+                Ok(v) => result = v,
+                Err(ControlFlowValue::Continue) => {}
+                Err(ControlFlowValue::Break) => break,
+                Err(e) => return Err(e),
+            }
+        }
 
         Ok(result)
     }
 
+    // runs before every expression and loop iteration
+    fn step(&mut self) -> Result<(), ControlFlowValue> {
+        if let Some(handler) = self
+            .signal_handlers
+            .iter()
+            .find(|handler| handler.pending.swap(false, Ordering::Relaxed))
+        {
+            // a handled Ctrl-C doesn't interrupt the program
+            if handler.name == "INT" {
+                self.interrupted.store(false, Ordering::Relaxed);
+            }
+            let function = handler.function.clone();
+            self.call_function(&function, vec![])?;
+        }
+
+        if self.interrupted.swap(false, Ordering::Relaxed) {
+            return Err(ControlFlowValue::Exception(Exception::Interrupted, None));
+        }
+
+        if let Some(fuel) = &mut self.fuel {
+            if *fuel == 0 {
+                return Err(ControlFlowValue::FuelExhausted);
+            }
+            *fuel -= 1;
+        }
+
+        if let Some(deadline) = self.deadline {
+            if self.steps_until_deadline_check == 0 {
+                if Instant::now() >= deadline {
+                    return Err(ControlFlowValue::TimedOut);
+                }
+                self.steps_until_deadline_check = DEADLINE_CHECK_INTERVAL;
+            }
+            self.steps_until_deadline_check -= 1;
+        }
+
+        Ok(())
+    }
+
     fn eval_expression(&mut self, expression: &Expression) -> Result<Value, ControlFlowValue> {
+        grow_stack(|| self.eval_expression_value(expression))
+            .map_err(|err| err.with_region(&expression.region))
+    }
+
+    fn eval_expression_value(
+        &mut self,
+        expression: &Expression,
+    ) -> Result<Value, ControlFlowValue> {
+        self.step()?;
+
         match &expression.value {
             ExpressionValue::Int(v) => Ok(Value::Int(*v)),
+            ExpressionValue::Float(v) => Ok(Value::Float(*v)),
             ExpressionValue::String(v) => Ok(Value::String(v.clone())),
             ExpressionValue::Bool(v) => Ok(Value::Bool(*v)),
             ExpressionValue::Null => Ok(Value::Null),
@@ -384,30 +1307,68 @@ impl Interpreter {
                 update,
                 body,
             } => self.eval_loop(init, test, update, body),
+            ExpressionValue::ForIn {
+                identifier,
+                iterable,
+                body,
+            } => self.eval_for_in(identifier, iterable, body),
             ExpressionValue::Continue => Err(ControlFlowValue::Continue),
             ExpressionValue::Break => Err(ControlFlowValue::Break),
-            ExpressionValue::Function(v) => Ok(Value::Function(Function::Defined(v.clone()))),
+            ExpressionValue::Discard(expression) => {
+                self.eval_expression(expression)?;
+                Ok(Value::Null)
+            }
+            ExpressionValue::Throw(expression) => Err(ControlFlowValue::Exception(
+                Exception::Thrown(self.eval_expression(expression)?),
+                None,
+            )),
+            ExpressionValue::Import(path) => self.eval_import(path),
+            ExpressionValue::Function(v) => Ok(Value::Function(Function::Defined(Closure::new(
+                v.clone(),
+                self.environment.current(),
+            )))),
             ExpressionValue::Block(v) => self.eval_block(true, v),
-            ExpressionValue::Identifier(id) => self.eval_identifier(id),
+            ExpressionValue::Identifier(id, slot) => self.environment.get_resolved(id, *slot),
             ExpressionValue::Call {
                 identifier,
+                slot,
                 arguments,
-            } => self.eval_call(identifier, arguments),
+                invariant,
+            } => self.eval_call(identifier, *slot, arguments, *invariant),
             ExpressionValue::List(expressions) => self.eval_list(expressions),
+            ExpressionValue::Map(entries) => self.eval_map(entries),
             ExpressionValue::Index { expression, index } => self.eval_index(expression, index),
+            ExpressionValue::Member { expression, member } => self.eval_member(expression, member),
+            ExpressionValue::MemberCall {
+                expression,
+                member,
+                arguments,
+                invariant,
+            } => self.eval_member_call(expression, member, arguments, *invariant),
             ExpressionValue::VariableDeclaration {
                 identifier,
+                slot,
                 expression,
-            } => self.eval_declare_variable(identifier, expression),
+            } => self.eval_declare_variable(identifier, *slot, expression),
             ExpressionValue::Assign {
                 identifier,
+                slot,
                 operator,
                 expression,
-            } => self.eval_assign(identifier, operator, expression),
+            } => self.eval_assign(identifier, *slot, operator, expression),
+            ExpressionValue::IndexAssign {
+                identifier,
+                slot,
+                indices,
+                operator,
+                expression,
+            } => self.eval_index_assign(identifier, *slot, indices, operator, expression),
             ExpressionValue::Update {
                 identifier,
+                slot,
                 operator,
-            } => self.eval_update(identifier, operator),
+            } => self.eval_update(identifier, *slot, operator),
+            ExpressionValue::Unary { operator, operand } => self.eval_unary(operator, operand),
             ExpressionValue::Binary {
                 left,
                 operator,
@@ -417,26 +1378,473 @@ impl Interpreter {
     }
 
     pub fn new() -> Interpreter {
+        Interpreter::with_config(&Config::default())
+    }
+
+    pub fn with_config(config: &Config) -> Interpreter {
         Interpreter {
-            environment: Environment::default(),
+            environment: if config.stdlib {
+                Environment::with_builtins(config.flat_builtins)
+            } else {
+                Environment::new()
+            },
+            side_effects_allowed: true,
+            expression_side_effects: false,
+            interrupted: Arc::new(AtomicBool::new(false)),
+            io: Box::new(StdIo),
+            call_depth: 0,
+            max_recursion: config.stack_limit.unwrap_or(DEFAULT_MAX_RECURSION),
+            fuel: config.fuel,
+            deadline: None,
+            steps_until_deadline_check: 0,
+            clock: Clock::new(config.deterministic),
+            modules: Modules::default(),
+            args: vec![],
+            env_vars: HashMap::new(),
+            temp_files: vec![],
+            temp_dirs: vec![],
+            signal_handlers: vec![],
+            timers: Timers::default(),
+            random: random_generator(config.deterministic),
+            memoize: false,
+            memo: HashMap::new(),
+            invariants: vec![],
         }
     }
 
-    pub fn eval(&mut self, source: &str) -> Result<Value, EvalError> {
-        let program = Parser::new(source)?.parse()?;
+    // an interpreter which stops with FuelExhausted after evaluating the given
+    // number of expressions and loop iterations, so untrusted programs like
+    // `loop {}` can't run forever
+    pub fn with_fuel(fuel: u64) -> Interpreter {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_fuel(Some(fuel));
+        interpreter
+    }
+
+    // refill the fuel, None removes the limit
+    pub fn set_fuel(&mut self, fuel: Option<u64>) -> &mut Self {
+        self.fuel = fuel;
+        self
+    }
+
+    pub fn remaining_fuel(&self) -> Option<u64> {
+        self.fuel
+    }
+
+    // stop everything evaluated after the timeout has passed with TimedOut, the
+    // time starts now. None removes the deadline
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) -> &mut Self {
+        self.deadline = timeout.map(|timeout| Instant::now() + timeout);
+        self.steps_until_deadline_check = 0;
+        self
+    }
+
+    // evaluate a program which is stopped with TimedOut once it runs longer
+    // than the timeout
+    pub fn eval_with_timeout(
+        &mut self,
+        source: &str,
+        timeout: Duration,
+    ) -> Result<Value, EvalError> {
+        self.set_timeout(Some(timeout));
+        let result = self.eval_str(source);
+        self.set_timeout(None);
+        result
+    }
+
+    // setting the returned flag makes the evaluation raise an Interrupted exception,
+    // this is meant to be set from a signal handler or another thread
+    pub fn interrupt_flag(&self) -> Arc<AtomicBool> {
+        self.interrupted.clone()
+    }
+
+    // use a flag shared with other interpreters, one signal handler can then
+    // interrupt whichever of them is running
+    pub fn set_interrupt_flag(&mut self, flag: Arc<AtomicBool>) -> &mut Self {
+        self.interrupted = flag;
+        self
+    }
+
+    // expose a host function to the programs evaluated by this interpreter, the
+    // function is allowed in expression only evaluation and sandboxes
+    pub fn register_builtin(
+        &mut self,
+        name: &str,
+        function: impl Fn(Vec<Value>) -> Result<Value, ControlFlowValue> + 'static,
+    ) -> &mut Self {
+        self.environment.declare_builtin_function(
+            name.to_string(),
+            BuiltinFunction::Simple(Rc::new(function)),
+            false,
+        );
+        self
+    }
+
+    // like register_builtin, but the function is forbidden wherever printLn is
+    pub fn register_builtin_with_side_effects(
+        &mut self,
+        name: &str,
+        function: impl Fn(Vec<Value>) -> Result<Value, ControlFlowValue> + 'static,
+    ) -> &mut Self {
+        self.environment.declare_builtin_function(
+            name.to_string(),
+            BuiltinFunction::Simple(Rc::new(function)),
+            true,
+        );
+        self
+    }
+
+    // redirect the input and output of builtins like printLn and readLn
+    pub fn set_io(&mut self, io: impl Io + 'static) -> &mut Self {
+        self.io = Box::new(io);
+        self
+    }
+
+    pub fn io(&mut self) -> &mut dyn Io {
+        self.io.as_mut()
+    }
+
+    // the number of nested function calls after which RecursionLimitExceeded is raised,
+    // see DEFAULT_MAX_RECURSION for the memory every call takes
+    pub fn set_max_recursion(&mut self, limit: usize) -> &mut Self {
+        self.max_recursion = limit;
+        self
+    }
+
+    // make every builtin give the same result on every run and machine, time
+    // comes from a virtual clock that starts at 0 and sleeping only advances it.
+    // the deadline of set_timeout still uses the real time
+    pub fn set_deterministic(&mut self, deterministic: bool) -> &mut Self {
+        self.clock = Clock::new(deterministic);
+        self.random = random_generator(deterministic);
+        self
+    }
+
+    pub fn is_deterministic(&self) -> bool {
+        matches!(self.clock, Clock::Virtual(_))
+    }
+
+    // the generator of random and randomInt
+    pub(crate) fn random(&mut self) -> &mut Random {
+        &mut self.random
+    }
+
+    pub(crate) fn seed_random(&mut self, seed: u64) {
+        self.random = Random::from_seed(seed);
+    }
+
+    // the time since the interpreter was created
+    pub(crate) fn elapsed(&self) -> Duration {
+        match self.clock {
+            Clock::Real(start) => start.elapsed(),
+            Clock::Virtual(time) => time,
+        }
+    }
+
+    pub(crate) fn sleep(&mut self, duration: Duration) {
+        match &mut self.clock {
+            Clock::Real(_) => thread::sleep(duration),
+            Clock::Virtual(time) => *time += duration,
+        }
+    }
+
+    // the file the evaluated programs were read from, the files they import are
+    // looked up relative to it. without a path imports are relative to the
+    // working directory
+    pub fn set_path(&mut self, path: &Path) -> &mut Self {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        self.modules.importing = vec![path];
+        self
+    }
+
+    // the files imported by the evaluated programs, without the one set by set_path
+    pub fn imported_files(&self) -> impl Iterator<Item = &Path> {
+        self.modules.files.iter().map(PathBuf::as_path)
+    }
+
+    // the global variables, the imported modules and the loaded environment
+    // variables, see restore_snapshot
+    pub fn snapshot(&self) -> Result<Vec<u8>, SnapshotError> {
+        snapshot::record(&snapshot::State {
+            global: self.environment.global(),
+            modules: self.modules.cache.clone(),
+            files: self.modules.files.clone(),
+            env_vars: self.env_vars.clone(),
+        })
+    }
+
+    // continue from a snapshot instead of evaluating the programs which made it,
+    // the variables declared so far are replaced by the ones of the snapshot
+    pub fn restore_snapshot(&mut self, bytes: &[u8]) -> Result<&mut Self, SnapshotError> {
+        let state = snapshot::restore(bytes, &self.environment.global())?;
+        self.environment.replace_global(state.global);
+        self.modules.cache = state.modules;
+        self.modules.files = state.files;
+        self.env_vars = state.env_vars;
+        Ok(self)
+    }
+
+    // the command line arguments returned by the args builtin
+    pub fn set_args(&mut self, args: Vec<String>) -> &mut Self {
+        self.args = args;
+        self
+    }
+
+    pub fn args(&self) -> &[String] {
+        &self.args
+    }
+
+    // the variables set by set_env_var come before the ones of the process
+    pub fn env_var(&self, name: &str) -> Option<String> {
+        self.env_vars
+            .get(name)
+            .cloned()
+            .or_else(|| std::env::var(name).ok())
+    }
+
+    // the variables loaded by the program, they are passed on to the programs it runs
+    #[cfg(feature = "exec")]
+    pub(crate) fn env_vars(&self) -> &HashMap<String, String> {
+        &self.env_vars
+    }
+
+    pub fn set_env_var(&mut self, name: &str, value: &str) -> &mut Self {
+        self.env_vars.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    pub(crate) fn keep_temp_file(&mut self, file: TempPath) {
+        self.temp_files.push(file);
+    }
+
+    // the id can be given to clearTimer
+    pub(crate) fn add_timer(
+        &mut self,
+        function: Value,
+        delay: Duration,
+        interval: Option<Duration>,
+    ) -> i64 {
+        let id = self.timers.next_id;
+        self.timers.next_id += 1;
+        let due = self.elapsed() + delay;
+        self.timers.pending.push(Timer {
+            id,
+            due,
+            interval,
+            function,
+        });
+        id
+    }
+
+    // whether the timer was still pending
+    pub(crate) fn clear_timer(&mut self, id: i64) -> bool {
+        let count = self.timers.pending.len();
+        self.timers.pending.retain(|timer| timer.id != id);
+        self.timers.pending.len() != count
+    }
+
+    // waits for the timer which is due first and returns its function, timers
+    // due at the same time run in the order they were created. none once no
+    // timers are left
+    pub(crate) fn next_timer(&mut self) -> Option<Value> {
+        let index = (0..self.timers.pending.len())
+            .min_by_key(|&i| (self.timers.pending[i].due, self.timers.pending[i].id))?;
+        let due = self.timers.pending[index].due;
+        let now = self.elapsed();
+        if due > now {
+            self.sleep(due - now);
+        }
+
+        let timer = &mut self.timers.pending[index];
+        let function = timer.function.clone();
+        match timer.interval {
+            Some(interval) => timer.due += interval,
+            None => {
+                self.timers.pending.remove(index);
+            }
+        }
+        Some(function)
+    }
+
+    // registering a signal again replaces its function
+    pub(crate) fn on_signal(
+        &mut self,
+        name: &str,
+        signal: c_int,
+        function: Value,
+    ) -> io::Result<()> {
+        if let Some(handler) = self
+            .signal_handlers
+            .iter_mut()
+            .find(|handler| handler.name == name)
+        {
+            handler.function = function;
+            return Ok(());
+        }
+        let pending = Arc::new(AtomicBool::new(false));
+        let id = signal_hook::flag::register(signal, pending.clone())?;
+        self.signal_handlers.push(SignalHandler {
+            name: name.to_string(),
+            id,
+            pending,
+            function,
+        });
+        Ok(())
+    }
+
+    pub(crate) fn keep_temp_dir(&mut self, directory: TempDir) {
+        self.temp_dirs.push(directory);
+    }
+
+    // reuse the results of calls to functions the optimizer found to be pure
+    // when they are called with the same arguments again, and of the calls it
+    // found to be loop invariant until the loop is left
+    pub fn set_memoize(&mut self, memoize: bool) -> &mut Self {
+        self.memoize = memoize;
+        self.memo.clear();
+        self
+    }
+
+    // forbid builtins with side effects for everything evaluated by this
+    // interpreter, time and random numbers come from the outside world too so
+    // they become deterministic
+    pub fn sandbox(&mut self) -> &mut Self {
+        self.side_effects_allowed = false;
+        self.set_deterministic(true)
+    }
+
+    // top level variables declared by the evaluated programs, functions and the
+    // values holding them like the builtin namespaces are left out
+    pub fn variables(&self) -> BTreeMap<String, Value> {
+        self.environment
+            .top_level()
+            .into_iter()
+            .filter(|(_, value)| !contains_function(value))
+            .collect()
+    }
+
+    // every top level binding including functions and builtins
+    pub fn bindings(&self) -> BTreeMap<String, Value> {
+        self.environment.top_level().into_iter().collect()
+    }
+
+    pub fn memory_stats(&self) -> MemoryStats {
+        self.environment.memory_stats()
+    }
+
+    pub fn get_variable(&self, id: &str) -> Option<Value> {
+        self.environment.get(id)
+    }
+
+    // declare a top level variable for the programs evaluated afterwards, like
+    // the inputs of a script
+    pub fn declare_variable(&mut self, id: &str, value: Value) -> &mut Self {
+        self.environment.declare(id.to_string(), value);
+        self
+    }
+
+    fn eval_top_level(&mut self, expression: &Expression) -> Result<Value, EvalError> {
+        self.eval_expression(expression).map_err(EvalError::from)
+    }
+
+    // call a function declared by the evaluated programs, or a member of a
+    // namespace like "math.sum"
+    pub fn call(&mut self, id: &str, arguments: Vec<Value>) -> Result<Value, EvalError> {
+        let mut path = id.split('.');
+        let mut function = self
+            .environment
+            .get_or_undeclared(path.next().unwrap_or_default())
+            .map_err(EvalError::from)?;
+        for member in path {
+            function = function
+                .into_map()?
+                .get(member)
+                .cloned()
+                .ok_or(ControlFlowValue::Exception(Exception::KeyNotFound, None))?;
+        }
+        self.call_function(&function, arguments)
+            .map_err(EvalError::from)
+    }
+
+    // evaluate olang source into the global scope before any program runs, so
+    // hosts can provide their own helper functions written in olang
+    pub fn load_prelude(&mut self, source: &str) -> Result<&mut Self, EvalError> {
+        self.eval_str(source)?;
+        Ok(self)
+    }
+
+    pub fn eval_str(&mut self, source: &str) -> Result<Value, EvalError> {
+        let program = resolver::resolve(Parser::new(source)?.parse()?);
+        self.eval_program(&program)
+    }
+
+    // evaluate a program one top level expression at a time, the iterator yields
+    // the region and value of every expression as it completes and stops after
+    // the first error. meant for frontends that show intermediate results
+    pub fn eval_iter(&mut self, source: &str) -> Result<EvalIter<'_>, EvalError> {
+        let program = resolver::resolve(Parser::new(source)?.parse()?);
+        Ok(EvalIter {
+            interpreter: self,
+            expressions: program.ast.into_iter(),
+        })
+    }
+
+    pub fn eval_program(&mut self, program: &Program) -> Result<Value, EvalError> {
+        // the program can declare the functions called by the ones of earlier
+        // programs again
+        self.memo.clear();
         let mut result = Value::Null;
 
-        for expression in program.ast {
-            match self.eval_expression(&expression) {
-                Ok(v) => Ok(result = v),
-                Err(err) => match err {
-                    ControlFlowValue::Exception(e) => Err(EvalError::UnhandledException(e)),
-                    ControlFlowValue::Continue => Err(EvalError::ContinueOutsideLoop),
-                    ControlFlowValue::Break => Err(EvalError::BreakOutsideLoop),
-                },
-            }?;
+        for expression in &program.ast {
+            result = self.eval_top_level(expression)?;
         }
 
-        return Ok(result);
+        Ok(result)
+    }
+
+    // allow builtins with side effects like printLn to be called by eval_expression_str
+    pub fn allow_expression_side_effects(&mut self, allow: bool) -> &mut Self {
+        self.expression_side_effects = allow;
+        self
+    }
+
+    // evaluate a source string consisting of exactly one expression,
+    // this is meant for using olang as an embedded expression or rules language
+    pub fn eval_expression_str(&mut self, source: &str) -> Result<Value, EvalError> {
+        let expression = Parser::new(source)?.parse_single_expression()?;
+
+        if self.expression_side_effects {
+            return self.eval_top_level(&expression);
+        }
+        // without side effects the same expression always gives the same
+        // value, also when it reads the time or draws random numbers
+        let side_effects_allowed = mem::replace(&mut self.side_effects_allowed, false);
+        let clock = mem::replace(&mut self.clock, Clock::new(true));
+        let random = mem::replace(&mut self.random, random_generator(true));
+        let result = self.eval_top_level(&expression);
+        self.side_effects_allowed = side_effects_allowed;
+        self.clock = clock;
+        self.random = random;
+
+        result
+    }
+}
+
+pub struct EvalIter<'a> {
+    interpreter: &'a mut Interpreter,
+    expressions: std::vec::IntoIter<Expression>,
+}
+
+impl Iterator for EvalIter<'_> {
+    type Item = Result<(Region, Value), EvalError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let expression = self.expressions.next()?;
+        let result = self.interpreter.eval_top_level(&expression);
+        if result.is_err() {
+            // the expressions after an error are never evaluated
+            self.expressions = Vec::new().into_iter();
+        }
+        Some(result.map(|value| (expression.region, value)))
     }
 }
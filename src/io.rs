@@ -0,0 +1,127 @@
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    io::{self, IsTerminal, Write},
+    process::{Command, Stdio},
+    rc::Rc,
+};
+
+// where the IO builtins like printLn and readLn read from and write to
+pub trait Io {
+    fn write(&mut self, text: &str) -> io::Result<()>;
+
+    // a line without its line ending, an empty string at the end of the input
+    fn read_line(&mut self) -> io::Result<String>;
+
+    // the next line of the input, none at its end. unlike read_line an empty
+    // line can be told apart from the end
+    fn next_line(&mut self) -> io::Result<Option<String>> {
+        let line = self.read_line()?;
+        Ok(Some(line).filter(|line| !line.is_empty()))
+    }
+
+    // like read_line, but the typed text isn't shown
+    fn read_password(&mut self) -> io::Result<String> {
+        self.read_line()
+    }
+
+    // whether a person answers the questions of builtins like confirm, piped
+    // input can't be asked again after an invalid answer
+    fn is_interactive(&self) -> bool {
+        false
+    }
+}
+
+// the standard input and output of the process
+pub struct StdIo;
+
+impl Io for StdIo {
+    fn write(&mut self, text: &str) -> io::Result<()> {
+        let mut stdout = io::stdout();
+        stdout.write_all(text.as_bytes())?;
+        stdout.flush()
+    }
+
+    fn read_line(&mut self) -> io::Result<String> {
+        let mut line = String::new();
+        io::stdin().read_line(&mut line)?;
+        Ok(line.trim_end_matches(['\n', '\r']).to_string())
+    }
+
+    fn next_line(&mut self) -> io::Result<Option<String>> {
+        let mut line = String::new();
+        if io::stdin().read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        Ok(Some(line.trim_end_matches(['\n', '\r']).to_string()))
+    }
+
+    // the echo of the terminal is turned off with stty, where that isn't
+    // available the password is shown
+    fn read_password(&mut self) -> io::Result<String> {
+        if !self.is_interactive() {
+            return self.read_line();
+        }
+        let stty = |argument| {
+            Command::new("stty")
+                .arg(argument)
+                .stdin(Stdio::inherit())
+                .status()
+        };
+        let _ = stty("-echo");
+        let line = self.read_line();
+        let _ = stty("echo");
+        // the newline typed by the user wasn't echoed either
+        self.write("\n")?;
+        line
+    }
+
+    fn is_interactive(&self) -> bool {
+        io::stdin().is_terminal()
+    }
+}
+
+#[derive(Default)]
+struct Buffers {
+    input: VecDeque<String>,
+    output: String,
+}
+
+// input and output kept in memory, clones share the same buffers so a clone can
+// be given to the interpreter while the original is used to read the output
+#[derive(Clone, Default)]
+pub struct MemoryIo {
+    buffers: Rc<RefCell<Buffers>>,
+}
+
+impl MemoryIo {
+    pub fn new(input: &str) -> Self {
+        let io = MemoryIo::default();
+        io.buffers.borrow_mut().input = input.lines().map(String::from).collect();
+        io
+    }
+
+    pub fn output(&self) -> String {
+        self.buffers.borrow().output.clone()
+    }
+}
+
+impl Io for MemoryIo {
+    fn write(&mut self, text: &str) -> io::Result<()> {
+        self.buffers.borrow_mut().output.push_str(text);
+        Ok(())
+    }
+
+    fn read_line(&mut self) -> io::Result<String> {
+        Ok(self
+            .buffers
+            .borrow_mut()
+            .input
+            .pop_front()
+            .unwrap_or_default())
+    }
+
+    fn next_line(&mut self) -> io::Result<Option<String>> {
+        Ok(self.buffers.borrow_mut().input.pop_front())
+    }
+}
@@ -1,4 +1,5 @@
 use phf::phf_map;
+use serde::{Deserialize, Serialize};
 use std::{fmt, string::String, vec::Vec};
 use strum::{Display, EnumDiscriminants};
 use thiserror::Error;
@@ -17,9 +18,16 @@ static KEYWORDS: phf::Map<&'static str, TokenValue> = phf_map! {
     "loop" => TokenValue::KeywordLoop,
     "continue" => TokenValue::KeywordContinue,
     "break" => TokenValue::KeywordBreak,
+    "discard" => TokenValue::KeywordDiscard,
+    "throw" => TokenValue::KeywordThrow,
+    "in" => TokenValue::KeywordIn,
+    "import" => TokenValue::KeywordImport,
 };
 
-#[derive(EnumDiscriminants, Display, Debug, PartialEq, Clone)]
+// longer than any keyword, words that don't fit can't be keywords
+const KEYWORD_MAX_LEN: usize = 16;
+
+#[derive(EnumDiscriminants, Display, Debug, PartialEq, Clone, Serialize)]
 #[strum_discriminants(derive(Display))]
 pub enum TokenValue {
     KeywordFun,            // fun
@@ -35,7 +43,12 @@ pub enum TokenValue {
     KeywordLoop,           // loop
     KeywordContinue,       // continue
     KeywordBreak,          // break
+    KeywordDiscard,        // discard
+    KeywordThrow,          // throw
+    KeywordIn,             // in
+    KeywordImport,         // import
     DoubleExclamationMark, // !!
+    ExclamationMark,       // !
     EqualSign,             // =
     CloseParenthesis,      // )
     OpenParenthesis,       // (
@@ -43,6 +56,8 @@ pub enum TokenValue {
     CloseBracket,          // ]
     OpenBrace,             // {
     CloseBrace,            // }
+    Colon,                 // :
+    Dot,                   // .
     PlusSign,              // +
     MinusSign,             // -
     DivisionSign,          // /
@@ -53,6 +68,7 @@ pub enum TokenValue {
     Identifier(String),    // print
     String(String),        // "Hello World"
     Int(i64),              // 100
+    Float(f64),            // 3.14
     IsLessThan,            // <
     IsLessThanOrEqual,     // <=
     IsGreaterThan,         // >
@@ -70,7 +86,7 @@ pub enum TokenValue {
     Decrement,             // --
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Token {
     pub value: TokenValue,
     pub region: Region,
@@ -82,34 +98,15 @@ impl Token {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Location {
-    row: usize,
-    col: usize,
+    pub row: usize,
+    pub col: usize,
 }
 
 impl Location {
-    fn from_index(source: &Vec<char>, index: usize) -> Self {
-        let mut location = Location { row: 1, col: 1 };
-
-        let target = if index > source.len() {
-            // if the index is out of bounds
-            // return the last character in the source
-            source.len() - 1
-        } else {
-            index
-        };
-
-        for i in 0..target {
-            if source[i] == '\n' {
-                location.row += 1;
-                location.col = 1;
-            } else {
-                location.col += 1
-            };
-        }
-
-        location
+    fn start() -> Self {
+        Location { row: 1, col: 1 }
     }
 }
 
@@ -119,29 +116,111 @@ impl fmt::Display for Location {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Region {
     pub start: Location,
     pub end: Location,
 }
 
+impl Region {
+    // a region for expressions that were not parsed from source code
+    pub fn synthetic() -> Self {
+        Region {
+            start: Location { row: 0, col: 0 },
+            end: Location { row: 0, col: 0 },
+        }
+    }
+
+    pub fn is_synthetic(&self) -> bool {
+        self.start.row == 0
+    }
+}
+
 impl fmt::Display for Region {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{} -> {}", self.start, self.end)
     }
 }
 
+// regions are on every node of the AST, the short form keeps dumps of it readable
+impl fmt::Debug for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl fmt::Debug for Region {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum LexerError {
     #[error("{location} unexpected character found during parsing: {char}")]
     UnexpectedCharacter { location: Location, char: char },
     #[error("{location} expected digit in int token, found: {char}")]
     NotDigit { location: Location, char: char },
+    #[error("{location} string is never terminated, expected a closing \"")]
+    UnterminatedString { location: Location },
+    #[error("{location} block comment is never terminated, expected a closing ]#")]
+    UnterminatedComment { location: Location },
+    #[error("{} int literal does not fit in 64 bits: {literal}", region.start)]
+    IntLiteralTooLarge { region: Region, literal: String },
+}
+
+impl LexerError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            LexerError::UnexpectedCharacter { .. } => "OL0001",
+            LexerError::NotDigit { .. } => "OL0002",
+            LexerError::UnterminatedString { .. } => "OL0017",
+            LexerError::UnterminatedComment { .. } => "OL0018",
+            LexerError::IntLiteralTooLarge { .. } => "OL0026",
+        }
+    }
+
+    // most errors point at a single character, too large literals at the whole literal
+    pub fn region(&self) -> Region {
+        let location = match self {
+            LexerError::UnexpectedCharacter { location, .. }
+            | LexerError::NotDigit { location, .. }
+            | LexerError::UnterminatedString { location }
+            | LexerError::UnterminatedComment { location } => location,
+            LexerError::IntLiteralTooLarge { region, .. } => return region.clone(),
+        };
+        Region {
+            start: location.clone(),
+            end: location.clone(),
+        }
+    }
+}
+
+// keywords are short and ascii, so the lookup copies the chars of the word into
+// a buffer on the stack instead of allocating a string for every identifier
+fn keyword(chars: &[char]) -> Option<TokenValue> {
+    let mut buffer = [0; KEYWORD_MAX_LEN];
+    if chars.len() > KEYWORD_MAX_LEN {
+        return None;
+    }
+    for (byte, c) in buffer.iter_mut().zip(chars) {
+        if !c.is_ascii() {
+            return None;
+        }
+        *byte = *c as u8;
+    }
+
+    let word = std::str::from_utf8(&buffer[..chars.len()]).ok()?;
+    KEYWORDS.get(word).cloned()
 }
 
+// the lexer is an iterator over the tokens of the source, which ends with an
+// EndOfFile token or the first error
 pub struct Lexer {
     source: Vec<char>,
     c: usize,
+    location: Location,
+    finished: bool,
 }
 
 impl Lexer {
@@ -149,18 +228,42 @@ impl Lexer {
         Lexer {
             source: source.chars().collect(),
             c: 0,
+            location: Location::start(),
+            finished: false,
         }
     }
 
     fn current_location(&self) -> Location {
-        Location::from_index(&self.source, self.c)
+        self.location.clone()
     }
 
     fn advance(&mut self) -> &mut Self {
+        match self.source.get(self.c) {
+            Some('\n') => {
+                self.location.row += 1;
+                self.location.col = 1;
+            }
+            Some(_) => self.location.col += 1,
+            None => {}
+        }
         self.c += 1;
         self
     }
 
+    // whether the lexer is at the exponent of a float literal, like e-5 in 1e-5
+    fn at_exponent(&self) -> bool {
+        if self.c >= self.source.len() || !matches!(self.current(), 'e' | 'E') {
+            return false;
+        }
+        match self.next_or_space() {
+            '+' | '-' => self
+                .source
+                .get(self.c + 2)
+                .is_some_and(|c| c.is_ascii_digit()),
+            c => c.is_ascii_digit(),
+        }
+    }
+
     fn current(&self) -> char {
         self.source[self.c]
     }
@@ -172,11 +275,14 @@ impl Lexer {
         }
     }
 
-    pub fn tokenize(&mut self) -> Result<Vec<Token>, LexerError> {
-        let mut result: Vec<Token> = vec![];
-        self.c = 0;
-
+    // returns None once the end of the source has been reached
+    fn next_token(&mut self) -> Result<Option<Token>, LexerError> {
         while self.c < self.source.len() {
+            if self.current().is_whitespace() {
+                self.advance();
+                continue;
+            }
+
             let mut region = Region {
                 start: Location { row: 0, col: 0 },
                 end: Location { row: 0, col: 0 },
@@ -185,11 +291,13 @@ impl Lexer {
             region.start = self.current_location();
 
             // match for simple one char poiters
-            match match self.source[self.c] {
+            if let Some(v) = match self.source[self.c] {
                 '(' => Some(TokenValue::OpenParenthesis),
                 ')' => Some(TokenValue::CloseParenthesis),
                 '{' => Some(TokenValue::OpenBrace),
                 '}' => Some(TokenValue::CloseBrace),
+                ':' => Some(TokenValue::Colon),
+                '.' => Some(TokenValue::Dot),
                 '[' => Some(TokenValue::OpenBracket),
                 ']' => Some(TokenValue::CloseBracket),
                 '+' => match self.next_or_space() {
@@ -262,7 +370,7 @@ impl Lexer {
                         self.advance();
                         Some(TokenValue::IsNotEqual)
                     }
-                    _ => None,
+                    _ => Some(TokenValue::ExclamationMark),
                 },
                 '=' => match self.next_or_space() {
                     '=' => {
@@ -287,30 +395,25 @@ impl Lexer {
                 },
                 _ => None,
             } {
-                Some(v) => {
-                    region.end = self.current_location();
-                    result.push(Token::new(region, v));
-                    self.advance();
-                    continue;
-                }
-                _ => {}
-            }
-
-            if self.current().is_whitespace() {
+                region.end = self.current_location();
                 self.advance();
-                continue;
+                return Ok(Some(Token::new(region, v)));
             }
 
             // check for comments
             if self.current() == '#' {
+                let start = self.current_location();
                 self.advance();
                 // block comment
-                if self.current() == '[' {
+                if self.c < self.source.len() && self.current() == '[' {
                     while self.c < self.source.len()
                         && !(self.current() == ']' && self.next_or_space() == &'#')
                     {
                         self.advance();
                     }
+                    if self.c >= self.source.len() {
+                        return Err(LexerError::UnterminatedComment { location: start });
+                    }
                     self.advance();
                     // else single line comments
                 } else {
@@ -329,58 +432,96 @@ impl Lexer {
                     value.push(self.current());
                     self.advance();
                 }
+                if self.c >= self.source.len() {
+                    return Err(LexerError::UnterminatedString {
+                        location: region.start,
+                    });
+                }
                 self.advance();
 
                 region.end = self.current_location();
-                result.push(Token::new(region, TokenValue::String(value)));
+                return Ok(Some(Token::new(region, TokenValue::String(value))));
             }
-            // int token
-            else if self.current().is_digit(10) || self.current() == '-' {
-                let mut value: i64 = 0;
+            // int or float token
+            else if self.current().is_ascii_digit() || self.current() == '-' {
+                let number_start = self.c;
                 let mut negative = false;
 
                 if self.current() == '-' {
                     negative = true;
-                    self.c += 1;
+                    self.advance();
                 };
 
-                while self.c < self.source.len() && self.current().is_digit(10) {
-                    value = value * 10
-                        + self.current().to_digit(10).ok_or(LexerError::NotDigit {
-                            location: self.current_location(),
-                            char: self.source[self.c],
-                        })? as i64;
+                let digits_start = self.c;
+                while self.c < self.source.len() && self.current().is_ascii_digit() {
                     self.advance();
                 }
 
-                if negative {
-                    value *= -1
+                // float token, either with a fraction (3.14) and/or an exponent (1e-5)
+                let has_fraction = self.c < self.source.len()
+                    && self.current() == '.'
+                    && self.next_or_space().is_ascii_digit();
+                if has_fraction || self.at_exponent() {
+                    if has_fraction {
+                        self.advance();
+                        while self.c < self.source.len() && self.current().is_ascii_digit() {
+                            self.advance();
+                        }
+                    }
+                    if self.at_exponent() {
+                        self.advance();
+                        if self.current() == '+' || self.current() == '-' {
+                            self.advance();
+                        }
+                        while self.c < self.source.len() && self.current().is_ascii_digit() {
+                            self.advance();
+                        }
+                    }
+
+                    let text: String = self.source[digits_start..self.c].iter().collect();
+                    // the text only consists of digits, a dot and an exponent at this point
+                    let mut value: f64 = text.parse().unwrap();
+                    if negative {
+                        value *= -1.0
+                    }
+
+                    region.end = self.current_location();
+                    return Ok(Some(Token::new(region, TokenValue::Float(value))));
                 }
 
                 region.end = self.current_location();
-                result.push(Token::new(region, TokenValue::Int(value)));
+                let text: String = self.source[number_start..self.c].iter().collect();
+                let value = text.parse().map_err(|_| LexerError::IntLiteralTooLarge {
+                    region: region.clone(),
+                    literal: text.clone(),
+                })?;
+                return Ok(Some(Token::new(region, TokenValue::Int(value))));
             }
             // identifier or keyword
             else if self.current().is_alphanumeric() && !self.current().is_whitespace() {
-                let mut value = "".to_string();
+                let start = self.c;
 
                 while self.c < self.source.len()
                     && (self.current().is_alphanumeric() || self.current() == '_')
                     && !self.current().is_whitespace()
                 {
-                    value.push(self.current());
                     self.advance();
                 }
 
                 region.end = self.current_location();
 
-                result.push(Token::new(
+                let chars = &self.source[start..self.c];
+                return Ok(Some(Token::new(
                     region,
-                    match KEYWORDS.get(value.as_str()) {
-                        Some(v) => v.clone(),
-                        None => TokenValue::Identifier(value),
+                    match keyword(chars) {
+                        Some(v) => v,
+                        None => {
+                            let mut identifier = String::with_capacity(chars.len());
+                            identifier.extend(chars);
+                            TokenValue::Identifier(identifier)
+                        }
                     },
-                ))
+                )));
             } else {
                 return Err(LexerError::UnexpectedCharacter {
                     location: self.current_location(),
@@ -389,13 +530,34 @@ impl Lexer {
             }
         }
 
-        result.push(Token::new(
-            Region {
-                start: Location::from_index(&self.source, usize::MAX),
-                end: Location::from_index(&self.source, usize::MAX),
-            },
-            TokenValue::EndOfFile,
-        ));
-        Ok(result)
+        Ok(None)
+    }
+}
+
+impl Iterator for Lexer {
+    type Item = Result<Token, LexerError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        match self.next_token() {
+            Ok(Some(token)) => Some(Ok(token)),
+            Ok(None) => {
+                self.finished = true;
+                Some(Ok(Token::new(
+                    Region {
+                        start: self.current_location(),
+                        end: self.current_location(),
+                    },
+                    TokenValue::EndOfFile,
+                )))
+            }
+            Err(err) => {
+                self.finished = true;
+                Some(Err(err))
+            }
+        }
     }
 }
@@ -0,0 +1,82 @@
+// olang as a library, the olang binary is a thin command line wrapper around it
+
+pub mod builder;
+mod builtin;
+pub mod config;
+pub mod diagnostic;
+pub mod doctor;
+mod environment;
+pub mod explain;
+pub mod export;
+pub mod header;
+pub mod interpreter;
+pub mod io;
+pub mod lexer;
+pub mod lint;
+#[cfg(any(feature = "xml", feature = "html"))]
+mod markup;
+pub mod memory;
+pub mod optimizer;
+pub mod parser;
+pub mod printer;
+pub mod project;
+pub mod purity;
+mod random;
+pub mod repl;
+pub mod resolver;
+pub mod script;
+pub mod snapshot;
+pub mod test_runner;
+pub mod tokens;
+pub mod value;
+pub mod visitor;
+pub mod warnings;
+
+#[cfg(test)]
+mod tests;
+
+pub use interpreter::{ErrorReport, EvalError, EvalIter, Interpreter};
+pub use script::Script;
+pub use value::{ControlFlowValue, Exception, Native, Value};
+
+use io::MemoryIo;
+use lexer::{Lexer, LexerError, Token};
+use parser::{Parser, Program};
+
+// evaluate a program in a fresh interpreter
+pub fn eval(source: &str) -> Result<Value, EvalError> {
+    Interpreter::new().eval_str(source)
+}
+
+// every stage can be run on its own, for example by fuzz targets and property
+// tests. the tokens end with an EndOfFile token
+pub fn lex(source: &str) -> Result<Vec<Token>, LexerError> {
+    Lexer::new(source).collect()
+}
+
+pub fn parse(source: &str) -> Result<Program, EvalError> {
+    Ok(Parser::new(source)?.parse()?)
+}
+
+// every syntax error of the source, the program is not evaluated
+pub fn check(source: &str) -> Vec<EvalError> {
+    match Parser::new(source) {
+        Ok(mut parser) => parser
+            .parse_all()
+            .1
+            .into_iter()
+            .map(EvalError::from)
+            .collect(),
+        Err(err) => vec![err.into()],
+    }
+}
+
+// evaluate a program without touching the outside world, builtins with side
+// effects like printLn and sleep raise an exception and the fuel bounds how
+// long it runs, so the same source always gives the same result
+pub fn eval_pure(source: &str, fuel: u64) -> Result<Value, EvalError> {
+    Interpreter::with_fuel(fuel)
+        .set_io(MemoryIo::default())
+        .sandbox()
+        .eval_str(source)
+}
@@ -0,0 +1,172 @@
+use crate::{
+    interpreter::grow_stack,
+    lexer::Region,
+    parser::{Block, Expression, ExpressionValue, Program},
+    visitor::{self, Visitor},
+    warnings::{self, Warning},
+};
+use std::collections::HashSet;
+
+// the checks of `olang lint`, on top of the warnings printed on every run they
+// report variables which are never used and declarations which hide a variable
+// of an enclosing scope. both are often intentional, so they are left out of
+// normal runs. the scopes follow the ones the interpreter creates, see resolver
+pub fn lint(program: &Program) -> Vec<Warning> {
+    let mut scopes = Scopes::default();
+    scopes.scope(&[], |scopes| scopes.visit_block(&program.ast));
+
+    warnings::sorted(
+        warnings::check(program)
+            .into_iter()
+            .chain(scopes.warnings)
+            .collect(),
+    )
+}
+
+struct Variable {
+    identifier: String,
+    region: Region,
+    // parameters and loop variables are only checked for shadowing
+    declared_with_var: bool,
+}
+
+#[derive(Default)]
+struct Scope {
+    variables: Vec<Variable>,
+    // every name used in the scope or the scopes nested in it, a variable
+    // counts as used even when it is only used before its declaration, for
+    // example by a function declared earlier in the same scope
+    used: HashSet<String>,
+}
+
+#[derive(Default)]
+struct Scopes {
+    scopes: Vec<Scope>,
+    warnings: Vec<Warning>,
+}
+
+impl Scopes {
+    fn scope(&mut self, variables: &[(&str, &Region)], f: impl FnOnce(&mut Self)) {
+        self.scopes.push(Scope::default());
+        for (identifier, region) in variables {
+            self.declare(identifier, region, false);
+        }
+        f(self);
+
+        let scope = self.scopes.pop().unwrap();
+        // top level variables can be used by the host, the repl and other files
+        if !self.scopes.is_empty() {
+            for variable in &scope.variables {
+                if variable.declared_with_var && !scope.used.contains(&variable.identifier) {
+                    self.warnings.push(Warning {
+                        region: variable.region.clone(),
+                        message: format!(
+                            "the variable `{}` is never used, use `discard` if only the value is needed",
+                            variable.identifier
+                        ),
+                    });
+                }
+            }
+        }
+        if let Some(parent) = self.scopes.last_mut() {
+            parent.used.extend(scope.used);
+        }
+    }
+
+    fn declare(&mut self, identifier: &str, region: &Region, declared_with_var: bool) {
+        let (current, enclosing) = self.scopes.split_last_mut().unwrap();
+        let shadowed = enclosing
+            .iter()
+            .rev()
+            .flat_map(|scope| &scope.variables)
+            .find(|variable| variable.identifier == identifier);
+        if let (Some(shadowed), true) = (shadowed, declared_with_var) {
+            self.warnings.push(Warning {
+                region: region.clone(),
+                message: format!(
+                    "the variable `{}` shadows the variable declared at {}",
+                    identifier, shadowed.region.start
+                ),
+            });
+        }
+
+        current.variables.push(Variable {
+            identifier: identifier.to_string(),
+            region: region.clone(),
+            declared_with_var,
+        });
+    }
+
+    fn use_variable(&mut self, identifier: &str) {
+        self.scopes
+            .last_mut()
+            .unwrap()
+            .used
+            .insert(identifier.to_string());
+    }
+
+    fn visit_scope(&mut self, block: &Block) {
+        self.scope(&[], |scopes| scopes.visit_block(block));
+    }
+}
+
+impl Visitor for Scopes {
+    fn visit_expression(&mut self, expression: &Expression) {
+        grow_stack(|| {
+            match &expression.value {
+                ExpressionValue::VariableDeclaration {
+                    identifier,
+                    expression: value,
+                    ..
+                } => {
+                    self.visit_expression(value);
+                    self.declare(identifier, &expression.region, true);
+                }
+                ExpressionValue::Identifier(identifier, _)
+                | ExpressionValue::Update { identifier, .. } => self.use_variable(identifier),
+                ExpressionValue::Assign { identifier, .. }
+                | ExpressionValue::IndexAssign { identifier, .. }
+                | ExpressionValue::Call { identifier, .. } => {
+                    self.use_variable(identifier);
+                    visitor::visit_expression(self, expression);
+                }
+                // the parameters don't have regions of their own
+                ExpressionValue::Function(function) => {
+                    let parameters: Vec<_> = function
+                        .parameters
+                        .iter()
+                        .map(|parameter| (parameter.as_str(), &expression.region))
+                        .collect();
+                    self.scope(&parameters, |scopes| scopes.visit_block(&function.body));
+                }
+                ExpressionValue::Block(block) => self.visit_scope(block),
+                ExpressionValue::If {
+                    clauses,
+                    else_block,
+                } => {
+                    for clause in clauses {
+                        self.visit_expression(&clause.test);
+                        self.visit_scope(&clause.body);
+                    }
+                    if let Some(block) = else_block {
+                        self.visit_scope(block);
+                    }
+                }
+                ExpressionValue::Loop { .. } => {
+                    self.scope(&[], |scopes| visitor::visit_expression(scopes, expression))
+                }
+                ExpressionValue::ForIn {
+                    identifier,
+                    iterable,
+                    body,
+                } => {
+                    self.visit_expression(iterable);
+                    self.scope(&[(identifier, &expression.region)], |scopes| {
+                        scopes.visit_block(body)
+                    });
+                }
+                _ => visitor::visit_expression(self, expression),
+            }
+        })
+    }
+}
@@ -1,58 +1,698 @@
 use ::std::fs::File;
-use anyhow::Result;
-use interpreter::EvalError;
-use std::{io::Read, path::PathBuf};
+use anyhow::{anyhow, bail, Result};
+use olang::{
+    config::Config,
+    diagnostic::{self, OutputFormat},
+    doctor, explain,
+    export::{self, ExportFormat},
+    lint, optimizer,
+    parser::{Parser, Program},
+    project, repl, resolver,
+    test_runner::{self, ReportFormat},
+    tokens::{self, TokenFormat},
+    warnings, ErrorReport, EvalError, Interpreter, Value,
+};
+use signal_hook::consts::SIGINT;
+use std::{
+    env, fs,
+    io::{self, IsTerminal, Read, Write},
+    panic,
+    path::{Path, PathBuf},
+    process,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, OnceLock,
+    },
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
 use structopt::StructOpt;
-use value::Value;
+use strum::VariantNames;
+use thiserror::Error;
 
-mod builtin;
-mod environment;
-mod interpreter;
-mod lexer;
-mod parser;
-mod value;
-
-#[cfg(test)]
-mod tests;
-
-/// The easy to use interpreter
+/// The easy to use interpreter, starts an interactive session when no source is given
 #[derive(StructOpt, Debug)]
 #[structopt(name = "olang")]
 struct Options {
-    // /// print token sequence and AST of the source code
-    // #[structopt(short, long)]
-    // debug: bool,
+    /// Print the tokens of the source with their regions instead of running it
+    #[structopt(long)]
+    dump_tokens: bool,
+
+    /// Print the AST of the source instead of running it
+    #[structopt(long)]
+    dump_ast: bool,
+
+    /// Format of errors and of the dumps of tokens and the AST
+    #[structopt(long, default_value = "text", possible_values = OutputFormat::VARIANTS)]
+    output_format: OutputFormat,
+
     /// Source string to process
     #[structopt(short, long)]
     command_string: Option<String>,
 
-    /// Source file to process
+    /// Single expression to evaluate without side effects, its value is printed
+    #[structopt(long)]
+    expr: Option<String>,
+
+    /// Allow builtins with side effects like printLn in --expr
+    #[structopt(long)]
+    allow_side_effects: bool,
+
+    /// Stop the program after running this many seconds, for example 2.5
+    #[structopt(long, parse(try_from_str = parse_timeout))]
+    timeout: Option<Duration>,
+
+    /// Maximum depth of nested function calls, overrides OLANG_STACK_LIMIT
+    #[structopt(long)]
+    max_recursion: Option<usize>,
+
+    /// Run FILE again whenever it or a file it imports changes
+    #[structopt(long)]
+    watch: bool,
+
+    /// Print the number and size of the lists, strings and environments still alive when the program exits
+    #[structopt(long)]
+    mem_stats: bool,
+
+    /// Evaluate the program as written, without constant folding
+    #[structopt(long)]
+    no_optimize: bool,
+
+    /// Reuse the results of pure functions called with the same arguments again,
+    /// and of pure calls which give the same result in every iteration of a loop
+    #[structopt(long)]
+    memoize: bool,
+
+    /// Use a virtual clock so every run gives the same output, overrides OLANG_DETERMINISTIC
+    #[structopt(long)]
+    deterministic: bool,
+
+    /// Declare builtins like math.sum globally as well, as sum, overrides OLANG_FLAT_BUILTINS
+    #[structopt(long)]
+    flat_builtins: bool,
+
+    /// Print errors without colors, overrides OLANG_NO_COLOR
+    #[structopt(long)]
+    no_color: bool,
+
+    /// Don't load the prelude from ~/.config/olang/prelude.olang
+    #[structopt(long)]
+    no_prelude: bool,
+
+    /// Start from a snapshot written by `olang snapshot` instead of loading the prelude
+    #[structopt(long, parse(from_os_str))]
+    snapshot: Option<PathBuf>,
+
+    /// Source file to process, - reads the program from standard input
     #[structopt(name = "FILE", parse(from_os_str))]
     file: Option<PathBuf>,
+
+    /// Arguments after -- are passed to the program, which reads them with sys.args()
+    #[structopt(name = "ARGS", last = true)]
+    args: Vec<String>,
+
+    #[structopt(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(StructOpt, Debug)]
+enum Command {
+    /// Show the extended description of an error code
+    Explain {
+        /// Error code to explain, for example OL0012
+        code: String,
+    },
+    /// Evaluate a file without side effects and print its top level variables
+    Export {
+        /// Format of the output
+        #[structopt(long, default_value = "json", possible_values = ExportFormat::VARIANTS)]
+        format: ExportFormat,
+
+        /// Only export the variable with this name, for example config
+        #[structopt(long)]
+        value: Option<String>,
+
+        /// Source file to export
+        #[structopt(name = "FILE", parse(from_os_str))]
+        file: PathBuf,
+    },
+    /// Run test files and report the results
+    Test {
+        /// Format of the report
+        #[structopt(long, default_value = "text", possible_values = ReportFormat::VARIANTS)]
+        format: ReportFormat,
+
+        /// Write the report to a file instead of standard output
+        #[structopt(short, long, parse(from_os_str))]
+        output: Option<PathBuf>,
+
+        /// Test files or directories to run, directories are searched for files ending
+        /// with _test.olang. defaults to the tests of the current project
+        #[structopt(name = "FILES", parse(from_os_str))]
+        files: Vec<PathBuf>,
+    },
+    /// Report the syntax errors of files without running them
+    Check {
+        /// Files to check, defaults to the files of the current project
+        #[structopt(name = "FILES", parse(from_os_str))]
+        files: Vec<PathBuf>,
+    },
+    /// Report suspicious code like unused variables without running it
+    Lint {
+        /// Files to lint, defaults to the files of the current project
+        #[structopt(name = "FILES", parse(from_os_str))]
+        files: Vec<PathBuf>,
+    },
+    /// Print the tokens of a file with their kind, value and position
+    Tokens {
+        /// Format of the output
+        #[structopt(long, default_value = "text", possible_values = TokenFormat::VARIANTS)]
+        format: TokenFormat,
+
+        /// Source file to split into tokens
+        #[structopt(name = "FILE", parse(from_os_str))]
+        file: PathBuf,
+    },
+    /// Create a new project with an olang.toml, main.olang and tests directory
+    New {
+        /// Directory to create, its name is used as the project name
+        #[structopt(name = "PATH", parse(from_os_str))]
+        path: PathBuf,
+    },
+    /// Run the entry point of the project in the current directory
+    Run,
+    /// Print the version, features, paths and settings olang runs with, for bug reports
+    Doctor,
+    /// Run a file after the prelude and save the resulting variables and
+    /// imported modules, --snapshot starts later runs from them
+    Snapshot {
+        /// File to write the snapshot to
+        #[structopt(short, long, parse(from_os_str))]
+        output: PathBuf,
+
+        /// Source file which declares the variables and imports the modules
+        #[structopt(name = "FILE", parse(from_os_str))]
+        file: PathBuf,
+    },
+}
+
+// the flag set by Ctrl-C, pressing Ctrl-C again before it is cleared
+// terminates the process. the handlers are registered the first time an
+// interpreter is created and shared by all of them, so --watch doesn't add
+// new ones for every run
+fn ctrl_c_flag() -> Result<Arc<AtomicBool>> {
+    static FLAG: OnceLock<Arc<AtomicBool>> = OnceLock::new();
+    if let Some(flag) = FLAG.get() {
+        return Ok(flag.clone());
+    }
+    let flag = Arc::new(AtomicBool::new(false));
+    signal_hook::flag::register_conditional_shutdown(SIGINT, 130, flag.clone())?;
+    signal_hook::flag::register(SIGINT, flag.clone())?;
+    Ok(FLAG.get_or_init(|| flag).clone())
+}
+
+// create an interpreter which raises an Interrupted exception on Ctrl-C
+fn interruptible_interpreter(
+    config: &Config,
+    prelude: Option<&str>,
+    snapshot: Option<&[u8]>,
+) -> Result<Interpreter> {
+    let mut interpreter = Interpreter::with_config(config);
+    interpreter.set_interrupt_flag(ctrl_c_flag()?);
+
+    // the snapshot already contains what the prelude declared
+    if let Some(snapshot) = snapshot {
+        interpreter.restore_snapshot(snapshot)?;
+    } else if let Some(prelude) = prelude {
+        interpreter.load_prelude(prelude).map_err(|err| {
+            let report = ErrorReport::from(err).with_source(prelude);
+            ErrorReport {
+                message: format!("in the prelude: {}", report.message),
+                ..report
+            }
+        })?;
+    }
+    Ok(interpreter)
+}
+
+// the user's own prelude, its functions are available in every program run
+// from the command line. tests and exports don't load it so they behave the
+// same on every machine
+fn read_prelude() -> Result<Option<String>> {
+    match prelude_path() {
+        Some(path) if path.is_file() => Ok(Some(fs::read_to_string(path)?)),
+        _ => Ok(None),
+    }
+}
+
+fn prelude_path() -> Option<PathBuf> {
+    let config_dir = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_dir.join("olang").join("prelude.olang"))
+}
+
+fn parse(source: &str) -> Result<Program, EvalError> {
+    Ok(Parser::new(source)?.parse()?)
 }
 
-pub fn eval(source: &str) -> Result<Value, EvalError> {
-    interpreter::Interpreter::new().eval(source)
+fn parse_timeout(seconds: &str) -> Result<Duration> {
+    Ok(Duration::try_from_secs_f64(seconds.parse()?)?)
 }
 
-fn main() -> Result<()> {
-    run_cli()?;
+// evaluate a program after reporting the warnings found in it
+fn run_source(
+    source: &str,
+    interpreter: &mut Interpreter,
+    optimize: bool,
+    timeout: Option<Duration>,
+    mem_stats: bool,
+) -> Result<Value> {
+    let report = report(source);
+    let mut program = parse(source).map_err(&report)?;
+    for warning in warnings::check(&program) {
+        eprintln!("warning: {}", warning);
+    }
+    if optimize {
+        program = optimizer::optimize(program);
+    }
+    let program = resolver::resolve(program);
+
+    let result = interpreter.set_timeout(timeout).eval_program(&program);
+    if mem_stats {
+        eprintln!("{}", interpreter.memory_stats());
+    }
+    result.map_err(report)
+}
+
+// the program called exit(), the process exits with its status
+#[derive(Error, Debug)]
+#[error("the program exited with status {0}")]
+struct Exit(i32);
+
+// errors of olang programs are rendered by run_cli with the line they point at
+fn report(source: &str) -> impl Fn(EvalError) -> anyhow::Error + '_ {
+    move |err| match err {
+        EvalError::Exit(code) => Exit(code).into(),
+        err => ErrorReport::from(err).with_source(source).into(),
+    }
+}
+
+// the given files, or the entry and the test files of the current project
+fn source_files(files: Vec<PathBuf>) -> Result<Vec<PathBuf>> {
+    if !files.is_empty() {
+        return Ok(files);
+    }
+    let project = project::find(&env::current_dir()?)?;
+    let mut files = project.test_files()?;
+    files.insert(0, project.entry);
+    Ok(files)
+}
+
+fn print_file_error(file: &Path, source: &str, err: EvalError, format: OutputFormat, color: bool) {
+    let report = ErrorReport::from(err).with_source(source);
+    let report = ErrorReport {
+        message: format!("{}: {}", file.display(), report.message),
+        ..report
+    };
+    match format {
+        OutputFormat::Text => eprint!("{}", diagnostic::render(&report, color)),
+        OutputFormat::Json => eprint!("{}", diagnostic::render_json(&report)),
+    }
+}
+
+// print the tokens and the parsed AST of a program without evaluating it
+fn dump_source(
+    source: &str,
+    dump_tokens: bool,
+    dump_ast: bool,
+    format: OutputFormat,
+) -> Result<()> {
+    let report = |err| ErrorReport::from(err).with_source(source);
+    if dump_tokens {
+        let token_format = match format {
+            OutputFormat::Text => TokenFormat::Text,
+            OutputFormat::Json => TokenFormat::Json,
+        };
+        let tokens = tokens::dump(source, token_format).map_err(EvalError::from);
+        print!("{}", tokens.map_err(report)?);
+    }
+    if dump_ast {
+        let program = parse(source).map_err(report)?;
+        match format {
+            OutputFormat::Text => println!("{:#?}", program),
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&program)?),
+        }
+    }
     Ok(())
 }
 
-pub fn run_cli() -> Result<()> {
+// nested olang calls grow the stack by themselves, but deeply nested values
+// are still printed, compared and dropped recursively on the native stack, so
+// the interpreter runs on a thread with a larger stack than the main thread
+const STACK_SIZE: usize = 256 * 1024 * 1024;
+
+fn main() {
+    let cli = thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(run_cli)
+        .expect("failed to start the interpreter thread");
+    let result = cli
+        .join()
+        .unwrap_or_else(|panic| panic::resume_unwind(panic));
+
+    if let Err(err) = result {
+        eprintln!("error: {}", err);
+        process::exit(1);
+    }
+}
+
+// errors of olang programs are rendered with the source line they point at,
+// every other error is left to main
+fn run_cli() -> Result<()> {
     let options = Options::from_args();
+    let mut config = Config::from_env()?;
+    if let Some(limit) = options.max_recursion {
+        config.stack_limit = Some(limit);
+    }
+    config.deterministic |= options.deterministic;
+    config.flat_builtins |= options.flat_builtins;
+    config.color &= !options.no_color && io::stderr().is_terminal();
+
+    let color = config.color;
+    let format = options.output_format;
+    run(options, config).or_else(|err| process::exit(print_error(err, format, color)?))
+}
+
+// render the error of an olang program and return the status the process exits
+// with, every other error is given back
+fn print_error(err: anyhow::Error, format: OutputFormat, color: bool) -> Result<i32> {
+    if let Some(Exit(code)) = err.downcast_ref() {
+        return Ok(*code);
+    }
+    let report = err.downcast::<ErrorReport>()?;
+    match format {
+        OutputFormat::Text => eprint!("{}", diagnostic::render(&report, color)),
+        OutputFormat::Json => eprint!("{}", diagnostic::render_json(&report)),
+    }
+    Ok(report.exit_code)
+}
+
+// the modification times of the files, files which can't be read have none
+fn modified(files: &[PathBuf]) -> Vec<Option<SystemTime>> {
+    files
+        .iter()
+        .map(|file| {
+            fs::metadata(file)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+        })
+        .collect()
+}
+
+// run the file in a fresh interpreter every time it or one of the files it
+// imports changes, until Ctrl-C is pressed while waiting for a change. the
+// files are polled since editors save them in too many different ways
+fn watch(
+    file: &Path,
+    interpreter: impl Fn() -> Result<Interpreter>,
+    optimize: bool,
+    timeout: Option<Duration>,
+    mem_stats: bool,
+    format: OutputFormat,
+    color: bool,
+) -> Result<()> {
+    loop {
+        print!("\x1b[2J\x1b[H");
+        io::stdout().flush()?;
+
+        let mut interpreter = interpreter()?;
+        interpreter.set_path(file);
+        let start = Instant::now();
+        let result = fs::read_to_string(file)
+            .map_err(anyhow::Error::from)
+            .and_then(|source| run_source(&source, &mut interpreter, optimize, timeout, mem_stats));
+        let elapsed = start.elapsed();
+        match result {
+            Ok(_) => eprintln!("finished in {:.2?}", elapsed),
+            Err(err) => {
+                let status = print_error(err, format, color)?;
+                eprintln!("exited with status {} after {:.2?}", status, elapsed);
+            }
+        }
+
+        let mut files = vec![file.to_path_buf()];
+        files.extend(interpreter.imported_files().map(Path::to_path_buf));
+        eprintln!("waiting for changes");
+        let interrupted = interpreter.interrupt_flag();
+        let before = modified(&files);
+        while modified(&files) == before {
+            if interrupted.load(Ordering::Relaxed) {
+                return Ok(());
+            }
+            thread::sleep(WATCH_INTERVAL);
+        }
+    }
+}
 
-    if let Some(command) = options.command_string {
-        eval(command.as_str())?;
+const WATCH_INTERVAL: Duration = Duration::from_millis(200);
+
+fn run(options: Options, config: Config) -> Result<()> {
+    let prelude = if options.no_prelude {
+        None
+    } else {
+        read_prelude()?
+    };
+    let snapshot = options.snapshot.as_ref().map(fs::read).transpose()?;
+    let interpreter = || -> Result<Interpreter> {
+        let mut interpreter =
+            interruptible_interpreter(&config, prelude.as_deref(), snapshot.as_deref())?;
+        interpreter
+            .set_args(options.args.clone())
+            .set_memoize(options.memoize);
+        Ok(interpreter)
     };
 
-    if let Some(path) = options.file {
-        let mut file = File::open(path)?;
-        let mut content = String::new();
-        file.read_to_string(&mut content)?;
+    match options.command {
+        Some(Command::Explain { code }) => {
+            match explain::explain(&code) {
+                Some(explanation) => print!("{}", explanation),
+                None => bail!("{} is not a valid error code", code),
+            }
+            return Ok(());
+        }
+        Some(Command::Export {
+            format,
+            value,
+            file,
+        }) => {
+            let source = fs::read_to_string(file)?;
+            let output = export::export(&source, value.as_deref(), format, &config)?;
+            println!("{}", output.trim_end());
+            return Ok(());
+        }
+        Some(Command::Test {
+            format,
+            output,
+            mut files,
+        }) => {
+            if files.is_empty() {
+                files = project::find(&env::current_dir()?)?.test_files()?;
+            }
+            let files = files
+                .iter()
+                .map(|file| test_runner::discover(file))
+                .collect::<io::Result<Vec<_>>>()?
+                .concat();
+            let results: Vec<_> = files
+                .iter()
+                .flat_map(|file| test_runner::run_file(file, &config))
+                .collect();
+            let report = test_runner::report(&results, format);
+            match output {
+                Some(path) => fs::write(path, report)?,
+                None => print!("{}", report),
+            }
+
+            if !results.iter().all(|result| result.passed()) {
+                process::exit(1);
+            }
+            return Ok(());
+        }
+        Some(Command::Check { files }) => {
+            let mut failed = false;
+            for file in source_files(files)? {
+                let source = fs::read_to_string(&file)?;
+                for err in olang::check(&source) {
+                    print_file_error(&file, &source, err, options.output_format, config.color);
+                    failed = true;
+                }
+            }
+
+            if failed {
+                process::exit(1);
+            }
+            return Ok(());
+        }
+        Some(Command::Lint { files }) => {
+            let mut failed = false;
+            for file in source_files(files)? {
+                let source = fs::read_to_string(&file)?;
+                let program = match parse(&source) {
+                    Ok(program) => program,
+                    Err(err) => {
+                        print_file_error(&file, &source, err, options.output_format, config.color);
+                        failed = true;
+                        continue;
+                    }
+                };
+                for warning in lint::lint(&program) {
+                    match options.output_format {
+                        OutputFormat::Text => {
+                            eprintln!("{}: warning: {}", file.display(), warning)
+                        }
+                        OutputFormat::Json => {
+                            eprintln!("{}", serde_json::json!({"file": file, "warning": warning}))
+                        }
+                    }
+                    failed = true;
+                }
+            }
 
-        eval(content.as_str())?;
+            if failed {
+                process::exit(1);
+            }
+            return Ok(());
+        }
+        Some(Command::Tokens { format, file }) => {
+            let source = fs::read_to_string(file)?;
+            let output = tokens::dump(&source, format).map_err(EvalError::from);
+            print!(
+                "{}",
+                output.map_err(|err| ErrorReport::from(err).with_source(&source))?
+            );
+            return Ok(());
+        }
+        Some(Command::New { path }) => {
+            let project = project::create(&path)?;
+            println!("Created project {} in {}", project.name, path.display());
+            return Ok(());
+        }
+        Some(Command::Run) => {
+            let project = project::find(&env::current_dir()?)?;
+            let source = fs::read_to_string(&project.entry)?;
+            let mut interpreter = interpreter()?;
+            interpreter.set_path(&project.entry);
+            run_source(
+                &source,
+                &mut interpreter,
+                !options.no_optimize,
+                options.timeout,
+                options.mem_stats,
+            )?;
+            return Ok(());
+        }
+        Some(Command::Doctor) => {
+            print!(
+                "{}",
+                doctor::report(
+                    &config,
+                    env::vars(),
+                    prelude_path().as_deref(),
+                    repl::history_path().as_deref()
+                )
+            );
+            return Ok(());
+        }
+        Some(Command::Snapshot { output, file }) => {
+            let source = fs::read_to_string(&file)?;
+            let mut interpreter = interpreter()?;
+            interpreter.set_path(&file);
+            run_source(&source, &mut interpreter, !options.no_optimize, None, false)?;
+            fs::write(output, interpreter.snapshot()?)?;
+            return Ok(());
+        }
+        None => {}
+    }
+
+    // a program piped into olang is run like a file, the session is only
+    // started when someone can type into it
+    let stdin = Path::new("-");
+    let file = match options.file.as_deref() {
+        None if options.expr.is_none()
+            && options.command_string.is_none()
+            && !io::stdin().is_terminal() =>
+        {
+            Some(stdin)
+        }
+        file => file,
+    };
+    if options.watch {
+        let file = file
+            .filter(|file| *file != stdin)
+            .ok_or_else(|| anyhow!("--watch needs a FILE to run"))?;
+        return watch(
+            file,
+            interpreter,
+            !options.no_optimize,
+            options.timeout,
+            options.mem_stats,
+            options.output_format,
+            config.color,
+        );
+    }
+    if options.expr.is_none() && options.command_string.is_none() && file.is_none() {
+        return repl::run(interpreter()?, config.color);
+    }
+
+    if let Some(expression) = options.expr {
+        let mut interpreter = interpreter()?;
+        interpreter.allow_expression_side_effects(options.allow_side_effects);
+        let value = interpreter
+            .eval_expression_str(&expression)
+            .map_err(report(&expression))?;
+        println!("{}", value);
+    };
+
+    // imports are relative to the file, or to the working directory for -c
+    let process = |source: &str, path: Option<&Path>| -> Result<()> {
+        if options.dump_tokens || options.dump_ast {
+            return dump_source(
+                source,
+                options.dump_tokens,
+                options.dump_ast,
+                options.output_format,
+            );
+        }
+        let mut interpreter = interpreter()?;
+        if let Some(path) = path {
+            interpreter.set_path(path);
+        }
+        run_source(
+            source,
+            &mut interpreter,
+            !options.no_optimize,
+            options.timeout,
+            options.mem_stats,
+        )?;
+        Ok(())
+    };
+
+    if let Some(command) = &options.command_string {
+        process(command, None)?;
+    };
+
+    // `-` reads the program from standard input, its imports are relative to
+    // the working directory like the ones of -c
+    if let Some(path) = file {
+        let mut content = String::new();
+        if path == stdin {
+            io::stdin().read_to_string(&mut content)?;
+            process(&content, None)?;
+        } else {
+            File::open(path)?.read_to_string(&mut content)?;
+            process(&content, Some(path))?;
+        }
     };
 
     Ok(())
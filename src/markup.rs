@@ -0,0 +1,111 @@
+use crate::value::{ControlFlowValue, Exception, Value};
+use std::collections::BTreeMap;
+
+// elements of both xml and html documents become maps with their name, their
+// attributes, their children, which are elements or strings of text, and all
+// the text inside of them. text between elements that is only whitespace, like
+// the indentation of a document, is left out of the children
+fn element(name: &str, attributes: BTreeMap<String, Value>, children: Vec<Value>) -> Value {
+    let text: String = children
+        .iter()
+        .map(|child| match child {
+            Value::String(text) => text.as_str(),
+            Value::Map(element) => match element.get("text") {
+                Some(Value::String(text)) => text.as_str(),
+                _ => "",
+            },
+            _ => "",
+        })
+        .collect();
+
+    Value::Map(BTreeMap::from([
+        ("name".to_string(), Value::String(name.to_string())),
+        ("attributes".to_string(), Value::Map(attributes)),
+        ("children".to_string(), Value::list(children)),
+        ("text".to_string(), Value::String(text)),
+    ]))
+}
+
+fn markup_error(builtin: &str, message: impl ToString) -> ControlFlowValue {
+    ControlFlowValue::Exception(
+        Exception::Custom(format!("{}: {}", builtin, message.to_string())),
+        None,
+    )
+}
+
+#[cfg(feature = "xml")]
+fn xml_element(node: roxmltree::Node) -> Value {
+    let attributes = node
+        .attributes()
+        .map(|attribute| {
+            (
+                attribute.name().to_string(),
+                Value::String(attribute.value().to_string()),
+            )
+        })
+        .collect();
+    let children = node
+        .children()
+        .filter_map(|child| match child.text() {
+            _ if child.is_element() => Some(xml_element(child)),
+            Some(text) if child.is_text() && !text.trim().is_empty() => {
+                Some(Value::String(text.to_string()))
+            }
+            _ => None,
+        })
+        .collect();
+    element(node.tag_name().name(), attributes, children)
+}
+
+// the root element of the document
+#[cfg(feature = "xml")]
+pub fn xml_parse(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    if arguments.len() != 1 {
+        return Err(ControlFlowValue::Exception(
+            Exception::WrongNumberOfArguments,
+            None,
+        ));
+    }
+    let document = roxmltree::Document::parse(arguments[0].into_str()?)
+        .map_err(|err| markup_error("xmlParse", err))?;
+    Ok(xml_element(document.root_element()))
+}
+
+#[cfg(feature = "html")]
+fn html_element(node: scraper::ElementRef) -> Value {
+    let attributes = node
+        .value()
+        .attrs()
+        .map(|(name, value)| (name.to_string(), Value::String(value.to_string())))
+        .collect();
+    let children = node
+        .children()
+        .filter_map(|child| match child.value() {
+            scraper::Node::Element(_) => scraper::ElementRef::wrap(child).map(html_element),
+            scraper::Node::Text(text) if !text.trim().is_empty() => {
+                Some(Value::String(text.to_string()))
+            }
+            _ => None,
+        })
+        .collect();
+    element(node.value().name(), attributes, children)
+}
+
+// the elements of an html document which match a css selector, in the order
+// they appear in the document. browsers accept almost any html, so only the
+// selector can be invalid
+#[cfg(feature = "html")]
+pub fn html_query(arguments: Vec<Value>) -> Result<Value, ControlFlowValue> {
+    if arguments.len() != 2 {
+        return Err(ControlFlowValue::Exception(
+            Exception::WrongNumberOfArguments,
+            None,
+        ));
+    }
+    let document = scraper::Html::parse_document(arguments[0].into_str()?);
+    let selector = scraper::Selector::parse(arguments[1].into_str()?)
+        .map_err(|err| markup_error("htmlQuery", err))?;
+    Ok(Value::List(
+        document.select(&selector).map(html_element).collect(),
+    ))
+}
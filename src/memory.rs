@@ -0,0 +1,111 @@
+use crate::{
+    environment::ScopeRef,
+    value::{Function, Value},
+};
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt,
+    mem::size_of,
+};
+
+// the values and scopes the running program can still reach, the bytes are an
+// estimate of their heap allocations and leave out the allocator overhead
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MemoryStats {
+    pub lists: usize,
+    pub list_bytes: usize,
+    pub strings: usize,
+    pub string_bytes: usize,
+    pub maps: usize,
+    pub map_bytes: usize,
+    pub environments: usize,
+    pub environment_bytes: usize,
+}
+
+impl MemoryStats {
+    pub fn total_bytes(&self) -> usize {
+        self.list_bytes + self.string_bytes + self.map_bytes + self.environment_bytes
+    }
+
+    // count the value and everything inside of it, the scopes of closures are
+    // collected so the environment can walk them afterwards. lists are shared
+    // between bindings, every list is only counted once
+    pub(crate) fn add_value(
+        &mut self,
+        value: &Value,
+        scopes: &mut Vec<ScopeRef>,
+        lists: &mut HashSet<*const ()>,
+    ) {
+        match value {
+            Value::String(v) => {
+                self.strings += 1;
+                self.string_bytes += v.capacity();
+            }
+            Value::List(list) => {
+                if !lists.insert(list.id()) {
+                    return;
+                }
+                let list = list.borrow();
+                self.lists += 1;
+                self.list_bytes += list.capacity() * size_of::<Value>();
+                for v in list.iter() {
+                    self.add_value(v, scopes, lists);
+                }
+            }
+            Value::Map(map) => {
+                self.maps += 1;
+                for (key, v) in map {
+                    self.map_bytes += size_of::<String>() + size_of::<Value>() + key.capacity();
+                    self.add_value(v, scopes, lists);
+                }
+            }
+            Value::Function(Function::Defined(closure)) => scopes.push(closure.scope()),
+            Value::Function(Function::Builtin(_))
+            | Value::Int(_)
+            | Value::Float(_)
+            | Value::Bool(_)
+            | Value::Native(_)
+            | Value::Null => {}
+        }
+    }
+
+    pub fn to_value(&self) -> Value {
+        let entries = [
+            ("lists", self.lists),
+            ("listBytes", self.list_bytes),
+            ("strings", self.strings),
+            ("stringBytes", self.string_bytes),
+            ("maps", self.maps),
+            ("mapBytes", self.map_bytes),
+            ("environments", self.environments),
+            ("environmentBytes", self.environment_bytes),
+            ("totalBytes", self.total_bytes()),
+        ];
+        Value::Map(BTreeMap::from_iter(
+            entries
+                .into_iter()
+                .map(|(key, v)| (key.to_string(), Value::Int(v as i64))),
+        ))
+    }
+}
+
+impl fmt::Display for MemoryStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rows = [
+            ("lists", self.lists, self.list_bytes),
+            ("strings", self.strings, self.string_bytes),
+            ("maps", self.maps, self.map_bytes),
+            ("environments", self.environments, self.environment_bytes),
+        ];
+        for (name, count, bytes) in rows {
+            writeln!(f, "{:<14}{:>10}{:>14} bytes", name, count, bytes)?;
+        }
+        write!(
+            f,
+            "{:<14}{:>10}{:>14} bytes",
+            "total",
+            "",
+            self.total_bytes()
+        )
+    }
+}
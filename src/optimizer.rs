@@ -0,0 +1,149 @@
+use crate::{
+    interpreter::{binary_operation, unary_operation},
+    parser::{BinaryOperationOperator, Block, Expression, ExpressionValue, IfClause, Program},
+    purity,
+    value::Value,
+    visitor::{self, Fold},
+};
+
+// rewrites the AST before it is evaluated, folding operations on literals,
+// removing `if false` branches and flattening blocks that only hold a block.
+// an operation that would fail at runtime is left alone so the error is still
+// raised when (and if) the expression is evaluated. afterwards the pure
+// functions are marked, see purity
+pub fn optimize(program: Program) -> Program {
+    let mut optimizer = Optimizer;
+    purity::mark(Program {
+        ast: optimizer.fold_block(program.ast),
+    })
+}
+
+struct Optimizer;
+
+fn literal(expression: &Expression) -> Option<Value> {
+    Some(match &expression.value {
+        ExpressionValue::Int(v) => Value::Int(*v),
+        ExpressionValue::Float(v) => Value::Float(*v),
+        ExpressionValue::String(v) => Value::String(v.clone()),
+        ExpressionValue::Bool(v) => Value::Bool(*v),
+        ExpressionValue::Null => Value::Null,
+        _ => return None,
+    })
+}
+
+fn from_value(value: Value) -> Option<ExpressionValue> {
+    Some(match value {
+        Value::Int(v) => ExpressionValue::Int(v),
+        Value::Float(v) => ExpressionValue::Float(v),
+        Value::String(v) => ExpressionValue::String(v),
+        Value::Bool(v) => ExpressionValue::Bool(v),
+        Value::Null => ExpressionValue::Null,
+        _ => return None,
+    })
+}
+
+// a repeated string is only folded when it is short, a long one would take up
+// memory in the AST even when the expression never runs
+const MAX_FOLDED_STRING: usize = 4096;
+
+// integer arithmetic panics on overflow and division by zero, those operations
+// are only folded when the checked version succeeds
+fn skip_folding(operator: &BinaryOperationOperator, left: &Value, right: &Value) -> bool {
+    if let (BinaryOperationOperator::Multiply, Value::String(string), Value::Int(count)) =
+        (operator, left, right)
+    {
+        return usize::try_from(*count)
+            .ok()
+            .and_then(|count| string.len().checked_mul(count))
+            .is_none_or(|len| len > MAX_FOLDED_STRING);
+    }
+    let (Value::Int(left), Value::Int(right)) = (left, right) else {
+        return false;
+    };
+
+    match operator {
+        BinaryOperationOperator::Plus => left.checked_add(*right).is_none(),
+        BinaryOperationOperator::Minus => left.checked_sub(*right).is_none(),
+        BinaryOperationOperator::Multiply => left.checked_mul(*right).is_none(),
+        BinaryOperationOperator::Divide => left.checked_div(*right).is_none(),
+        BinaryOperationOperator::Modulus => left.checked_rem(*right).is_none(),
+        _ => false,
+    }
+}
+
+fn fold_if(clauses: Vec<IfClause>, else_block: Option<Block>) -> ExpressionValue {
+    let mut kept = Vec::new();
+    let mut else_block = else_block;
+
+    for clause in clauses {
+        match clause.test.value {
+            ExpressionValue::Bool(false) => continue,
+            ExpressionValue::Bool(true) => {
+                // every clause after this one and the else block are unreachable
+                else_block = Some(clause.body);
+                break;
+            }
+            _ => kept.push(clause),
+        }
+    }
+
+    if kept.is_empty() {
+        return match else_block {
+            Some(block) => ExpressionValue::Block(block),
+            None => ExpressionValue::Null,
+        };
+    }
+
+    ExpressionValue::If {
+        clauses: kept,
+        else_block,
+    }
+}
+
+impl Fold for Optimizer {
+    fn fold_expression(&mut self, expression: Expression) -> Expression {
+        let expression = visitor::fold_expression(self, expression);
+        let region = expression.region;
+
+        let value = match expression.value {
+            ExpressionValue::Binary {
+                left,
+                operator,
+                right,
+            } => {
+                let folded = match (literal(&left), literal(&right)) {
+                    (Some(l), Some(r)) if !skip_folding(&operator, &l, &r) => {
+                        binary_operation(&operator, l, r).ok().and_then(from_value)
+                    }
+                    _ => None,
+                };
+                folded.unwrap_or(ExpressionValue::Binary {
+                    left,
+                    operator,
+                    right,
+                })
+            }
+            ExpressionValue::Unary { operator, operand } => literal(&operand)
+                .and_then(|value| unary_operation(&operator, value).ok())
+                .and_then(from_value)
+                .unwrap_or(ExpressionValue::Unary { operator, operand }),
+            ExpressionValue::If {
+                clauses,
+                else_block,
+            } => fold_if(clauses, else_block),
+            ExpressionValue::Block(mut block) => match block.pop() {
+                Some(Expression {
+                    value: ExpressionValue::Block(inner),
+                    ..
+                }) if block.is_empty() => ExpressionValue::Block(inner),
+                last => {
+                    block.extend(last);
+                    ExpressionValue::Block(block)
+                }
+            },
+            value => value,
+        };
+
+        Expression { region, value }
+    }
+}
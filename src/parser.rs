@@ -1,4 +1,10 @@
-use crate::lexer::{Lexer, LexerError, Region, Token, TokenValue, TokenValueDiscriminants};
+use crate::{
+    header::{self, HeaderError},
+    interpreter::grow_stack,
+    lexer::{Lexer, LexerError, Region, Token, TokenValue, TokenValueDiscriminants},
+};
+use serde::{Deserialize, Serialize};
+use std::num::NonZeroU64;
 use strum::{Display, EnumDiscriminants};
 use thiserror::Error;
 
@@ -15,16 +21,56 @@ pub enum ParserError {
         while_parsing: Option<ExpressionValueDiscriminants>,
         found: Token,
     },
+    #[error(transparent)]
+    Lexer(#[from] LexerError),
+    #[error(transparent)]
+    Header(#[from] HeaderError),
+    #[error("{} expressions are nested more than {limit} levels deep", .found.region)]
+    TooDeeplyNested { found: Token, limit: usize },
 }
 
-#[derive(Debug, Clone)]
+impl ParserError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ParserError::ExpectedToken { .. } => "OL0003",
+            ParserError::UnexpectedToken { .. } => "OL0004",
+            ParserError::Lexer(err) => err.code(),
+            ParserError::Header(_) => "OL0032",
+            ParserError::TooDeeplyNested { .. } => "OL0033",
+        }
+    }
+
+    pub fn region(&self) -> Region {
+        match self {
+            ParserError::ExpectedToken { found, .. }
+            | ParserError::UnexpectedToken { found, .. }
+            | ParserError::TooDeeplyNested { found, .. } => found.region.clone(),
+            ParserError::Lexer(err) => err.region(),
+            ParserError::Header(err) => err.region(),
+        }
+    }
+
+    // whether the source ended in the middle of an expression, meaning that
+    // more input could make it valid
+    pub fn is_unexpected_end(&self) -> bool {
+        match self {
+            ParserError::ExpectedToken { found, .. }
+            | ParserError::UnexpectedToken { found, .. } => found.value == TokenValue::EndOfFile,
+            ParserError::Lexer(_)
+            | ParserError::Header(_)
+            | ParserError::TooDeeplyNested { .. } => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum BinaryOperationOperator {
     Plus,                 // +
     Minus,                // -
     Multiply,             // *
     Divide,               // /
     Modulus,              // %
-    Exponentiation,       // ^
+    Exponentiation,       // **
     IsLessThan,           // <
     IsLessThanOrEqual,    // <=
     IsGreaterThan,        // >
@@ -35,7 +81,13 @@ pub enum BinaryOperationOperator {
     LogicalOr,            // ||
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum UnaryOperator {
+    Negate, // -
+    Not,    // !
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum AssignmentOperator {
     Set,      // =
     Plus,     // +=
@@ -45,7 +97,19 @@ pub enum AssignmentOperator {
     Modulo,   // %=
 }
 
-#[derive(Debug, Clone)]
+fn assignment_operator(value: &TokenValue) -> Option<AssignmentOperator> {
+    match value {
+        TokenValue::EqualSign => Some(AssignmentOperator::Set),
+        TokenValue::AdditionAssign => Some(AssignmentOperator::Plus),
+        TokenValue::SubtractionAssign => Some(AssignmentOperator::Minus),
+        TokenValue::MultiplicationAssign => Some(AssignmentOperator::Multiply),
+        TokenValue::DivisionAssign => Some(AssignmentOperator::Divide),
+        TokenValue::ModuloAssign => Some(AssignmentOperator::Modulo),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum UpdateOperator {
     Increment, // ++
     Decremet,  // --
@@ -53,55 +117,126 @@ pub enum UpdateOperator {
 
 pub type Block = Vec<Expression>;
 
-#[derive(Debug, Clone)]
+// where a variable lives relative to the scope it is used in, the number of
+// scopes to go up and the index of the value in that scope. filled in by the
+// resolver, variables without a slot are looked up by name
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Slot {
+    pub depth: usize,
+    pub index: usize,
+}
+
+// where a function was defined and its code as it was written, functions built
+// with the builder have a synthetic region and no code
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FunctionSource {
+    pub region: Region,
+    pub code: Option<String>,
+}
+
+impl FunctionSource {
+    pub fn synthetic() -> Self {
+        FunctionSource {
+            region: Region::synthetic(),
+            code: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DefinedFunction {
     pub parameters: Vec<String>,
     pub body: Block,
+    // boxed to keep values small, they are moved around in every result
+    pub source: Box<FunctionSource>,
+    // set by the optimizer when the result of the function only depends on its
+    // arguments, see purity. the id tells the functions apart in the memo cache
+    // of the interpreter, it is only valid in the process which assigned it.
+    // never zero, which keeps the option as small as the id
+    #[serde(skip)]
+    pub pure: Option<NonZeroU64>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IfClause {
     pub test: Box<Expression>,
     pub body: Vec<Expression>,
 }
 
-#[derive(Debug, Clone, EnumDiscriminants)]
+#[derive(Debug, Clone, EnumDiscriminants, Serialize, Deserialize)]
 #[strum_discriminants(derive(Display))]
 pub enum ExpressionValue {
     Int(i64),
+    Float(f64),
     String(String),
     Bool(bool),
     Null,
     List(Vec<Expression>),
+    Map(Vec<(Expression, Expression)>),
     Block(Block),
-    Identifier(String),
+    Identifier(String, Option<Slot>),
     Binary {
         left: Box<Expression>,
         operator: BinaryOperationOperator,
         right: Box<Expression>,
     },
+    Unary {
+        operator: UnaryOperator,
+        operand: Box<Expression>,
+    },
     VariableDeclaration {
         identifier: String,
+        // declarations always go into the current scope, only the index is needed
+        slot: Option<usize>,
         expression: Box<Expression>,
     },
     Assign {
         identifier: String,
+        slot: Option<Slot>,
         operator: AssignmentOperator,
         expression: Box<Expression>,
     },
     Update {
         identifier: String,
+        slot: Option<Slot>,
         operator: UpdateOperator,
     },
+    // assignment into an element of a list or map, `map !! "key" = value`
+    IndexAssign {
+        identifier: String,
+        slot: Option<Slot>,
+        indices: Vec<Expression>,
+        operator: AssignmentOperator,
+        expression: Box<Expression>,
+    },
     Function(DefinedFunction),
     Call {
         identifier: String,
+        slot: Option<Slot>,
         arguments: Vec<Expression>,
+        // set by the optimizer when the call is inside of a loop and gives the
+        // same result in every iteration, see purity. the id tells the calls
+        // apart while the loop runs, like the id of a pure function
+        #[serde(skip)]
+        invariant: Option<NonZeroU64>,
     },
     Index {
         expression: Box<Expression>,
         index: Box<Expression>,
     },
+    // `math.sqrt` is the same as `math !! "sqrt"`, used for the builtin namespaces
+    Member {
+        expression: Box<Expression>,
+        member: String,
+    },
+    // `math.sqrt(2)`
+    MemberCall {
+        expression: Box<Expression>,
+        member: String,
+        arguments: Vec<Expression>,
+        #[serde(skip)]
+        invariant: Option<NonZeroU64>,
+    },
     If {
         clauses: Vec<IfClause>,
         else_block: Option<Block>,
@@ -112,52 +247,204 @@ pub enum ExpressionValue {
         update: Option<Box<Expression>>,
         body: Block,
     },
+    // for item in iterable { }
+    ForIn {
+        identifier: String,
+        iterable: Box<Expression>,
+        body: Block,
+    },
     Continue,
     Break,
+    Discard(Box<Expression>),
+    Throw(Box<Expression>),
+    // the path of the file, relative to the importing file
+    Import(String),
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Expression {
-    region: Region,
+    pub region: Region,
     pub value: ExpressionValue,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct Program {
     pub ast: Vec<Expression>,
 }
 
+// the parser pulls tokens from the lexer as it goes and only keeps the
+// previous, current and next token around
 pub struct Parser {
-    tokens: Vec<Token>,
-    t: usize,
+    lexer: Lexer,
+    // the lines of the source, for the code of functions
+    lines: Vec<String>,
+    previous: Option<Token>,
+    current: Token,
+    next: Token,
+    // the number of braces opened before the current token, used to find the
+    // next top level expression after an error
+    depth: isize,
+    // the requirements of the script header which aren't met, reported
+    // before anything is parsed
+    header: Option<HeaderError>,
+    // how many expressions the current one is nested in, see nested
+    nesting: usize,
+    max_nesting: usize,
+    // the deepest nesting reached since it was last reset, see measured
+    deepest: usize,
 }
 
+// the parser, the passes and the interpreter grow their stack as they recurse,
+// but the tree is still cloned and dropped recursively on the stack of the
+// thread holding it. this is far more than any program needs and still drops
+// on a thread with a 2 MiB stack in a debug build
+pub const DEFAULT_MAX_NESTING: usize = 5000;
+
 impl Parser {
     pub fn new(source: &str) -> Result<Parser, LexerError> {
+        let mut lexer = Lexer::new(source);
+        let current = Self::pull(&mut lexer, None)?;
+        let next = Self::pull(&mut lexer, Some(&current))?;
         Ok(Parser {
-            tokens: Lexer::new(source).tokenize()?,
-            t: 0,
+            lexer,
+            lines: source.lines().map(str::to_string).collect(),
+            previous: None,
+            current,
+            next,
+            depth: 0,
+            header: header::check(source).err(),
+            nesting: 0,
+            max_nesting: DEFAULT_MAX_NESTING,
+            deepest: 0,
         })
     }
 
-    fn advance(&mut self) {
-        self.t += 1
+    // the parser recurses for every nested expression, input like thousands of
+    // nested parentheses would overflow the native stack
+    pub fn set_max_nesting(&mut self, max_nesting: usize) -> &mut Self {
+        self.max_nesting = max_nesting;
+        self
+    }
+
+    fn nested<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<T, ParserError>,
+    ) -> Result<T, ParserError> {
+        if self.nesting >= self.max_nesting {
+            return Err(ParserError::TooDeeplyNested {
+                found: self.current().clone(),
+                limit: self.max_nesting,
+            });
+        }
+        self.nesting += 1;
+        self.deepest = self.deepest.max(self.nesting);
+        let result = grow_stack(|| parse(self));
+        self.nesting -= 1;
+        result
+    }
+
+    // parse something and find out how many levels deeper than the current
+    // one it is nested
+    fn measured<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<T, ParserError>,
+    ) -> Result<(T, usize), ParserError> {
+        let deepest = std::mem::replace(&mut self.deepest, self.nesting);
+        let result = parse(self);
+        let height = self.deepest - self.nesting;
+        self.deepest = self.deepest.max(deepest);
+        Ok((result?, height))
+    }
+
+    // chains like `1 + 2 + 3` are parsed in a loop but give ((1 + 2) + 3), every
+    // operator puts the expression on its left one level deeper. the levels are
+    // counted like nesting, so long chains can't overflow the stack when the
+    // tree is dropped
+    fn chained(&self, height: usize, operator: &Token) -> Result<usize, ParserError> {
+        if self.nesting + height > self.max_nesting {
+            return Err(ParserError::TooDeeplyNested {
+                found: operator.clone(),
+                limit: self.max_nesting,
+            });
+        }
+        Ok(height)
+    }
+
+    // the end of a chain, the expressions around it see how deep it is
+    fn end_chain(&mut self, height: usize) {
+        self.deepest = self.deepest.max(self.nesting + height);
+    }
+
+    // a chain of binary operators with the same precedence
+    fn parse_chain(
+        &mut self,
+        operand: fn(&mut Self) -> Result<Expression, ParserError>,
+        operator: fn(&TokenValue) -> Option<BinaryOperationOperator>,
+    ) -> Result<Expression, ParserError> {
+        let (mut left, mut height) = self.measured(operand)?;
+
+        while let Some(operator) = operator(self.current_val()) {
+            let token = self.current().clone();
+            self.advance()?;
+
+            let (right, right_height) = self.measured(operand)?;
+            height = self.chained(height.max(right_height) + 1, &token)?;
+            left = Expression {
+                region: Region {
+                    start: left.region.start.clone(),
+                    end: right.region.end.clone(),
+                },
+                value: ExpressionValue::Binary {
+                    left: Box::new(left),
+                    operator,
+                    right: Box::new(right),
+                },
+            }
+        }
+
+        self.end_chain(height);
+        Ok(left)
+    }
+
+    // get the next token from the lexer, after the end of the file has been
+    // reached the EndOfFile token is repeated
+    fn pull(lexer: &mut Lexer, last: Option<&Token>) -> Result<Token, LexerError> {
+        match lexer.next() {
+            Some(token) => token,
+            None => Ok(last
+                .expect("lexer ended without an EndOfFile token")
+                .clone()),
+        }
+    }
+
+    fn advance(&mut self) -> Result<(), ParserError> {
+        match self.current_val() {
+            TokenValue::OpenBrace => self.depth += 1,
+            TokenValue::CloseBrace => self.depth -= 1,
+            _ => {}
+        }
+        let next = Self::pull(&mut self.lexer, Some(&self.next))?;
+        let current = std::mem::replace(&mut self.next, next);
+        self.previous = Some(std::mem::replace(&mut self.current, current));
+        Ok(())
     }
 
     fn current(&self) -> &Token {
-        &self.tokens[self.t]
+        &self.current
     }
 
     fn current_val(&self) -> &TokenValue {
-        &self.tokens[self.t].value
+        &self.current.value
     }
 
     fn previous(&self) -> &Token {
-        &self.tokens[self.t - 1]
+        self.previous
+            .as_ref()
+            .expect("previous token requested before advancing")
     }
 
     fn next_val(&self) -> &TokenValue {
-        &self.tokens[self.t + 1].value
+        &self.next.value
     }
 
     #[track_caller]
@@ -195,26 +482,74 @@ impl Parser {
             ExpressionValueDiscriminants::Block,
             TokenValueDiscriminants::OpenBrace,
         )?;
-        self.advance();
+        self.advance()?;
 
-        let mut expressions: Vec<Expression> = vec![];
+        self.parse_block_rest(vec![])
+    }
+
+    // parse the remaining expressions of a block up to and including the
+    // closing brace
+    fn parse_block_rest(&mut self, mut expressions: Vec<Expression>) -> Result<Block, ParserError> {
         loop {
             match self.current_val() {
                 TokenValue::CloseBrace => break,
                 _ => expressions.push(self.parse_expression()?),
             };
         }
-        self.advance(); // skip the closing brace
+        self.advance()?; // skip the closing brace
 
         Ok(expressions)
     }
 
+    // braces hold either a block or a map literal, a map is recognised by the
+    // colon after its first key and `{:}` is the empty map
+    fn parse_block_or_map(&mut self) -> Result<ExpressionValue, ParserError> {
+        match self.next_val() {
+            TokenValue::CloseBrace => return Ok(ExpressionValue::Block(self.parse_block()?)),
+            TokenValue::Colon => {
+                self.advance()?; // skip the opening brace
+                self.advance()?; // skip the colon
+                self.expect_token_discriminant(
+                    ExpressionValueDiscriminants::Map,
+                    TokenValueDiscriminants::CloseBrace,
+                )?;
+                self.advance()?;
+                return Ok(ExpressionValue::Map(vec![]));
+            }
+            _ => {}
+        }
+        self.advance()?; // skip the opening brace
+
+        let mut key = self.parse_expression()?;
+        if self.current_val() != &TokenValue::Colon {
+            return Ok(ExpressionValue::Block(self.parse_block_rest(vec![key])?));
+        }
+
+        let mut entries = vec![];
+        loop {
+            self.expect_token_discriminant(
+                ExpressionValueDiscriminants::Map,
+                TokenValueDiscriminants::Colon,
+            )?;
+            self.advance()?;
+            entries.push((key, self.parse_expression()?));
+
+            if self.current_val() == &TokenValue::CloseBrace {
+                break;
+            }
+            key = self.parse_expression()?;
+        }
+        self.advance()?; // skip the closing brace
+
+        Ok(ExpressionValue::Map(entries))
+    }
+
     fn parse_list(&mut self) -> Result<ExpressionValue, ParserError> {
         self.expect_token_discriminant(
             ExpressionValueDiscriminants::List,
             TokenValueDiscriminants::OpenBracket,
         )?;
-        self.advance();
+        self.advance()?;
 
         let mut expressions: Vec<Expression> = vec![];
         loop {
@@ -223,7 +558,7 @@ impl Parser {
                 _ => expressions.push(self.parse_expression()?),
             };
         }
-        self.advance(); // skip the closing bracket ]
+        self.advance()?; // skip the closing bracket ]
 
         Ok(ExpressionValue::List(expressions))
     }
@@ -236,8 +571,8 @@ impl Parser {
                 TokenValueDiscriminants::Identifier,
             )),
         }?;
-        self.advance();
-        Ok(ExpressionValue::Identifier(value))
+        self.advance()?;
+        Ok(ExpressionValue::Identifier(value, None))
     }
 
     fn parse_int(&mut self) -> Result<ExpressionValue, ParserError> {
@@ -248,10 +583,22 @@ impl Parser {
                 TokenValueDiscriminants::Int,
             )),
         }?;
-        self.advance();
+        self.advance()?;
         Ok(ExpressionValue::Int(value))
     }
 
+    fn parse_float(&mut self) -> Result<ExpressionValue, ParserError> {
+        let value = match self.current_val() {
+            TokenValue::Float(v) => Ok(*v),
+            _ => Err(self.expect_token_err(
+                ExpressionValueDiscriminants::Float,
+                TokenValueDiscriminants::Float,
+            )),
+        }?;
+        self.advance()?;
+        Ok(ExpressionValue::Float(value))
+    }
+
     fn parse_string(&mut self) -> Result<ExpressionValue, ParserError> {
         let value = match self.current_val() {
             TokenValue::String(v) => Ok(v.clone()),
@@ -260,7 +607,7 @@ impl Parser {
                 TokenValueDiscriminants::String,
             )),
         }?;
-        self.advance();
+        self.advance()?;
         Ok(ExpressionValue::String(value))
     }
 
@@ -269,7 +616,7 @@ impl Parser {
             ExpressionValueDiscriminants::Null,
             TokenValueDiscriminants::KeywordNull,
         )?;
-        self.advance();
+        self.advance()?;
         Ok(ExpressionValue::Null)
     }
 
@@ -282,7 +629,7 @@ impl Parser {
                 found: self.current().clone(),
             }),
         }?;
-        self.advance();
+        self.advance()?;
         Ok(ExpressionValue::Bool(value))
     }
 
@@ -291,7 +638,7 @@ impl Parser {
             ExpressionValueDiscriminants::VariableDeclaration,
             TokenValueDiscriminants::KeywordVar,
         )?;
-        self.advance();
+        self.advance()?;
 
         let identifier = match self.current_val() {
             TokenValue::Identifier(v) => Ok(v),
@@ -301,16 +648,17 @@ impl Parser {
             )),
         }?
         .clone();
-        self.advance();
+        self.advance()?;
 
         self.expect_token_discriminant(
             ExpressionValueDiscriminants::VariableDeclaration,
             TokenValueDiscriminants::EqualSign,
         )?;
-        self.advance();
+        self.advance()?;
 
         Ok(ExpressionValue::VariableDeclaration {
             identifier,
+            slot: None,
             expression: Box::new(self.parse_expression()?),
         })
     }
@@ -323,44 +671,69 @@ impl Parser {
                 TokenValueDiscriminants::Identifier,
             )),
         }?;
-        self.advance();
+        self.advance()?;
 
-        self.expect_token_discriminant(
-            ExpressionValueDiscriminants::Call,
-            TokenValueDiscriminants::OpenParenthesis,
-        )?;
-        self.advance();
+        Ok(ExpressionValue::Call {
+            identifier,
+            slot: None,
+            arguments: self.parse_arguments(ExpressionValueDiscriminants::Call)?,
+            invariant: None,
+        })
+    }
+
+    fn parse_arguments(
+        &mut self,
+        while_parsing: ExpressionValueDiscriminants,
+    ) -> Result<Vec<Expression>, ParserError> {
+        self.expect_token_discriminant(while_parsing, TokenValueDiscriminants::OpenParenthesis)?;
+        self.advance()?;
 
         let mut arguments = vec![];
         while *self.current_val() != TokenValue::CloseParenthesis {
             arguments.push(self.parse_expression()?);
         }
-        self.advance(); // skip the clogin parenthesis )
+        self.advance()?; // skip the clogin parenthesis )
 
-        Ok(ExpressionValue::Call {
-            identifier,
-            arguments,
-        })
+        Ok(arguments)
+    }
+
+    // the code of a function from its first to its last character
+    fn function_source(&self, region: Region) -> FunctionSource {
+        let (start, end) = (&region.start, &region.end);
+        let code = (start.row..=end.row)
+            .map(|row| {
+                let line = self.lines.get(row - 1).map_or("", String::as_str);
+                let from = if row == start.row { start.col - 1 } else { 0 };
+                let to = if row == end.row { end.col } else { usize::MAX };
+                line.chars().take(to).skip(from).collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        FunctionSource {
+            region,
+            code: Some(code),
+        }
     }
 
     fn parse_function(&mut self) -> Result<ExpressionValue, ParserError> {
+        let start = self.current().region.start.clone();
         self.expect_token_discriminant(
             ExpressionValueDiscriminants::Function,
             TokenValueDiscriminants::KeywordFun,
         )?;
-        self.advance();
+        self.advance()?;
 
         self.expect_token_discriminant(
             ExpressionValueDiscriminants::Function,
             TokenValueDiscriminants::OpenParenthesis,
         )?;
-        self.advance();
+        self.advance()?;
 
         let mut parameters = vec![];
         loop {
             match self.current_val() {
                 TokenValue::CloseParenthesis => {
-                    self.advance();
+                    self.advance()?;
                     break;
                 }
                 TokenValue::Identifier(v) => {
@@ -373,12 +746,18 @@ impl Parser {
                     })
                 }
             }
-            self.advance();
+            self.advance()?;
         }
 
+        let body = self.parse_block()?;
         Ok(ExpressionValue::Function(DefinedFunction {
             parameters,
-            body: self.parse_block()?,
+            body,
+            source: Box::new(self.function_source(Region {
+                start,
+                end: self.previous().region.end.clone(),
+            })),
+            pure: None,
         }))
     }
 
@@ -387,7 +766,7 @@ impl Parser {
             ExpressionValueDiscriminants::If,
             TokenValueDiscriminants::KeywordIf,
         )?;
-        self.advance();
+        self.advance()?;
 
         // parse the first if
         let first_test = self.parse_expression()?;
@@ -399,7 +778,7 @@ impl Parser {
 
         // parse any amount of elifs
         while self.current_val() == &TokenValue::KeywordElif {
-            self.advance();
+            self.advance()?;
 
             let test = self.parse_expression()?;
             let body = self.parse_block()?;
@@ -411,13 +790,10 @@ impl Parser {
         }
 
         let mut else_block = None;
-        match self.current_val() {
-            TokenValue::KeywordElse => {
-                // parse the else block
-                self.advance();
-                else_block = Some(self.parse_block()?);
-            }
-            _ => {}
+        if self.current_val() == &TokenValue::KeywordElse {
+            // parse the else block
+            self.advance()?;
+            else_block = Some(self.parse_block()?);
         }
 
         Ok(ExpressionValue::If {
@@ -443,16 +819,29 @@ impl Parser {
                 })
             }
         };
-        self.advance();
+        self.advance()?;
+
+        if let (LoopType::For, TokenValue::Identifier(identifier), TokenValue::KeywordIn) =
+            (&loop_type, self.current_val(), self.next_val())
+        {
+            let identifier = identifier.clone();
+            self.advance()?; // skip the identifier
+            self.advance()?; // skip the in keyword
+
+            return Ok(ExpressionValue::ForIn {
+                identifier,
+                iterable: Box::new(self.parse_expression()?),
+                body: self.parse_block()?,
+            });
+        }
 
         let mut init = None;
         let mut test = None;
         let mut update = None;
 
         // parse the loop initalization expression
-        match loop_type {
-            LoopType::For => init = Some(Box::new(self.parse_expression()?)),
-            _ => {}
+        if let LoopType::For = loop_type {
+            init = Some(Box::new(self.parse_expression()?));
         };
         // parse the test expression
         match loop_type {
@@ -460,9 +849,8 @@ impl Parser {
             _ => {}
         };
         // parse update expression
-        match loop_type {
-            LoopType::For => update = Some(Box::new(self.parse_expression()?)),
-            _ => {}
+        if let LoopType::For = loop_type {
+            update = Some(Box::new(self.parse_expression()?));
         };
 
         let body = self.parse_block()?;
@@ -484,31 +872,32 @@ impl Parser {
             )),
         }?
         .clone();
-        self.advance();
+        self.advance()?;
 
-        let operator = match self.current_val() {
-            TokenValue::EqualSign => AssignmentOperator::Set,
-            TokenValue::AdditionAssign => AssignmentOperator::Plus,
-            TokenValue::SubtractionAssign => AssignmentOperator::Minus,
-            TokenValue::MultiplicationAssign => AssignmentOperator::Multiply,
-            TokenValue::DivisionAssign => AssignmentOperator::Divide,
-            TokenValue::ModuloAssign => AssignmentOperator::Modulo,
-            _ => {
-                return Err(ParserError::UnexpectedToken {
-                    while_parsing: Some(ExpressionValueDiscriminants::Assign),
-                    found: self.current().clone(),
-                })
-            }
-        };
-        self.advance();
+        let operator = self.parse_assignment_operator(ExpressionValueDiscriminants::Assign)?;
 
         Ok(ExpressionValue::Assign {
             identifier,
+            slot: None,
             operator,
             expression: Box::new(self.parse_expression()?),
         })
     }
 
+    fn parse_assignment_operator(
+        &mut self,
+        while_parsing: ExpressionValueDiscriminants,
+    ) -> Result<AssignmentOperator, ParserError> {
+        let operator = assignment_operator(self.current_val()).ok_or_else(|| {
+            ParserError::UnexpectedToken {
+                while_parsing: Some(while_parsing),
+                found: self.current().clone(),
+            }
+        })?;
+        self.advance()?;
+        Ok(operator)
+    }
+
     fn parse_update(&mut self) -> Result<ExpressionValue, ParserError> {
         let identifier = match self.current_val() {
             TokenValue::Identifier(v) => Ok(v),
@@ -518,7 +907,7 @@ impl Parser {
             )),
         }?
         .clone();
-        self.advance();
+        self.advance()?;
 
         let operator = match self.current_val() {
             TokenValue::Increment => UpdateOperator::Increment,
@@ -530,10 +919,11 @@ impl Parser {
                 })
             }
         };
-        self.advance();
+        self.advance()?;
 
         Ok(ExpressionValue::Update {
             identifier,
+            slot: None,
             operator,
         })
     }
@@ -543,7 +933,7 @@ impl Parser {
             ExpressionValueDiscriminants::Continue,
             TokenValueDiscriminants::KeywordContinue,
         )?;
-        self.advance();
+        self.advance()?;
         Ok(ExpressionValue::Continue)
     }
 
@@ -552,28 +942,63 @@ impl Parser {
             ExpressionValueDiscriminants::Break,
             TokenValueDiscriminants::KeywordBreak,
         )?;
-        self.advance();
+        self.advance()?;
         Ok(ExpressionValue::Break)
     }
 
+    fn parse_discard(&mut self) -> Result<ExpressionValue, ParserError> {
+        self.expect_token_discriminant(
+            ExpressionValueDiscriminants::Discard,
+            TokenValueDiscriminants::KeywordDiscard,
+        )?;
+        self.advance()?;
+        Ok(ExpressionValue::Discard(Box::new(self.parse_expression()?)))
+    }
+
+    fn parse_throw(&mut self) -> Result<ExpressionValue, ParserError> {
+        self.expect_token_discriminant(
+            ExpressionValueDiscriminants::Throw,
+            TokenValueDiscriminants::KeywordThrow,
+        )?;
+        self.advance()?;
+        Ok(ExpressionValue::Throw(Box::new(self.parse_expression()?)))
+    }
+
+    // `import utils` is the same as `import "utils.olang"`
+    fn parse_import(&mut self) -> Result<ExpressionValue, ParserError> {
+        self.expect_token_discriminant(
+            ExpressionValueDiscriminants::Import,
+            TokenValueDiscriminants::KeywordImport,
+        )?;
+        self.advance()?;
+        let path = match self.current_val() {
+            TokenValue::String(path) => path.clone(),
+            TokenValue::Identifier(name) => format!("{}.olang", name),
+            _ => {
+                return Err(self.expect_token_err(
+                    ExpressionValueDiscriminants::Import,
+                    TokenValueDiscriminants::String,
+                ))
+            }
+        };
+        self.advance()?;
+        Ok(ExpressionValue::Import(path))
+    }
+
     fn parse_primary(&mut self) -> Result<Expression, ParserError> {
         let start = self.current().region.start.clone();
         let value = match self.current_val() {
             TokenValue::Int(_) => self.parse_int(),
+            TokenValue::Float(_) => self.parse_float(),
             TokenValue::String(_) => self.parse_string(),
             TokenValue::Identifier(_) => match self.next_val() {
                 TokenValue::OpenParenthesis => self.parse_call(),
-                TokenValue::EqualSign
-                | TokenValue::AdditionAssign
-                | TokenValue::SubtractionAssign
-                | TokenValue::MultiplicationAssign
-                | TokenValue::DivisionAssign
-                | TokenValue::ModuloAssign => self.parse_assign(),
+                next if assignment_operator(next).is_some() => self.parse_assign(),
                 TokenValue::Increment | TokenValue::Decrement => self.parse_update(),
                 _ => self.parse_identifier(),
             },
             TokenValue::OpenParenthesis => {
-                self.advance(); // skip the open parenthesis (
+                self.advance()?; // skip the open parenthesis (
 
                 let expression = self.parse_expression()?;
 
@@ -581,13 +1006,13 @@ impl Parser {
                     ExpressionValueDiscriminants::Binary, // FIXME: this dosen't have a type, binary is the closest but idk
                     TokenValueDiscriminants::CloseParenthesis,
                 )?;
-                self.advance();
+                self.advance()?;
 
                 Ok(expression.value)
             }
             TokenValue::KeywordNull => self.parse_null(),
             TokenValue::KeywordTrue | TokenValue::KeywordFalse => self.parse_bool(),
-            TokenValue::OpenBrace => Ok(ExpressionValue::Block(self.parse_block()?)),
+            TokenValue::OpenBrace => self.parse_block_or_map(),
             TokenValue::OpenBracket => self.parse_list(),
             TokenValue::KeywordVar => self.parse_variable_declaration(),
             TokenValue::KeywordFun => self.parse_function(),
@@ -597,6 +1022,9 @@ impl Parser {
             }
             TokenValue::KeywordContinue => self.parse_continue(),
             TokenValue::KeywordBreak => self.parse_break(),
+            TokenValue::KeywordDiscard => self.parse_discard(),
+            TokenValue::KeywordThrow => self.parse_throw(),
+            TokenValue::KeywordImport => self.parse_import(),
             _ => Err(ParserError::UnexpectedToken {
                 while_parsing: None,
                 found: self.current().clone(),
@@ -611,193 +1039,293 @@ impl Parser {
     }
 
     fn parse_index(&mut self) -> Result<Expression, ParserError> {
-        let mut left = self.parse_primary()?;
-
-        while self.current_val() == &TokenValue::DoubleExclamationMark {
-            self.advance(); // skip the exclamation mark (!)
+        let (mut left, mut height) = self.measured(Self::parse_primary)?;
 
-            let right = self.parse_primary()?;
-            left = Expression {
-                region: Region {
-                    start: left.region.start.clone(),
-                    end: right.region.end.clone(),
-                },
-                value: ExpressionValue::Index {
-                    expression: Box::new(left),
-                    index: Box::new(right),
-                },
+        loop {
+            let token = self.current().clone();
+            match self.current_val() {
+                TokenValue::DoubleExclamationMark => {
+                    self.advance()?; // skip the exclamation mark (!)
+
+                    let (right, right_height) = self.measured(Self::parse_index_operand)?;
+                    height = self.chained(height.max(right_height) + 1, &token)?;
+                    left = Expression {
+                        region: Region {
+                            start: left.region.start.clone(),
+                            end: right.region.end.clone(),
+                        },
+                        value: ExpressionValue::Index {
+                            expression: Box::new(left),
+                            index: Box::new(right),
+                        },
+                    }
+                }
+                TokenValue::Dot => {
+                    let (member, arguments_height) =
+                        self.measured(|parser| parser.parse_member(left))?;
+                    height = self.chained(height.max(arguments_height) + 1, &token)?;
+                    left = member;
+                }
+                _ => break,
             }
         }
+        self.end_chain(height);
+
+        if matches!(left.value, ExpressionValue::Index { .. })
+            && assignment_operator(self.current_val()).is_some()
+        {
+            return self.parse_index_assign(left);
+        }
 
         Ok(left)
     }
 
-    fn parse_exponentiative(&mut self) -> Result<Expression, ParserError> {
-        let mut left = self.parse_index()?;
-
-        loop {
-            let operator = match self.current_val() {
-                TokenValue::ExponentSign => BinaryOperationOperator::Exponentiation,
-                _ => {
-                    break;
-                }
-            };
-            self.advance();
+    // `left.member` or `left.member(arguments)`, only names can follow the dot
+    fn parse_member(&mut self, left: Expression) -> Result<Expression, ParserError> {
+        let start = left.region.start.clone();
+        self.advance()?; // skip the dot (.)
 
-            let right = self.parse_index()?;
-            left = Expression {
-                region: Region {
-                    start: left.region.start.clone(),
-                    end: right.region.end.clone(),
-                },
-                value: ExpressionValue::Binary {
-                    left: Box::new(left),
-                    operator,
-                    right: Box::new(right),
-                },
+        let member = match self.current_val() {
+            TokenValue::Identifier(v) => Ok(v.clone()),
+            _ => Err(self.expect_token_err(
+                ExpressionValueDiscriminants::Member,
+                TokenValueDiscriminants::Identifier,
+            )),
+        }?;
+        self.advance()?;
+
+        let value = if *self.current_val() == TokenValue::OpenParenthesis {
+            ExpressionValue::MemberCall {
+                expression: Box::new(left),
+                member,
+                arguments: self.parse_arguments(ExpressionValueDiscriminants::MemberCall)?,
+                invariant: None,
             }
-        }
+        } else {
+            ExpressionValue::Member {
+                expression: Box::new(left),
+                member,
+            }
+        };
 
-        Ok(left)
+        Ok(Expression {
+            region: Region {
+                start,
+                end: self.previous().region.end.clone(),
+            },
+            value,
+        })
     }
 
-    fn parse_multiplicative(&mut self) -> Result<Expression, ParserError> {
-        let mut left = self.parse_exponentiative()?;
+    // the identifier in `map !! key = value` is the index and not the target of
+    // an assignment
+    fn parse_index_operand(&mut self) -> Result<Expression, ParserError> {
+        if matches!(self.current_val(), TokenValue::Identifier(_))
+            && assignment_operator(self.next_val()).is_some()
+        {
+            let value = self.parse_identifier()?;
+            return Ok(Expression {
+                region: self.previous().region.clone(),
+                value,
+            });
+        }
 
-        loop {
-            let operator = match self.current_val() {
-                TokenValue::MultiplicationSign => BinaryOperationOperator::Multiply,
-                TokenValue::DivisionSign => BinaryOperationOperator::Divide,
-                TokenValue::ModuloSign => BinaryOperationOperator::Modulus,
+        self.parse_primary()
+    }
+
+    // turns the chain of indices on the left of an assignment into an IndexAssign
+    fn parse_index_assign(&mut self, target: Expression) -> Result<Expression, ParserError> {
+        let start = target.region.start.clone();
+        let mut indices = vec![];
+        let mut target = target;
+        let identifier = loop {
+            match target.value {
+                ExpressionValue::Index { expression, index } => {
+                    indices.push(*index);
+                    target = *expression;
+                }
+                ExpressionValue::Identifier(identifier, _) => break identifier,
                 _ => {
-                    break;
+                    return Err(ParserError::UnexpectedToken {
+                        while_parsing: Some(ExpressionValueDiscriminants::IndexAssign),
+                        found: self.current().clone(),
+                    })
                 }
-            };
-            self.advance();
-
-            let right = self.parse_exponentiative()?;
-            left = Expression {
-                region: Region {
-                    start: left.region.start.clone(),
-                    end: right.region.end.clone(),
-                },
-                value: ExpressionValue::Binary {
-                    left: Box::new(left),
-                    operator,
-                    right: Box::new(right),
-                },
             }
+        };
+        indices.reverse();
+
+        let operator = self.parse_assignment_operator(ExpressionValueDiscriminants::IndexAssign)?;
+        let expression = self.parse_expression()?;
+
+        Ok(Expression {
+            region: Region {
+                start,
+                end: expression.region.end.clone(),
+            },
+            value: ExpressionValue::IndexAssign {
+                identifier,
+                slot: None,
+                indices,
+                operator,
+                expression: Box::new(expression),
+            },
+        })
+    }
+
+    // exponentiation is right associative, 2**3**2 is parsed as 2**(3**2)
+    fn parse_exponentiative(&mut self) -> Result<Expression, ParserError> {
+        let left = self.parse_index()?;
+
+        if self.current_val() != &TokenValue::ExponentSign {
+            return Ok(left);
         }
+        self.advance()?;
 
-        Ok(left)
+        // the exponent may be negated, 2**-1 is 2**(-1)
+        let right = self.nested(Self::parse_unary)?;
+        Ok(Expression {
+            region: Region {
+                start: left.region.start.clone(),
+                end: right.region.end.clone(),
+            },
+            value: ExpressionValue::Binary {
+                left: Box::new(left),
+                operator: BinaryOperationOperator::Exponentiation,
+                right: Box::new(right),
+            },
+        })
     }
 
-    fn parse_additive(&mut self) -> Result<Expression, ParserError> {
-        let mut left = self.parse_multiplicative()?;
+    // unary operators bind weaker than exponentiation, -2**2 is -(2**2)
+    fn parse_unary(&mut self) -> Result<Expression, ParserError> {
+        let operator = match self.current_val() {
+            TokenValue::MinusSign => UnaryOperator::Negate,
+            TokenValue::ExclamationMark => UnaryOperator::Not,
+            _ => return self.parse_exponentiative(),
+        };
+        let start = self.current().region.start.clone();
+        self.advance()?;
 
-        loop {
-            let operator = match self.current_val() {
-                TokenValue::PlusSign => BinaryOperationOperator::Plus,
-                TokenValue::MinusSign => BinaryOperationOperator::Minus,
-                _ => {
-                    break;
-                }
-            };
-            self.advance();
+        let operand = self.nested(Self::parse_unary)?;
+        Ok(Expression {
+            region: Region {
+                start,
+                end: operand.region.end.clone(),
+            },
+            value: ExpressionValue::Unary {
+                operator,
+                operand: Box::new(operand),
+            },
+        })
+    }
 
-            let right = self.parse_multiplicative()?;
-            left = Expression {
-                region: Region {
-                    start: left.region.start.clone(),
-                    end: right.region.end.clone(),
-                },
-                value: ExpressionValue::Binary {
-                    left: Box::new(left),
-                    operator,
-                    right: Box::new(right),
-                },
-            }
-        }
+    fn parse_multiplicative(&mut self) -> Result<Expression, ParserError> {
+        self.parse_chain(Self::parse_unary, |token| match token {
+            TokenValue::MultiplicationSign => Some(BinaryOperationOperator::Multiply),
+            TokenValue::DivisionSign => Some(BinaryOperationOperator::Divide),
+            TokenValue::ModuloSign => Some(BinaryOperationOperator::Modulus),
+            _ => None,
+        })
+    }
 
-        Ok(left)
+    fn parse_additive(&mut self) -> Result<Expression, ParserError> {
+        self.parse_chain(Self::parse_multiplicative, |token| match token {
+            TokenValue::PlusSign => Some(BinaryOperationOperator::Plus),
+            TokenValue::MinusSign => Some(BinaryOperationOperator::Minus),
+            _ => None,
+        })
     }
 
     fn parse_comparative(&mut self) -> Result<Expression, ParserError> {
-        let mut left = self.parse_additive()?;
+        self.parse_chain(Self::parse_additive, |token| match token {
+            TokenValue::IsEqual => Some(BinaryOperationOperator::IsEqual),
+            TokenValue::IsNotEqual => Some(BinaryOperationOperator::IsNotEqual),
+            TokenValue::IsGreaterThan => Some(BinaryOperationOperator::IsGreaterThan),
+            TokenValue::IsGreaterThanOrEqual => Some(BinaryOperationOperator::IsGreaterThanOrEqual),
+            TokenValue::IsLessThan => Some(BinaryOperationOperator::IsLessThan),
+            TokenValue::IsLessThanOrEqual => Some(BinaryOperationOperator::IsLessThanOrEqual),
+            _ => None,
+        })
+    }
 
-        loop {
-            let operator = match self.current_val() {
-                TokenValue::IsEqual => BinaryOperationOperator::IsEqual,
-                TokenValue::IsNotEqual => BinaryOperationOperator::IsNotEqual,
-                TokenValue::IsGreaterThan => BinaryOperationOperator::IsGreaterThan,
-                TokenValue::IsGreaterThanOrEqual => BinaryOperationOperator::IsGreaterThanOrEqual,
-                TokenValue::IsLessThan => BinaryOperationOperator::IsLessThan,
-                TokenValue::IsLessThanOrEqual => BinaryOperationOperator::IsLessThanOrEqual,
-                _ => {
-                    break;
-                }
-            };
-            self.advance();
+    fn parse_logical(&mut self) -> Result<Expression, ParserError> {
+        self.parse_chain(Self::parse_comparative, |token| match token {
+            TokenValue::And => Some(BinaryOperationOperator::LogicalAnd),
+            TokenValue::Or => Some(BinaryOperationOperator::LogicalOr),
+            _ => None,
+        })
+    }
 
-            let right = self.parse_additive()?;
-            left = Expression {
-                region: Region {
-                    start: left.region.start.clone(),
-                    end: right.region.end.clone(),
-                },
-                value: ExpressionValue::Binary {
-                    left: Box::new(left),
-                    operator,
-                    right: Box::new(right),
-                },
-            }
+    fn parse_expression(&mut self) -> Result<Expression, ParserError> {
+        self.nested(Self::parse_logical)
+    }
+
+    pub fn parse(&mut self) -> Result<Program, ParserError> {
+        let mut program: Program = Program { ast: vec![] };
+        if let Some(err) = self.header.take() {
+            return Err(err.into());
         }
 
-        Ok(left)
+        while self.current_val() != &TokenValue::EndOfFile {
+            program.ast.push(self.parse_expression()?);
+        }
+
+        Ok(program)
     }
 
-    fn parse_logical(&mut self) -> Result<Expression, ParserError> {
-        let mut left = self.parse_comparative()?;
+    // parse the whole source even after errors, so every independent mistake
+    // is reported at once. after an error the tokens up to the next top level
+    // expression are skipped, a lexer error ends the parsing
+    pub fn parse_all(&mut self) -> (Program, Vec<ParserError>) {
+        let mut program = Program { ast: vec![] };
+        let mut errors = vec![];
+        if let Some(err) = self.header.take() {
+            errors.push(err.into());
+        }
 
-        loop {
-            let operator = match self.current_val() {
-                TokenValue::And => BinaryOperationOperator::LogicalAnd,
-                TokenValue::Or => BinaryOperationOperator::LogicalOr,
-                _ => {
-                    break;
+        while self.current_val() != &TokenValue::EndOfFile {
+            match self.parse_expression() {
+                Ok(expression) => program.ast.push(expression),
+                Err(err) => {
+                    let lexer_error = matches!(err, ParserError::Lexer(_));
+                    errors.push(err);
+                    if lexer_error {
+                        break;
+                    }
+                    if let Err(err) = self.synchronize() {
+                        errors.push(err);
+                        break;
+                    }
                 }
-            };
-            self.advance();
-
-            let right = self.parse_comparative()?;
-            left = Expression {
-                region: Region {
-                    start: left.region.start.clone(),
-                    end: right.region.end.clone(),
-                },
-                value: ExpressionValue::Binary {
-                    left: Box::new(left),
-                    operator,
-                    right: Box::new(right),
-                },
             }
         }
 
-        Ok(left)
+        (program, errors)
     }
 
-    fn parse_expression(&mut self) -> Result<Expression, ParserError> {
-        self.parse_logical()
+    // skip to the first token outside of all braces that starts a new line,
+    // this is most likely where the next top level expression starts
+    fn synchronize(&mut self) -> Result<(), ParserError> {
+        loop {
+            self.advance()?;
+            let new_line = self.current().region.start.row > self.previous().region.start.row;
+            if self.current_val() == &TokenValue::EndOfFile || (self.depth <= 0 && new_line) {
+                return Ok(());
+            }
+        }
     }
 
-    pub fn parse(&mut self) -> Result<Program, ParserError> {
-        self.t = 0;
-        let mut program: Program = Program { ast: vec![] };
+    pub fn parse_single_expression(&mut self) -> Result<Expression, ParserError> {
+        let expression = self.parse_expression()?;
 
-        while self.tokens[self.t].value != TokenValue::EndOfFile {
-            program.ast.push(self.parse_expression()?);
+        if self.current_val() != &TokenValue::EndOfFile {
+            return Err(ParserError::UnexpectedToken {
+                while_parsing: None,
+                found: self.current().clone(),
+            });
         }
 
-        Ok(program)
+        Ok(expression)
     }
 }
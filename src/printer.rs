@@ -0,0 +1,411 @@
+use crate::{
+    interpreter::grow_stack,
+    parser::{
+        AssignmentOperator, BinaryOperationOperator, Block, DefinedFunction, Expression,
+        ExpressionValue, Program, UnaryOperator, UpdateOperator,
+    },
+    value::{Function, Value},
+};
+use std::collections::HashSet;
+
+// turns the AST back into olang source which evaluates the same, parentheses are
+// only added where the precedence of the operators needs them
+
+// how tightly an expression binds, an operand with a lower precedence than its
+// position needs has to be put in parentheses
+const GREEDY: u8 = 0; // var x = 1 + 2 takes everything to its right
+const LOGICAL: u8 = 1;
+const COMPARISON: u8 = 2;
+const ADDITIVE: u8 = 3;
+const MULTIPLICATIVE: u8 = 4;
+const UNARY: u8 = 5;
+const EXPONENT: u8 = 6;
+const PRIMARY: u8 = 7;
+
+fn binary_operator(operator: &BinaryOperationOperator) -> (&'static str, u8) {
+    match operator {
+        BinaryOperationOperator::Plus => ("+", ADDITIVE),
+        BinaryOperationOperator::Minus => ("-", ADDITIVE),
+        BinaryOperationOperator::Multiply => ("*", MULTIPLICATIVE),
+        BinaryOperationOperator::Divide => ("/", MULTIPLICATIVE),
+        BinaryOperationOperator::Modulus => ("%", MULTIPLICATIVE),
+        BinaryOperationOperator::Exponentiation => ("**", EXPONENT),
+        BinaryOperationOperator::IsLessThan => ("<", COMPARISON),
+        BinaryOperationOperator::IsLessThanOrEqual => ("<=", COMPARISON),
+        BinaryOperationOperator::IsGreaterThan => (">", COMPARISON),
+        BinaryOperationOperator::IsGreaterThanOrEqual => (">=", COMPARISON),
+        BinaryOperationOperator::IsEqual => ("==", COMPARISON),
+        BinaryOperationOperator::IsNotEqual => ("!=", COMPARISON),
+        BinaryOperationOperator::LogicalAnd => ("&&", LOGICAL),
+        BinaryOperationOperator::LogicalOr => ("||", LOGICAL),
+    }
+}
+
+fn assignment_operator(operator: &AssignmentOperator) -> &'static str {
+    match operator {
+        AssignmentOperator::Set => "=",
+        AssignmentOperator::Plus => "+=",
+        AssignmentOperator::Minus => "-=",
+        AssignmentOperator::Multiply => "*=",
+        AssignmentOperator::Divide => "/=",
+        AssignmentOperator::Modulo => "%=",
+    }
+}
+
+fn indent(text: &str) -> String {
+    text.lines().map(|line| format!("    {}\n", line)).collect()
+}
+
+// expressions are only separated by whitespace, so an expression starting with
+// a minus would continue the one before it as a subtraction, and one starting
+// with a parenthesis would turn a name before it into a call
+fn sequence<'a>(expressions: impl IntoIterator<Item = &'a Expression>) -> Vec<String> {
+    let mut texts: Vec<String> = vec![];
+    for expression in expressions {
+        let text = expression_text(expression);
+        let after_name = texts
+            .last()
+            .and_then(|previous| previous.chars().last())
+            .is_some_and(|c| c.is_alphanumeric() || c == '_');
+        let text = match text.chars().next() {
+            Some('-' | '(') if after_name => format!("{{ {} }}", text),
+            Some('-') if !texts.is_empty() => format!("({})", text),
+            _ => text,
+        };
+        texts.push(text);
+    }
+    texts
+}
+
+fn block(block: &Block) -> String {
+    if block.is_empty() {
+        return "{}".to_string();
+    }
+    format!("{{\n{}}}", indent(&sequence(block).join("\n")))
+}
+
+// the text of an expression which binds at least as tightly as precedence
+fn operand(expression: &Expression, precedence: u8) -> String {
+    let (text, own) = printed(expression);
+    if own < precedence {
+        format!("({})", text)
+    } else {
+        text
+    }
+}
+
+fn number(text: String) -> (String, u8) {
+    // negative numbers are written with a unary minus
+    let precedence = if text.starts_with('-') {
+        UNARY
+    } else {
+        PRIMARY
+    };
+    (text, precedence)
+}
+
+fn printed(expression: &Expression) -> (String, u8) {
+    grow_stack(|| {
+        match &expression.value {
+            ExpressionValue::Int(v) => number(v.to_string()),
+            ExpressionValue::Float(v) => number(format!("{:?}", v)),
+            ExpressionValue::String(v) => (format!("\"{}\"", v), PRIMARY),
+            ExpressionValue::Bool(v) => (v.to_string(), PRIMARY),
+            ExpressionValue::Null => ("null".to_string(), PRIMARY),
+            ExpressionValue::List(expressions) => {
+                (format!("[{}]", sequence(expressions).join(" ")), PRIMARY)
+            }
+            ExpressionValue::Map(entries) if entries.is_empty() => ("{:}".to_string(), PRIMARY),
+            ExpressionValue::Map(entries) => {
+                let texts = sequence(entries.iter().flat_map(|(key, value)| [key, value]));
+                let entries: Vec<_> = texts
+                    .chunks(2)
+                    .map(|entry| format!("{}: {}", entry[0], entry[1]))
+                    .collect();
+                (format!("{{{}}}", entries.join(" ")), PRIMARY)
+            }
+            ExpressionValue::Block(v) => (block(v), PRIMARY),
+            ExpressionValue::Identifier(id, _) => (id.clone(), PRIMARY),
+            ExpressionValue::Binary {
+                left,
+                operator,
+                right,
+            } => {
+                let (symbol, precedence) = binary_operator(operator);
+                // exponentiation is right associative, the other operators left associative
+                let (left, right) = match operator {
+                    BinaryOperationOperator::Exponentiation => {
+                        (operand(left, PRIMARY), operand(right, UNARY))
+                    }
+                    _ => (operand(left, precedence), operand(right, precedence + 1)),
+                };
+                (format!("{} {} {}", left, symbol, right), precedence)
+            }
+            ExpressionValue::Unary {
+                operator,
+                operand: v,
+            } => {
+                let symbol = match operator {
+                    UnaryOperator::Negate => "-",
+                    UnaryOperator::Not => "!",
+                };
+                (format!("{}{}", symbol, operand(v, EXPONENT)), UNARY)
+            }
+            ExpressionValue::VariableDeclaration {
+                identifier,
+                expression,
+                ..
+            } => (
+                format!("var {} = {}", identifier, expression_text(expression)),
+                GREEDY,
+            ),
+            ExpressionValue::Assign {
+                identifier,
+                operator,
+                expression,
+                ..
+            } => (
+                format!(
+                    "{} {} {}",
+                    identifier,
+                    assignment_operator(operator),
+                    expression_text(expression)
+                ),
+                GREEDY,
+            ),
+            ExpressionValue::Update {
+                identifier,
+                operator,
+                ..
+            } => {
+                let symbol = match operator {
+                    UpdateOperator::Increment => "++",
+                    UpdateOperator::Decremet => "--",
+                };
+                (format!("{}{}", identifier, symbol), PRIMARY)
+            }
+            ExpressionValue::IndexAssign {
+                identifier,
+                indices,
+                operator,
+                expression,
+                ..
+            } => {
+                let indices: String = indices
+                    .iter()
+                    .map(|index| format!(" !! {}", operand(index, PRIMARY)))
+                    .collect();
+                (
+                    format!(
+                        "{}{} {} {}",
+                        identifier,
+                        indices,
+                        assignment_operator(operator),
+                        expression_text(expression)
+                    ),
+                    GREEDY,
+                )
+            }
+            ExpressionValue::Function(v) => (function(v), PRIMARY),
+            ExpressionValue::Call {
+                identifier,
+                arguments,
+                ..
+            } => (
+                format!("{}({})", identifier, sequence(arguments).join(" ")),
+                PRIMARY,
+            ),
+            ExpressionValue::Index { expression, index } => (
+                format!(
+                    "{} !! {}",
+                    operand(expression, PRIMARY),
+                    operand(index, PRIMARY)
+                ),
+                PRIMARY,
+            ),
+            ExpressionValue::Member { expression, member } => (
+                format!("{}.{}", operand(expression, PRIMARY), member),
+                PRIMARY,
+            ),
+            ExpressionValue::MemberCall {
+                expression,
+                member,
+                arguments,
+                ..
+            } => (
+                format!(
+                    "{}.{}({})",
+                    operand(expression, PRIMARY),
+                    member,
+                    sequence(arguments).join(" ")
+                ),
+                PRIMARY,
+            ),
+            ExpressionValue::If {
+                clauses,
+                else_block,
+            } => {
+                let mut text = String::new();
+                for (i, clause) in clauses.iter().enumerate() {
+                    let keyword = if i == 0 { "if" } else { " elif" };
+                    text += &format!(
+                        "{} {} {}",
+                        keyword,
+                        expression_text(&clause.test),
+                        block(&clause.body)
+                    );
+                }
+                if let Some(else_block) = else_block {
+                    text += &format!(" else {}", block(else_block));
+                }
+                (text, PRIMARY)
+            }
+            ExpressionValue::Loop {
+                init: None,
+                test: None,
+                update: None,
+                body,
+            } => (format!("loop {}", block(body)), PRIMARY),
+            ExpressionValue::Loop {
+                init: None,
+                test: Some(test),
+                update: None,
+                body,
+            } => (
+                format!("while {} {}", expression_text(test), block(body)),
+                PRIMARY,
+            ),
+            // a for loop needs all three parts, the missing ones do nothing
+            ExpressionValue::Loop {
+                init,
+                test,
+                update,
+                body,
+            } => {
+                let null = Expression {
+                    region: expression.region.clone(),
+                    value: ExpressionValue::Null,
+                };
+                let test_true = Expression {
+                    region: expression.region.clone(),
+                    value: ExpressionValue::Bool(true),
+                };
+                let parts = sequence([
+                    init.as_deref().unwrap_or(&null),
+                    test.as_deref().unwrap_or(&test_true),
+                    update.as_deref().unwrap_or(&null),
+                ]);
+                (format!("for {} {}", parts.join(" "), block(body)), PRIMARY)
+            }
+            ExpressionValue::ForIn {
+                identifier,
+                iterable,
+                body,
+            } => (
+                format!(
+                    "for {} in {} {}",
+                    identifier,
+                    expression_text(iterable),
+                    block(body)
+                ),
+                PRIMARY,
+            ),
+            ExpressionValue::Continue => ("continue".to_string(), PRIMARY),
+            ExpressionValue::Break => ("break".to_string(), PRIMARY),
+            ExpressionValue::Discard(v) => (format!("discard {}", expression_text(v)), GREEDY),
+            ExpressionValue::Throw(v) => (format!("throw {}", expression_text(v)), GREEDY),
+            ExpressionValue::Import(path) => (format!("import \"{}\"", path), PRIMARY),
+        }
+    })
+}
+
+fn expression_text(expression: &Expression) -> String {
+    printed(expression).0
+}
+
+pub fn expression(expression: &Expression) -> String {
+    expression_text(expression)
+}
+
+pub fn function(function: &DefinedFunction) -> String {
+    format!(
+        "fun({}) {}",
+        function.parameters.join(" "),
+        block(&function.body)
+    )
+}
+
+// the code of a function as it was written, functions built without source are
+// written out instead
+pub fn function_source(function: &DefinedFunction) -> String {
+    function
+        .source
+        .code
+        .clone()
+        .unwrap_or_else(|| self::function(function))
+}
+
+pub fn program(program: &Program) -> String {
+    sequence(&program.ast)
+        .into_iter()
+        .map(|text| text + "\n")
+        .collect()
+}
+
+// the source of a literal that evaluates to the value, builtins, native objects
+// and values the lexer can't read back, like strings with quotes, have none.
+// functions lose the variables they captured from scopes other than the global one.
+// lists containing themselves have no source either
+pub fn value(value: &Value) -> Option<String> {
+    value_in(value, &mut HashSet::new())
+}
+
+// the lists being written, the ones containing the current value
+fn value_in(value: &Value, lists: &mut HashSet<*const ()>) -> Option<String> {
+    Some(match value {
+        Value::Int(v) => v.to_string(),
+        Value::Float(v) if v.is_finite() => format!("{:?}", v),
+        Value::String(v) if !v.contains('"') => format!("\"{}\"", v),
+        Value::Bool(v) => v.to_string(),
+        Value::Null => "null".to_string(),
+        Value::List(list) => {
+            if !lists.insert(list.id()) {
+                return None;
+            }
+            let items = list
+                .borrow()
+                .iter()
+                .map(|v| element(v, lists))
+                .collect::<Option<Vec<_>>>();
+            lists.remove(&list.id());
+            format!("[{}]", items?.join(" "))
+        }
+        Value::Map(map) if map.is_empty() => "{:}".to_string(),
+        Value::Map(map) => {
+            let entries = map
+                .iter()
+                .map(|(key, v)| {
+                    Some(format!(
+                        "{}: {}",
+                        self::value(&Value::String(key.clone()))?,
+                        element(v, lists)?
+                    ))
+                })
+                .collect::<Option<Vec<_>>>()?;
+            format!("{{{}}}", entries.join(" "))
+        }
+        Value::Function(Function::Defined(closure)) => function(&closure.function),
+        _ => return None,
+    })
+}
+
+// the elements of lists and maps are separated by spaces, negative numbers need
+// parentheses so they are not read as a subtraction
+fn element(v: &Value, lists: &mut HashSet<*const ()>) -> Option<String> {
+    let text = value_in(v, lists)?;
+    Some(if text.starts_with('-') {
+        format!("({})", text)
+    } else {
+        text
+    })
+}
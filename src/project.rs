@@ -0,0 +1,119 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+pub const MANIFEST: &str = "olang.toml";
+
+// a directory with an olang.toml, the manifest names the project and its entry
+// point, tests live in the tests directory next to it
+//
+//     [project]
+//     name = "myproject"
+//     entry = "main.olang"
+#[derive(Debug)]
+pub struct Project {
+    pub root: PathBuf,
+    pub name: String,
+    pub entry: PathBuf,
+}
+
+#[derive(Error, Debug)]
+pub enum ProjectError {
+    #[error("{0} already exists")]
+    AlreadyExists(PathBuf),
+    #[error("could not find {MANIFEST} in {0} or any parent directory")]
+    NotFound(PathBuf),
+    #[error("invalid {MANIFEST}: {0}")]
+    InvalidManifest(String),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+const MAIN: &str = "printLn(\"Hello, World!\")\n";
+
+const TESTS: &str = "var testAddition = fun() {
+    assertEqual(1 + 1 2)
+}
+";
+
+// create a new project in a directory that doesn't exist yet
+pub fn create(path: &Path) -> Result<Project, ProjectError> {
+    if path.exists() {
+        return Err(ProjectError::AlreadyExists(path.to_path_buf()));
+    }
+
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "main".to_string());
+
+    fs::create_dir_all(path.join("tests"))?;
+    fs::write(
+        path.join(MANIFEST),
+        format!("[project]\nname = \"{}\"\nentry = \"main.olang\"\n", name),
+    )?;
+    fs::write(path.join("main.olang"), MAIN)?;
+    fs::write(path.join("tests").join("main.olang"), TESTS)?;
+
+    load(path)
+}
+
+// find the project containing the given directory by looking for the manifest
+// in it and its parents
+pub fn find(start: &Path) -> Result<Project, ProjectError> {
+    start
+        .ancestors()
+        .find(|dir| dir.join(MANIFEST).is_file())
+        .ok_or_else(|| ProjectError::NotFound(start.to_path_buf()))
+        .and_then(load)
+}
+
+pub fn load(root: &Path) -> Result<Project, ProjectError> {
+    let source = fs::read_to_string(root.join(MANIFEST))?;
+    let manifest: toml::Table =
+        toml::from_str(&source).map_err(|err| ProjectError::InvalidManifest(err.to_string()))?;
+
+    let project = manifest
+        .get("project")
+        .and_then(|project| project.as_table())
+        .ok_or_else(|| ProjectError::InvalidManifest("missing [project] table".to_string()))?;
+    let string = |key: &str, default: &str| match project.get(key) {
+        None => Ok(default.to_string()),
+        Some(toml::Value::String(value)) => Ok(value.clone()),
+        Some(_) => Err(ProjectError::InvalidManifest(format!(
+            "project.{} has to be a string",
+            key
+        ))),
+    };
+
+    Ok(Project {
+        root: root.to_path_buf(),
+        name: string("name", "main")?,
+        entry: root.join(string("entry", "main.olang")?),
+    })
+}
+
+impl Project {
+    // every .olang file in the tests directory, sorted by name
+    pub fn test_files(&self) -> Result<Vec<PathBuf>, ProjectError> {
+        let dir = self.root.join("tests");
+        if !dir.is_dir() {
+            return Ok(Vec::new());
+        }
+
+        let mut files = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "olang")
+            {
+                files.push(path);
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+}
@@ -0,0 +1,481 @@
+use crate::{
+    interpreter::grow_stack,
+    parser::{Block, DefinedFunction, Expression, ExpressionValue, Program},
+    visitor::{self, Fold, Visitor},
+};
+use std::{
+    collections::{HashMap, HashSet},
+    mem,
+    num::NonZeroU64,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+// finds the functions whose result only depends on their arguments, so the
+// interpreter can reuse the result of an earlier call with the same arguments
+// instead of calling them again, see Interpreter::set_memoize. a function is
+// pure when it is declared at the top level with `var name = fun` and the
+// name is never declared again or assigned anywhere in the program, and its body
+//  - only assigns variables declared inside of it
+//  - only reads its own variables, other pure functions and builtins which
+//    don't touch the outside world, and the program doesn't declare the names
+//    of those builtins itself
+//  - doesn't import files
+// variables of the function are only counted once their declaration was
+// reached, before that the name refers to a variable outside of it. the
+// interpreter only reuses results for arguments and results without lists,
+// functions and native values, those could be changed between the calls.
+// calls of pure functions and builtins inside of a loop are marked invariant
+// when their arguments are the same in every iteration, see Marker. the
+// interpreter then only makes them once while the loop runs
+pub fn mark(program: Program) -> Program {
+    let mut names = Names::default();
+    names.visit_block(&program.ast);
+
+    let candidates = candidate_functions(&program.ast, &names);
+    // a function calling another one is only pure when that one is, drop the
+    // impure ones until only pure functions are left
+    let mut pure: HashSet<String> = candidates.keys().cloned().collect();
+    loop {
+        let impure: Vec<_> = pure
+            .iter()
+            .filter(|name| !is_pure(candidates[*name], &pure, &names))
+            .cloned()
+            .collect();
+        if impure.is_empty() {
+            break;
+        }
+        for name in impure {
+            pure.remove(&name);
+        }
+    }
+
+    let mut marker = Marker {
+        pure,
+        names: &names,
+        in_loop: None,
+    };
+    Program {
+        ast: marker.fold_block(program.ast),
+    }
+}
+
+// the builtins whose result only depends on their arguments, the ones which
+// change lists only ever see lists created during the call
+const PURE_BUILTINS: &[&str] = &[
+    "len",
+    "toString",
+    "typeof",
+    "parseInt",
+    "parseFloat",
+    "assertEqual",
+    "list.zip",
+    "list.enumerate",
+    "list.flatten",
+    "list.unique",
+    "list.sort",
+    "list.reverse",
+    "list.newList",
+    "list.push",
+    "list.extend",
+    "list.pop",
+    "list.insert",
+    "list.removeAt",
+    "list.clear",
+    "sys.olangVersion",
+    "sys.hasFeature",
+    "sys.platform",
+    "math.sum",
+    "math.product",
+    "math.minOf",
+    "math.maxOf",
+    "math.average",
+    "math.mod",
+    "math.clamp",
+    "math.lerp",
+    "math.sign",
+    "math.absDiff",
+    "math.abs",
+    "math.min",
+    "math.max",
+    "math.sqrt",
+    "math.floor",
+    "math.ceil",
+    "math.round",
+    "math.pow",
+    "math.PI",
+    "math.E",
+    "dict.merge",
+    "dict.getOr",
+    "dict.entries",
+    "dict.fromEntries",
+    "str.padLeft",
+    "str.padRight",
+    "str.center",
+    "str.repeat",
+    "str.scan",
+    "str.naturalCompare",
+    "str.compareIgnoreCase",
+    "str.format",
+];
+
+// builtins are called by their name or, in flat mode, by the name of the member
+fn is_pure_builtin(name: &str) -> bool {
+    PURE_BUILTINS.iter().any(|builtin| {
+        *builtin == name
+            || builtin
+                .split_once('.')
+                .is_some_and(|(_, member)| member == name)
+    })
+}
+
+// the names declared and assigned anywhere in the program
+#[derive(Default)]
+struct Names {
+    declarations: HashMap<String, usize>,
+    assigned: HashSet<String>,
+}
+
+impl Names {
+    fn declared(&self, name: &str) -> bool {
+        self.declarations.contains_key(name)
+    }
+
+    fn declare(&mut self, name: &str) {
+        *self.declarations.entry(name.to_string()).or_default() += 1;
+    }
+}
+
+impl Visitor for Names {
+    fn visit_expression(&mut self, expression: &Expression) {
+        match &expression.value {
+            ExpressionValue::VariableDeclaration { identifier, .. }
+            | ExpressionValue::ForIn { identifier, .. } => self.declare(identifier),
+            ExpressionValue::Assign { identifier, .. }
+            | ExpressionValue::Update { identifier, .. }
+            | ExpressionValue::IndexAssign { identifier, .. } => {
+                self.assigned.insert(identifier.clone());
+            }
+            ExpressionValue::Function(function) => {
+                for parameter in &function.parameters {
+                    self.declare(parameter);
+                }
+            }
+            _ => {}
+        }
+        visitor::visit_expression(self, expression);
+    }
+}
+
+// functions declared at the top level with var, under a name which always
+// refers to them
+fn candidate_functions<'a>(
+    block: &'a Block,
+    names: &Names,
+) -> HashMap<String, &'a DefinedFunction> {
+    block
+        .iter()
+        .filter_map(|expression| match &expression.value {
+            ExpressionValue::VariableDeclaration {
+                identifier,
+                expression: value,
+                ..
+            } => match &value.value {
+                ExpressionValue::Function(function)
+                    if names.declarations[identifier] == 1
+                        && !names.assigned.contains(identifier) =>
+                {
+                    Some((identifier.clone(), function))
+                }
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+fn is_pure(function: &DefinedFunction, pure: &HashSet<String>, names: &Names) -> bool {
+    let mut check = Check {
+        scopes: vec![],
+        pure,
+        names,
+        is_pure: true,
+    };
+    check.visit_function(function);
+    check.is_pure
+}
+
+// walks the body of a function with the scopes the interpreter creates for it,
+// like lint does
+struct Check<'a> {
+    scopes: Vec<HashSet<String>>,
+    pure: &'a HashSet<String>,
+    names: &'a Names,
+    is_pure: bool,
+}
+
+impl Check<'_> {
+    fn scope(&mut self, variables: &[String], f: impl FnOnce(&mut Self)) {
+        self.scopes.push(variables.iter().cloned().collect());
+        f(self);
+        self.scopes.pop();
+    }
+
+    fn is_local(&self, name: &str) -> bool {
+        self.scopes.iter().any(|scope| scope.contains(name))
+    }
+
+    fn read(&mut self, name: &str) {
+        let readable = self.is_local(name)
+            || self.pure.contains(name)
+            || (is_pure_builtin(name) && !self.names.declared(name));
+        self.is_pure &= readable;
+    }
+
+    fn assign(&mut self, name: &str) {
+        self.is_pure &= self.is_local(name);
+    }
+
+    fn visit_scope(&mut self, block: &Block) {
+        self.scope(&[], |check| check.visit_block(block));
+    }
+}
+
+impl Visitor for Check<'_> {
+    fn visit_function(&mut self, function: &DefinedFunction) {
+        self.scope(&function.parameters, |check| {
+            check.visit_block(&function.body)
+        });
+    }
+
+    fn visit_expression(&mut self, expression: &Expression) {
+        grow_stack(|| {
+            match &expression.value {
+                ExpressionValue::VariableDeclaration {
+                    identifier,
+                    expression: value,
+                    ..
+                } => {
+                    self.visit_expression(value);
+                    if let Some(scope) = self.scopes.last_mut() {
+                        scope.insert(identifier.clone());
+                    }
+                }
+                ExpressionValue::Identifier(identifier, _) => self.read(identifier),
+                ExpressionValue::Call { identifier, .. } => {
+                    self.read(identifier);
+                    visitor::visit_expression(self, expression);
+                }
+                ExpressionValue::Update { identifier, .. } => self.assign(identifier),
+                ExpressionValue::Assign { identifier, .. }
+                | ExpressionValue::IndexAssign { identifier, .. } => {
+                    self.assign(identifier);
+                    visitor::visit_expression(self, expression);
+                }
+                // builtins in their namespace, like math.sqrt(x)
+                ExpressionValue::Member {
+                    expression: namespace,
+                    member,
+                }
+                | ExpressionValue::MemberCall {
+                    expression: namespace,
+                    member,
+                    ..
+                } if matches!(&namespace.value, ExpressionValue::Identifier(name, _)
+                if !self.is_local(name) && !self.names.declared(name)) =>
+                {
+                    if let ExpressionValue::Identifier(name, _) = &namespace.value {
+                        self.is_pure &=
+                            PURE_BUILTINS.contains(&format!("{}.{}", name, member).as_str());
+                    }
+                    if let ExpressionValue::MemberCall { arguments, .. } = &expression.value {
+                        for argument in arguments {
+                            self.visit_expression(argument);
+                        }
+                    }
+                }
+                ExpressionValue::Import(_) => self.is_pure = false,
+                ExpressionValue::Block(block) => self.visit_scope(block),
+                ExpressionValue::If {
+                    clauses,
+                    else_block,
+                } => {
+                    for clause in clauses {
+                        self.visit_expression(&clause.test);
+                        self.visit_scope(&clause.body);
+                    }
+                    if let Some(block) = else_block {
+                        self.visit_scope(block);
+                    }
+                }
+                ExpressionValue::Loop { .. } => {
+                    self.scope(&[], |check| visitor::visit_expression(check, expression))
+                }
+                ExpressionValue::ForIn {
+                    identifier,
+                    iterable,
+                    body,
+                } => {
+                    self.visit_expression(iterable);
+                    self.scope(std::slice::from_ref(identifier), |check| {
+                        check.visit_block(body)
+                    });
+                }
+                _ => visitor::visit_expression(self, expression),
+            }
+        })
+    }
+}
+
+// the ids of the pure functions and invariant calls are unique in the process,
+// so functions of different programs evaluated by the same interpreter never
+// share results
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+fn next_id() -> Option<NonZeroU64> {
+    NonZeroU64::new(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+}
+
+struct Marker<'a> {
+    pure: HashSet<String>,
+    names: &'a Names,
+    // the names declared in the innermost loop around the expression. none
+    // outside of loops and in functions, which can also be called after the loop
+    in_loop: Option<Names>,
+}
+
+impl Marker<'_> {
+    fn with_loop<T>(&mut self, in_loop: Option<Names>, f: impl FnOnce(&mut Self) -> T) -> T {
+        let outer = mem::replace(&mut self.in_loop, in_loop);
+        let result = f(self);
+        self.in_loop = outer;
+        result
+    }
+
+    // literals, variables which are declared outside of the loop and never
+    // assigned and the results of invariant calls don't change between the
+    // iterations. a variable holding a list can still be changed, the
+    // interpreter only reuses results of calls with copied values
+    fn is_invariant(&self, expression: &Expression) -> bool {
+        let Some(in_loop) = &self.in_loop else {
+            return false;
+        };
+        match &expression.value {
+            ExpressionValue::Int(_)
+            | ExpressionValue::Float(_)
+            | ExpressionValue::String(_)
+            | ExpressionValue::Bool(_)
+            | ExpressionValue::Null => true,
+            ExpressionValue::Identifier(name, _) => {
+                !in_loop.declared(name) && !self.names.assigned.contains(name)
+            }
+            ExpressionValue::Call { invariant, .. }
+            | ExpressionValue::MemberCall { invariant, .. } => invariant.is_some(),
+            _ => false,
+        }
+    }
+
+    // a call of a pure function or builtin with invariant arguments
+    fn is_invariant_call(&self, expression: &Expression) -> bool {
+        let (pure, arguments) = match &expression.value {
+            ExpressionValue::Call {
+                identifier,
+                arguments,
+                ..
+            } => (
+                self.pure.contains(identifier)
+                    || (is_pure_builtin(identifier) && !self.names.declared(identifier)),
+                arguments,
+            ),
+            ExpressionValue::MemberCall {
+                expression: namespace,
+                member,
+                arguments,
+                ..
+            } => (
+                matches!(&namespace.value, ExpressionValue::Identifier(name, _)
+                if !self.names.declared(name)
+                    && PURE_BUILTINS.contains(&format!("{}.{}", name, member).as_str())),
+                arguments,
+            ),
+            _ => return false,
+        };
+        pure && self.in_loop.is_some()
+            && arguments.iter().all(|argument| self.is_invariant(argument))
+    }
+}
+
+impl Fold for Marker<'_> {
+    fn fold_function(&mut self, function: DefinedFunction) -> DefinedFunction {
+        self.with_loop(None, |marker| visitor::fold_function(marker, function))
+    }
+
+    fn fold_expression(&mut self, expression: Expression) -> Expression {
+        grow_stack(|| {
+            // the init of a loop and the iterable of a for loop are evaluated
+            // once before the iterations
+            let region = expression.region;
+            let mut expression = match expression.value {
+                ExpressionValue::Loop {
+                    init,
+                    test,
+                    update,
+                    body,
+                } => {
+                    let mut declared = Names::default();
+                    for expression in [&init, &test, &update].into_iter().flatten() {
+                        declared.visit_expression(expression);
+                    }
+                    declared.visit_block(&body);
+
+                    let init = init.map(|init| Box::new(self.fold_expression(*init)));
+                    let value = self.with_loop(Some(declared), |marker| ExpressionValue::Loop {
+                        init,
+                        test: test.map(|test| Box::new(marker.fold_expression(*test))),
+                        update: update.map(|update| Box::new(marker.fold_expression(*update))),
+                        body: marker.fold_block(body),
+                    });
+                    Expression { region, value }
+                }
+                ExpressionValue::ForIn {
+                    identifier,
+                    iterable,
+                    body,
+                } => {
+                    let mut declared = Names::default();
+                    declared.declare(&identifier);
+                    declared.visit_block(&body);
+
+                    let iterable = Box::new(self.fold_expression(*iterable));
+                    let value = self.with_loop(Some(declared), |marker| ExpressionValue::ForIn {
+                        identifier,
+                        iterable,
+                        body: marker.fold_block(body),
+                    });
+                    Expression { region, value }
+                }
+                value => visitor::fold_expression(self, Expression { region, value }),
+            };
+
+            if self.is_invariant_call(&expression) {
+                if let ExpressionValue::Call { invariant, .. }
+                | ExpressionValue::MemberCall { invariant, .. } = &mut expression.value
+                {
+                    *invariant = next_id();
+                }
+            }
+            if let ExpressionValue::VariableDeclaration {
+                identifier,
+                expression: value,
+                ..
+            } = &mut expression.value
+            {
+                if let ExpressionValue::Function(function) = &mut value.value {
+                    if self.pure.contains(identifier) {
+                        function.pure = next_id();
+                    }
+                }
+            }
+            expression
+        })
+    }
+}
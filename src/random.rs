@@ -0,0 +1,53 @@
+use std::{
+    process,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+// the generator behind random and randomInt, splitmix64. it is fast and good
+// enough for games and simulations, but not for anything security related
+pub(crate) struct Random(u64);
+
+impl Random {
+    pub(crate) fn from_seed(seed: u64) -> Self {
+        Random(seed)
+    }
+
+    // a different sequence on every run
+    pub(crate) fn from_time() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos() as u64;
+        Random(nanos ^ (process::id() as u64).rotate_left(32))
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    // from 0 up to but not including 1, every float uses 53 random bits
+    pub(crate) fn next_float(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    // min and max are both included, numbers above the largest multiple of the
+    // range are drawn again so every number is equally likely
+    pub(crate) fn next_int(&mut self, min: i64, max: i64) -> i64 {
+        let span = (max.wrapping_sub(min) as u64).wrapping_add(1);
+        if span == 0 {
+            // the range covers every int
+            return self.next_u64() as i64;
+        }
+        let zone = u64::MAX - (u64::MAX % span);
+        loop {
+            let x = self.next_u64();
+            if x < zone {
+                return min.wrapping_add((x % span) as i64);
+            }
+        }
+    }
+}
@@ -0,0 +1,310 @@
+use crate::{
+    diagnostic,
+    interpreter::{ErrorReport, EvalError, Interpreter},
+    printer,
+    value::{Function, Value},
+};
+use anyhow::Result;
+use rustyline::{
+    completion::Completer, error::ReadlineError, highlight::Highlighter, hint::Hinter,
+    history::FileHistory, validate::Validator, Context, Helper,
+};
+use std::{
+    env, fs,
+    io::{self, BufRead, IsTerminal, Write},
+    path::PathBuf,
+    process,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+const PROMPT: &str = "> ";
+const CONTINUATION_PROMPT: &str = "... ";
+
+// builtins and the namespaces holding them, next to the builtins namespaces
+// may hold constants like math.PI
+fn is_builtin(value: &Value) -> bool {
+    match value {
+        Value::Function(Function::Builtin(_)) => true,
+        Value::Map(map) => {
+            map.values().any(is_builtin)
+                && map
+                    .values()
+                    .all(|v| is_builtin(v) || matches!(v, Value::Float(_)))
+        }
+        _ => false,
+    }
+}
+
+// the top level bindings of a session as olang source which declares them
+// again, together with the names of the bindings that can't be written as
+// source. builtins are left out
+pub fn session_source(interpreter: &Interpreter) -> (String, Vec<String>) {
+    let mut source = String::new();
+    let mut skipped = vec![];
+
+    for (id, value) in interpreter.bindings() {
+        if is_builtin(&value) {
+            continue;
+        }
+        match printer::value(&value) {
+            Some(text) => source += &format!("var {} = {}\n", id, text),
+            None => skipped.push(id),
+        }
+    }
+
+    (source, skipped)
+}
+
+// lines starting with a colon are commands of the repl instead of olang code
+fn command(interpreter: &mut Interpreter, line: &str, color: bool) -> Result<()> {
+    let (name, argument) = line.split_once(' ').unwrap_or((line, ""));
+    let argument = argument.trim();
+
+    match name {
+        ":save" if !argument.is_empty() => {
+            let (source, skipped) = session_source(interpreter);
+            fs::write(argument, source)?;
+            if !skipped.is_empty() {
+                eprintln!("not saved: {}", skipped.join(" "));
+            }
+        }
+        ":load" if !argument.is_empty() => {
+            let source = fs::read_to_string(argument)?;
+            if let Err(err) = interpreter.eval_str(&source) {
+                eprint!(
+                    "{}",
+                    diagnostic::render(&ErrorReport::from(err).with_source(&source), color)
+                );
+            }
+        }
+        ":source" if !argument.is_empty() => match interpreter.get_variable(argument) {
+            Some(Value::Function(Function::Defined(closure))) => {
+                println!("{}", printer::function_source(&closure.function))
+            }
+            Some(Value::Function(Function::Builtin(_))) => {
+                eprintln!("{} is a builtin, it has no source", argument)
+            }
+            Some(_) => eprintln!("{} is not a function", argument),
+            None => eprintln!("{} is not declared", argument),
+        },
+        _ => eprintln!("unknown command, try :save FILE, :load FILE or :source NAME"),
+    }
+
+    Ok(())
+}
+
+// where the session reads its lines from, an editor with history and
+// completion in a terminal and plain lines otherwise
+trait Readline {
+    fn read_line(&mut self, prompt: &str) -> Result<Input>;
+
+    fn add_history(&mut self, _line: &str) {}
+
+    // the names tab completion offers
+    fn set_names(&mut self, _names: Vec<String>) {}
+}
+
+enum Input {
+    Line(String),
+    // Ctrl-C while typing
+    Interrupted,
+    // Ctrl-D
+    End,
+}
+
+struct Plain {
+    interrupted: Arc<AtomicBool>,
+}
+
+impl Readline for Plain {
+    fn read_line(&mut self, prompt: &str) -> Result<Input> {
+        print!("{}", prompt);
+        io::stdout().flush()?;
+
+        let mut line = String::new();
+        if io::stdin().lock().read_line(&mut line)? == 0 {
+            println!();
+            return Ok(Input::End);
+        }
+        if self.interrupted.swap(false, Ordering::Relaxed) {
+            return Ok(Input::Interrupted);
+        }
+        Ok(Input::Line(line.trim_end_matches(['\n', '\r']).to_string()))
+    }
+}
+
+struct Editor {
+    editor: rustyline::Editor<Completion, FileHistory>,
+    history: Option<PathBuf>,
+}
+
+impl Readline for Editor {
+    fn read_line(&mut self, prompt: &str) -> Result<Input> {
+        match self.editor.readline(prompt) {
+            Ok(line) => Ok(Input::Line(line)),
+            Err(ReadlineError::Interrupted) => Ok(Input::Interrupted),
+            Err(ReadlineError::Eof) => Ok(Input::End),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    // the history is saved after every line, the session can end with exit()
+    fn add_history(&mut self, line: &str) {
+        let _ = self.editor.add_history_entry(line);
+        if let Some(path) = &self.history {
+            if let Err(err) = self.editor.save_history(path) {
+                eprintln!("failed to save the history to {}: {}", path.display(), err);
+                self.history = None;
+            }
+        }
+    }
+
+    fn set_names(&mut self, names: Vec<String>) {
+        if let Some(completion) = self.editor.helper_mut() {
+            completion.names = names;
+        }
+    }
+}
+
+// completes the identifier before the cursor, members of namespaces like
+// math.sum are completed with their namespace
+#[derive(Default)]
+struct Completion {
+    names: Vec<String>,
+}
+
+impl Completer for Completion {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let start = line[..pos]
+            .rfind(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.'))
+            .map_or(0, |i| i + 1);
+        let word = &line[start..pos];
+        let candidates = self
+            .names
+            .iter()
+            .filter(|name| name.starts_with(word))
+            .cloned()
+            .collect();
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for Completion {
+    type Hint = String;
+}
+
+impl Highlighter for Completion {}
+
+impl Validator for Completion {}
+
+impl Helper for Completion {}
+
+// the top level bindings and the members of the maps among them
+fn names(interpreter: &Interpreter) -> Vec<String> {
+    let mut names = vec![];
+    for (id, value) in interpreter.bindings() {
+        if let Value::Map(map) = &value {
+            names.extend(map.keys().map(|key| format!("{}.{}", id, key)));
+        }
+        names.push(id);
+    }
+    names.sort();
+    names
+}
+
+// the history is kept in the data directory of the user, like
+// ~/.local/share/olang/history
+pub fn history_path() -> Option<PathBuf> {
+    let data_dir = env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))?;
+    Some(data_dir.join("olang").join("history"))
+}
+
+fn editor() -> Result<Editor> {
+    let mut editor = rustyline::Editor::new()?;
+    editor.set_helper(Some(Completion::default()));
+
+    let history = history_path().filter(|path| {
+        path.parent()
+            .is_some_and(|directory| fs::create_dir_all(directory).is_ok())
+    });
+    if let Some(path) = &history {
+        // there is no history before the first session
+        let _ = editor.load_history(path);
+    }
+    Ok(Editor { editor, history })
+}
+
+pub fn run(mut interpreter: Interpreter, color: bool) -> Result<()> {
+    let mut readline: Box<dyn Readline> = if io::stdin().is_terminal() {
+        Box::new(editor()?)
+    } else {
+        Box::new(Plain {
+            interrupted: interpreter.interrupt_flag(),
+        })
+    };
+    let mut input = String::new();
+
+    loop {
+        readline.set_names(names(&interpreter));
+        let prompt = if input.is_empty() {
+            PROMPT
+        } else {
+            CONTINUATION_PROMPT
+        };
+        let line = match readline.read_line(prompt)? {
+            Input::Line(line) => line,
+            // Ctrl-C while typing discards the current input
+            Input::Interrupted => {
+                input.clear();
+                continue;
+            }
+            Input::End => return Ok(()),
+        };
+        if !line.trim().is_empty() {
+            readline.add_history(&line);
+        }
+
+        if input.is_empty() && line.starts_with(':') {
+            if let Err(err) = command(&mut interpreter, line.trim_end(), color) {
+                eprintln!("error: {}", err);
+            }
+            continue;
+        }
+
+        input.push_str(&line);
+        input.push('\n');
+        if input.trim().is_empty() {
+            input.clear();
+            continue;
+        }
+
+        match interpreter.eval_str(&input) {
+            Ok(Value::Null) => {}
+            Ok(value) => println!("{}", value),
+            // keep reading lines until the expression is complete
+            Err(err) if err.is_unexpected_end() => continue,
+            Err(EvalError::Exit(code)) => {
+                // the temporary files of the session are deleted with it
+                drop(interpreter);
+                process::exit(code)
+            }
+            Err(err) => eprint!(
+                "{}",
+                diagnostic::render(&ErrorReport::from(err).with_source(&input), color)
+            ),
+        }
+        input.clear();
+    }
+}
@@ -0,0 +1,267 @@
+use crate::{
+    interpreter::grow_stack,
+    parser::{Block, DefinedFunction, Expression, ExpressionValue, IfClause, Program, Slot},
+    visitor::{self, Fold, Visitor},
+};
+use std::collections::{HashMap, HashSet};
+
+// assigns every variable used inside a function, block or loop the slot it is
+// stored in at runtime, so the interpreter can index into the scope chain
+// instead of looking the name up in every scope. the resolver follows the
+// scopes the interpreter creates, top level variables live in the global scope
+// which the host and the repl can add to, so they are always looked up by name.
+//
+// a variable that is declared later in an enclosing scope than where it is used
+// can refer to different declarations depending on when the code runs, for
+// example in the body of a loop or of a function that is called later, those
+// are left unresolved as well
+pub fn resolve(program: Program) -> Program {
+    Program {
+        ast: Resolver::default().fold_block(program.ast),
+    }
+}
+
+#[derive(Default)]
+struct Resolver {
+    scopes: Vec<Scope>,
+}
+
+struct Scope {
+    slots: HashMap<String, usize>,
+    // every name declared directly in this scope, including the ones whose
+    // declaration hasn't been reached yet
+    declared: HashSet<String>,
+}
+
+// collects the declarations of a scope without entering the scopes nested in it
+#[derive(Default)]
+struct Declarations(HashSet<String>);
+
+impl Visitor for Declarations {
+    fn visit_expression(&mut self, expression: &Expression) {
+        grow_stack(|| match &expression.value {
+            ExpressionValue::VariableDeclaration { identifier, .. } => {
+                self.0.insert(identifier.clone());
+                visitor::visit_expression(self, expression);
+            }
+            ExpressionValue::If { clauses, .. } => {
+                for clause in clauses {
+                    self.visit_expression(&clause.test);
+                }
+            }
+            ExpressionValue::ForIn { iterable, .. } => self.visit_expression(iterable),
+            ExpressionValue::Block(_)
+            | ExpressionValue::Function(_)
+            | ExpressionValue::Loop { .. } => {}
+            _ => visitor::visit_expression(self, expression),
+        })
+    }
+}
+
+fn declarations<'a>(expressions: impl IntoIterator<Item = &'a Expression>) -> HashSet<String> {
+    let mut declarations = Declarations::default();
+    for expression in expressions {
+        declarations.visit_expression(expression);
+    }
+    declarations.0
+}
+
+impl Resolver {
+    fn push(&mut self, declared: HashSet<String>) {
+        self.scopes.push(Scope {
+            slots: HashMap::new(),
+            declared,
+        });
+    }
+
+    // the slot of a new declaration in the current scope, declaring a name twice
+    // reuses its slot
+    fn declare(&mut self, identifier: &str) -> Option<usize> {
+        let scope = self.scopes.last_mut()?;
+        let next = scope.slots.len();
+        Some(*scope.slots.entry(identifier.to_string()).or_insert(next))
+    }
+
+    fn lookup(&self, identifier: &str) -> Option<Slot> {
+        for (depth, scope) in self.scopes.iter().rev().enumerate() {
+            if let Some(&index) = scope.slots.get(identifier) {
+                return Some(Slot { depth, index });
+            }
+            if scope.declared.contains(identifier) {
+                return None;
+            }
+        }
+
+        None
+    }
+
+    fn fold_scope(&mut self, block: Block) -> Block {
+        self.push(declarations(&block));
+        let block = self.fold_block(block);
+        self.scopes.pop();
+        block
+    }
+
+    fn resolve(&mut self, value: ExpressionValue) -> ExpressionValue {
+        match value {
+            ExpressionValue::Identifier(identifier, _) => {
+                let slot = self.lookup(&identifier);
+                ExpressionValue::Identifier(identifier, slot)
+            }
+            ExpressionValue::VariableDeclaration {
+                identifier,
+                expression,
+                ..
+            } => ExpressionValue::VariableDeclaration {
+                slot: self.declare(&identifier),
+                identifier,
+                expression,
+            },
+            ExpressionValue::Assign {
+                identifier,
+                operator,
+                expression,
+                ..
+            } => ExpressionValue::Assign {
+                slot: self.lookup(&identifier),
+                identifier,
+                operator,
+                expression,
+            },
+            ExpressionValue::IndexAssign {
+                identifier,
+                indices,
+                operator,
+                expression,
+                ..
+            } => ExpressionValue::IndexAssign {
+                slot: self.lookup(&identifier),
+                identifier,
+                indices,
+                operator,
+                expression,
+            },
+            ExpressionValue::Update {
+                identifier,
+                operator,
+                ..
+            } => ExpressionValue::Update {
+                slot: self.lookup(&identifier),
+                identifier,
+                operator,
+            },
+            ExpressionValue::Call {
+                identifier,
+                arguments,
+                invariant,
+                ..
+            } => ExpressionValue::Call {
+                slot: self.lookup(&identifier),
+                identifier,
+                arguments,
+                invariant,
+            },
+            value => value,
+        }
+    }
+}
+
+impl Fold for Resolver {
+    fn fold_function(&mut self, function: DefinedFunction) -> DefinedFunction {
+        let mut declared = declarations(&function.body);
+        declared.extend(function.parameters.iter().cloned());
+        self.push(declared);
+        for parameter in &function.parameters {
+            self.declare(parameter);
+        }
+        let body = self.fold_block(function.body);
+        self.scopes.pop();
+
+        DefinedFunction {
+            parameters: function.parameters,
+            body,
+            source: function.source,
+            pure: function.pure,
+        }
+    }
+
+    fn fold_expression(&mut self, expression: Expression) -> Expression {
+        grow_stack(|| {
+            let region = expression.region;
+            let value = match expression.value {
+                ExpressionValue::Block(block) => ExpressionValue::Block(self.fold_scope(block)),
+                ExpressionValue::If {
+                    clauses,
+                    else_block,
+                } => ExpressionValue::If {
+                    clauses: clauses
+                        .into_iter()
+                        .map(|clause| IfClause {
+                            test: Box::new(self.fold_expression(*clause.test)),
+                            body: self.fold_scope(clause.body),
+                        })
+                        .collect(),
+                    else_block: else_block.map(|block| self.fold_scope(block)),
+                },
+                // the whole loop runs in one scope, in the order init, test, body, update
+                ExpressionValue::Loop {
+                    init,
+                    test,
+                    update,
+                    body,
+                } => {
+                    self.push(declarations(
+                        init.iter()
+                            .chain(&test)
+                            .chain(&update)
+                            .map(|expression| expression.as_ref())
+                            .chain(&body),
+                    ));
+                    let init = init.map(|expression| Box::new(self.fold_expression(*expression)));
+                    let test = test.map(|expression| Box::new(self.fold_expression(*expression)));
+                    let body = self.fold_block(body);
+                    let update =
+                        update.map(|expression| Box::new(self.fold_expression(*expression)));
+                    self.scopes.pop();
+
+                    ExpressionValue::Loop {
+                        init,
+                        test,
+                        update,
+                        body,
+                    }
+                }
+                // every iteration gets a scope with the item in its first slot
+                ExpressionValue::ForIn {
+                    identifier,
+                    iterable,
+                    body,
+                } => {
+                    let iterable = Box::new(self.fold_expression(*iterable));
+                    let mut declared = declarations(&body);
+                    declared.insert(identifier.clone());
+                    self.push(declared);
+                    self.declare(&identifier);
+                    let body = self.fold_block(body);
+                    self.scopes.pop();
+
+                    ExpressionValue::ForIn {
+                        identifier,
+                        iterable,
+                        body,
+                    }
+                }
+                value => {
+                    // the children are evaluated before the variable is accessed
+                    let expression = visitor::fold_expression(self, Expression { region, value });
+                    return Expression {
+                        region: expression.region,
+                        value: self.resolve(expression.value),
+                    };
+                }
+            };
+
+            Expression { region, value }
+        })
+    }
+}
@@ -0,0 +1,54 @@
+use crate::{
+    config::Config, optimizer, parser::Parser, parser::Program, resolver, EvalError, Interpreter,
+    Value,
+};
+use std::sync::Arc;
+
+// a program parsed, optimized and resolved once and evaluated many times, for
+// example by a server which runs the same rules for every request with other
+// inputs. the AST is only read while it is evaluated, so it is shared between
+// the clones of a script, also ones sent to other threads. values can't cross
+// threads, every evaluation runs in an interpreter of its own and never sees
+// the variables of another one
+#[derive(Debug, Clone)]
+pub struct Script {
+    program: Arc<Program>,
+}
+
+impl Script {
+    pub fn compile(source: &str) -> Result<Self, EvalError> {
+        let program = Parser::new(source)?.parse()?;
+        Ok(Script::from_program(optimizer::optimize(program)))
+    }
+
+    // a program built with the builder or parsed by the host
+    pub fn from_program(program: Program) -> Self {
+        Script {
+            program: Arc::new(resolver::resolve(program)),
+        }
+    }
+
+    pub fn program(&self) -> &Program {
+        &self.program
+    }
+
+    // evaluate the script in a new interpreter, the inputs are declared as
+    // top level variables before it runs
+    pub fn eval(
+        &self,
+        config: &Config,
+        inputs: impl IntoIterator<Item = (String, Value)>,
+    ) -> Result<Value, EvalError> {
+        let mut interpreter = Interpreter::with_config(config);
+        for (id, value) in inputs {
+            interpreter.declare_variable(&id, value);
+        }
+        self.eval_in(&mut interpreter)
+    }
+
+    // evaluate the script in an interpreter set up by the host, for example with
+    // its own builtins, io or sandbox
+    pub fn eval_in(&self, interpreter: &mut Interpreter) -> Result<Value, EvalError> {
+        interpreter.eval_program(&self.program)
+    }
+}
@@ -0,0 +1,347 @@
+use crate::{
+    environment::{Scope, ScopeRef},
+    interpreter::Module,
+    parser::DefinedFunction,
+    value::{Closure, Function, List, Value},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet, HashMap},
+    path::PathBuf,
+    rc::Rc,
+    time::SystemTime,
+};
+use thiserror::Error;
+
+// the state of an interpreter after the prelude and the imports of a program
+// ran, written to a file so later runs can start from it instead of evaluating
+// everything again. scopes and lists are stored once and referred to by their
+// index, so closures sharing a scope and lists containing themselves are
+// restored the way they were
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+    // snapshots are only read by the version of olang which wrote them, the
+    // AST changes between versions
+    version: String,
+    global: usize,
+    scopes: Vec<SnapshotScope>,
+    lists: Vec<SnapshotList>,
+    modules: Vec<SnapshotModule>,
+    files: BTreeSet<PathBuf>,
+    env_vars: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct SnapshotScope {
+    slots: Vec<(String, usize)>,
+    values: Vec<Option<SnapshotValue>>,
+    parent: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SnapshotModule {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    namespace: SnapshotValue,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct SnapshotList {
+    items: Vec<SnapshotValue>,
+    frozen: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+enum SnapshotValue {
+    Int(i64),
+    Float(f64),
+    String(String),
+    Bool(bool),
+    Null,
+    List(usize),
+    Map(BTreeMap<String, SnapshotValue>),
+    Function {
+        function: DefinedFunction,
+        scope: usize,
+    },
+    // builtins are looked up by their name when the snapshot is restored
+    Builtin(String),
+}
+
+#[derive(Error, Debug)]
+pub enum SnapshotError {
+    #[error("{0} values can't be stored in a snapshot")]
+    Unsupported(&'static str),
+    #[error("the snapshot was written by olang {0}, this is olang {version}", version = env!("CARGO_PKG_VERSION"))]
+    Version(String),
+    #[error("the snapshot uses the builtin {0} which doesn't exist in this interpreter")]
+    UnknownBuiltin(String),
+    #[error("invalid snapshot: {0}")]
+    Invalid(String),
+}
+
+// the parts of the interpreter stored in a snapshot
+pub(crate) struct State {
+    pub(crate) global: ScopeRef,
+    pub(crate) modules: HashMap<PathBuf, Module>,
+    pub(crate) files: BTreeSet<PathBuf>,
+    pub(crate) env_vars: HashMap<String, String>,
+}
+
+#[derive(Default)]
+struct Recorder {
+    scopes: Vec<SnapshotScope>,
+    lists: Vec<SnapshotList>,
+    // the index of every scope and list already recorded, by their address
+    scope_indices: HashMap<*const RefCell<Scope>, usize>,
+    list_indices: HashMap<*const (), usize>,
+}
+
+impl Recorder {
+    // the index is reserved before the contents are recorded, so a scope or
+    // list reached again from inside of itself refers to the same index
+    fn scope(&mut self, scope: &ScopeRef) -> Result<usize, SnapshotError> {
+        if let Some(&index) = self.scope_indices.get(&Rc::as_ptr(scope)) {
+            return Ok(index);
+        }
+        let index = self.scopes.len();
+        self.scopes.push(SnapshotScope::default());
+        self.scope_indices.insert(Rc::as_ptr(scope), index);
+
+        let scope = scope.borrow();
+        let values = scope
+            .values
+            .iter()
+            .map(|value| value.as_ref().map(|value| self.value(value)).transpose())
+            .collect::<Result<_, _>>()?;
+        let parent = scope
+            .parent
+            .as_ref()
+            .map(|parent| self.scope(parent))
+            .transpose()?;
+        self.scopes[index] = SnapshotScope {
+            slots: scope
+                .slots
+                .iter()
+                .map(|(id, &slot)| (id.clone(), slot))
+                .collect(),
+            values,
+            parent,
+        };
+        Ok(index)
+    }
+
+    fn list(&mut self, list: &List) -> Result<usize, SnapshotError> {
+        if let Some(&index) = self.list_indices.get(&list.id()) {
+            return Ok(index);
+        }
+        let index = self.lists.len();
+        self.lists.push(SnapshotList::default());
+        self.list_indices.insert(list.id(), index);
+
+        let items = list
+            .to_vec()
+            .iter()
+            .map(|item| self.value(item))
+            .collect::<Result<_, _>>()?;
+        self.lists[index] = SnapshotList {
+            items,
+            frozen: list.is_frozen(),
+        };
+        Ok(index)
+    }
+
+    fn value(&mut self, value: &Value) -> Result<SnapshotValue, SnapshotError> {
+        Ok(match value {
+            Value::Int(v) => SnapshotValue::Int(*v),
+            Value::Float(v) => SnapshotValue::Float(*v),
+            Value::String(v) => SnapshotValue::String(v.clone()),
+            Value::Bool(v) => SnapshotValue::Bool(*v),
+            Value::Null => SnapshotValue::Null,
+            Value::List(list) => SnapshotValue::List(self.list(list)?),
+            Value::Map(map) => SnapshotValue::Map(
+                map.iter()
+                    .map(|(key, value)| Ok((key.clone(), self.value(value)?)))
+                    .collect::<Result<_, _>>()?,
+            ),
+            Value::Function(Function::Defined(closure)) => SnapshotValue::Function {
+                function: closure.function.clone(),
+                scope: self.scope(&closure.scope())?,
+            },
+            Value::Function(Function::Builtin(builtin)) => {
+                SnapshotValue::Builtin(builtin.name.clone())
+            }
+            // files, timers and the like belong to the process which created them
+            Value::Native(_) => return Err(SnapshotError::Unsupported("native")),
+        })
+    }
+}
+
+pub(crate) fn record(state: &State) -> Result<Vec<u8>, SnapshotError> {
+    let mut recorder = Recorder::default();
+    let global = recorder.scope(&state.global)?;
+    let modules = state
+        .modules
+        .iter()
+        .map(|(path, module)| {
+            Ok(SnapshotModule {
+                path: path.clone(),
+                modified: module.modified,
+                namespace: recorder.value(&module.namespace)?,
+            })
+        })
+        .collect::<Result<_, SnapshotError>>()?;
+
+    let snapshot = Snapshot {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        global,
+        scopes: recorder.scopes,
+        lists: recorder.lists,
+        modules,
+        files: state.files.clone(),
+        env_vars: state.env_vars.clone().into_iter().collect(),
+    };
+    let mut bytes = vec![];
+    ciborium::into_writer(&snapshot, &mut bytes)
+        .map_err(|err| SnapshotError::Invalid(err.to_string()))?;
+    Ok(bytes)
+}
+
+struct Restorer {
+    scopes: Vec<ScopeRef>,
+    lists: Vec<List>,
+    builtins: HashMap<String, Value>,
+}
+
+impl Restorer {
+    fn scope(&self, index: usize) -> Result<ScopeRef, SnapshotError> {
+        self.scopes
+            .get(index)
+            .cloned()
+            .ok_or_else(|| SnapshotError::Invalid(format!("there is no scope {}", index)))
+    }
+
+    fn value(&self, value: SnapshotValue) -> Result<Value, SnapshotError> {
+        Ok(match value {
+            SnapshotValue::Int(v) => Value::Int(v),
+            SnapshotValue::Float(v) => Value::Float(v),
+            SnapshotValue::String(v) => Value::String(v),
+            SnapshotValue::Bool(v) => Value::Bool(v),
+            SnapshotValue::Null => Value::Null,
+            SnapshotValue::List(index) => Value::List(
+                self.lists
+                    .get(index)
+                    .cloned()
+                    .ok_or_else(|| SnapshotError::Invalid(format!("there is no list {}", index)))?,
+            ),
+            SnapshotValue::Map(map) => Value::Map(
+                map.into_iter()
+                    .map(|(key, value)| Ok((key, self.value(value)?)))
+                    .collect::<Result<_, SnapshotError>>()?,
+            ),
+            SnapshotValue::Function { function, scope } => Value::Function(Function::Defined(
+                Closure::new(function, self.scope(scope)?),
+            )),
+            SnapshotValue::Builtin(name) => self
+                .builtins
+                .get(&name)
+                .cloned()
+                .ok_or(SnapshotError::UnknownBuiltin(name))?,
+        })
+    }
+}
+
+// the builtins of a global scope by their name, like printLn and math.sum
+fn builtins(global: &ScopeRef) -> HashMap<String, Value> {
+    let mut builtins = HashMap::new();
+    let global = global.borrow();
+    let namespaces = global
+        .values
+        .iter()
+        .flatten()
+        .flat_map(|value| match value {
+            Value::Map(namespace) => namespace.values().collect(),
+            value => vec![value],
+        });
+    for value in namespaces {
+        if let Value::Function(Function::Builtin(builtin)) = value {
+            builtins.insert(builtin.name.clone(), value.clone());
+        }
+    }
+    builtins
+}
+
+// builtins are taken from the global scope of the interpreter the snapshot is
+// restored into, it has to have the standard library the snapshot was made with
+pub(crate) fn restore(bytes: &[u8], global: &ScopeRef) -> Result<State, SnapshotError> {
+    let snapshot: Snapshot =
+        ciborium::from_reader(bytes).map_err(|err| SnapshotError::Invalid(err.to_string()))?;
+    if snapshot.version != env!("CARGO_PKG_VERSION") {
+        return Err(SnapshotError::Version(snapshot.version));
+    }
+
+    // everything is created empty first since scopes and lists can refer to
+    // the ones after them
+    let restorer = Restorer {
+        scopes: snapshot
+            .scopes
+            .iter()
+            .map(|_| ScopeRef::default())
+            .collect(),
+        lists: snapshot
+            .lists
+            .iter()
+            .map(|list| List::empty(list.frozen))
+            .collect(),
+        builtins: builtins(global),
+    };
+    for (list, snapshot) in restorer.lists.iter().zip(snapshot.lists) {
+        let items = snapshot
+            .items
+            .into_iter()
+            .map(|item| restorer.value(item))
+            .collect::<Result<_, _>>()?;
+        list.set_items(items);
+    }
+    for (scope, snapshot) in restorer.scopes.iter().zip(snapshot.scopes) {
+        let values = snapshot
+            .values
+            .into_iter()
+            .map(|value| {
+                value
+                    .map(|value| Ok(restorer.value(value)?.stored_in(scope)))
+                    .transpose()
+            })
+            .collect::<Result<_, _>>()?;
+        let parent = snapshot
+            .parent
+            .map(|parent| restorer.scope(parent))
+            .transpose()?;
+        *scope.borrow_mut() = Scope {
+            slots: snapshot.slots.into_iter().collect(),
+            values,
+            parent,
+        };
+    }
+
+    Ok(State {
+        global: restorer.scope(snapshot.global)?,
+        modules: snapshot
+            .modules
+            .into_iter()
+            .map(|module| {
+                let namespace = restorer.value(module.namespace)?;
+                Ok((
+                    module.path,
+                    Module {
+                        modified: module.modified,
+                        namespace,
+                    },
+                ))
+            })
+            .collect::<Result<_, SnapshotError>>()?,
+        files: snapshot.files,
+        env_vars: snapshot.env_vars.into_iter().collect(),
+    })
+}
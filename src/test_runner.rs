@@ -0,0 +1,248 @@
+use crate::{
+    config::Config,
+    interpreter::{EvalError, Interpreter},
+    parser::{ExpressionValue, Parser, Program},
+    resolver,
+};
+use serde_json::json;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+use strum::{Display, EnumString, VariantNames};
+
+#[derive(Debug, Clone, Copy, Display, EnumString, VariantNames)]
+#[strum(serialize_all = "lowercase")]
+pub enum ReportFormat {
+    Text,
+    Junit,
+    Json,
+}
+
+#[derive(Debug)]
+pub struct TestResult {
+    pub name: String,
+    pub duration: Duration,
+    pub failure: Option<String>,
+}
+
+impl TestResult {
+    pub fn passed(&self) -> bool {
+        self.failure.is_none()
+    }
+}
+
+fn failure(err: EvalError) -> Option<String> {
+    Some(format!("error[{}]: {}", err.code(), err))
+}
+
+// functions declared at the top level whose name starts with test
+fn test_functions(program: &Program) -> Vec<&str> {
+    program
+        .ast
+        .iter()
+        .filter_map(|expression| match &expression.value {
+            ExpressionValue::VariableDeclaration {
+                identifier,
+                expression,
+                ..
+            } if identifier.starts_with("test")
+                && matches!(expression.value, ExpressionValue::Function(_)) =>
+            {
+                Some(identifier.as_str())
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+// the files in a directory and its subdirectories whose name ends with
+// _test.olang, sorted by their path. any other path is a test file itself
+pub fn discover(path: &Path) -> io::Result<Vec<PathBuf>> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let mut files = vec![];
+    for entry in fs::read_dir(path)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            files.extend(discover(&path)?);
+        } else if path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().ends_with("_test.olang"))
+        {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+// every test function of a file is a test case, which runs in a fresh
+// interpreter after the top level of the file was evaluated, so a failing test
+// doesn't affect the others. a file without test functions is a single test
+// case which passes if it evaluates without an error
+pub fn run_file(path: &Path, config: &Config) -> Vec<TestResult> {
+    let name = path.display().to_string();
+    let start = Instant::now();
+    let single = |failure| {
+        vec![TestResult {
+            name: name.clone(),
+            duration: start.elapsed(),
+            failure,
+        }]
+    };
+
+    let source = match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(err) => return single(Some(format!("could not read file: {}", err))),
+    };
+    let program = match Parser::new(&source)
+        .map_err(EvalError::from)
+        .and_then(|mut parser| Ok(parser.parse()?))
+    {
+        Ok(program) => resolver::resolve(program),
+        Err(err) => return single(failure(err)),
+    };
+
+    let tests = test_functions(&program);
+    if tests.is_empty() {
+        return single(
+            Interpreter::with_config(config)
+                .set_path(path)
+                .eval_program(&program)
+                .err()
+                .and_then(failure),
+        );
+    }
+
+    tests
+        .into_iter()
+        .map(|test| {
+            let start = Instant::now();
+            let mut interpreter = Interpreter::with_config(config);
+            interpreter.set_path(path);
+            let result = interpreter
+                .eval_program(&program)
+                .and_then(|_| interpreter.call(test, vec![]));
+
+            TestResult {
+                name: format!("{}::{}", name, test),
+                duration: start.elapsed(),
+                failure: result.err().and_then(failure),
+            }
+        })
+        .collect()
+}
+
+pub fn report(results: &[TestResult], format: ReportFormat) -> String {
+    match format {
+        ReportFormat::Text => report_text(results),
+        ReportFormat::Junit => report_junit(results),
+        ReportFormat::Json => report_json(results),
+    }
+}
+
+fn report_text(results: &[TestResult]) -> String {
+    let mut output = String::new();
+    for result in results {
+        match &result.failure {
+            None => output.push_str(&format!("test {} ... ok\n", result.name)),
+            Some(failure) => {
+                output.push_str(&format!("test {} ... FAILED\n", result.name));
+                output.push_str(&format!("    {}\n", failure));
+            }
+        }
+    }
+
+    let failed = results.iter().filter(|result| !result.passed()).count();
+    output.push_str(&format!(
+        "\ntest result: {}. {} passed; {} failed\n",
+        if failed == 0 { "ok" } else { "FAILED" },
+        results.len() - failed,
+        failed
+    ));
+    output
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn report_junit(results: &[TestResult]) -> String {
+    let failed = results.iter().filter(|result| !result.passed()).count();
+    let total: Duration = results.iter().map(|result| result.duration).sum();
+
+    let mut output = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    output.push_str(&format!(
+        "<testsuites tests=\"{}\" failures=\"{}\" time=\"{:.6}\">\n",
+        results.len(),
+        failed,
+        total.as_secs_f64()
+    ));
+    output.push_str(&format!(
+        "  <testsuite name=\"olang\" tests=\"{}\" failures=\"{}\" time=\"{:.6}\">\n",
+        results.len(),
+        failed,
+        total.as_secs_f64()
+    ));
+    for result in results {
+        let name = escape_xml(&result.name);
+        let time = result.duration.as_secs_f64();
+        match &result.failure {
+            None => output.push_str(&format!(
+                "    <testcase name=\"{}\" classname=\"olang\" time=\"{:.6}\"/>\n",
+                name, time
+            )),
+            Some(failure) => {
+                let failure = escape_xml(failure);
+                output.push_str(&format!(
+                    "    <testcase name=\"{}\" classname=\"olang\" time=\"{:.6}\">\n",
+                    name, time
+                ));
+                output.push_str(&format!(
+                    "      <failure message=\"{}\">{}</failure>\n",
+                    failure, failure
+                ));
+                output.push_str("    </testcase>\n");
+            }
+        }
+    }
+    output.push_str("  </testsuite>\n</testsuites>\n");
+    output
+}
+
+fn report_json(results: &[TestResult]) -> String {
+    let failed = results.iter().filter(|result| !result.passed()).count();
+    let tests: Vec<_> = results
+        .iter()
+        .map(|result| {
+            json!({
+                "name": result.name,
+                "passed": result.passed(),
+                "duration": result.duration.as_secs_f64(),
+                "failure": result.failure,
+            })
+        })
+        .collect();
+
+    let report = json!({
+        "passed": results.len() - failed,
+        "failed": failed,
+        "tests": tests,
+    });
+    format!("{:#}\n", report)
+}
@@ -1,3 +1,6 @@
+use config::Config;
+use export::ExportFormat;
+use test_runner::ReportFormat;
 use value::Exception;
 
 use super::*;
@@ -93,6 +96,13 @@ fn exponents() {
     // assert_eq!(eval("(0-2)**2").unwrap(), Value::Int(4));
     // assert_eq!(eval("(-2)**3").unwrap(), Value::Int(-8));
     // assert_eq!(eval("(-2)**2").unwrap(), Value::Int(4));
+
+    assert_eq!(eval("2**62").unwrap(), Value::Int(1 << 62));
+    for source in ["2**63", "2**4294967296", "2**4294967298", "(0-1)**2"] {
+        assert_eq!(eval(source).unwrap_err().code(), "OL0012", "{}", source);
+    }
+    assert_eq!(eval("1**4294967296").unwrap(), Value::Int(1));
+    assert_eq!(eval("0**4294967296").unwrap(), Value::Int(0));
 }
 
 #[test]
@@ -111,3 +121,3508 @@ fn pemdas_braces() {
     assert_eq!(eval("{{5-2}+{3*4}}").unwrap(), Value::Int(15));
     assert_eq!(eval("{{2+3}*{4-{1+1}}}").unwrap(), Value::Int(10));
 }
+
+#[test]
+fn error_codes() {
+    assert_eq!(eval("2**(0-2)").unwrap_err().code(), "OL0012");
+    assert_eq!(eval("continue").unwrap_err().code(), "OL0005");
+    assert_eq!(eval("var = 1").unwrap_err().code(), "OL0003");
+    assert_eq!(eval("1 $ 2").unwrap_err().code(), "OL0001");
+    assert!(explain::explain("ol0012").is_some());
+    assert!(explain::explain("OL9999").is_none());
+}
+
+#[test]
+fn test_reports() {
+    let mut results = test_runner::run_file(
+        std::path::Path::new("src/tests/fib.olang"),
+        &Config::default(),
+    );
+    results.push(test_runner::TestResult {
+        name: "<broken>".to_string(),
+        duration: std::time::Duration::from_millis(5),
+        failure: Some("error[OL0011]: \"wrong\" type".to_string()),
+    });
+    assert!(results[0].passed());
+
+    let junit = test_runner::report(&results, ReportFormat::Junit);
+    assert!(junit.contains("<testsuites tests=\"2\" failures=\"1\""));
+    assert!(junit.contains("name=\"&lt;broken&gt;\""));
+    assert!(junit.contains("error[OL0011]: &quot;wrong&quot; type</failure>"));
+
+    let json: serde_json::Value =
+        serde_json::from_str(&test_runner::report(&results, ReportFormat::Json)).unwrap();
+    assert_eq!(json["failed"], 1);
+    assert_eq!(json["tests"][1]["duration"], 0.005);
+}
+
+#[test]
+fn expression_only() {
+    let mut interpreter = interpreter::Interpreter::new();
+    assert_eq!(
+        interpreter.eval_expression_str("len([1 2 3]) * 2").unwrap(),
+        Value::Int(6)
+    );
+    assert_eq!(
+        interpreter
+            .eval_expression_str("printLn(1)")
+            .unwrap_err()
+            .unwrap_exception(),
+        &Exception::SideEffectForbidden
+    );
+    assert_eq!(
+        interpreter.eval_expression_str("1 2").unwrap_err().code(),
+        "OL0004"
+    );
+    // time and random numbers are the same for every evaluation
+    let source = "[math.random() math.randomInt(1 1000000) time.timeMillis() time.clock()]";
+    let first = interpreter.eval_expression_str(source).unwrap();
+    interpreter.eval_str("math.random()").unwrap();
+    assert_eq!(interpreter.eval_expression_str(source).unwrap(), first);
+    assert!(!interpreter.is_deterministic());
+    assert_eq!(eval_pure(source, 100).unwrap(), first);
+
+    interpreter.allow_expression_side_effects(true);
+    assert_eq!(
+        interpreter.eval_expression_str("toString(1)").unwrap(),
+        Value::String("1".to_string())
+    );
+    // side effects stay allowed for regular evaluation
+    assert_eq!(interpreter.eval_str("time.sleep(0)").unwrap(), Value::Null);
+}
+
+#[test]
+fn export() {
+    let source = "var name = \"server\" var ports = [80 443] var double = fun(x) { x * 2 }";
+    assert_eq!(
+        export::export(source, None, ExportFormat::Json, &Config::default()).unwrap(),
+        "{\n  \"name\": \"server\",\n  \"ports\": [\n    80,\n    443\n  ]\n}"
+    );
+    assert_eq!(
+        export::export(source, None, ExportFormat::Toml, &Config::default()).unwrap(),
+        "name = \"server\"\nports = [\n    80,\n    443,\n]\n"
+    );
+    assert_eq!(
+        export::export(
+            source,
+            Some("ports"),
+            ExportFormat::Json,
+            &Config::default()
+        )
+        .unwrap(),
+        "[\n  80,\n  443\n]"
+    );
+    assert!(export::export("printLn(1)", None, ExportFormat::Json, &Config::default()).is_err());
+}
+
+#[test]
+fn interrupt() {
+    let mut interpreter = Interpreter::new();
+    let flag = interpreter.interrupt_flag();
+
+    let handle = std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        flag.store(true, std::sync::atomic::Ordering::Relaxed);
+    });
+    assert_eq!(
+        interpreter
+            .eval_str("loop { }")
+            .unwrap_err()
+            .unwrap_exception(),
+        &Exception::Interrupted
+    );
+    handle.join().unwrap();
+
+    // the flag is reset once the exception has been raised
+    assert_eq!(interpreter.eval_str("1").unwrap(), Value::Int(1));
+}
+
+#[test]
+fn token_stream() {
+    use lexer::{Lexer, TokenValue};
+
+    let tokens: Vec<_> = Lexer::new("var x = 1 # comment\nx")
+        .map(|token| token.unwrap().value)
+        .collect();
+    assert_eq!(
+        tokens,
+        vec![
+            TokenValue::KeywordVar,
+            TokenValue::Identifier("x".to_string()),
+            TokenValue::EqualSign,
+            TokenValue::Int(1),
+            TokenValue::Identifier("x".to_string()),
+            TokenValue::EndOfFile,
+        ]
+    );
+
+    // only whole words are keywords
+    let tokens: Vec<_> = Lexer::new("continue continues iff café averyveryverylongname")
+        .map(|token| token.unwrap().value)
+        .collect();
+    assert_eq!(
+        tokens,
+        vec![
+            TokenValue::KeywordContinue,
+            TokenValue::Identifier("continues".to_string()),
+            TokenValue::Identifier("iff".to_string()),
+            TokenValue::Identifier("café".to_string()),
+            TokenValue::Identifier("averyveryverylongname".to_string()),
+            TokenValue::EndOfFile,
+        ]
+    );
+
+    let last = Lexer::new("x\ny").nth(1).unwrap().unwrap();
+    assert_eq!(last.region.start.to_string(), "2:1");
+
+    // the lexer stops at the first error
+    let mut lexer = Lexer::new("1 $ 2");
+    assert!(lexer.next().unwrap().is_ok());
+    assert!(lexer.next().unwrap().is_err());
+    assert!(lexer.next().is_none());
+
+    assert_eq!(eval("").unwrap(), Value::Null);
+    assert_eq!(eval("1 + 2 $").unwrap_err().code(), "OL0001");
+}
+
+#[test]
+fn ast_traversal() {
+    use parser::{Expression, ExpressionValue, Parser};
+    use visitor::{Fold, Visitor};
+
+    struct CountIdentifiers(usize);
+    impl Visitor for CountIdentifiers {
+        fn visit_expression(&mut self, expression: &Expression) {
+            if let ExpressionValue::Identifier(..) = expression.value {
+                self.0 += 1;
+            }
+            visitor::visit_expression(self, expression)
+        }
+    }
+
+    // replaces every int with its double
+    struct DoubleInts;
+    impl Fold for DoubleInts {
+        fn fold_expression(&mut self, expression: Expression) -> Expression {
+            match expression.value {
+                ExpressionValue::Int(v) => Expression {
+                    region: expression.region,
+                    value: ExpressionValue::Int(v * 2),
+                },
+                _ => visitor::fold_expression(self, expression),
+            }
+        }
+    }
+
+    let program = Parser::new(include_str!("fib.olang"))
+        .unwrap()
+        .parse()
+        .unwrap();
+    let mut counter = CountIdentifiers(0);
+    for expression in &program.ast {
+        counter.visit_expression(expression);
+    }
+    assert_eq!(counter.0, 4);
+
+    let expression = Parser::new("if true { [1 2] } else { 3 + 4 }")
+        .unwrap()
+        .parse_single_expression()
+        .unwrap();
+    let folded = format!("{:?}", DoubleInts.fold_expression(expression));
+    for int in ["Int(2)", "Int(4)", "Int(6)", "Int(8)"] {
+        assert!(folded.contains(int));
+    }
+    assert!(!folded.contains("Int(1)") && !folded.contains("Int(3)"));
+}
+
+#[test]
+fn ast_builders() {
+    use builder::Expr;
+    use parser::{AssignmentOperator, BinaryOperationOperator, Program};
+
+    // var total = 0 for var i = 1 i <= 10 i++ { total += i } toString(total)
+    let program = Program::new(vec![
+        Expr::declare("total", Expr::int(0)),
+        Expr::for_loop(
+            Expr::declare("i", Expr::int(1)),
+            Expr::binary(
+                Expr::identifier("i"),
+                BinaryOperationOperator::IsLessThanOrEqual,
+                Expr::int(10),
+            ),
+            Expr::update("i", parser::UpdateOperator::Increment),
+            vec![Expr::assign(
+                "total",
+                AssignmentOperator::Plus,
+                Expr::identifier("i"),
+            )],
+        ),
+        Expr::call("toString", vec![Expr::identifier("total")]),
+    ]);
+    assert_eq!(
+        Interpreter::new().eval_program(&program).unwrap(),
+        Value::String("55".to_string())
+    );
+}
+
+#[test]
+fn incomplete_input() {
+    assert!(eval("var f = fun(n) {").unwrap_err().is_unexpected_end());
+    assert!(eval("1 +").unwrap_err().is_unexpected_end());
+    assert!(!eval("var = 1").unwrap_err().is_unexpected_end());
+    assert!(!eval("1 + \"a\"").unwrap_err().is_unexpected_end());
+}
+
+#[test]
+fn unused_results() {
+    let check =
+        |source: &str| warnings::check(&parser::Parser::new(source).unwrap().parse().unwrap());
+
+    assert_eq!(check("var x = 1 x == 5 x").len(), 1);
+    assert_eq!(check("var x = 1 x == 5").len(), 0);
+    assert_eq!(check("var x = 1 discard x == 5 x").len(), 0);
+    assert_eq!(check("fun(x) { x < 1 x > 1 x }").len(), 2);
+    assert_eq!(eval("discard 1 + 1").unwrap(), Value::Null);
+}
+
+#[test]
+fn lint_warnings() {
+    let program = |source: &str| parser::Parser::new(source).unwrap().parse().unwrap();
+    let messages = |warnings: Vec<warnings::Warning>| -> Vec<String> {
+        warnings
+            .into_iter()
+            .map(|warning| format!("{} {}", warning.region.start, warning.message))
+            .collect()
+    };
+
+    let source = "var x = 0
+var f = fun(xs) {
+    var unused = 1
+    var x = 0
+    for item in xs {
+        if x = item {
+            break
+            x++
+        }
+    }
+    x
+}";
+    let warnings = messages(warnings::check(&program(source)));
+    assert_eq!(warnings.len(), 2);
+    assert!(warnings[0].starts_with("6:12 this assignment is used as a condition"));
+    assert!(warnings[1].starts_with("8:13 this code is unreachable"));
+
+    let warnings = messages(lint::lint(&program(source)));
+    assert_eq!(warnings.len(), 4);
+    assert!(warnings[0].starts_with("3:5 the variable `unused` is never used"));
+    assert!(warnings[1].starts_with("4:5 the variable `x` shadows the variable declared at 1:1"));
+
+    // functions can use variables declared after them, top level variables are not checked
+    let source = "var unused = fun() { var f = fun() { g() } var g = fun() { 1 } f() }";
+    assert!(lint::lint(&program(source)).is_empty());
+}
+
+#[test]
+fn floats() {
+    assert_eq!(eval("2.75").unwrap(), Value::Float(2.75));
+    assert_eq!(eval("1e-5").unwrap(), Value::Float(1e-5));
+    assert_eq!(eval("2.5E+2").unwrap(), Value::Float(250.0));
+    assert_eq!(eval("1 + 0.5").unwrap(), Value::Float(1.5));
+    assert_eq!(eval("7 / 2").unwrap(), Value::Int(3));
+    assert_eq!(eval("7 / 2.0").unwrap(), Value::Float(3.5));
+    assert_eq!(eval("2 ** 0.5 > 1.41").unwrap(), Value::Bool(true));
+    assert_eq!(eval("1 == 1.0").unwrap(), Value::Bool(true));
+    assert_eq!(eval("0.1 + 0.2 != 0.3").unwrap(), Value::Bool(true));
+    assert_eq!(eval("5.5 % 2").unwrap(), Value::Float(1.5));
+    assert_eq!(
+        eval("toString(3.0)").unwrap(),
+        Value::String("3.0".to_string())
+    );
+    // an identifier starting with e is not an exponent
+    assert_eq!(
+        eval("var e = 2 [1 e]").unwrap(),
+        Value::list(vec![Value::Int(1), Value::Int(2)])
+    );
+}
+
+#[test]
+fn precedence() {
+    // expected values are taken from python 3, which uses the same precedence
+    let matrix = [
+        ("2**3**2", 512),
+        ("(2**3)**2", 64),
+        ("2**2**2**2", 65536),
+        ("2*3**2", 18),
+        ("3**2*2", 18),
+        ("2**3*2**2", 32),
+        ("100/10/5", 2),
+        ("100/(10/5)", 50),
+        ("10-4-3", 3),
+        ("10-(4-3)", 9),
+        ("2+3*4**2", 50),
+        ("17%5*2", 4),
+        ("17%(5*2)", 7),
+        ("2**3%3", 2),
+        ("1+2*3-4/2", 5),
+    ];
+    for (source, expected) in matrix {
+        assert_eq!(eval(source).unwrap(), Value::Int(expected), "{}", source);
+    }
+
+    assert_eq!(eval("1 + 2 < 4 && 2 * 2 == 4").unwrap(), Value::Bool(true));
+    assert_eq!(eval("1 < 2 == true").unwrap(), Value::Bool(true));
+}
+
+#[test]
+fn unterminated() {
+    let err = eval("var x = 1\nprintLn(\"abc)").unwrap_err();
+    assert_eq!(err.code(), "OL0017");
+    assert!(err.to_string().starts_with("2:9 "));
+
+    let err = eval("1 #[ ]\n 2").unwrap_err();
+    assert_eq!(err.code(), "OL0018");
+    assert!(err.to_string().starts_with("1:3 "));
+
+    assert_eq!(eval("1 #").unwrap(), Value::Int(1));
+    assert_eq!(eval("1 #[]#").unwrap(), Value::Int(1));
+}
+
+#[test]
+fn modulo() {
+    let matrix = [
+        ("7 % 3", 1),
+        ("-7 % 3", -1),
+        ("7 % -3", 1),
+        ("-7 % -3", -1),
+        ("math.mod(7 3)", 1),
+        ("math.mod(-7 3)", 2),
+        ("math.mod(7 (-3))", 1),
+        ("math.mod(-7 (-3))", 2),
+        ("-7 / 2", -3),
+    ];
+    for (source, expected) in matrix {
+        assert_eq!(eval(source).unwrap(), Value::Int(expected), "{}", source);
+    }
+    assert_eq!(eval("-7.5 % 2").unwrap(), Value::Float(-1.5));
+    assert_eq!(eval("math.mod(-7.5 2)").unwrap(), Value::Float(0.5));
+
+    for source in ["1 / 0", "1 % 0", "math.mod(1 0)"] {
+        assert_eq!(eval(source).unwrap_err().code(), "OL0027", "{}", source);
+    }
+    assert_eq!(eval("1.0 / 0").unwrap(), Value::Float(f64::INFINITY));
+}
+
+#[test]
+fn numeric_helpers() {
+    let matrix = [
+        ("math.clamp(5 0 3)", Value::Int(3)),
+        ("math.clamp(-5 0 3)", Value::Int(0)),
+        ("math.clamp(2 0 3)", Value::Int(2)),
+        ("math.clamp(0.5 0 1)", Value::Float(0.5)),
+        ("math.lerp(0 10 0.25)", Value::Float(2.5)),
+        ("math.lerp(10 20 1.5)", Value::Float(25.0)),
+        ("math.sign(-7)", Value::Int(-1)),
+        ("math.sign(0)", Value::Int(0)),
+        ("math.sign(2.5)", Value::Float(1.0)),
+        ("math.absDiff(3 10)", Value::Int(7)),
+        ("math.absDiff(10 3)", Value::Int(7)),
+        ("math.absDiff(1 2.5)", Value::Float(1.5)),
+    ];
+    for (source, expected) in matrix {
+        assert_eq!(eval(source).unwrap(), expected, "{}", source);
+    }
+
+    assert_eq!(eval("math.clamp(1 3 0)").unwrap_err().code(), "OL0014");
+    assert_eq!(eval("math.sign(\"a\")").unwrap_err().code(), "OL0011");
+    assert_eq!(
+        eval("math.absDiff(9223372036854775807 (0-9223372036854775807))")
+            .unwrap_err()
+            .code(),
+        "OL0035"
+    );
+}
+
+#[test]
+fn large_int_literals() {
+    assert_eq!(eval("9223372036854775807").unwrap(), Value::Int(i64::MAX));
+    assert_eq!(
+        eval("-9223372036854775807 - 1").unwrap(),
+        Value::Int(i64::MIN)
+    );
+
+    let err = eval("var x = 1\nx + 9223372036854775808").unwrap_err();
+    assert_eq!(err.code(), "OL0026");
+    let region = err.region().unwrap();
+    assert_eq!((region.start.row, region.start.col), (2, 5));
+    assert_eq!(region.end.col - region.start.col, 19);
+
+    // floats don't overflow
+    assert_eq!(
+        eval("99999999999999999999.0").unwrap(),
+        Value::Float(99999999999999999999.0)
+    );
+}
+
+#[test]
+fn maps() {
+    assert_eq!(
+        eval("var m = {\"a\": 1 \"b\": 2} m !! \"b\"").unwrap(),
+        Value::Int(2)
+    );
+    assert_eq!(
+        eval("var m = {:} m !! \"a\" = 1 m !! \"a\" += 2 m").unwrap(),
+        Value::Map([("a".to_string(), Value::Int(3))].into())
+    );
+    assert_eq!(
+        eval("var m = {\"l\": [1 2]} var k = \"l\" m !! k !! 1 = 5 m !! k").unwrap(),
+        Value::list(vec![Value::Int(1), Value::Int(5)])
+    );
+    assert_eq!(
+        eval("var l = [1 2 3] l !! 0 *= 10 l").unwrap(),
+        Value::list(vec![Value::Int(10), Value::Int(2), Value::Int(3)])
+    );
+    assert_eq!(eval("len({\"a\": 1 \"b\": 2})").unwrap(), Value::Int(2));
+    assert_eq!(
+        eval("toString({\"b\": 1 \"a\": \"x\"})").unwrap(),
+        Value::String("{a: x b: 1}".to_string())
+    );
+    // braces without a colon are still a block
+    assert_eq!(eval("{ var x = 1 x + 1 }").unwrap(), Value::Int(2));
+    assert_eq!(eval("{}").unwrap(), Value::Null);
+
+    assert_eq!(eval("{\"a\": 1} !! \"b\"").unwrap_err().code(), "OL0019");
+    assert_eq!(eval("var l = [1] l !! 1 = 2").unwrap_err().code(), "OL0013");
+}
+
+#[test]
+fn compound_assignment() {
+    // compound assignment goes through the same operations as the binary operators
+    let cases = [
+        ("var s = \"ab\" s += \"c\" s", "\"ab\" + \"c\""),
+        ("var s = \"ab\" s *= 3 s", "\"ab\" * 3"),
+        ("var l = [1] l += [2 3] l", "[1] + [2 3]"),
+        ("var l = [1] l += 2 l", "[1] + 2"),
+        ("var l = [[1]] l += [[2]] l", "[[1]] + [[2]]"),
+    ];
+    for (assignment, binary) in cases {
+        assert_eq!(
+            eval(assignment).unwrap(),
+            eval(binary).unwrap(),
+            "{}",
+            assignment
+        );
+    }
+
+    assert_eq!(
+        eval("var s = \"ab\" s *= 3 s").unwrap(),
+        Value::String("ababab".to_string())
+    );
+    assert_eq!(eval("\"ab\" * 0").unwrap(), Value::String(String::new()));
+    assert_eq!(
+        eval("var l = [1] l += [2 3] l").unwrap(),
+        Value::list(vec![Value::Int(1), Value::Int(2), Value::Int(3)])
+    );
+    assert_eq!(eval("\"ab\" * -1").unwrap_err().code(), "OL0011");
+    for count in ["9223372036854775807", "4611686018427387904"] {
+        let source = format!("var n = {} \"ab\" * n", count);
+        assert_eq!(eval(&source).unwrap_err().code(), "OL0034");
+    }
+    assert_eq!(
+        eval("var n = 9223372036854775807 \"\" * n").unwrap(),
+        Value::String(String::new())
+    );
+    assert_eq!(
+        eval("var s = \"a\" s -= \"a\"").unwrap_err().code(),
+        "OL0011"
+    );
+    assert_eq!(eval("var s = \"a\" s += 1").unwrap_err().code(), "OL0011");
+}
+
+#[test]
+fn list_builtins() {
+    assert_eq!(
+        eval("toString(list.zip([1 2 3] [\"a\" \"b\"]))").unwrap(),
+        Value::String("[[1 a] [2 b]]".to_string())
+    );
+    assert_eq!(
+        eval("toString(list.enumerate([\"a\" \"b\"]))").unwrap(),
+        Value::String("[[0 a] [1 b]]".to_string())
+    );
+    assert_eq!(
+        eval("toString(list.flatten([[1 2] 3 [] [[4]]]))").unwrap(),
+        Value::String("[1 2 3 [4]]".to_string())
+    );
+    // ints and floats are different values even when they compare equal
+    assert_eq!(
+        eval("toString(list.unique([3 1 3 2 1 1.0]))").unwrap(),
+        Value::String("[3 1 2 1.0]".to_string())
+    );
+    assert_eq!(eval("list.zip([1] 2)").unwrap_err().code(), "OL0011");
+    assert_eq!(eval("list.unique()").unwrap_err().code(), "OL0007");
+}
+
+#[test]
+fn aggregate_builtins() {
+    assert_eq!(eval("math.sum([1 2 3])").unwrap(), Value::Int(6));
+    assert_eq!(eval("math.sum([1 2.5])").unwrap(), Value::Float(3.5));
+    assert_eq!(eval("math.sum([])").unwrap(), Value::Int(0));
+    assert_eq!(eval("math.product([2 3 4])").unwrap(), Value::Int(24));
+    assert_eq!(eval("math.product([])").unwrap(), Value::Int(1));
+    assert_eq!(eval("math.minOf([3 1.5 2])").unwrap(), Value::Float(1.5));
+    assert_eq!(eval("math.maxOf([3 1.5 2])").unwrap(), Value::Int(3));
+    assert_eq!(eval("math.average([1 2])").unwrap(), Value::Float(1.5));
+
+    for source in ["math.minOf([])", "math.maxOf([])", "math.average([])"] {
+        assert_eq!(eval(source).unwrap_err().code(), "OL0020", "{}", source);
+    }
+    assert_eq!(eval("math.sum([1 \"2\"])").unwrap_err().code(), "OL0011");
+    for source in [
+        "math.sum([9223372036854775807 1])",
+        "math.product([4294967296 4294967296])",
+    ] {
+        assert_eq!(eval(source).unwrap_err().code(), "OL0035", "{}", source);
+    }
+    assert_eq!(
+        eval("math.average([9223372036854775807 9223372036854775807])").unwrap(),
+        Value::Float(i64::MAX as f64)
+    );
+    assert_eq!(eval("math.maxOf([1 [2]])").unwrap_err().code(), "OL0011");
+}
+
+#[test]
+fn throw() {
+    let err = eval("var check = fun(x) { if x > 1 { throw \"too big\" } x } check(5)").unwrap_err();
+    assert_eq!(err.code(), "OL0021");
+    assert_eq!(
+        err.thrown_value(),
+        Some(&Value::String("too big".to_string()))
+    );
+    assert_eq!(err.to_string(), "1:33 -> 1:48 Unhandled exception: too big");
+
+    let err = eval("throw [1 2] printLn(\"unreachable\")").unwrap_err();
+    assert_eq!(
+        err.thrown_value(),
+        Some(&Value::list(vec![Value::Int(1), Value::Int(2)]))
+    );
+    assert_eq!(eval("1 + \"a\"").unwrap_err().thrown_value(), None);
+}
+
+#[test]
+fn map_builtins() {
+    assert_eq!(
+        eval("toString(dict.merge({\"a\": 1 \"b\": 2} {\"b\": 3 \"c\": 4}))").unwrap(),
+        Value::String("{a: 1 b: 3 c: 4}".to_string())
+    );
+    assert_eq!(
+        eval("dict.getOr({\"a\": 1} \"a\" 0)").unwrap(),
+        Value::Int(1)
+    );
+    assert_eq!(
+        eval("dict.getOr({\"a\": 1} \"b\" 0)").unwrap(),
+        Value::Int(0)
+    );
+    assert_eq!(
+        eval("toString(dict.entries({\"b\": 2 \"a\": 1}))").unwrap(),
+        Value::String("[[a 1] [b 2]]".to_string())
+    );
+    assert_eq!(
+        eval("var m = {\"x\": [1] \"y\": null} dict.fromEntries(dict.entries(m)) == m").unwrap(),
+        Value::Bool(true)
+    );
+    assert_eq!(
+        eval("dict.fromEntries([[\"a\" 1 2]])").unwrap_err().code(),
+        "OL0011"
+    );
+    assert_eq!(
+        eval("dict.fromEntries([[1 2]])").unwrap_err().code(),
+        "OL0011"
+    );
+    assert_eq!(eval("dict.merge({:} [])").unwrap_err().code(), "OL0011");
+}
+
+#[test]
+fn closures() {
+    use std::{cell::Cell, rc::Rc};
+
+    assert_eq!(
+        eval(include_str!("closures.olang")).unwrap(),
+        Value::list(vec![Value::Int(3), Value::Int(1), Value::Int(6)])
+    );
+    // a function does not see the locals of its caller
+    assert_eq!(
+        eval("var f = fun() { secret } var g = fun() { var secret = 1 f() } g()")
+            .unwrap_err()
+            .code(),
+        "OL0009"
+    );
+
+    // scopes are left even when an exception is raised inside of them
+    let mut interpreter = Interpreter::new();
+    assert!(interpreter.eval_str("{ var inner = 1 throw 1 }").is_err());
+    assert!(interpreter.eval_str("var f = fun(a) { a } f()").is_err());
+    interpreter.eval_str("var outer = 2").unwrap();
+    assert!(interpreter.variables().contains_key("outer"));
+    assert!(!interpreter.variables().contains_key("inner"));
+
+    // the scope of a call is freed after it returned, even when a function
+    // defined in it refers to it. a returned function keeps it alive until it
+    // is gone as well
+    let freed = Rc::new(Cell::new(0));
+    let counter = freed.clone();
+    let mut interpreter = Interpreter::new();
+    interpreter.register_builtin("resource", move |_| {
+        let counter = counter.clone();
+        Ok(Value::native_with_finalizer((), move |_| {
+            counter.set(counter.get() + 1)
+        }))
+    });
+    interpreter
+        .eval_str(
+            "var call = fun() { var r = resource() var inner = fun() { r } inner() 1 }
+var make = fun() { var r = resource() var inner = fun() { r } inner }
+for var i = 0 i < 1000 i++ { call() }
+var kept = make()",
+        )
+        .unwrap();
+    assert_eq!(freed.get(), 1000);
+    assert!(matches!(
+        interpreter.eval_str("kept()").unwrap(),
+        Value::Native(_)
+    ));
+    interpreter.eval_str("kept = null").unwrap();
+    assert_eq!(freed.get(), 1001);
+    // a function referring to itself by its name
+    interpreter
+        .eval_str("var repeat = fun() { var r = resource() var f = fun(n) { if n > 0 { f(n - 1) } else { r } } f(3) } repeat() repeat()")
+        .unwrap();
+    assert_eq!(freed.get(), 1003);
+}
+
+#[test]
+fn padding_builtins() {
+    let cases = [
+        ("str.padLeft(\"7\" 3 \"0\")", "007"),
+        ("str.padLeft(\"abc\" 2)", "abc"),
+        ("str.padRight(\"ab\" 4)", "ab  "),
+        ("str.padRight(\"ab\" 4 \".\")", "ab.."),
+        ("str.center(\"ab\" 5 \"*\")", "*ab**"),
+        ("str.center(\"äb\" 4)", " äb "),
+        ("str.repeat(\"ab\" 3)", "ababab"),
+        ("str.padLeft(\"x\" (-1))", "x"),
+    ];
+    for (source, expected) in cases {
+        assert_eq!(
+            eval(source).unwrap(),
+            Value::String(expected.to_string()),
+            "{}",
+            source
+        );
+    }
+
+    assert_eq!(
+        eval("str.padLeft(\"x\" 3 \"ab\")").unwrap_err().code(),
+        "OL0011"
+    );
+    assert_eq!(eval("str.padLeft(\"x\")").unwrap_err().code(), "OL0007");
+    assert_eq!(eval("str.repeat(3 3)").unwrap_err().code(), "OL0011");
+    for source in [
+        "str.repeat(\"ab\" 9223372036854775807)",
+        "str.padLeft(\"a\" 9223372036854775807)",
+        "str.padRight(\"a\" 9223372036854775807 \"é\")",
+        "str.center(\"a\" 9223372036854775807)",
+    ] {
+        assert_eq!(eval(source).unwrap_err().code(), "OL0034", "{}", source);
+    }
+}
+
+#[test]
+fn unary_operators() {
+    assert_eq!(eval("-5").unwrap(), Value::Int(-5));
+    assert_eq!(eval("var x = 3 (-x)").unwrap(), Value::Int(-3));
+    // after an operand a minus is always the binary operator
+    assert_eq!(eval("var x = 3 [x -1]").unwrap(), eval("[2]").unwrap());
+    assert_eq!(eval("-(-2.5)").unwrap(), Value::Float(2.5));
+    assert_eq!(eval("2 * -3").unwrap(), Value::Int(-6));
+    assert_eq!(eval("4 - -3").unwrap(), Value::Int(7));
+    // unary operators bind weaker than exponentiation like in python
+    assert_eq!(eval("-2**2").unwrap(), Value::Int(-4));
+    assert_eq!(eval("2.0**-1").unwrap(), Value::Float(0.5));
+    assert_eq!(eval("-[4 5] !! 1").unwrap(), Value::Int(-5));
+
+    assert_eq!(eval("!true").unwrap(), Value::Bool(false));
+    // !! is the index operator, double negation needs a space or parentheses
+    assert_eq!(eval("!!true").unwrap_err().code(), "OL0004");
+    assert_eq!(eval("! !true").unwrap(), Value::Bool(true));
+    assert_eq!(
+        eval("var flag = false !(!flag)").unwrap(),
+        Value::Bool(false)
+    );
+    assert_eq!(eval("!(1 > 2) && true").unwrap(), Value::Bool(true));
+
+    assert_eq!(eval("-\"a\"").unwrap_err().code(), "OL0011");
+    assert_eq!(eval("!1").unwrap_err().code(), "OL0011");
+    let smallest = "var smallest = 0 - 9223372036854775807 - 1\n";
+    assert_eq!(
+        eval(&format!("{}(-smallest)", smallest))
+            .unwrap_err()
+            .code(),
+        "OL0035"
+    );
+    assert_eq!(
+        eval(&format!("{}(-(smallest + 1))", smallest)).unwrap(),
+        Value::Int(i64::MAX)
+    );
+    assert_eq!(
+        eval("-(0-9223372036854775807-1)").unwrap_err().code(),
+        "OL0035"
+    );
+
+    // the binary operators raise instead of wrapping around as well
+    for source in [
+        "9223372036854775807 + 1",
+        "-9223372036854775807 - 2",
+        "4294967296 * 4294967296",
+        "(-9223372036854775807 - 1) / -1",
+        "(-9223372036854775807 - 1) % -1",
+        "math.mod(-9223372036854775807 - 1 (-1))",
+        "var x = 9223372036854775807 x += 1",
+    ] {
+        assert_eq!(eval(source).unwrap_err().code(), "OL0035", "{}", source);
+    }
+    assert_eq!(
+        eval("9223372036854775807.0 + 1").unwrap(),
+        Value::Float(i64::MAX as f64 + 1.0)
+    );
+}
+
+#[test]
+fn table_print() {
+    let rows = eval("[[1 \"apple\" 2.5] [10 \"kiwi\"]]").unwrap();
+    let headers = eval("[\"id\" \"name\" \"price\"]").unwrap();
+    assert_eq!(
+        builtin::format_table(
+            &rows.into_list().unwrap(),
+            Some(&headers.into_list().unwrap())
+        )
+        .unwrap(),
+        "\
++----+-------+-------+
+| id | name  | price |
++----+-------+-------+
+|  1 | apple |   2.5 |
+| 10 | kiwi  |       |
++----+-------+-------+
+"
+    );
+
+    // the columns of map rows are their keys, missing keys are left empty
+    let rows =
+        eval("[{\"name\": \"bob\" \"age\": 31} {\"name\": \"alice\" \"city\": \"Oslo\"}]").unwrap();
+    assert_eq!(
+        builtin::format_table(&rows.into_list().unwrap(), None).unwrap(),
+        "\
++-----+-------+------+
+| age | name  | city |
++-----+-------+------+
+|  31 | bob   |      |
+|     | alice | Oslo |
++-----+-------+------+
+"
+    );
+    assert_eq!(builtin::format_table(&[], None).unwrap(), "");
+
+    assert_eq!(eval("io.tablePrint([1 2])").unwrap_err().code(), "OL0011");
+    assert_eq!(eval("io.tablePrint()").unwrap_err().code(), "OL0007");
+}
+
+#[test]
+fn for_in() {
+    assert_eq!(
+        eval("var total = 0 for x in [1 2 3 4] { if x == 3 { continue } total += x } total")
+            .unwrap(),
+        Value::Int(7)
+    );
+    assert_eq!(
+        eval("var s = \"\" for c in \"abc\" { if c == \"c\" { break } s = c + s } s").unwrap(),
+        Value::String("ba".to_string())
+    );
+    // every iteration binds its own item
+    assert_eq!(
+        eval("var fs = [] for x in [1 2] { fs += fun() { x } } var f = fs !! 0 f()").unwrap(),
+        Value::Int(1)
+    );
+    // the item is not visible after the loop
+    assert_eq!(eval("for x in [1] { } x").unwrap_err().code(), "OL0009");
+    assert_eq!(eval("for x in 5 { }").unwrap_err().code(), "OL0011");
+    // the classic for loop still works
+    assert_eq!(
+        eval("var n = 0 for var i = 0 i < 3 i++ { n += i } n").unwrap(),
+        Value::Int(3)
+    );
+}
+
+#[test]
+fn benchmark() {
+    let result =
+        eval("var n = 0 var r = time.benchmark(fun() { n += 1 } 5) [r !! \"runs\" n]").unwrap();
+    // one calibration run and the measured runs
+    assert_eq!(result, Value::list(vec![Value::Int(5), Value::Int(6)]));
+
+    let result = eval("time.benchmark(fun() { time.sleep(40) })").unwrap();
+    let result = result.into_map().unwrap();
+    let runs = *result["runs"].into_int().unwrap();
+    assert!((1..=5).contains(&runs), "{}", runs);
+    let [min, mean, max] = ["min", "mean", "max"].map(|key| result[key].into_float().unwrap());
+    assert!(40.0 <= min && min <= mean && mean <= max);
+
+    assert_eq!(eval("time.benchmark(1)").unwrap_err().code(), "OL0011");
+    assert_eq!(
+        eval("time.benchmark(fun(x) { x })").unwrap_err().code(),
+        "OL0007"
+    );
+    for runs in ["0", "(-1)", "(-4294967296)"] {
+        let source = format!("time.benchmark(fun() {{ 1 }} {})", runs);
+        assert_eq!(eval(&source).unwrap_err().code(), "OL0014", "{}", source);
+    }
+}
+
+#[test]
+fn register_builtin() {
+    let calls = std::rc::Rc::new(std::cell::Cell::new(0));
+    let mut interpreter = Interpreter::new();
+    let counter = calls.clone();
+    interpreter
+        .register_builtin("double", |arguments| {
+            Ok(Value::Int(arguments[0].into_int()? * 2))
+        })
+        .register_builtin_with_side_effects("count", move |_| {
+            counter.set(counter.get() + 1);
+            Ok(Value::Int(counter.get()))
+        });
+
+    assert_eq!(interpreter.eval_str("double(21)").unwrap(), Value::Int(42));
+    assert_eq!(
+        interpreter.eval_str("count() count()").unwrap(),
+        Value::Int(2)
+    );
+    assert_eq!(calls.get(), 2);
+
+    // builtins with side effects are still forbidden in expression only evaluation
+    assert_eq!(
+        interpreter.eval_expression_str("double(2)").unwrap(),
+        Value::Int(4)
+    );
+    assert_eq!(
+        interpreter
+            .eval_expression_str("count()")
+            .unwrap_err()
+            .code(),
+        "OL0015"
+    );
+}
+
+#[test]
+fn config_from_env() {
+    let vars = |vars: &[(&str, &str)]| {
+        Config::from_vars(
+            vars.iter()
+                .map(|(name, value)| (name.to_string(), value.to_string())),
+        )
+    };
+
+    let config = vars(&[
+        ("OLANG_STACK_LIMIT", "200"),
+        ("OLANG_FUEL", "1000"),
+        ("OLANG_NO_COLOR", "1"),
+        ("OLANG_STDLIB", "0"),
+        ("OLANG_DETERMINISTIC", "1"),
+        ("OLANG_FLAT_BUILTINS", "1"),
+        ("PATH", "/bin"),
+    ])
+    .unwrap();
+    assert_eq!(config.stack_limit, Some(200));
+    assert_eq!(config.fuel, Some(1000));
+    assert!(!config.color);
+    assert!(!config.stdlib);
+    assert!(config.deterministic);
+    assert!(config.flat_builtins);
+
+    let config = vars(&[("OLANG_NO_COLOR", "")]).unwrap();
+    assert!(config.color && config.stdlib);
+    assert_eq!(config.stack_limit, None);
+
+    let err = vars(&[("OLANG_FUEL", "lots")]).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "environment variable OLANG_FUEL has to be a number, found \"lots\""
+    );
+    assert!(vars(&[("OLANG_STDLIB", "no")]).is_err());
+
+    // without the standard library not even printLn is declared
+    let mut interpreter = Interpreter::with_config(&vars(&[("OLANG_STDLIB", "0")]).unwrap());
+    assert_eq!(
+        interpreter.eval_str("printLn(1)").unwrap_err().code(),
+        "OL0009"
+    );
+    assert_eq!(interpreter.eval_str("1 + 1").unwrap(), Value::Int(2));
+}
+
+#[test]
+fn test_functions() {
+    let results = test_runner::run_file(
+        std::path::Path::new("src/tests/test-functions.olang"),
+        &Config::default(),
+    );
+    let results: Vec<_> = results
+        .iter()
+        .map(|result| (result.name.as_str(), result.failure.as_deref()))
+        .collect();
+    assert_eq!(
+        results,
+        [
+            ("src/tests/test-functions.olang::testFirst", None),
+            (
+                "src/tests/test-functions.olang::testFails",
+                Some("error[OL0021]: 11:5 -> 11:29 Unhandled exception: expected failure")
+            ),
+            ("src/tests/test-functions.olang::testSecond", None),
+        ]
+    );
+
+    let results = test_runner::run_file(
+        std::path::Path::new("src/tests/missing.olang"),
+        &Config::default(),
+    );
+    assert_eq!(results.len(), 1);
+    assert!(!results[0].passed());
+}
+
+#[test]
+fn redirected_io() {
+    let io = io::MemoryIo::new("Ada\n  Grace  \n");
+    let mut interpreter = Interpreter::new();
+    interpreter.set_io(io.clone());
+
+    interpreter
+        .eval_str("printLn(\"Hello, \" readLn()) printLn(\"Hello, \" readLn() \"!\") readLn()")
+        .unwrap();
+    assert_eq!(io.output(), "Hello, Ada\nHello, Grace!\n");
+    // the input is exhausted
+    assert_eq!(
+        interpreter.eval_str("readLn()").unwrap(),
+        Value::String(String::new())
+    );
+
+    interpreter.eval_str("io.tablePrint([[1]])").unwrap();
+    assert!(io.output().ends_with("+---+\n| 1 |\n+---+\n"));
+}
+
+#[test]
+fn stdin_lines() {
+    let io = io::MemoryIo::new("error: disk full\n\ninfo: started\nerror: timeout\n");
+    let mut interpreter = Interpreter::new();
+    interpreter.set_io(io.clone());
+
+    // the empty line doesn't end the loop, only the end of the input does
+    interpreter
+        .eval_str(
+            "var lines = 0
+            for line in io.stdinLines() {
+                lines += 1
+                if line != \"info: started\" { printLn(line) }
+            }
+            printLn(lines)",
+        )
+        .unwrap();
+    assert_eq!(io.output(), "error: disk full\n\nerror: timeout\n4\n");
+
+    // lines are read as the loop goes, a break leaves the rest of the input
+    let io = io::MemoryIo::new("a\nb\nc\n");
+    interpreter.set_io(io.clone());
+    assert_eq!(
+        interpreter
+            .eval_str("for line in io.stdinLines() { if line == \"b\" { break } } readLn()")
+            .unwrap(),
+        Value::String("c".to_string())
+    );
+    assert_eq!(
+        eval_pure("io.stdinLines()", 100).unwrap_err().code(),
+        "OL0015"
+    );
+}
+
+#[test]
+fn prompts() {
+    let io = io::MemoryIo::new("yes\nmaybe\n\n2\nblue\npurple\nhunter2\n");
+    let mut interpreter = Interpreter::new();
+    interpreter.set_io(io.clone());
+
+    let answers = interpreter
+        .eval_str(
+            "var colors = [\"red\" \"green\" \"blue\"]
+[io.confirm(\"Proceed?\") io.confirm(\"Proceed?\") io.confirm(\"Again?\")
+ io.select(\"Pick one\" colors) io.select(\"Pick one\" colors)]",
+        )
+        .unwrap();
+    let expected = eval("[true false false \"green\" \"blue\"]").unwrap();
+    assert_eq!(answers, expected);
+    assert!(io
+        .output()
+        .starts_with("Proceed? [y/N] Proceed? [y/N] Again? [y/N] Pick one\n  1) red\n"));
+
+    // piped input can't be asked again
+    let err = interpreter
+        .eval_str("io.select(\"Pick one\" [\"red\"])")
+        .unwrap_err();
+    assert_eq!(err.code(), "OL0014");
+    assert_eq!(
+        interpreter.eval_str("io.password(\"Password: \")").unwrap(),
+        Value::String("hunter2".to_string())
+    );
+
+    assert_eq!(
+        eval_pure("io.confirm(\"Proceed?\")", 100)
+            .unwrap_err()
+            .code(),
+        "OL0015"
+    );
+}
+
+#[test]
+fn optimizer() {
+    use parser::{ExpressionValue, Parser};
+
+    let optimize = |source: &str| {
+        optimizer::optimize(Parser::new(source).unwrap().parse().unwrap())
+            .ast
+            .remove(0)
+            .value
+    };
+
+    assert!(matches!(optimize("2 ** 10"), ExpressionValue::Int(1024)));
+    assert!(matches!(
+        optimize("(1 + 2) * 3 == 9"),
+        ExpressionValue::Bool(true)
+    ));
+    assert!(matches!(optimize("\"a\" + \"b\""), ExpressionValue::String(s) if s == "ab"));
+    assert!(matches!(optimize("!(1 < 2)"), ExpressionValue::Bool(false)));
+    assert!(matches!(optimize("x + 1"), ExpressionValue::Binary { .. }));
+    assert!(matches!(optimize("if false { 1 }"), ExpressionValue::Null));
+    assert!(
+        matches!(optimize("{ { 1 } }"), ExpressionValue::Block(b) if b.len() == 1 && matches!(b[0].value, ExpressionValue::Int(1)))
+    );
+    assert!(matches!(
+        optimize("if false { 1 } elif x { 2 } elif true { 3 } else { 4 }"),
+        ExpressionValue::If { clauses, else_block: Some(_) } if clauses.len() == 1
+    ));
+
+    // errors are still raised when the expression runs
+    assert!(matches!(optimize("1 / 0"), ExpressionValue::Binary { .. }));
+    assert!(matches!(optimize("\"ab\" * 3"), ExpressionValue::String(s) if s == "ababab"));
+    for source in [
+        "\"ab\" * 9223372036854775807",
+        "\"ab\" * 1000000000",
+        "\"ab\" * -1",
+    ] {
+        assert!(matches!(optimize(source), ExpressionValue::Binary { .. }));
+    }
+    assert_eq!(
+        eval("if false { printLn(\"ab\" * 9223372036854775807) } 1").unwrap(),
+        Value::Int(1)
+    );
+    let program = optimizer::optimize(
+        Parser::new("if false { 1 / 0 } else { \"a\" - 1 }")
+            .unwrap()
+            .parse()
+            .unwrap(),
+    );
+    assert_eq!(
+        Interpreter::new()
+            .eval_program(&program)
+            .unwrap_err()
+            .code(),
+        eval("\"a\" - 1").unwrap_err().code()
+    );
+}
+
+#[test]
+fn project_scaffolding() {
+    let path = std::env::temp_dir().join(format!("olang-project-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&path);
+
+    let project = project::create(&path).unwrap();
+    assert!(project.name.starts_with("olang-project-"));
+    assert!(project::create(&path).is_err());
+
+    // the project is found from any directory inside it
+    let found = project::find(&path.join("tests")).unwrap();
+    assert_eq!(found.entry, path.join("main.olang"));
+    let tests = found.test_files().unwrap();
+    assert_eq!(tests, vec![path.join("tests").join("main.olang")]);
+    assert!(test_runner::run_file(&tests[0], &Config::default())
+        .iter()
+        .all(|result| result.passed()));
+
+    std::fs::write(path.join("olang.toml"), "[project]\nentry = 1\n").unwrap();
+    assert!(project::load(&path).is_err());
+
+    std::fs::remove_dir_all(&path).unwrap();
+}
+
+#[test]
+fn resolver() {
+    use parser::{ExpressionValue, Parser, Slot};
+
+    let program = resolver::resolve(
+        Parser::new("var f = fun(a) { var b = a { b + a } }")
+            .unwrap()
+            .parse()
+            .unwrap(),
+    );
+    let ExpressionValue::VariableDeclaration {
+        slot: None,
+        expression,
+        ..
+    } = &program.ast[0].value
+    else {
+        panic!("top level variables are not resolved");
+    };
+    let ExpressionValue::Function(function) = &expression.value else {
+        panic!("expected a function");
+    };
+    assert!(matches!(
+        function.body[0].value,
+        ExpressionValue::VariableDeclaration { slot: Some(1), .. }
+    ));
+    let ExpressionValue::Block(block) = &function.body[1].value else {
+        panic!("expected a block");
+    };
+    let ExpressionValue::Binary { left, right, .. } = &block[0].value else {
+        panic!("expected a binary operation");
+    };
+    assert!(matches!(
+        left.value,
+        ExpressionValue::Identifier(_, Some(Slot { depth: 1, index: 1 }))
+    ));
+    assert!(matches!(
+        right.value,
+        ExpressionValue::Identifier(_, Some(Slot { depth: 1, index: 0 }))
+    ));
+
+    // variables declared later in an enclosing scope are looked up when they are used
+    assert_eq!(
+        eval("var x = 1 var f = fun() { var g = fun() { x } var x = 2 g() } f()").unwrap(),
+        Value::Int(2)
+    );
+    assert_eq!(
+        eval("var r = [] for var i = 0 i < 2 i++ { if i > 0 { r += x } var x = i } r").unwrap(),
+        Value::list(vec![Value::Int(0)])
+    );
+    assert_eq!(
+        eval("var f = fun(n) { var a = n fun() { a += 1 a } } var c = f(5) c() c()").unwrap(),
+        Value::Int(7)
+    );
+}
+
+#[test]
+fn recursion_limit() {
+    let config = Config {
+        stack_limit: Some(50),
+        ..Config::default()
+    };
+    let mut interpreter = Interpreter::with_config(&config);
+    interpreter
+        .eval_str("var depth = fun(n) { if n == 0 { 0 } else { 1 + depth(n - 1) } }")
+        .unwrap();
+
+    assert_eq!(interpreter.eval_str("depth(49)").unwrap(), Value::Int(49));
+    assert_eq!(
+        *interpreter
+            .eval_str("depth(50)")
+            .unwrap_err()
+            .unwrap_exception(),
+        Exception::RecursionLimitExceeded
+    );
+    // the depth is reset after the exception unwound the calls
+    assert_eq!(interpreter.eval_str("depth(49)").unwrap(), Value::Int(49));
+
+    interpreter.set_max_recursion(10);
+    assert_eq!(
+        interpreter.eval_str("depth(10)").unwrap_err().code(),
+        "OL0022"
+    );
+
+    // the default limit is reached before the stack of a thread with the
+    // default size of 2MB overflows
+    let limit = interpreter::DEFAULT_MAX_RECURSION;
+    let result = std::thread::spawn(move || {
+        let deep = "var depth = fun(n) { if n == 0 { 0 } else { 1 + depth(n - 1) } }";
+        (
+            *eval(&format!("{} depth({})", deep, limit - 1))
+                .unwrap()
+                .into_int()
+                .unwrap(),
+            eval(&format!("{} depth({})", deep, limit))
+                .unwrap_err()
+                .code(),
+        )
+    })
+    .join()
+    .unwrap();
+    assert_eq!(result, (limit as i64 - 1, "OL0022"));
+}
+
+#[test]
+fn prelude() {
+    let mut interpreter = Interpreter::new();
+    interpreter
+        .load_prelude("var square = fun(x) { x * x } var answer = 42")
+        .unwrap()
+        .sandbox();
+    assert_eq!(
+        interpreter.eval_str("square(answer) - 1000").unwrap(),
+        Value::Int(764)
+    );
+
+    assert!(Interpreter::new().load_prelude("var = 1").is_err());
+}
+
+#[test]
+fn fuel() {
+    let mut interpreter = Interpreter::with_fuel(10_000);
+    let err = interpreter.eval_str("loop {}").unwrap_err();
+    assert!(matches!(err, EvalError::FuelExhausted));
+    assert_eq!(err.code(), "OL0023");
+    assert_eq!(interpreter.remaining_fuel(), Some(0));
+
+    // refilling lets the interpreter continue
+    interpreter.set_fuel(Some(100));
+    assert_eq!(interpreter.eval_str("1 + 2 * 3").unwrap(), Value::Int(7));
+    assert!(interpreter.remaining_fuel().unwrap() < 100);
+
+    // functions called by builtins use the same fuel
+    assert!(matches!(
+        Interpreter::with_fuel(200).eval_str("var f = fun() { loop {} } time.benchmark(f)"),
+        Err(EvalError::FuelExhausted)
+    ));
+
+    let config = Config {
+        fuel: Some(5),
+        ..Config::default()
+    };
+    assert!(matches!(
+        Interpreter::with_config(&config).eval_str("while true { }"),
+        Err(EvalError::FuelExhausted)
+    ));
+}
+
+#[test]
+fn token_dump() {
+    use tokens::{dump, TokenFormat};
+
+    let text = dump("var x = \"hi\"\nx", TokenFormat::Text).unwrap();
+    let lines: Vec<_> = text.lines().collect();
+    assert_eq!(lines.len(), 6);
+    assert!(lines[0].starts_with("1:1 -> 1:4") && lines[0].ends_with("KeywordVar"));
+    assert!(lines[3].contains("String") && lines[3].ends_with("\"hi\""));
+
+    let json: serde_json::Value =
+        serde_json::from_str(&dump("42", TokenFormat::Json).unwrap()).unwrap();
+    assert_eq!(json[0]["kind"], "Int");
+    assert_eq!(json[0]["value"], 42);
+    assert_eq!(json[0]["start"]["col"], 1);
+    assert_eq!(json[1]["kind"], "EndOfFile");
+    assert!(json[1]["value"].is_null());
+
+    assert_eq!(dump("1 $", TokenFormat::Text).unwrap_err().code(), "OL0001");
+}
+
+#[test]
+fn timeout() {
+    use std::time::{Duration, Instant};
+
+    let mut interpreter = Interpreter::new();
+    let start = Instant::now();
+    let err = interpreter
+        .eval_with_timeout("loop {}", Duration::from_millis(50))
+        .unwrap_err();
+    assert!(matches!(err, EvalError::TimedOut));
+    assert_eq!(err.code(), "OL0024");
+    assert!(start.elapsed() < Duration::from_secs(5));
+
+    // the deadline only applies to that evaluation
+    assert_eq!(
+        interpreter
+            .eval_with_timeout("1 + 1", Duration::from_secs(10))
+            .unwrap(),
+        Value::Int(2)
+    );
+    interpreter
+        .eval_str("var i = 0 while i < 5000 { i++ }")
+        .unwrap();
+}
+
+#[test]
+fn runtime_error_regions() {
+    use builder::Expr;
+    use parser::{BinaryOperationOperator, Program};
+
+    let err = eval("var x = 1\nx + \"a\"").unwrap_err();
+    assert_eq!(err.code(), "OL0011");
+    assert_eq!(err.region().unwrap().start.row, 2);
+    assert!(err.to_string().starts_with("2:1 -> "));
+
+    // the innermost expression is reported, not the call
+    let err = eval("var f = fun() {\n    undefined\n}\nf()").unwrap_err();
+    let region = err.region().unwrap();
+    assert_eq!((region.start.row, region.start.col), (2, 5));
+
+    // programs built without source have no region to report
+    let program = Program::new(vec![Expr::binary(
+        Expr::int(1),
+        BinaryOperationOperator::Minus,
+        Expr::string("a"),
+    )]);
+    let err = Interpreter::new().eval_program(&program).unwrap_err();
+    assert!(err.region().is_none());
+    assert!(err.to_string().starts_with("Unhandled exception"));
+}
+
+#[test]
+fn assert_equal() {
+    assert_eq!(
+        eval("assertEqual([1 {\"a\": \"x\"}] [1 {\"a\": \"x\"}])").unwrap(),
+        Value::Null
+    );
+
+    let err = eval("assertEqual([1 2 {\"a\": \"x\"}] [1 3 {\"a\": 1 \"b\": null}])").unwrap_err();
+    assert_eq!(err.code(), "OL0025");
+    assert_eq!(
+        err.unwrap_exception().to_string(),
+        "assertion failed:
+    [1]: 2 != 3
+    [2][\"a\"]: \"x\" != 1
+    [2][\"b\"]: null is missing on the left"
+    );
+
+    let err = eval("assertEqual(\"1\" 1)").unwrap_err();
+    assert_eq!(
+        err.unwrap_exception().to_string(),
+        "assertion failed:\n    value: \"1\" != 1"
+    );
+}
+
+#[test]
+fn library_stages() {
+    let tokens = lex("var x = 1").unwrap();
+    assert_eq!(tokens.len(), 5);
+    assert_eq!(lex("1 $").unwrap_err().code(), "OL0001");
+
+    assert_eq!(parse("var x = 1 x + 2").unwrap().ast.len(), 2);
+    assert_eq!(parse("var = 1").unwrap_err().code(), "OL0003");
+
+    assert_eq!(eval_pure("var x = 1 x + 2", 100).unwrap(), Value::Int(3));
+    assert_eq!(eval_pure("loop {}", 100).unwrap_err().code(), "OL0023");
+    assert_eq!(eval_pure("printLn(1)", 100).unwrap_err().code(), "OL0015");
+    assert_eq!(eval_pure("readLn()", 100).unwrap_err().code(), "OL0015");
+}
+
+#[test]
+fn deterministic() {
+    use std::time::{Duration, Instant};
+
+    let mut interpreter = Interpreter::new();
+    interpreter.set_deterministic(true);
+    assert!(interpreter.is_deterministic());
+
+    // sleeping only advances the virtual clock, so timings are exact
+    let start = Instant::now();
+    let result = interpreter
+        .eval_str("time.benchmark(fun() { time.sleep(1000) })")
+        .unwrap();
+    assert!(start.elapsed() < Duration::from_secs(5));
+    let result = result.into_map().unwrap();
+    let [min, mean, max] = ["min", "mean", "max"].map(|key| result[key].into_float().unwrap());
+    assert_eq!((min, mean, max), (1000.0, 1000.0, 1000.0));
+
+    let config = Config {
+        deterministic: true,
+        ..Config::default()
+    };
+    assert!(Interpreter::with_config(&config).is_deterministic());
+    assert!(!Interpreter::new().is_deterministic());
+}
+
+#[test]
+fn clocks() {
+    let mut interpreter = Interpreter::new();
+    interpreter.set_deterministic(true);
+    assert_eq!(
+        interpreter
+            .eval_str("var start = [time.timeMillis() time.clock()] time.sleep(1500) [start [time.timeMillis() time.clock()]]")
+            .unwrap(),
+        eval("[[0 0.0] [1500 1.5]]").unwrap()
+    );
+
+    // 2020-01-01
+    assert_eq!(
+        eval("time.timeMillis() > 1577836800000").unwrap(),
+        Value::Bool(true)
+    );
+    assert_eq!(
+        eval("var start = time.clock() time.sleep(20) time.clock() - start >= 0.02").unwrap(),
+        Value::Bool(true)
+    );
+    assert_eq!(eval("time.sleep(-1)").unwrap_err().code(), "OL0011");
+    assert_eq!(
+        eval_pure("time.clock() >= 0.0", 100).unwrap(),
+        Value::Bool(true)
+    );
+}
+
+#[test]
+fn random_numbers() {
+    let draw = "[math.random() math.randomInt(1 6) math.randomInt(-5 5) math.random()]";
+    let seeded = format!("math.seedRandom(42) {}", draw);
+    assert_eq!(eval(&seeded).unwrap(), eval(&seeded).unwrap());
+    assert_ne!(eval(draw).unwrap(), eval(draw).unwrap());
+
+    // deterministic interpreters start from the same seed
+    let mut a = Interpreter::new();
+    let mut b = Interpreter::new();
+    a.set_deterministic(true);
+    b.set_deterministic(true);
+    assert_eq!(a.eval_str(draw).unwrap(), b.eval_str(draw).unwrap());
+
+    assert_eq!(
+        eval(
+            "math.seedRandom(7)
+            var ok = true
+            var seen = list.newList(3 false)
+            for var i = 0 i < 1000 i++ {
+                var f = math.random()
+                var n = math.randomInt(0 2)
+                ok = ok && f >= 0.0 && f < 1.0 && n >= 0 && n <= 2
+                seen !! n = true
+            }
+            [ok seen math.randomInt(3 3)]"
+        )
+        .unwrap(),
+        eval("[true [true true true] 3]").unwrap()
+    );
+    // the whole range of ints
+    assert!(eval("math.randomInt(-9223372036854775807 - 1 9223372036854775807)").is_ok());
+    assert!(eval("math.randomInt(2 1)")
+        .unwrap_err()
+        .to_string()
+        .contains("min is greater than max"));
+    assert!(eval_pure("math.randomInt(1 2)", 100).is_ok());
+}
+
+#[test]
+fn timers() {
+    use std::time::Duration;
+
+    let mut interpreter = Interpreter::new();
+    interpreter.set_deterministic(true);
+    let source = "var events = []
+var ticks = 0
+var ticker = time.setInterval(fun() {
+    ticks++
+    list.push(events \"tick \" + toString(ticks))
+    if ticks == 3 { time.clearTimer(ticker) }
+} 100)
+time.setTimeout(fun() { list.push(events \"timeout\") } 250)
+time.setTimeout(fun() { list.push(events \"first\") } 0)
+var cancelled = time.setTimeout(fun() { list.push(events \"cancelled\") } 50)
+time.clearTimer(cancelled)
+time.runEventLoop()
+events";
+    assert_eq!(
+        interpreter.eval_str(source).unwrap(),
+        eval("[\"first\" \"tick 1\" \"tick 2\" \"timeout\" \"tick 3\"]").unwrap()
+    );
+    assert_eq!(interpreter.elapsed(), Duration::from_millis(300));
+    // nothing is left to run
+    assert_eq!(
+        interpreter
+            .eval_str("time.runEventLoop() time.clearTimer(ticker)")
+            .unwrap(),
+        Value::Bool(false)
+    );
+
+    assert_eq!(eval("time.setTimeout(1 10)").unwrap_err().code(), "OL0011");
+    assert_eq!(
+        eval("time.setInterval(fun() {} (-1))").unwrap_err().code(),
+        "OL0011"
+    );
+    assert_eq!(
+        eval("time.setTimeout(fun() { throw \"late\" } 0) time.runEventLoop()")
+            .unwrap_err()
+            .code(),
+        eval("throw \"late\"").unwrap_err().code()
+    );
+}
+
+#[test]
+fn scanning_strings() {
+    assert_eq!(
+        eval("str.scan(\"name: {str} age: {int}\" \"name: Ada Lovelace age: 36\")").unwrap(),
+        eval("[\"Ada Lovelace\" 36]").unwrap()
+    );
+    assert_eq!(
+        eval("str.scan(\"{float},{float} {str}\" \"-1.5,2e3 north west\")").unwrap(),
+        eval("[(-1.5) 2000.0 \"north west\"]").unwrap()
+    );
+    // strings stay as short as possible
+    assert_eq!(
+        eval("str.scan(\"{str}={str}\" \"a=b=c\")").unwrap(),
+        eval("[\"a\" \"b=c\"]").unwrap()
+    );
+    assert_eq!(eval("str.scan(\"{int}\" \"12.5\")").unwrap(), Value::Null);
+    assert_eq!(
+        eval("str.scan(\"age: {int}\" \"age: 36 years\")").unwrap(),
+        Value::Null
+    );
+    assert_eq!(
+        eval("str.scan(\"{int}\" \"99999999999999999999\")").unwrap(),
+        Value::Null
+    );
+    assert_eq!(eval("str.scan(\"x\" \"x\")").unwrap(), eval("[]").unwrap());
+
+    assert!(eval("str.scan(\"{date}\" \"today\")")
+        .unwrap_err()
+        .to_string()
+        .contains("unknown placeholder {date}"));
+    assert!(eval("str.scan(\"{int\" \"1\")").is_err());
+}
+
+#[test]
+fn comparing_strings() {
+    assert_eq!(
+        eval(
+            "list.sortBy([\"file10\" \"File2\" \"file1\" \"file02\" \"file\"] str.naturalCompare)"
+        )
+        .unwrap(),
+        eval("[\"file\" \"file1\" \"File2\" \"file02\" \"file10\"]").unwrap()
+    );
+    assert_eq!(
+        eval("[str.naturalCompare(\"a2b\" \"a10\") str.naturalCompare(\"x\" \"x\") str.naturalCompare(\"v1.10\" \"v1.9\")]").unwrap(),
+        eval("[(-1) 0 1]").unwrap()
+    );
+    assert_eq!(
+        eval("list.sortBy([\"banana\" \"Apple\" \"cherry\" \"apple\"] str.compareIgnoreCase)")
+            .unwrap(),
+        eval("[\"Apple\" \"apple\" \"banana\" \"cherry\"]").unwrap()
+    );
+    // letters are compared without accents and case first, not by their code points
+    let cases = [
+        ("str.compareIgnoreCase(\"Ärger\" \"ärger\")", 0),
+        ("str.compareIgnoreCase(\"Straße\" \"STRASSE\")", 0),
+        ("str.compareIgnoreCase(\"ä\" \"b\")", -1),
+        ("str.compareIgnoreCase(\"a\" \"ä\")", -1),
+        ("str.compareIgnoreCase(\"äb\" \"ac\")", -1),
+        ("str.compareIgnoreCase(\"é\" \"e\u{301}\")", 0),
+        ("str.naturalCompare(\"ä\" \"b\")", -1),
+        ("str.naturalCompare(\"Straße2\" \"strasse10\")", -1),
+        ("str.naturalCompare(\"Straße\" \"STRASSE\")", 1),
+    ];
+    for (source, expected) in cases {
+        assert_eq!(eval(source).unwrap(), Value::Int(expected), "{}", source);
+    }
+    assert_eq!(
+        eval("list.sortBy([\"Zebra\" \"Äpfel\" \"apfel\" \"Birne\"] str.compareIgnoreCase)")
+            .unwrap(),
+        eval("[\"apfel\" \"Äpfel\" \"Birne\" \"Zebra\"]").unwrap()
+    );
+    assert_eq!(
+        eval("str.naturalCompare(1 2)").unwrap_err().code(),
+        "OL0011"
+    );
+}
+
+#[test]
+fn namespaces() {
+    assert_eq!(eval("math.sum([1 2 3])").unwrap(), Value::Int(6));
+    assert_eq!(
+        eval("str.padLeft(\"7\" 3 \"0\") + str.repeat(\"!\" 2)").unwrap(),
+        Value::String("007!!".to_string())
+    );
+    // members work on every map and can be chained with indexing
+    assert_eq!(
+        eval("var m = {\"a\": {\"b\": [1 2]}} m.a.b !! 1").unwrap(),
+        Value::Int(2)
+    );
+    assert_eq!(
+        eval("var m = {\"f\": fun(x) { x * 2 }} m.f(4)").unwrap(),
+        Value::Int(8)
+    );
+    assert_eq!(eval("math.cbrt(8)").unwrap_err().code(), "OL0019");
+    assert_eq!(eval("var x = 1 x.y").unwrap_err().code(), "OL0011");
+    assert_eq!(eval("math.1").unwrap_err().code(), "OL0003");
+
+    // the builtins of the namespaces are only declared globally in flat mode
+    assert_eq!(eval("sum([1 2])").unwrap_err().code(), "OL0009");
+    assert_eq!(eval("var sum = 1 sum").unwrap(), Value::Int(1));
+    let config = Config {
+        flat_builtins: true,
+        ..Config::default()
+    };
+    // the globals of older versions stay global without flat mode
+    assert_eq!(eval("sleep(1) time.sleep(1)").unwrap(), Value::Null);
+    let mut interpreter = Interpreter::with_config(&config);
+    assert_eq!(
+        interpreter.eval_str("sum([1 2]) + math.sum([1])").unwrap(),
+        Value::Int(4)
+    );
+    assert_eq!(
+        interpreter.eval_str("unique([1 1 len([1])])").unwrap(),
+        Value::list(vec![Value::Int(1)])
+    );
+
+    // namespaces hold functions, so they are not exported
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str("var x = 1").unwrap();
+    assert_eq!(interpreter.variables().len(), 1);
+}
+
+#[test]
+fn diagnostics() {
+    let render = |source: &str, color| {
+        let report = ErrorReport::from(eval(source).unwrap_err()).with_source(source);
+        diagnostic::render(&report, color)
+    };
+
+    assert_eq!(
+        render("var x = 1\nx + \"ab\"", false),
+        "error[OL0011]: 2:1 -> 2:9 Unhandled exception: ValueIsWrongType
+  |
+2 | x + \"ab\"
+  | ^^^^^^^^
+For more information try `olang explain OL0011`
+"
+    );
+    // parser and lexer errors point at a single character
+    assert!(render("var = 1", false).contains("1 | var = 1\n  |     ^\n"));
+    assert!(render("\"abc", false).contains("1 | \"abc\n  | ^\n"));
+    assert!(render("1 + \"a\"", true).contains("\x1b[1;31m^^^^^^^\x1b[0m"));
+
+    // without the source only the message is shown
+    let report = ErrorReport::from(eval("1 + \"a\"").unwrap_err());
+    assert_eq!(diagnostic::render(&report, false).lines().count(), 2);
+}
+
+#[test]
+fn native_values() {
+    use std::cell::RefCell;
+
+    struct Entity {
+        health: RefCell<i64>,
+    }
+
+    let mut interpreter = Interpreter::new();
+    interpreter
+        .register_builtin("spawn", |arguments| {
+            Ok(Value::native(Entity {
+                health: RefCell::new(*arguments[0].into_int()?),
+            }))
+        })
+        .register_builtin("damage", |arguments| {
+            let entity = arguments[0].into_native::<Entity>()?;
+            *entity.health.borrow_mut() -= arguments[1].into_int()?;
+            Ok(Value::Int(*entity.health.borrow()))
+        });
+
+    // copies of a native share the host object
+    assert_eq!(
+        interpreter
+            .eval_str("var e = spawn(10) var f = e damage(f 3) [damage(e 2) e == f toString(e)]")
+            .unwrap(),
+        Value::list(vec![
+            Value::Int(5),
+            Value::Bool(true),
+            Value::String("<native>".to_string())
+        ])
+    );
+    assert_eq!(
+        interpreter.eval_str("spawn(1) == spawn(1)").unwrap(),
+        Value::Bool(false)
+    );
+    assert_eq!(
+        interpreter.eval_str("damage(1 1)").unwrap_err().code(),
+        "OL0011"
+    );
+    assert!(Value::native(1).into_native::<String>().is_err());
+    assert!(serde_json::to_string(&Value::native(1)).is_err());
+}
+
+#[test]
+fn weak_references() {
+    use std::{cell::Cell, rc::Rc};
+
+    let mut interpreter = Interpreter::new();
+    assert_eq!(
+        interpreter
+            .eval_str("var cache = [1 2] var weak = sys.weakRef(cache) sys.deref(weak)")
+            .unwrap(),
+        eval("[1 2]").unwrap()
+    );
+    // deref gives the same list, not a copy
+    assert_eq!(
+        interpreter
+            .eval_str("list.push(sys.deref(weak) 3) cache")
+            .unwrap(),
+        eval("[1 2 3]").unwrap()
+    );
+    assert_eq!(
+        interpreter
+            .eval_str("cache = null sys.deref(weak)")
+            .unwrap(),
+        Value::Null
+    );
+    assert_eq!(eval("sys.weakRef(1)").unwrap_err().code(), "OL0011");
+    assert_eq!(eval("sys.deref([1])").unwrap_err().code(), "OL0011");
+
+    // finalizers run when the last copy of a native is gone
+    let closed = Rc::new(Cell::new(0));
+    let counter = closed.clone();
+    interpreter.register_builtin("connect", move |_| {
+        let counter = counter.clone();
+        Ok(Value::native_with_finalizer(
+            "connection".to_string(),
+            move |name: &String| {
+                assert_eq!(name, "connection");
+                counter.set(counter.get() + 1);
+            },
+        ))
+    });
+    interpreter
+        .eval_str("var c = connect() var d = c var w = sys.weakRef(c) c = null")
+        .unwrap();
+    assert_eq!(closed.get(), 0);
+    assert_eq!(
+        interpreter.eval_str("d == sys.deref(w)").unwrap(),
+        Value::Bool(true)
+    );
+    interpreter.eval_str("d = null").unwrap();
+    assert_eq!(closed.get(), 1);
+    assert_eq!(interpreter.eval_str("sys.deref(w)").unwrap(), Value::Null);
+}
+
+#[test]
+fn ast_dump() {
+    let program = parse("x + 1").unwrap();
+    let dump = format!("{:#?}", program);
+    assert!(dump.contains("region: 1:1 -> 1:6,"), "{}", dump);
+    assert!(dump.contains("region: 1:5 -> 1:6,"), "{}", dump);
+}
+
+#[test]
+fn eval_iter() {
+    let mut interpreter = Interpreter::new();
+    let results: Vec<_> = interpreter
+        .eval_iter("var x = 2\nx * 3\nx + \"a\"\nx = 10")
+        .unwrap()
+        .collect();
+    assert_eq!(results.len(), 3);
+    let (region, value) = results[1].as_ref().unwrap();
+    assert_eq!((region.start.row, value), (2, &Value::Int(6)));
+    assert_eq!(results[2].as_ref().unwrap_err().code(), "OL0011");
+    // the interpreter keeps the state of the expressions that ran
+    assert_eq!(interpreter.get_variable("x"), Some(Value::Int(2)));
+
+    assert!(interpreter.eval_iter("var = 1").is_err());
+}
+
+#[test]
+fn json_output() {
+    let json = |source: &str| {
+        let report = ErrorReport::from(eval(source).unwrap_err());
+        serde_json::from_str::<serde_json::Value>(&diagnostic::render_json(&report)).unwrap()
+    };
+
+    let error = json("var x = 1\nx + \"a\"");
+    assert_eq!(error["code"], "OL0011");
+    assert_eq!(error["region"]["start"]["row"], 2);
+    assert_eq!(json("var = 1")["region"]["start"]["col"], 5);
+    assert_eq!(json("\"abc")["code"], "OL0017");
+    assert!(json("break")["region"].is_null());
+
+    let ast = serde_json::to_value(parse("f(1)").unwrap()).unwrap();
+    let call = &ast["ast"][0]["value"]["Call"];
+    assert_eq!(call["identifier"], "f");
+    assert_eq!(call["arguments"][0]["value"]["Int"], 1);
+    assert_eq!(call["arguments"][0]["region"]["end"]["col"], 4);
+}
+
+#[test]
+fn printer() {
+    let sources = [
+        "var x = 1 + 2 * 3 (1 + 2) * 3 2 ** 3 ** 2 (2 ** 3) ** 2 -(1 + 2) 1 - (2 - 3)",
+        "var f = fun(a b) { if a < b { a } elif a == b { 0 } else { b } } f(2 1)",
+        "var x = 3 var xs = [1 (-2) (-x) x] xs !! 0 = 5 xs !! 1 += 1 xs",
+        "var m = {\"a\": [1 2] \"b\": {:}} m.a !! 1 + len(m.b)",
+        "var n = 0 for var i = 0 i < 5 i++ { if i == 3 { continue } n += i } n",
+        "var s = \"\" for c in \"abc\" { s = c + s } while s != \"x\" { s = \"x\" } s",
+        "var y = 2 (y + 1) * 2",
+        "!(1 < 2) || -2 ** 2 == -4",
+    ];
+    for source in sources {
+        let printed = printer::program(&parse(source).unwrap());
+        assert_eq!(
+            eval(&printed).unwrap(),
+            eval(source).unwrap(),
+            "{}",
+            printed
+        );
+    }
+    assert_eq!(
+        printer::expression(&parse("a-b*c").unwrap().ast[0]),
+        "a - b * c"
+    );
+
+    // a parenthesis or minus after a name would make it a call or a subtraction
+    use builder::Expr;
+    use parser::{BinaryOperationOperator, UnaryOperator};
+    let sum = Expr::binary(
+        Expr::identifier("x"),
+        BinaryOperationOperator::Plus,
+        Expr::int(1),
+    );
+    let list = Expr::list(vec![
+        Expr::identifier("x"),
+        Expr::binary(sum, BinaryOperationOperator::Multiply, Expr::int(2)),
+        Expr::identifier("x"),
+        Expr::unary(UnaryOperator::Negate, Expr::identifier("x")),
+        Expr::int(-1),
+    ]);
+    let printed = printer::expression(&list);
+    assert_eq!(printed, "[x { (x + 1) * 2 } x { -x } (-1)]");
+    assert_eq!(
+        eval(&format!("var x = 1 {}", printed)).unwrap(),
+        Value::list([1, 4, 1, -1, -1].map(Value::Int).to_vec())
+    );
+
+    assert_eq!(
+        printer::value(&eval("[1 (-2) {\"k\": null} 1.5]").unwrap()).unwrap(),
+        "[1 (-2) {\"k\": null} 1.5]"
+    );
+    assert!(printer::value(&Value::String("\"".to_string())).is_none());
+}
+
+#[test]
+fn repl_sessions() {
+    let mut interpreter = Interpreter::new();
+    interpreter
+        .eval_str("var x = [1 2] var double = fun(n) { n * 2 } var bad = [printLn]")
+        .unwrap();
+    let (source, skipped) = repl::session_source(&interpreter);
+    assert_eq!(skipped, vec!["bad".to_string()]);
+
+    let mut resumed = Interpreter::new();
+    resumed.eval_str(&source).unwrap();
+    assert_eq!(resumed.eval_str("double(x !! 1)").unwrap(), Value::Int(4));
+}
+
+#[test]
+fn check_syntax() {
+    assert!(check("var x = 1\nprintLn(x)").is_empty());
+
+    let errors = check("var = 1\nvar f = fun(x) {\n    x +\n}\nprintLn(1)\n1 +");
+    let rows: Vec<_> = errors
+        .iter()
+        .map(|err| err.region().unwrap().start.row)
+        .collect();
+    assert_eq!(rows, vec![1, 4, 6]);
+    assert!(errors
+        .iter()
+        .all(|err| err.code() == "OL0004" || err.code() == "OL0003"));
+
+    // the lexer can't continue after an error
+    let errors = check("var x = \"abc\nvar = 1");
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].code(), "OL0017");
+}
+
+#[test]
+fn memory_stats() {
+    let mut interpreter = Interpreter::new();
+    let baseline = interpreter.memory_stats();
+
+    interpreter
+        .eval_str("var big = [\"abc\" [1 2 3]] var keep = fun() { big }")
+        .unwrap();
+    let stats = interpreter.memory_stats();
+    assert_eq!(stats.lists, baseline.lists + 2);
+    assert_eq!(stats.strings, baseline.strings + 1);
+    assert!(stats.total_bytes() > baseline.total_bytes());
+
+    // the values are released once nothing refers to them anymore
+    interpreter.eval_str("big = null").unwrap();
+    assert_eq!(interpreter.memory_stats().lists, baseline.lists);
+
+    // the scopes of callers waiting for a call to return are still alive
+    let count = |source| match Interpreter::new().eval_str(source).unwrap() {
+        Value::Map(map) => map["lists"].clone(),
+        _ => panic!("sys.memoryStats should return a map"),
+    };
+    assert_eq!(
+        count("var f = fun() { var xs = [1] g() } var g = fun() { sys.memoryStats() } f()"),
+        count("var xs = [1] sys.memoryStats()")
+    );
+}
+
+#[test]
+fn imports() {
+    let path = std::env::temp_dir().join(format!("olang-imports-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&path);
+    std::fs::create_dir_all(path.join("lib")).unwrap();
+    let write = |file: &str, source: &str| std::fs::write(path.join(file), source).unwrap();
+    write(
+        "lib/shapes.olang",
+        "var helpers = import helpers\nvar area = fun(w h) { helpers.times(w h) }\nprintLn(\"loaded\")",
+    );
+    write("lib/helpers.olang", "var times = fun(a b) { a * b }");
+    write("a.olang", "var b = import b");
+    write("b.olang", "var a = import \"a.olang\"");
+    write("broken.olang", "var x = 1 +");
+
+    let io = io::MemoryIo::default();
+    let mut interpreter = Interpreter::new();
+    interpreter
+        .set_io(io.clone())
+        .set_path(&path.join("main.olang"));
+    let source = "var shapes = import \"lib/shapes.olang\"
+var again = import \"lib/shapes.olang\"
+again.area(3 4)";
+    assert_eq!(interpreter.eval_str(source).unwrap(), Value::Int(12));
+    // every file is evaluated once
+    assert_eq!(io.output(), "loaded\n");
+    // the importing program doesn't see the variables of the imported file
+    assert!(interpreter.eval_str("helpers").is_err());
+
+    for source in ["import a", "import broken", "import missing"] {
+        let err = interpreter.eval_str(source).unwrap_err();
+        assert_eq!(err.code(), "OL0028", "{}", source);
+    }
+    let err = interpreter.eval_str("import a").unwrap_err().to_string();
+    assert!(err.contains("cycle"), "{}", err);
+
+    // the files watch mode looks at, also the ones which failed
+    let files: Vec<_> = interpreter
+        .imported_files()
+        .map(|file| file.strip_prefix(path.canonicalize().unwrap()).unwrap())
+        .map(|file| file.to_str().unwrap().to_string())
+        .collect();
+    assert_eq!(
+        files,
+        [
+            "a.olang",
+            "b.olang",
+            "broken.olang",
+            "lib/helpers.olang",
+            "lib/shapes.olang"
+        ]
+    );
+
+    // a changed file is evaluated again, an unchanged one comes from the cache
+    write("lib/helpers.olang", "var times = fun(a b) { a * b * 2 }");
+    let later = std::time::SystemTime::now() + std::time::Duration::from_secs(60);
+    std::fs::File::options()
+        .write(true)
+        .open(path.join("lib/helpers.olang"))
+        .unwrap()
+        .set_modified(later)
+        .unwrap();
+    let source = "var shapes = import \"lib/shapes.olang\"
+var helpers = import \"lib/helpers.olang\"
+[shapes.area(3 4) helpers.times(3 4)]";
+    assert_eq!(
+        interpreter.eval_str(source).unwrap(),
+        Value::list(vec![Value::Int(12), Value::Int(24)])
+    );
+    assert_eq!(io.output(), "loaded\n");
+
+    // imports read files, which is a side effect
+    assert_eq!(
+        eval_pure("import \"lib/helpers.olang\"", 100)
+            .unwrap_err()
+            .code(),
+        "OL0015"
+    );
+
+    std::fs::remove_dir_all(&path).unwrap();
+}
+
+#[test]
+fn script_args() {
+    let mut interpreter = Interpreter::new();
+    assert_eq!(
+        interpreter.eval_str("sys.args()").unwrap(),
+        Value::list(vec![])
+    );
+
+    interpreter.set_args(vec!["-v".to_string(), "input.txt".to_string()]);
+    assert_eq!(
+        interpreter.eval_str("sys.args() !! 1").unwrap(),
+        Value::String("input.txt".to_string())
+    );
+}
+
+#[test]
+fn config_files() {
+    let ini = eval(
+        r#"data.iniParse("name = demo
+; a comment
+[server]
+host = 'localhost'
+port=8080
+
+[empty]")"#,
+    )
+    .unwrap();
+    let expected =
+        eval(r#"{"name": "demo" "server": {"host": "localhost" "port": "8080"} "empty": {:}}"#)
+            .unwrap();
+    assert_eq!(ini, expected);
+    assert_eq!(
+        eval("data.iniParse(\"[a]\nnot a pair\")")
+            .unwrap_err()
+            .to_string(),
+        "1:1 -> 2:12 Unhandled exception: iniParse: line 2 is neither a key = value pair nor a [section]"
+    );
+
+    let path = std::env::temp_dir().join(format!("olang-dotenv-{}", std::process::id()));
+    std::fs::write(
+        &path,
+        "# settings\nexport TOKEN=abc # secret\nGREETING=\"hello\\nworld\"\nKEPT=new\n",
+    )
+    .unwrap();
+    let mut interpreter = Interpreter::new();
+    interpreter.set_env_var("KEPT", "old");
+    assert_eq!(
+        interpreter.eval_str("sys.getEnv(\"TOKEN\")").unwrap(),
+        Value::Null
+    );
+    let loaded = interpreter
+        .eval_str(&format!("sys.dotenvLoad({:?})", path.display().to_string()))
+        .unwrap();
+    assert_eq!(loaded.into_map().unwrap().len(), 3);
+    let values = interpreter
+        .eval_str("[sys.getEnv(\"TOKEN\") sys.getEnv(\"GREETING\") sys.getEnv(\"KEPT\")]")
+        .unwrap();
+    assert_eq!(values, eval("[\"abc\" \"hello\nworld\" \"old\"]").unwrap());
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(
+        eval_pure("sys.getEnv(\"HOME\")", 100).unwrap_err().code(),
+        "OL0015"
+    );
+}
+
+#[test]
+fn exit_codes() {
+    let io = io::MemoryIo::default();
+    let mut interpreter = Interpreter::new();
+    interpreter.set_io(io.clone());
+    let err = interpreter
+        .eval_str("var f = fun() { for x in [1 2] { sys.exit(4) } } printLn(1) f() printLn(2)")
+        .unwrap_err();
+    assert!(matches!(err, EvalError::Exit(4)));
+    assert_eq!(err.exit_code(), 4);
+    assert_eq!(io.output(), "1\n");
+
+    assert_eq!(eval("sys.exit()").unwrap_err().exit_code(), 0);
+    assert_eq!(eval("1 +").unwrap_err().exit_code(), 2);
+    assert_eq!(eval("1 + \"a\"").unwrap_err().exit_code(), 3);
+    assert_eq!(eval("sys.exit(1.5)").unwrap_err().exit_code(), 3);
+}
+
+#[test]
+fn signal_handlers() {
+    use signal_hook::{
+        consts::{SIGINT, SIGTERM},
+        low_level::raise,
+    };
+
+    let mut interpreter = Interpreter::new();
+    interpreter
+        .eval_str(
+            "var signals = []
+            sys.onSignal(\"TERM\" fun() { list.push(signals \"TERM\") })
+            sys.onSignal(\"INT\" fun() { list.push(signals \"INT\") })",
+        )
+        .unwrap();
+
+    // the functions run at the next step, the loop sees their changes
+    raise(SIGTERM).unwrap();
+    assert_eq!(
+        interpreter
+            .eval_str("while len(signals) == 0 {} signals")
+            .unwrap(),
+        Value::list(vec![Value::String("TERM".to_string())])
+    );
+
+    // a handled Ctrl-C doesn't raise Interrupted
+    interpreter
+        .interrupt_flag()
+        .store(true, std::sync::atomic::Ordering::Relaxed);
+    raise(SIGINT).unwrap();
+    assert_eq!(interpreter.eval_str("len(signals)").unwrap(), Value::Int(2));
+
+    assert!(eval("sys.onSignal(\"USR1\" fun() {})").is_err());
+    assert!(eval("sys.onSignal(\"TERM\" 1)").is_err());
+    assert_eq!(
+        eval_pure("sys.onSignal(\"TERM\" fun() {})", 100)
+            .unwrap_err()
+            .code(),
+        "OL0015"
+    );
+}
+
+#[cfg(feature = "xml")]
+#[test]
+fn xml_parse() {
+    let root = eval(
+        r#"data.xmlParse("<?xml version='1.0'?>
+<report date='2024-01-02'>
+    <entry id='1'>first</entry>
+    <entry id='2'>second <b>bold</b></entry>
+</report>")"#,
+    )
+    .unwrap();
+    let expected = eval(
+        r#"{
+    "name": "report"
+    "attributes": {"date": "2024-01-02"}
+    "children": [
+        {"name": "entry" "attributes": {"id": "1"} "children": ["first"] "text": "first"}
+        {"name": "entry" "attributes": {"id": "2"} "children": [
+            "second "
+            {"name": "b" "attributes": {:} "children": ["bold"] "text": "bold"}
+        ] "text": "second bold"}
+    ]
+    "text": "firstsecond bold"
+}"#,
+    )
+    .unwrap();
+    assert_eq!(root, expected);
+    assert!(eval("data.xmlParse(\"<a><b></a>\")")
+        .unwrap_err()
+        .to_string()
+        .contains("xmlParse: "));
+}
+
+#[cfg(feature = "html")]
+#[test]
+fn html_query() {
+    let page = r#"var page = "<html><body>
+<a class='download' href='/a.zip'>A</a>
+<p>text <a href='/other'>other</a></p>
+<a class='download big' href='/b.zip'>B</a>
+</body></html>"
+"#;
+    assert_eq!(
+        eval(&format!(
+            "{}var links = []
+for link in data.htmlQuery(page \"a.download\") {{ links += [link.attributes.href link.text] }}
+links",
+            page
+        ))
+        .unwrap(),
+        eval(r#"["/a.zip" "A" "/b.zip" "B"]"#).unwrap()
+    );
+    assert_eq!(
+        eval(&format!("{}len(data.htmlQuery(page \"p > a\"))", page)).unwrap(),
+        Value::Int(1)
+    );
+    assert!(eval("data.htmlQuery(\"<p></p>\" \"p >\")").is_err());
+}
+
+#[test]
+fn binary_formats() {
+    let value =
+        r#"{"name": "olang" "version": [0 1] "ratio": 0.5 "tags": {:} "missing": null "ok": true}"#;
+    for format in ["msgpack", "cbor"] {
+        assert_eq!(
+            eval(&format!(
+                "data.{0}Decode(data.{0}Encode({1}))",
+                format, value
+            ))
+            .unwrap(),
+            eval(value).unwrap()
+        );
+    }
+    assert_eq!(
+        eval("data.msgpackEncode([1 \"a\"])").unwrap(),
+        eval("[146 1 161 97]").unwrap()
+    );
+    assert_eq!(eval("data.cborEncode(-1)").unwrap(), eval("[32]").unwrap());
+    assert_eq!(
+        eval("data.cborDecode([67 1 2 3])").unwrap(),
+        eval("[1 2 3]").unwrap()
+    );
+
+    assert!(eval("data.msgpackEncode(fun() {})").is_err());
+    assert!(eval("data.msgpackDecode([256])").is_err());
+    assert!(eval("data.cborDecode([161])")
+        .unwrap_err()
+        .to_string()
+        .contains("cborDecode: "));
+}
+
+#[test]
+fn json() {
+    // olang strings can't contain quotes, so the json is passed in from here
+    let mut interpreter = Interpreter::new();
+    let parse = |interpreter: &mut Interpreter, json: &str| {
+        interpreter.call("data.jsonParse", vec![Value::String(json.to_string())])
+    };
+    assert_eq!(
+        parse(
+            &mut interpreter,
+            r#"{"name": "olang", "version": [0, 1], "ratio": 0.5, "big": 1e3, "tags": {}, "missing": null, "ok": true}"#
+        )
+        .unwrap(),
+        eval(r#"{"name": "olang" "version": [0 1] "ratio": 0.5 "big": 1000.0 "tags": {:} "missing": null "ok": true}"#)
+            .unwrap()
+    );
+    assert!(parse(&mut interpreter, "[1,")
+        .unwrap_err()
+        .to_string()
+        .contains("jsonParse: "));
+    assert!(parse(&mut interpreter, "18446744073709551615").is_err());
+
+    assert_eq!(
+        interpreter
+            .call(
+                "data.jsonStringify",
+                vec![eval(r#"{"b": [1 2.5 null] "a": {:} "c": true}"#).unwrap()]
+            )
+            .unwrap(),
+        Value::String(r#"{"a":{},"b":[1,2.5,null],"c":true}"#.to_string())
+    );
+    let err = eval("var l = [1] list.push(l {\"inner\": l}) data.jsonStringify(l)").unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("jsonStringify: lists containing themselves"));
+    // a list appearing twice is written twice
+    assert_eq!(
+        eval("var s = [1] data.jsonStringify([s s])").unwrap(),
+        Value::String("[[1],[1]]".to_string())
+    );
+    // round trips keep the value
+    let value = r#"{"list": [1 "a" [(-2)]] "text": "line one\nline two"}"#;
+    assert_eq!(
+        eval(&format!("data.jsonParse(data.jsonStringify({}))", value)).unwrap(),
+        eval(value).unwrap()
+    );
+    assert_eq!(
+        eval("data.jsonStringify(0.0 / 0.0)").unwrap(),
+        Value::String("null".to_string())
+    );
+    assert!(eval("data.jsonStringify([fun() {}])").is_err());
+}
+
+#[test]
+fn checksums() {
+    assert_eq!(
+        eval("hash.crc32(\"hello\")").unwrap(),
+        Value::Int(0x3610a686)
+    );
+    assert_eq!(
+        eval("hash.crc32([104 101 108 108 111])").unwrap(),
+        Value::Int(0x3610a686)
+    );
+    assert_eq!(
+        eval("hash.adler32(\"Wikipedia\")").unwrap(),
+        Value::Int(0x11e60398)
+    );
+    assert!(eval("hash.adler32([300])").is_err());
+
+    let path = std::env::temp_dir().join(format!("olang-sha256-{}", std::process::id()));
+    std::fs::write(&path, "abc").unwrap();
+    let source = format!("hash.fileSha256(\"{}\")", path.display());
+    assert_eq!(
+        eval(&source).unwrap(),
+        Value::String(
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad".to_string()
+        )
+    );
+    // reading files is a side effect
+    assert_eq!(eval_pure(&source, 100).unwrap_err().code(), "OL0015");
+    std::fs::remove_file(&path).unwrap();
+    assert!(eval(&source).is_err());
+}
+
+#[test]
+fn temporary_files() {
+    let path = |interpreter: &Interpreter, id: &str| match interpreter.get_variable(id) {
+        Some(Value::String(path)) => std::path::PathBuf::from(path),
+        v => panic!("{:?}", v),
+    };
+
+    let mut interpreter = Interpreter::new();
+    interpreter
+        .eval_str("var file = fs.tempFile() var directory = fs.tempDir()")
+        .unwrap();
+    let (file, directory) = (path(&interpreter, "file"), path(&interpreter, "directory"));
+    assert!(file.is_file() && directory.is_dir());
+    drop(interpreter);
+    assert!(!file.exists() && !directory.exists());
+
+    let mut interpreter = Interpreter::new();
+    assert_eq!(
+        interpreter
+            .eval_str("var inside = null fs.withTempDir(fun(dir) { inside = dir 7 })")
+            .unwrap(),
+        Value::Int(7)
+    );
+    assert!(!path(&interpreter, "inside").exists());
+    // the directory is also deleted when the function raises an exception
+    assert!(interpreter
+        .eval_str("fs.withTempDir(fun(dir) { inside = dir throw \"failed\" })")
+        .is_err());
+    assert!(!path(&interpreter, "inside").exists());
+    assert_eq!(eval_pure("fs.tempDir()", 100).unwrap_err().code(), "OL0015");
+}
+
+#[test]
+fn file_metadata() {
+    let mut interpreter = Interpreter::new();
+    let run = |interpreter: &mut Interpreter, source: &str| interpreter.eval_str(source);
+    run(
+        &mut interpreter,
+        "var dir = fs.tempDir() var file = dir + \"/a.txt\" var copy = dir + \"/b.txt\"",
+    )
+    .unwrap();
+    let file = match interpreter.get_variable("file") {
+        Some(Value::String(file)) => file,
+        v => panic!("{:?}", v),
+    };
+    std::fs::write(&file, "hello").unwrap();
+
+    assert_eq!(
+        run(&mut interpreter, "fs.fileSize(file)").unwrap(),
+        Value::Int(5)
+    );
+    assert_eq!(
+        run(
+            &mut interpreter,
+            "[fs.isFile(file) fs.isDir(file) fs.isDir(dir) fs.isFile(dir + \"/missing\")]"
+        )
+        .unwrap(),
+        eval("[true false true false]").unwrap()
+    );
+    let modified = run(&mut interpreter, "fs.fileModifiedTime(file)").unwrap();
+    assert!(matches!(modified, Value::Int(millis) if millis > 1_600_000_000_000));
+
+    run(
+        &mut interpreter,
+        "fs.copyFile(file copy) fs.renameFile(file dir + \"/c.txt\")",
+    )
+    .unwrap();
+    assert_eq!(
+        run(
+            &mut interpreter,
+            "[fs.isFile(file) fs.fileSize(copy) fs.fileSize(dir + \"/c.txt\")]"
+        )
+        .unwrap(),
+        eval("[false 5 5]").unwrap()
+    );
+
+    let err = run(&mut interpreter, "fs.fileSize(file)").unwrap_err();
+    assert_eq!(err.code(), "OL0030");
+    assert!(err.to_string().contains("a.txt"), "{}", err);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        run(&mut interpreter, "fs.chmod(copy \"640\")").unwrap();
+        let copy = std::path::Path::new(&file).with_file_name("b.txt");
+        let mode = std::fs::metadata(copy).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o640);
+        run(&mut interpreter, "fs.chmod(copy 420)").unwrap();
+    }
+    assert!(run(&mut interpreter, "fs.chmod(copy \"rwx\")").is_err());
+}
+
+#[test]
+fn math_library() {
+    let cases = [
+        ("math.abs(-3)", "3"),
+        ("math.abs(-2.5)", "2.5"),
+        ("math.min(3 1 2)", "1"),
+        ("math.max(3 1.5 2)", "3"),
+        ("math.sqrt(16)", "4.0"),
+        ("math.floor(2.7)", "2"),
+        ("math.ceil(2.1)", "3"),
+        ("math.round(-2.5)", "-3"),
+        ("math.round(4)", "4"),
+        ("math.pow(2 10)", "1024"),
+        ("math.pow(4 0.5)", "2.0"),
+        ("math.pow((-2) 3)", "-8"),
+        ("math.pow((-2) 2)", "4"),
+        ("math.pow(2 (-1))", "0.5"),
+        ("math.pow((-2) (-2))", "0.25"),
+        ("math.floor(math.PI * 100)", "314"),
+        ("math.round(math.E * 1000)", "2718"),
+    ];
+    for (source, expected) in cases {
+        assert_eq!(eval(source).unwrap(), eval(expected).unwrap(), "{}", source);
+    }
+    assert!(matches!(eval("math.sqrt(-1)").unwrap(), Value::Float(v) if v.is_nan()));
+    assert_eq!(eval("math.min()").unwrap_err().code(), "OL0007");
+    assert_eq!(eval("math.abs(\"1\")").unwrap_err().code(), "OL0011");
+    assert_eq!(
+        eval("math.abs(-9223372036854775807 - 1)")
+            .unwrap_err()
+            .unwrap_exception(),
+        &Exception::IntegerOverflowed
+    );
+    for source in ["math.pow(2 63)", "math.pow(2 4294967296)"] {
+        assert_eq!(
+            eval(source).unwrap_err().unwrap_exception(),
+            &Exception::ExponentiationOverflowed,
+            "{}",
+            source
+        );
+    }
+    assert_eq!(eval("math.pow((-2) 63)").unwrap(), Value::Int(i64::MIN));
+    // math.pow and ** agree wherever both are defined
+    for (base, exponent) in [
+        (2, 62i64),
+        (2, 63),
+        (3, 40),
+        (1, 4294967296),
+        (7, 4294967296),
+    ] {
+        assert_eq!(
+            eval(&format!("math.pow({} {})", base, exponent)).map_err(|err| err.code()),
+            eval(&format!("{}**{}", base, exponent)).map_err(|err| err.code()),
+            "{} {}",
+            base,
+            exponent
+        );
+    }
+    assert_eq!(eval("math.pow((-1) 4294967297)").unwrap(), Value::Int(-1));
+    // the constants are only in the namespace, they are not exported as variables
+    assert!(eval("PI").is_err());
+    assert!(Interpreter::new().variables().is_empty());
+}
+
+#[test]
+fn reading_lines() {
+    let path = std::env::temp_dir().join(format!("olang-lines-{}", std::process::id()));
+    std::fs::write(&path, "first\r\nsecond\n\nlast").unwrap();
+    let path = path.display().to_string();
+
+    assert_eq!(
+        eval(&format!("fs.readLines(\"{}\")", path)).unwrap(),
+        eval("[\"first\" \"second\" \"\" \"last\"]").unwrap()
+    );
+    let source = format!(
+        "var lines = fs.openLines(\"{}\")
+var seen = []
+for line in lines {{
+    if line == \"\" {{ break }}
+    seen += [line]
+}}
+for line in lines {{ seen += [\"after \" + line] }}
+seen",
+        path
+    );
+    // the second loop continues after the line the first one stopped at
+    assert_eq!(
+        eval(&source).unwrap(),
+        eval("[\"first\" \"second\" \"after last\"]").unwrap()
+    );
+
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(
+        eval(&format!("fs.openLines(\"{}\")", path))
+            .unwrap_err()
+            .code(),
+        "OL0030"
+    );
+    assert_eq!(eval("for x in 5 {}").unwrap_err().code(), "OL0011");
+}
+
+#[test]
+fn mutating_lists() {
+    let source = "var a = [1 2]
+var b = a
+list.push(b 3)
+list.insert(a 0 0)
+var removed = list.removeAt(b 1)
+var last = list.pop(a)
+var add = fun(l) { list.push(l \"x\") }
+add(a)
+a !! 0 = 9
+[a b removed last]";
+    assert_eq!(
+        eval(source).unwrap(),
+        eval("[[9 2 \"x\"] [9 2 \"x\"] 1 3]").unwrap()
+    );
+    // + creates a new list
+    assert_eq!(
+        eval("var a = [1] var b = a b += [2] [a b]").unwrap(),
+        eval("[[1] [1 2]]").unwrap()
+    );
+    // nested lists inside maps are shared too, the maps are copies
+    assert_eq!(
+        eval("var m = {\"l\": [1]} var n = m list.push(n.l 2) n !! \"k\" = 1 [m n]").unwrap(),
+        eval("[{\"l\": [1 2]} {\"l\": [1 2] \"k\": 1}]").unwrap()
+    );
+    assert_eq!(
+        eval("var a = [1 2] list.clear(a) a").unwrap(),
+        eval("[]").unwrap()
+    );
+    assert_eq!(eval("list.pop([])").unwrap_err().code(), "OL0020");
+    assert_eq!(eval("list.insert([1] 2 0)").unwrap_err().code(), "OL0013");
+    assert_eq!(
+        eval("list.removeAt([1] (-1))").unwrap_err().code(),
+        "OL0013"
+    );
+    assert_eq!(eval("list.push(\"ab\" 1)").unwrap_err().code(), "OL0011");
+
+    // a list containing itself can still be shown and measured
+    let mut interpreter = Interpreter::new();
+    let value = interpreter
+        .eval_str("var l = [1] list.push(l l) l")
+        .unwrap();
+    assert_eq!(value.to_string(), "[1 [...]]");
+    assert_eq!(interpreter.memory_stats().lists, 1);
+    assert!(interpreter.variables().contains_key("l"));
+}
+
+#[test]
+fn building_lists() {
+    assert_eq!(eval("list.newList(3 0)").unwrap(), eval("[0 0 0]").unwrap());
+    assert_eq!(eval("list.newList(0 1)").unwrap(), eval("[]").unwrap());
+    assert_eq!(eval("list.newList((-1) 0)").unwrap_err().code(), "OL0011");
+    assert_eq!(
+        eval("list.newList(9223372036854775807 0)")
+            .unwrap_err()
+            .code(),
+        "OL0034"
+    );
+    assert_eq!(
+        eval("list.newList(4611686018427387904 \"text\")")
+            .unwrap_err()
+            .code(),
+        "OL0034"
+    );
+    assert_eq!(
+        eval("var a = [1] list.extend(a [2 3]) list.extend(a a) a").unwrap(),
+        eval("[1 2 3 1 2 3]").unwrap()
+    );
+    assert_eq!(eval("list.extend([1] 2)").unwrap_err().code(), "OL0011");
+
+    // += appends in place when nothing else holds the list, which keeps long
+    // lists from being copied on every append
+    let mut interpreter = Interpreter::new();
+    assert_eq!(
+        interpreter
+            .eval_str("var l = [] for var i = 0 i < 100000 i++ { l += i } len(l)")
+            .unwrap(),
+        Value::Int(100000)
+    );
+    // a list held anywhere else is still copied
+    assert_eq!(
+        eval(
+            "var a = [1] var b = a b += 2
+            var outer = [a] a += 3
+            var m = {\"l\": [1]} m !! \"l\" += [2]
+            var c = [1] c += c
+            [a b outer m c]"
+        )
+        .unwrap(),
+        eval("[[1 3] [1 2] [[1]] {\"l\": [1 2]} [1 1]]").unwrap()
+    );
+}
+
+#[test]
+fn frozen_values() {
+    let mut interpreter = Interpreter::new();
+    interpreter
+        .eval_str(
+            "var config = {\"hosts\": [\"a\" \"b\"] \"port\": 80} var frozen = sys.freeze(config)",
+        )
+        .unwrap();
+    for source in [
+        "list.push(frozen.hosts \"c\")",
+        "var hosts = frozen.hosts hosts !! 0 = \"z\"",
+        "frozen !! \"hosts\" !! 0 = \"z\"",
+        "list.clear(frozen.hosts)",
+        "list.extend(frozen.hosts [1])",
+    ] {
+        assert_eq!(interpreter.eval_str(source).unwrap_err().code(), "OL0031");
+    }
+    // the original is a different value and can still be changed, new lists
+    // made from frozen ones aren't frozen
+    assert_eq!(
+        interpreter
+            .eval_str(
+                "list.push(config.hosts \"c\")
+                var more = frozen.hosts more += [\"d\"]
+                [config.hosts frozen.hosts more sys.isFrozen(more) sys.isFrozen(list.sort(frozen.hosts))]"
+            )
+            .unwrap(),
+        eval("[[\"a\" \"b\" \"c\"] [\"a\" \"b\"] [\"a\" \"b\" \"d\"] false false]").unwrap()
+    );
+    // a callback given the frozen value can't change what the host sees
+    interpreter
+        .eval_str("var callback = fun(c) { list.push(c.hosts \"evil\") }")
+        .unwrap();
+    let frozen = interpreter.eval_str("frozen").unwrap();
+    assert!(matches!(
+        interpreter.call("callback", vec![frozen]),
+        Err(EvalError::UnhandledException(Exception::ValueIsFrozen, _))
+    ));
+
+    assert_eq!(
+        eval("var l = [1] list.push(l l) var f = sys.freeze(l) [sys.isFrozen(f) sys.isFrozen(f !! 1) sys.isFrozen(1)]").unwrap(),
+        eval("[true true false]").unwrap()
+    );
+    assert_eq!(eval("sys.freeze(1)").unwrap(), Value::Int(1));
+    assert!(explain::explain("OL0031").is_some());
+}
+
+#[test]
+fn sorting_lists() {
+    assert_eq!(
+        eval("list.sort([3 1.5 (-2) 1])").unwrap(),
+        eval("[(-2) 1 1.5 3]").unwrap()
+    );
+    assert_eq!(
+        eval("list.sort([\"pear\" \"Apple\" \"apple\"])").unwrap(),
+        eval("[\"Apple\" \"apple\" \"pear\"]").unwrap()
+    );
+    // the original list is left alone
+    assert_eq!(
+        eval("var a = [2 1] var b = list.sort(a) [a b list.reverse(b)]").unwrap(),
+        eval("[[2 1] [1 2] [2 1]]").unwrap()
+    );
+    // equal elements keep their order
+    assert_eq!(
+        eval("list.sortBy([[2 \"a\"] [1 \"b\"] [2 \"c\"] [1 \"d\"]] fun(x y) { (x !! 0) - (y !! 0) })")
+            .unwrap(),
+        eval("[[1 \"b\"] [1 \"d\"] [2 \"a\"] [2 \"c\"]]").unwrap()
+    );
+    assert_eq!(
+        eval("list.sortBy([1 3 2] fun(x y) { y - x })").unwrap(),
+        eval("[3 2 1]").unwrap()
+    );
+    // an inconsistent comparison gives some order instead of failing
+    assert_eq!(
+        eval("len(list.sortBy([1 2 3 4 5] fun(x y) { 1 }))").unwrap(),
+        Value::Int(5)
+    );
+
+    assert_eq!(eval("list.sort([1 \"a\"])").unwrap_err().code(), "OL0011");
+    assert_eq!(eval("list.sort([[1] [2]])").unwrap_err().code(), "OL0011");
+    assert_eq!(
+        eval("list.sortBy([1 2] fun(x y) { true })")
+            .unwrap_err()
+            .code(),
+        "OL0011"
+    );
+    assert_eq!(
+        eval("list.sortBy([1 2] fun(x y) { throw \"no\" })")
+            .unwrap_err()
+            .code(),
+        eval("throw \"no\"").unwrap_err().code()
+    );
+}
+
+#[test]
+fn snapshots() {
+    let path = std::env::temp_dir().join(format!("olang-snapshots-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&path);
+    std::fs::create_dir_all(&path).unwrap();
+    std::fs::write(
+        path.join("shapes.olang"),
+        "printLn(\"loaded\")\nvar area = fun(w h) { w * h }",
+    )
+    .unwrap();
+
+    let mut interpreter = Interpreter::new();
+    interpreter.set_path(&path.join("main.olang"));
+    interpreter
+        .set_io(io::MemoryIo::default())
+        .eval_str(
+            "var shapes = import shapes
+var counter = 0
+var next = fun() { counter += 1 counter }
+var shared = [1 2]
+var pair = [shared shared]
+list.push(shared shared)
+var constants = sys.freeze([1 2])
+var total = math.sum
+var print = printLn",
+        )
+        .unwrap();
+    let snapshot = interpreter.snapshot().unwrap();
+
+    let io = io::MemoryIo::default();
+    let mut restored = Interpreter::new();
+    restored
+        .set_io(io.clone())
+        .set_path(&path.join("main.olang"))
+        .restore_snapshot(&snapshot)
+        .unwrap();
+    assert_eq!(
+        restored
+            .eval_str("next() [next() counter shapes.area(3 4) total([1 2])]")
+            .unwrap(),
+        eval("[2 2 12 3]").unwrap()
+    );
+    // the lists are still shared and contain themselves
+    assert_eq!(
+        restored
+            .eval_str(
+                "list.push(pair !! 0 3) [len(pair !! 1) len(shared !! 2) sys.isFrozen(constants)]"
+            )
+            .unwrap(),
+        eval("[4 4 true]").unwrap()
+    );
+    // imported modules are not evaluated again
+    restored
+        .eval_str("var again = import shapes print(again.area(2 2))")
+        .unwrap();
+    assert_eq!(io.output(), "4\n");
+
+    let mut fresh = Interpreter::new();
+    assert!(matches!(
+        fresh.restore_snapshot(b"not a snapshot"),
+        Err(snapshot::SnapshotError::Invalid(_))
+    ));
+    // builtins are taken from the interpreter the snapshot is restored into
+    let mut without_stdlib = Interpreter::with_config(&Config {
+        stdlib: false,
+        ..Config::default()
+    });
+    assert!(matches!(
+        without_stdlib.restore_snapshot(&snapshot),
+        Err(snapshot::SnapshotError::UnknownBuiltin(_))
+    ));
+
+    let mut interpreter = Interpreter::new();
+    interpreter
+        .eval_str("var lines = fs.openLines(\"Cargo.toml\")")
+        .unwrap();
+    assert!(matches!(
+        interpreter.snapshot(),
+        Err(snapshot::SnapshotError::Unsupported(_))
+    ));
+    std::fs::remove_dir_all(&path).unwrap();
+}
+
+#[cfg(all(feature = "exec", unix))]
+#[test]
+fn running_programs() {
+    let mut interpreter = Interpreter::new();
+    interpreter.set_env_var("OLANG_GREETING", "hello");
+    assert_eq!(
+        interpreter
+            .eval_str("sys.exec(\"sh\" [\"-c\" \"echo $OLANG_GREETING $0 && echo oops >&2 && exit 3\" \"world\"])")
+            .unwrap(),
+        eval("{\"stdout\": \"hello world\n\" \"stderr\": \"oops\n\" \"status\": 3}").unwrap()
+    );
+    assert_eq!(eval("sys.exec(\"true\" []).status").unwrap(), Value::Int(0));
+    // killed by a signal
+    assert_eq!(
+        eval("sys.exec(\"sh\" [\"-c\" \"kill -9 $$\"]).status").unwrap(),
+        Value::Null
+    );
+
+    assert_eq!(
+        eval("sys.exec(\"olang-no-such-program\" [])")
+            .unwrap_err()
+            .code(),
+        "OL0030"
+    );
+    assert_eq!(eval("sys.exec(\"true\" [1])").unwrap_err().code(), "OL0011");
+    assert_eq!(
+        eval_pure("sys.exec(\"true\" [])", 100).unwrap_err().code(),
+        eval_pure("printLn(1)", 100).unwrap_err().code()
+    );
+}
+
+#[test]
+fn shared_scripts() {
+    let script = Script::compile(
+        "var discount = if total > 100 { total / 10 } else { 0 }
+var total = total - discount
+total",
+    )
+    .unwrap();
+
+    let threads: Vec<_> = (0..4)
+        .map(|i| {
+            let script = script.clone();
+            std::thread::spawn(move || {
+                let inputs = [("total".to_string(), Value::Int(i * 100))];
+                match script.eval(&Config::default(), inputs).unwrap() {
+                    Value::Int(total) => total,
+                    _ => panic!("not an int"),
+                }
+            })
+        })
+        .collect();
+    let totals: Vec<_> = threads
+        .into_iter()
+        .map(|thread| thread.join().unwrap())
+        .collect();
+    assert_eq!(totals, vec![0, 100, 180, 270]);
+
+    let mut interpreter = Interpreter::with_fuel(1000);
+    interpreter
+        .sandbox()
+        .declare_variable("total", Value::Int(200));
+    assert_eq!(script.eval_in(&mut interpreter).unwrap(), Value::Int(180));
+    assert_eq!(interpreter.get_variable("discount"), Some(Value::Int(20)));
+
+    // the inputs are not left behind for the next evaluation
+    assert_eq!(
+        script.eval(&Config::default(), []).unwrap_err().code(),
+        eval("total").unwrap_err().code()
+    );
+    assert!(Script::compile("var x =").is_err());
+}
+
+#[test]
+fn type_names() {
+    assert_eq!(
+        eval("[typeof(1) typeof(1.5) typeof(\"a\") typeof(true) typeof(null) typeof([]) typeof({:})]")
+            .unwrap(),
+        eval("[\"int\" \"float\" \"string\" \"bool\" \"null\" \"list\" \"map\"]").unwrap()
+    );
+    assert_eq!(
+        eval("[typeof(fun() {}) typeof(len) typeof(math.sum)]").unwrap(),
+        eval("[\"function\" \"function\" \"function\"]").unwrap()
+    );
+    assert_eq!(
+        eval("var f = fun(x) { if typeof(x) == \"list\" { len(x) } else { x } } f([1 2]) + f(3)")
+            .unwrap(),
+        Value::Int(5)
+    );
+
+    let mut interpreter = Interpreter::new();
+    interpreter.register_builtin("handle", |_| Ok(Value::native(1)));
+    assert_eq!(
+        interpreter.eval_str("typeof(handle())").unwrap(),
+        Value::String("native".to_string())
+    );
+    assert_eq!(eval("typeof()").unwrap_err().code(), "OL0007");
+}
+
+#[test]
+fn assertions() {
+    assert_eq!(
+        eval("assert(1 < 2) assert(true \"fine\")").unwrap(),
+        Value::Null
+    );
+    let err = eval("assert(len([]) > 0 \"the list has elements\")").unwrap_err();
+    assert_eq!(err.code(), "OL0025");
+    assert_eq!(
+        err.unwrap_exception().to_string(),
+        "assertion failed: the list has elements"
+    );
+    assert_eq!(
+        eval("assert(false)")
+            .unwrap_err()
+            .unwrap_exception()
+            .to_string(),
+        "assertion failed:"
+    );
+    assert_eq!(eval("assert(1)").unwrap_err().code(), "OL0011");
+    assert_eq!(eval("assertEq([1 2] [1 2])").unwrap(), Value::Null);
+    assert_eq!(eval("assertEq(1 2)").unwrap_err().code(), "OL0025");
+
+    let path = std::env::temp_dir().join(format!("olang-test-discovery-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&path);
+    std::fs::create_dir_all(path.join("nested")).unwrap();
+    let write = |file: &str, source: &str| std::fs::write(path.join(file), source).unwrap();
+    write(
+        "math_test.olang",
+        "var test_add = fun() { assertEq(1 + 1 2) }",
+    );
+    write(
+        "nested/strings_test.olang",
+        "var test_fails = fun() { assert(false \"expected\") }",
+    );
+    write(
+        "helpers.olang",
+        "var test_ignored = fun() { assert(false) }",
+    );
+
+    let files = test_runner::discover(&path).unwrap();
+    let names: Vec<_> = files
+        .iter()
+        .map(|file| file.strip_prefix(&path).unwrap().to_str().unwrap())
+        .collect();
+    assert_eq!(names, ["math_test.olang", "nested/strings_test.olang"]);
+    let results: Vec<_> = files
+        .iter()
+        .flat_map(|file| test_runner::run_file(file, &Config::default()))
+        .map(|result| result.failure)
+        .collect();
+    assert_eq!(
+        results,
+        [
+            None,
+            Some(
+                "error[OL0025]: 1:26 -> 1:49 Unhandled exception: assertion failed: expected"
+                    .to_string()
+            )
+        ]
+    );
+    // files are run as they are
+    assert_eq!(
+        test_runner::discover(&path.join("helpers.olang")).unwrap(),
+        [path.join("helpers.olang")]
+    );
+    std::fs::remove_dir_all(&path).unwrap();
+}
+
+#[test]
+fn pure_functions() {
+    let pure = |source: &str| -> Vec<String> {
+        let program = optimizer::optimize(parse(source).unwrap());
+        let mut names: Vec<_> = program
+            .ast
+            .iter()
+            .filter_map(|expression| match &expression.value {
+                parser::ExpressionValue::VariableDeclaration {
+                    identifier,
+                    expression,
+                    ..
+                } => match &expression.value {
+                    parser::ExpressionValue::Function(function) if function.pure.is_some() => {
+                        Some(identifier.clone())
+                    }
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect();
+        names.sort();
+        names
+    };
+
+    assert_eq!(
+        pure("var fib = fun(n) { if n < 2 { n } else { fib(n - 1) + fib(n - 2) } }"),
+        ["fib"]
+    );
+    assert_eq!(
+        pure("var norm = fun(x y) { var sum = x * x + y * y for var i = 0 i < 2 i++ { sum += 0 } math.sqrt(sum) }
+var scaled = fun(x) { norm(x x) * 2 }
+var lengths = fun(words) { var result = [] for word in words { list.push(result len(word)) } result }"),
+        ["lengths", "norm", "scaled"]
+    );
+    // output, reading and changing outside variables, imports
+    assert_eq!(
+        pure("var f = fun(x) { printLn(x) x }"),
+        Vec::<String>::new()
+    );
+    assert_eq!(
+        pure("var rate = 2 var f = fun(x) { x * rate }"),
+        Vec::<String>::new()
+    );
+    assert_eq!(
+        pure("var n = 0 var f = fun() { n += 1 }"),
+        Vec::<String>::new()
+    );
+    assert_eq!(
+        pure("var l = [] var f = fun(x) { l !! 0 = x }"),
+        Vec::<String>::new()
+    );
+    assert_eq!(
+        pure("var f = fun() { import \"other.olang\" }"),
+        Vec::<String>::new()
+    );
+    assert_eq!(
+        pure("var f = fun() { math.random() }"),
+        Vec::<String>::new()
+    );
+    // calling an impure function or a parameter
+    assert_eq!(
+        pure("var g = fun() { readLn() } var f = fun() { g() }"),
+        Vec::<String>::new()
+    );
+    // the name refers to another function later on
+    assert_eq!(
+        pure("var f = fun(n) { n } f = fun(n) { printLn(n) }"),
+        Vec::<String>::new()
+    );
+    // a variable is only local after its declaration
+    assert_eq!(
+        pure("var x = 1 var f = fun() { var y = x var x = 2 y }"),
+        Vec::<String>::new()
+    );
+    // a builtin declared again by the program
+    assert_eq!(
+        pure("var len = fun(x) { printLn(x) } var f = fun(x) { len(x) }"),
+        Vec::<String>::new()
+    );
+    // only top level functions
+    assert_eq!(pure("{ var f = fun(x) { x } }"), Vec::<String>::new());
+
+    let fib = optimizer::optimize(
+        parse("var fib = fun(n) { if n < 2 { n } else { fib(n - 1) + fib(n - 2) } } fib(60)")
+            .unwrap(),
+    );
+    let fib = resolver::resolve(fib);
+    assert_eq!(
+        Interpreter::with_fuel(100_000)
+            .eval_program(&fib)
+            .unwrap_err()
+            .code(),
+        "OL0023"
+    );
+    assert_eq!(
+        Interpreter::with_fuel(100_000)
+            .set_memoize(true)
+            .eval_program(&fib)
+            .unwrap(),
+        Value::Int(1548008755920)
+    );
+
+    // results holding lists are not shared between calls
+    let program = resolver::resolve(optimizer::optimize(
+        parse("var make = fun(n) { [n] } var a = make(1) list.push(a 2) make(1)").unwrap(),
+    ));
+    assert_eq!(
+        Interpreter::new()
+            .set_memoize(true)
+            .eval_program(&program)
+            .unwrap(),
+        eval("[1]").unwrap()
+    );
+}
+
+#[test]
+fn loop_invariant_calls() {
+    use std::{cell::Cell, rc::Rc};
+
+    // len is replaced by one which counts its calls
+    let run = |source: &str, memoize: bool| {
+        let calls = Rc::new(Cell::new(0));
+        let counter = calls.clone();
+        let program = resolver::resolve(optimizer::optimize(parse(source).unwrap()));
+        let value = Interpreter::new()
+            .set_memoize(memoize)
+            .register_builtin("len", move |arguments| {
+                counter.set(counter.get() + 1);
+                match &arguments[0] {
+                    Value::String(string) => Ok(Value::Int(string.len() as i64)),
+                    _ => Err(ControlFlowValue::Exception(
+                        Exception::ValueIsWrongType,
+                        None,
+                    )),
+                }
+            })
+            .eval_program(&program)
+            .unwrap();
+        (value, calls.get())
+    };
+
+    let source = "var s = \"abc\" var total = 0 for x in [1 2 3 4 5] { total += len(s) } total";
+    assert_eq!(run(source, false), (Value::Int(15), 5));
+    assert_eq!(run(source, true), (Value::Int(15), 1));
+    // once for every time the inner loop runs
+    let source = "var s = \"abc\" var total = 0
+for x in [1 2 3] { for y in [1 2 3 4] { total += len(s) } } total";
+    assert_eq!(run(source, false), (Value::Int(36), 12));
+    assert_eq!(run(source, true), (Value::Int(36), 3));
+    // the test of a loop is evaluated in every iteration as well
+    let source = "var s = \"abcd\" var i = 0 for i = 0 i < len(s) i++ { } i";
+    assert_eq!(run(source, true), (Value::Int(4), 1));
+
+    // arguments which change between the iterations
+    for (source, result) in [
+        (
+            "var total = 0 for x in [\"a\" \"bb\" \"ccc\"] { total += len(x) } total",
+            6,
+        ),
+        (
+            "var s = \"a\" var total = 0 for x in [1 2 3] { total += len(s) s += \"a\" } total",
+            6,
+        ),
+        (
+            "var total = 0 for x in [1 2 3] { var s = \"ab\" total += len(s) } total",
+            6,
+        ),
+        // the body of a function can also run outside of the loop
+        (
+            "var s = \"ab\" var total = 0 for x in [1 2 3] { var f = fun() { len(s) } total += f() } total",
+            6,
+        ),
+        // the init of the inner loop runs in every iteration of the outer one
+        (
+            "var total = 0 for x in [\"a\" \"bb\" \"ccc\"] { for var i = len(x) i < 4 i++ { total += 1 } } total",
+            6,
+        ),
+    ] {
+        assert_eq!(run(source, true), (Value::Int(result), 3), "{}", source);
+    }
+
+    // lists can be changed without assigning the variable, calls with lists
+    // are made again
+    let program = resolver::resolve(optimizer::optimize(
+        parse("var a = [] for x in [1 2 3] { list.push(a len(a)) } a").unwrap(),
+    ));
+    assert_eq!(
+        Interpreter::new()
+            .set_memoize(true)
+            .eval_program(&program)
+            .unwrap(),
+        eval("[0 1 2]").unwrap()
+    );
+}
+
+#[test]
+fn formatting_strings() {
+    assert_eq!(
+        eval("str.format(\"hello {} you are {}\" \"Ada\" 36)").unwrap(),
+        Value::String("hello Ada you are 36".to_string())
+    );
+    assert_eq!(
+        eval("str.format(\"{1} {0} {1} {}\" 1.5 [1 true])").unwrap(),
+        Value::String("[1 true] 1.5 [1 true] 1.5".to_string())
+    );
+    assert_eq!(
+        eval("str.format(\"{{}} {{{}}} }}\" null)").unwrap(),
+        Value::String("{} {null} }".to_string())
+    );
+    assert_eq!(
+        eval("str.format(\"no placeholders\")").unwrap(),
+        Value::String("no placeholders".to_string())
+    );
+
+    for source in [
+        "str.format(\"{} {}\" 1)",
+        "str.format(\"{2}\" 1)",
+        "str.format(\"{x}\" 1)",
+        "str.format(\"{\" 1)",
+        "str.format(\"}\")",
+    ] {
+        assert_eq!(eval(source).unwrap_err().code(), "OL0014", "{}", source);
+    }
+    assert_eq!(eval("str.format()").unwrap_err().code(), "OL0007");
+    assert_eq!(eval("str.format(1)").unwrap_err().code(), "OL0011");
+}
+
+#[test]
+fn doctor_report() {
+    let vars = [
+        ("OLANG_FUEL".to_string(), "500".to_string()),
+        ("OLANG_FEUL".to_string(), "1".to_string()),
+        ("PATH".to_string(), "/bin".to_string()),
+    ];
+    let config = Config::from_vars(vars.clone()).unwrap();
+    let report = doctor::report(
+        &config,
+        vars,
+        Some(std::path::Path::new("Cargo.toml")),
+        Some(std::path::Path::new("missing/history")),
+    );
+
+    assert!(report.starts_with(&format!("olang {}\n", env!("CARGO_PKG_VERSION"))));
+    assert!(report.contains("prelude: Cargo.toml\n"), "{}", report);
+    assert!(report.contains("history: missing/history (does not exist)\n"));
+    assert!(report.contains("    fuel: 500\n"));
+    assert!(report.contains("    stack limit: 1000\n"));
+    assert!(report.contains("    OLANG_FEUL=1 (unknown, ignored)\n    OLANG_FUEL=500\n"));
+    assert!(!report.contains("PATH"));
+    #[cfg(feature = "xml")]
+    assert!(report.contains("features: xml"));
+
+    let report = doctor::report(&Config::default(), [], None, None);
+    assert!(report.contains("prelude: none"));
+    assert!(report.contains("environment: no OLANG_ variables are set"));
+}
+
+#[test]
+fn script_headers() {
+    assert_eq!(
+        eval("#!/usr/bin/env olang\n#! olang: version >= 0.1, features = []\n1 + 2").unwrap(),
+        Value::Int(3)
+    );
+    assert_eq!(
+        eval("#! olang: version < 0.1\n1").unwrap_err().code(),
+        "OL0032"
+    );
+    let err = eval("# tools\n#! olang: version >= 99\n1").unwrap_err();
+    assert_eq!(err.code(), "OL0032");
+    assert!(err.to_string().contains("needs olang >= 99"), "{}", err);
+    let err = eval("#! olang: features = [xml http]\n1").unwrap_err();
+    assert!(err.to_string().contains("features http "), "{}", err);
+    let err = eval("#! olang: python >= 3\n1").unwrap_err();
+    assert_eq!(err.code(), "OL0032");
+    assert!(err.to_string().contains("invalid script header"));
+    assert_eq!(
+        eval("#! olang: version = 1.2.3.4\n1").unwrap_err().code(),
+        "OL0032"
+    );
+
+    // only the comments before the code are the header
+    assert_eq!(
+        eval("var x = 1\n#! olang: version >= 99\nx").unwrap(),
+        Value::Int(1)
+    );
+}
+
+#[test]
+fn parsing_numbers() {
+    assert_eq!(eval("parseInt(\" 42 \")").unwrap(), Value::Int(42));
+    assert_eq!(eval("parseInt(\"-17\") + 1").unwrap(), Value::Int(-16));
+    assert_eq!(eval("parseInt(\"ff\" 16)").unwrap(), Value::Int(255));
+    assert_eq!(eval("parseInt(\"101\" 2)").unwrap(), Value::Int(5));
+    assert_eq!(eval("parseFloat(\"2.5\") * 2").unwrap(), Value::Float(5.0));
+    assert_eq!(eval("parseFloat(\"3\")").unwrap(), Value::Float(3.0));
+    assert_eq!(eval("parseFloat(\"1e-3\")").unwrap(), Value::Float(0.001));
+
+    let err = eval("parseInt(\"12abc\")").unwrap_err();
+    assert_eq!(err.code(), "OL0014");
+    assert!(
+        err.to_string().contains("\"12abc\" is not an int"),
+        "{}",
+        err
+    );
+    assert_eq!(eval("parseInt(\"2.5\")").unwrap_err().code(), "OL0014");
+    assert_eq!(eval("parseInt(\"\")").unwrap_err().code(), "OL0014");
+    assert_eq!(eval("parseInt(\"1\" 37)").unwrap_err().code(), "OL0014");
+    assert_eq!(eval("parseFloat(\"nan\")").unwrap_err().code(), "OL0014");
+    assert_eq!(eval("parseFloat(\"one\")").unwrap_err().code(), "OL0014");
+    assert_eq!(eval("parseInt(12)").unwrap_err().code(), "OL0011");
+    assert_eq!(eval("parseFloat()").unwrap_err().code(), "OL0007");
+}
+
+#[test]
+fn interpreter_introspection() {
+    assert_eq!(
+        eval("sys.olangVersion()").unwrap(),
+        Value::String(env!("CARGO_PKG_VERSION").to_string())
+    );
+    assert_eq!(
+        eval("sys.hasFeature(\"exec\")").unwrap(),
+        Value::Bool(cfg!(feature = "exec"))
+    );
+    assert_eq!(
+        eval("sys.hasFeature(\"teleport\")").unwrap(),
+        Value::Bool(false)
+    );
+    assert_eq!(
+        eval("sys.platform()").unwrap(),
+        Value::String(std::env::consts::OS.to_string())
+    );
+    assert_eq!(eval("sys.hasFeature(1)").unwrap_err().code(), "OL0011");
+    assert_eq!(eval("sys.platform(1)").unwrap_err().code(), "OL0007");
+}
+
+#[test]
+fn function_display() {
+    assert_eq!(
+        eval("var add = fun(a b) { a + b }\ntoString(add)").unwrap(),
+        Value::String("<fun (a b) at 1:11>".to_string())
+    );
+    assert_eq!(
+        eval("toString([printLn math.sum])").unwrap(),
+        Value::String("[<builtin printLn> <builtin math.sum>]".to_string())
+    );
+    assert_eq!(
+        eval("inspect(fun() { 1 })").unwrap(),
+        Value::String("<fun () at 1:9>".to_string())
+    );
+    assert_eq!(
+        eval("inspect(fun(x) { var y = x * 2\ny + 1 } true)").unwrap(),
+        Value::String("fun(x) {\n    var y = x * 2\n    y + 1\n}".to_string())
+    );
+    assert_eq!(
+        eval("inspect(\"text\" true)").unwrap(),
+        Value::String("\"text\"".to_string())
+    );
+    assert_eq!(
+        eval("inspect(printLn true)").unwrap(),
+        Value::String("<builtin printLn>".to_string())
+    );
+    // functions built without source have no location
+    let program = parser::Program::new(vec![builder::Expr::call(
+        "toString",
+        vec![builder::Expr::function(&["n"], vec![])],
+    )]);
+    assert_eq!(
+        Interpreter::new().eval_program(&program).unwrap(),
+        Value::String("<fun (n)>".to_string())
+    );
+    assert_eq!(eval("inspect(1 2)").unwrap_err().code(), "OL0011");
+}
+
+#[test]
+fn function_sources() {
+    assert_eq!(
+        eval("var add = fun(a b) { a + b } # adds\nsource(add)").unwrap(),
+        Value::String("fun(a b) { a + b }".to_string())
+    );
+    assert_eq!(
+        eval(
+            "var outer = fun() {
+    # the inner one
+    fun(x) {
+        x * 2
+    }
+}
+source(outer())"
+        )
+        .unwrap(),
+        Value::String("fun(x) {\n        x * 2\n    }".to_string())
+    );
+    assert_eq!(eval("source(printLn)").unwrap(), Value::Null);
+    assert_eq!(eval("source(1)").unwrap_err().code(), "OL0011");
+
+    // functions built without source are written out
+    let program = parser::Program::new(vec![builder::Expr::call(
+        "source",
+        vec![builder::Expr::function(
+            &["n"],
+            vec![builder::Expr::identifier("n")],
+        )],
+    )]);
+    assert_eq!(
+        Interpreter::new().eval_program(&program).unwrap(),
+        Value::String("fun(n) {\n    n\n}".to_string())
+    );
+}
+
+#[test]
+fn deeply_nested_input() {
+    let source = "(".repeat(100_000) + "1" + &")".repeat(100_000);
+    let err = eval(&source).unwrap_err();
+    assert_eq!(err.code(), "OL0033");
+    assert!(err.to_string().starts_with("1:5001 "), "{}", err);
+    assert_eq!(eval(&"2 ** ".repeat(100_000)).unwrap_err().code(), "OL0033");
+    assert_eq!(check(&"- ".repeat(100_000)).len(), 1);
+    // flat chains give deep trees as well, 1 + 1 + 1 is ((1 + 1) + 1)
+    for chain in ["1 + ", "1 * ", "1 == ", "true && ", "[1] !! 0 !! "] {
+        let source = format!("printLn({}1)", chain.repeat(100_000));
+        assert_eq!(eval(&source).unwrap_err().code(), "OL0033", "{}", chain);
+    }
+    let source = "printLn(sys".to_string() + &".x".repeat(100_000) + ")";
+    assert_eq!(eval(&source).unwrap_err().code(), "OL0033");
+    // everyday chains are far from the limit
+    assert_eq!(
+        eval(&format!("{}1", "1 + ".repeat(500))).unwrap(),
+        Value::Int(501)
+    );
+    assert_eq!(
+        eval(&format!("{}\"a\"", "\"a\" + ".repeat(500))).unwrap(),
+        Value::String("a".repeat(501))
+    );
+    assert_eq!(
+        eval(&format!("{}true", "true && ".repeat(500))).unwrap(),
+        Value::Bool(true)
+    );
+    let source = format!("var l = [1] list.push(l l) l{} !! 0", " !! 1".repeat(300));
+    assert_eq!(eval(&source).unwrap(), Value::Int(1));
+
+    let parse = |source: &str, limit| {
+        Parser::new(source)
+            .unwrap()
+            .set_max_nesting(limit)
+            .parse()
+            .map_err(|err| err.code())
+    };
+    assert!(parse("((1))", 3).is_ok());
+    assert_eq!(parse("(((1)))", 3).unwrap_err(), "OL0033");
+
+    // random mixes of everything that nests, the nesting can never crash the
+    // interpreter and every level counts at least once
+    let pairs = [
+        ("(", ")"),
+        ("[", "]"),
+        ("{", "}"),
+        ("fun() {", "}()"),
+        ("if true {", "}"),
+        ("len([", "])"),
+        ("-(", ")"),
+        ("2 ** (", ")"),
+        ("{\"k\": ", "}"),
+        ("for x in [1] {", "}"),
+        ("var v = ", ""),
+        ("! ", ""),
+        ("1 + ", ""),
+        ("(", " * 2)"),
+        ("[", "] !! 0"),
+        ("(1 == ", ") == true"),
+    ];
+    let mut random = random::Random::from_seed(7);
+    for _ in 0..20 {
+        let depth = random.next_int(1, 2 * parser::DEFAULT_MAX_NESTING as i64) as usize;
+        let (mut open, mut close) = (String::new(), String::new());
+        for _ in 0..depth {
+            let (start, end) = pairs[random.next_int(0, pairs.len() as i64 - 1) as usize];
+            open += start;
+            close = end.to_string() + &close;
+        }
+        let source = open + "1" + &close;
+        let result = eval(&source).map_err(|err| err.code());
+        if depth >= parser::DEFAULT_MAX_NESTING {
+            assert_eq!(result.unwrap_err(), "OL0033", "{}", source);
+        }
+    }
+}
+
+#[test]
+fn self_containing_lists() {
+    let lists =
+        "var a = [1] list.push(a a) var b = [1] list.push(b b) var c = [2] list.push(c c)\n";
+    let eval_lists = |source: &str| eval(&(lists.to_string() + source));
+
+    assert_eq!(eval_lists("a == b").unwrap(), Value::Bool(true));
+    assert_eq!(eval_lists("a == c").unwrap(), Value::Bool(false));
+    assert_eq!(eval_lists("[a a] == [b a]").unwrap(), Value::Bool(true));
+
+    assert_eq!(eval_lists("assertEqual(a a) 1").unwrap(), Value::Int(1));
+    assert_eq!(eval_lists("assertEqual(a b) 1").unwrap(), Value::Int(1));
+    let err = eval_lists("assertEqual(a c)").unwrap_err();
+    assert!(err.to_string().contains("[0]: 1 != 2"), "{}", err);
+
+    for encode in ["data.msgpackEncode", "data.cborEncode"] {
+        let err = eval_lists(&format!("{}(a)", encode)).unwrap_err();
+        assert_eq!(err.code(), "OL0014");
+        assert!(err.to_string().contains("containing themselves"), "{}", err);
+    }
+    assert_eq!(
+        eval_lists("data.cborDecode(data.cborEncode([a !! 0 [1 2]]))").unwrap(),
+        eval("[1 [1 2]]").unwrap()
+    );
+
+    assert_eq!(
+        eval_lists("inspect(a true)").unwrap(),
+        Value::String("[1 [...]]".to_string())
+    );
+    let a = eval_lists("a").unwrap();
+    assert!(printer::value(&a).is_none());
+    let shared = eval("var s = [1] [s s]").unwrap();
+    assert_eq!(printer::value(&shared).unwrap(), "[[1] [1]]");
+
+    let mut interpreter = Interpreter::new();
+    interpreter
+        .eval_str("var l = [1] list.push(l l) var n = 2")
+        .unwrap();
+    let (source, skipped) = repl::session_source(&interpreter);
+    assert_eq!(source, "var n = 2\n");
+    assert_eq!(skipped, vec!["l".to_string()]);
+
+    let err = export::export(
+        "var l = [1]\nlist.push(l l)",
+        None,
+        ExportFormat::Json,
+        &Config::default(),
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("containing themselves"), "{}", err);
+}
@@ -0,0 +1,56 @@
+use crate::lexer::{Lexer, LexerError, Token, TokenValue, TokenValueDiscriminants};
+use serde_json::{json, Value as JsonValue};
+use strum::{Display, EnumString, VariantNames};
+
+#[derive(Debug, Clone, Copy, Display, EnumString, VariantNames)]
+#[strum(serialize_all = "lowercase")]
+pub enum TokenFormat {
+    Text,
+    Json,
+}
+
+// the literal value of tokens that carry one
+fn literal(value: &TokenValue) -> Option<JsonValue> {
+    Some(match value {
+        TokenValue::Identifier(v) | TokenValue::String(v) => json!(v),
+        TokenValue::Int(v) => json!(v),
+        TokenValue::Float(v) => json!(v),
+        _ => return None,
+    })
+}
+
+fn text_line(token: &Token) -> String {
+    let line = format!(
+        "{:<14} {}",
+        token.region.to_string(),
+        TokenValueDiscriminants::from(&token.value)
+    );
+    match literal(&token.value) {
+        Some(value) => format!("{:<36} {}", line, value),
+        None => line,
+    }
+}
+
+fn json_token(token: &Token) -> JsonValue {
+    json!({
+        "kind": TokenValueDiscriminants::from(&token.value).to_string(),
+        "value": literal(&token.value),
+        "start": { "row": token.region.start.row, "col": token.region.start.col },
+        "end": { "row": token.region.end.row, "col": token.region.end.col },
+    })
+}
+
+// every token of the source with its kind, literal value and region, this is a
+// debugging aid for the lexer and the grammar
+pub fn dump(source: &str, format: TokenFormat) -> Result<String, LexerError> {
+    let tokens = Lexer::new(source).collect::<Result<Vec<_>, _>>()?;
+
+    Ok(match format {
+        TokenFormat::Text => tokens.iter().map(|token| text_line(token) + "\n").collect(),
+        TokenFormat::Json => {
+            let tokens: Vec<_> = tokens.iter().map(json_token).collect();
+            // serializing a json value can't fail
+            serde_json::to_string_pretty(&tokens).unwrap() + "\n"
+        }
+    })
+}
@@ -1,13 +1,149 @@
-use std::fmt::{self};
+use std::{
+    any::Any,
+    cell::{Ref, RefCell, RefMut},
+    collections::{BTreeMap, HashMap},
+    fmt::{self},
+    rc::{Rc, Weak},
+};
 
-use crate::parser::DefinedFunction;
+use crate::{
+    environment::{Scope, ScopeRef},
+    interpreter::Interpreter,
+    lexer::Region,
+    parser::DefinedFunction,
+};
+use serde::{
+    de::{self, MapAccess, SeqAccess, Visitor},
+    ser, Deserialize, Deserializer, Serialize, Serializer,
+};
 use strum::Display;
 use thiserror::Error;
 
+pub type SimpleBuiltin = dyn Fn(Vec<Value>) -> Result<Value, ControlFlowValue>;
+pub type InterpreterBuiltin =
+    dyn Fn(&mut Interpreter, Vec<Value>) -> Result<Value, ControlFlowValue>;
+
+// closures are allowed so hosts can register builtins which capture their own state
+#[derive(Clone)]
+pub enum BuiltinFunction {
+    Simple(Rc<SimpleBuiltin>),
+    // builtins that call back into the interpreter, for example to call a
+    // function passed as an argument
+    Interpreter(Rc<InterpreterBuiltin>),
+}
+
+impl fmt::Debug for BuiltinFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuiltinFunction::Simple(_) => write!(f, "Simple"),
+            BuiltinFunction::Interpreter(_) => write!(f, "Interpreter"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Builtin {
+    pub name: String,
+    pub function: BuiltinFunction,
+    // builtins with side effects (IO, sleeping) are forbidden in expression only evaluation
+    pub side_effects: bool,
+}
+
+// a function defined in olang together with the scope it was defined in
+#[derive(Clone)]
+pub struct Closure {
+    pub function: DefinedFunction,
+    scope: CapturedScope,
+}
+
+// a function stored in the scope it was defined in would keep that scope alive
+// forever, so that copy only refers to it weakly. the copy can only be read
+// while the scope exists, and every copy made of it refers to it strongly again
+enum CapturedScope {
+    Strong(ScopeRef),
+    Weak(Weak<RefCell<Scope>>),
+}
+
+impl Clone for CapturedScope {
+    fn clone(&self) -> Self {
+        match self {
+            CapturedScope::Strong(scope) => CapturedScope::Strong(scope.clone()),
+            CapturedScope::Weak(scope) => match scope.upgrade() {
+                Some(scope) => CapturedScope::Strong(scope),
+                None => CapturedScope::Weak(scope.clone()),
+            },
+        }
+    }
+}
+
+impl Closure {
+    pub fn new(function: DefinedFunction, scope: ScopeRef) -> Self {
+        Closure {
+            function,
+            scope: CapturedScope::Strong(scope),
+        }
+    }
+
+    pub fn scope(&self) -> ScopeRef {
+        match &self.scope {
+            CapturedScope::Strong(scope) => scope.clone(),
+            CapturedScope::Weak(scope) => scope
+                .upgrade()
+                .expect("functions only refer weakly to the scope holding them"),
+        }
+    }
+
+    pub(crate) fn defined_in(&self, scope: &ScopeRef) -> bool {
+        match &self.scope {
+            CapturedScope::Strong(captured) => Rc::ptr_eq(captured, scope),
+            CapturedScope::Weak(captured) => std::ptr::eq(captured.as_ptr(), Rc::as_ptr(scope)),
+        }
+    }
+}
+
+impl Value {
+    // the value to store in the given scope, see CapturedScope
+    pub(crate) fn stored_in(self, scope: &ScopeRef) -> Value {
+        match self {
+            Value::Function(Function::Defined(closure)) if closure.defined_in(scope) => {
+                Value::Function(Function::Defined(Closure {
+                    function: closure.function,
+                    scope: CapturedScope::Weak(Rc::downgrade(scope)),
+                }))
+            }
+            value => value,
+        }
+    }
+}
+
+// the scope is left out, it usually contains the closure itself
+impl fmt::Debug for Closure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.function.fmt(f)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum Function {
-    Defined(DefinedFunction),
-    Builtin(fn(Vec<Value>) -> Result<Value, ControlFlowValue>),
+    Defined(Closure),
+    Builtin(Builtin),
+}
+
+// the parameters and where the function was defined, the body is left out
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Function::Defined(closure) => {
+                let function = &closure.function;
+                write!(f, "<fun ({})", function.parameters.join(" "))?;
+                if !function.source.region.is_synthetic() {
+                    write!(f, " at {}", function.source.region.start)?;
+                }
+                write!(f, ">")
+            }
+            Function::Builtin(builtin) => write!(f, "<builtin {}>", builtin.name),
+        }
+    }
 }
 
 // FIXME: this implementation is pure bullshit
@@ -19,13 +155,221 @@ impl PartialEq for Function {
 
 impl Eq for Function {}
 
-#[derive(Debug, Clone, Eq, PartialEq)]
+// an object of the host like a database connection, programs can only pass it
+// around and back into builtins. it is shared when the value is copied, so
+// objects that builtins change need interior mutability
+#[derive(Clone)]
+pub struct Native(Rc<NativeObject>);
+
+type Finalizer = Box<dyn FnOnce(&dyn Any)>;
+
+struct NativeObject {
+    object: Box<dyn Any>,
+    finalizer: Option<Finalizer>,
+}
+
+impl Drop for NativeObject {
+    fn drop(&mut self) {
+        if let Some(finalizer) = self.finalizer.take() {
+            finalizer(self.object.as_ref());
+        }
+    }
+}
+
+impl Native {
+    pub fn new<T: 'static>(object: T) -> Self {
+        Native(Rc::new(NativeObject {
+            object: Box::new(object),
+            finalizer: None,
+        }))
+    }
+
+    // the finalizer gets the object once no value holds the native anymore,
+    // for example to close a connection the program didn't close itself
+    pub fn with_finalizer<T: 'static>(object: T, finalizer: impl FnOnce(&T) + 'static) -> Self {
+        Native(Rc::new(NativeObject {
+            object: Box::new(object),
+            finalizer: Some(Box::new(move |object: &dyn Any| {
+                if let Some(object) = object.downcast_ref() {
+                    finalizer(object);
+                }
+            })),
+        }))
+    }
+
+    pub fn downcast_ref<T: 'static>(&self) -> Option<&T> {
+        self.0.object.downcast_ref()
+    }
+}
+
+// the value of weakRef, it doesn't keep the list or native it refers to alive.
+// other values are copied anyway, so only those two can be referred to
+pub(crate) struct WeakValue(WeakTarget);
+
+enum WeakTarget {
+    List(Weak<ListData>),
+    Native(Weak<NativeObject>),
+}
+
+impl WeakValue {
+    pub(crate) fn new(value: &Value) -> Option<Self> {
+        Some(WeakValue(match value {
+            Value::List(list) => WeakTarget::List(Rc::downgrade(&list.0)),
+            Value::Native(native) => WeakTarget::Native(Rc::downgrade(&native.0)),
+            _ => return None,
+        }))
+    }
+
+    // none once nothing else holds the value
+    pub(crate) fn upgrade(&self) -> Option<Value> {
+        match &self.0 {
+            WeakTarget::List(list) => list.upgrade().map(|list| Value::List(List(list))),
+            WeakTarget::Native(native) => {
+                native.upgrade().map(|native| Value::Native(Native(native)))
+            }
+        }
+    }
+}
+
+// natives are equal when they are the same object
+impl PartialEq for Native {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl fmt::Debug for Native {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Native")
+    }
+}
+
+// lists are shared, changes made through one binding of a list like push(list 1)
+// are seen through every other binding. == compares the elements
+#[derive(Clone, Default)]
+pub struct List(Rc<ListData>);
+
+#[derive(Default)]
+struct ListData {
+    items: RefCell<Vec<Value>>,
+    // frozen lists are made by freeze and can never be changed
+    frozen: bool,
+}
+
+impl List {
+    pub fn new(values: Vec<Value>) -> Self {
+        List(Rc::new(ListData {
+            items: RefCell::new(values),
+            frozen: false,
+        }))
+    }
+
+    pub fn borrow(&self) -> Ref<'_, Vec<Value>> {
+        self.0.items.borrow()
+    }
+
+    // every change of a list goes through here, so frozen lists are only
+    // checked in one place
+    pub fn borrow_mut(&self) -> Result<RefMut<'_, Vec<Value>>, ControlFlowValue> {
+        if self.0.frozen {
+            return Err(ControlFlowValue::Exception(Exception::ValueIsFrozen, None));
+        }
+        Ok(self.0.items.borrow_mut())
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.0.frozen
+    }
+
+    // an empty list which is filled later with set_items, also when it is
+    // frozen. lists are restored from snapshots this way since they can
+    // contain themselves
+    pub(crate) fn empty(frozen: bool) -> Self {
+        List(Rc::new(ListData {
+            items: RefCell::new(vec![]),
+            frozen,
+        }))
+    }
+
+    pub(crate) fn set_items(&self, items: Vec<Value>) {
+        *self.0.items.borrow_mut() = items;
+    }
+
+    // the same for every binding of the list, lists can contain themselves so
+    // walking nested lists has to remember the ones it has seen
+    pub(crate) fn id(&self) -> *const () {
+        Rc::as_ptr(&self.0) as *const ()
+    }
+
+    // the number of values holding the list, elements of other lists and
+    // variables count as well as temporary copies of the interpreter
+    pub(crate) fn references(&self) -> usize {
+        Rc::strong_count(&self.0)
+    }
+
+    // the elements as a new list which doesn't share the storage
+    pub fn to_vec(&self) -> Vec<Value> {
+        self.borrow().clone()
+    }
+
+    // a frozen copy of the list, lists inside it are copied the same way.
+    // lists which appear more than once, like a list containing itself, are
+    // only copied once so the copy has the same shape
+    fn frozen(&self, copies: &mut HashMap<*const (), List>) -> List {
+        if let Some(copy) = copies.get(&self.id()) {
+            return copy.clone();
+        }
+        let copy = List::empty(true);
+        copies.insert(self.id(), copy.clone());
+        let items = self
+            .to_vec()
+            .iter()
+            .map(|item| item.frozen(copies))
+            .collect();
+        copy.set_items(items);
+        copy
+    }
+}
+
+impl FromIterator<Value> for List {
+    fn from_iter<I: IntoIterator<Item = Value>>(iter: I) -> Self {
+        List::new(iter.into_iter().collect())
+    }
+}
+
+// two lists containing themselves are equal when they are equal everywhere
+// else, a pair of lists which is compared again inside of itself counts as equal
+impl PartialEq for List {
+    fn eq(&self, other: &Self) -> bool {
+        let pair = (self.id(), other.id());
+        if Rc::ptr_eq(&self.0, &other.0)
+            || COMPARED_LISTS.with_borrow(|pairs| pairs.contains(&pair))
+        {
+            return true;
+        }
+        COMPARED_LISTS.with_borrow_mut(|pairs| pairs.push(pair));
+        let equal = *self.borrow() == *other.borrow();
+        COMPARED_LISTS.with_borrow_mut(|pairs| pairs.pop());
+        equal
+    }
+}
+
+impl fmt::Debug for List {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.borrow().fmt(f)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Value {
     Function(Function),
     String(String),
     Int(i64),
+    Float(f64),
     Bool(bool),
-    List(Vec<Value>),
+    List(List),
+    Map(BTreeMap<String, Value>),
+    Native(Native),
     Null,
 }
 
@@ -38,14 +382,111 @@ pub enum Exception {
     ValueIsWrongType,
     ExponentiationOverflowed,
     IndexOutOfRange,
+    #[strum(to_string = "{0}")]
     Custom(String),
+    SideEffectForbidden,
+    Interrupted,
+    KeyNotFound,
+    EmptyList,
+    // an int was divided by zero with `/`, `%` or mod
+    DivisionByZero,
+    // too many nested function calls, raised before the native stack overflows
+    RecursionLimitExceeded,
+    // raised by assert with its message and by assertEqual, whose message lists
+    // where the values differ
+    #[strum(to_string = "assertion failed:{0}")]
+    AssertionFailed(String),
+    // raised by the throw keyword
+    #[strum(to_string = "{0}")]
+    Thrown(Value),
+    // the imported file couldn't be read or evaluated, or imports itself
+    #[strum(to_string = "import failed: {0}")]
+    ImportFailed(String),
+    // reading or writing a file or the terminal failed
+    #[strum(to_string = "io error: {0}")]
+    Io(String),
+    // a list made by freeze was changed
+    ValueIsFrozen,
+    // a string or list would be larger than the memory can hold, like "ab" * a
+    // huge number
+    ResultTooLarge,
+    // the result of an int operation doesn't fit into 64 bits, like -x or x / -1
+    // for the smallest int
+    IntegerOverflowed,
+}
+
+impl Exception {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Exception::WrongNumberOfArguments => "OL0007",
+            Exception::NestedReturns => "OL0008",
+            Exception::UndeclaredIdentifier => "OL0009",
+            Exception::CalledValueIsNotFunction => "OL0010",
+            Exception::ValueIsWrongType => "OL0011",
+            Exception::ExponentiationOverflowed => "OL0012",
+            Exception::IndexOutOfRange => "OL0013",
+            Exception::Custom(_) => "OL0014",
+            Exception::SideEffectForbidden => "OL0015",
+            Exception::Interrupted => "OL0016",
+            Exception::KeyNotFound => "OL0019",
+            Exception::EmptyList => "OL0020",
+            Exception::Thrown(_) => "OL0021",
+            Exception::RecursionLimitExceeded => "OL0022",
+            Exception::AssertionFailed(_) => "OL0025",
+            Exception::DivisionByZero => "OL0027",
+            Exception::ImportFailed(_) => "OL0028",
+            Exception::Io(_) => "OL0030",
+            Exception::ValueIsFrozen => "OL0031",
+            Exception::ResultTooLarge => "OL0034",
+            Exception::IntegerOverflowed => "OL0035",
+        }
+    }
 }
 
 #[derive(Error, Debug, Display)]
 pub enum ControlFlowValue {
-    Exception(Exception),
+    // the region of the innermost expression the exception was raised in
+    Exception(Exception, Option<Region>),
     Continue,
     Break,
+    // the fuel of the interpreter ran out, this can't be handled by the program
+    FuelExhausted,
+    // the evaluation ran past its deadline, this can't be handled either
+    TimedOut,
+    // the program called exit with the status the process should exit with
+    Exit(i32),
+}
+
+impl ControlFlowValue {
+    // attach the region of the expression being evaluated to an exception that
+    // doesn't have one yet, so the innermost expression is reported
+    pub fn with_region(self, region: &Region) -> Self {
+        match self {
+            ControlFlowValue::Exception(exception, None) if !region.is_synthetic() => {
+                ControlFlowValue::Exception(exception, Some(region.clone()))
+            }
+            other => other,
+        }
+    }
+}
+
+thread_local! {
+    // the lists being displayed, the innermost last
+    static DISPLAYED_LISTS: RefCell<Vec<*const ()>> = const { RefCell::new(vec![]) };
+    // the pairs of lists being compared and the lists being serialized
+    static COMPARED_LISTS: RefCell<Vec<(*const (), *const ())>> = const { RefCell::new(vec![]) };
+    static SERIALIZED_LISTS: RefCell<Vec<*const ()>> = const { RefCell::new(vec![]) };
+}
+
+fn write_list(f: &mut fmt::Formatter<'_>, list: &[Value]) -> fmt::Result {
+    write!(f, "[")?;
+    for (i, value) in list.iter().enumerate() {
+        write!(f, "{}", value)?;
+        if i != list.len() - 1 {
+            write!(f, " ")?;
+        }
+    }
+    write!(f, "]")
 }
 
 impl fmt::Display for Value {
@@ -53,49 +494,282 @@ impl fmt::Display for Value {
         match self {
             Value::Bool(b) => write!(f, "{}", b),
             Value::Int(i) => write!(f, "{}", i),
+            // debug formatting keeps the fraction of whole floats, 3.0 instead of 3
+            Value::Float(v) => write!(f, "{:?}", v),
             Value::String(s) => write!(f, "{}", s),
-            Value::Function(v) => write!(f, "{:?}", v),
+            Value::Function(v) => write!(f, "{}", v),
             Value::Null => write!(f, "null"),
+            Value::Native(_) => write!(f, "<native>"),
+            // a list containing itself is shown as [...] inside of itself
+            Value::List(list)
+                if DISPLAYED_LISTS.with_borrow(|lists| lists.contains(&list.id())) =>
+            {
+                write!(f, "[...]")
+            }
             Value::List(list) => {
-                write!(f, "[")?;
-                for (i, value) in list.iter().enumerate() {
-                    write!(f, "{}", value)?;
-                    if i != list.len() - 1 {
+                DISPLAYED_LISTS.with_borrow_mut(|lists| lists.push(list.id()));
+                let result = write_list(f, &list.borrow());
+                DISPLAYED_LISTS.with_borrow_mut(|lists| lists.pop());
+                result
+            }
+            Value::Map(map) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in map.iter().enumerate() {
+                    write!(f, "{}: {}", key, value)?;
+                    if i != map.len() - 1 {
                         write!(f, " ")?;
                     }
                 }
-                write!(f, "]")
+                write!(f, "}}")
             }
         }
     }
 }
 
+#[allow(clippy::wrong_self_convention)]
 impl Value {
     pub fn into_int(&self) -> Result<&i64, ControlFlowValue> {
         match self {
             Value::Int(v) => Ok(v),
-            _ => Err(ControlFlowValue::Exception(Exception::ValueIsWrongType)),
+            _ => Err(ControlFlowValue::Exception(
+                Exception::ValueIsWrongType,
+                None,
+            )),
+        }
+    }
+
+    // ints are promoted to floats
+    pub fn into_float(&self) -> Result<f64, ControlFlowValue> {
+        match self {
+            Value::Int(v) => Ok(*v as f64),
+            Value::Float(v) => Ok(*v),
+            _ => Err(ControlFlowValue::Exception(
+                Exception::ValueIsWrongType,
+                None,
+            )),
         }
     }
 
     pub fn into_bool(&self) -> Result<&bool, ControlFlowValue> {
         match self {
             Value::Bool(v) => Ok(v),
-            _ => Err(ControlFlowValue::Exception(Exception::ValueIsWrongType)),
+            _ => Err(ControlFlowValue::Exception(
+                Exception::ValueIsWrongType,
+                None,
+            )),
+        }
+    }
+
+    // whether a list inside of the value contains itself, directly or through
+    // other lists and maps
+    pub fn contains_itself(&self) -> bool {
+        fn walk(value: &Value, lists: &mut Vec<*const ()>) -> bool {
+            match value {
+                Value::List(list) if lists.contains(&list.id()) => true,
+                Value::List(list) => {
+                    lists.push(list.id());
+                    let found = list.borrow().iter().any(|v| walk(v, lists));
+                    lists.pop();
+                    found
+                }
+                Value::Map(map) => map.values().any(|v| walk(v, lists)),
+                _ => false,
+            }
+        }
+        walk(self, &mut vec![])
+    }
+
+    // the name typeof returns for the value
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Function(_) => "function",
+            Value::String(_) => "string",
+            Value::Int(_) => "int",
+            Value::Float(_) => "float",
+            Value::Bool(_) => "bool",
+            Value::List(_) => "list",
+            Value::Map(_) => "map",
+            Value::Native(_) => "native",
+            Value::Null => "null",
         }
     }
 
     pub fn into_str(&self) -> Result<&str, ControlFlowValue> {
         match self {
             Value::String(v) => Ok(v),
-            _ => Err(ControlFlowValue::Exception(Exception::ValueIsWrongType)),
+            _ => Err(ControlFlowValue::Exception(
+                Exception::ValueIsWrongType,
+                None,
+            )),
         }
     }
 
-    pub fn into_list(&self) -> Result<&Vec<Value>, ControlFlowValue> {
+    pub fn into_list(&self) -> Result<Ref<'_, Vec<Value>>, ControlFlowValue> {
         match self {
-            Value::List(v) => Ok(v),
-            _ => Err(ControlFlowValue::Exception(Exception::ValueIsWrongType)),
+            Value::List(v) => Ok(v.borrow()),
+            _ => Err(ControlFlowValue::Exception(
+                Exception::ValueIsWrongType,
+                None,
+            )),
+        }
+    }
+
+    // the host object of a native value, other values and natives holding a
+    // different type are the wrong type
+    pub fn into_native<T: 'static>(&self) -> Result<&T, ControlFlowValue> {
+        match self {
+            Value::Native(native) => native.downcast_ref().ok_or(ControlFlowValue::Exception(
+                Exception::ValueIsWrongType,
+                None,
+            )),
+            _ => Err(ControlFlowValue::Exception(
+                Exception::ValueIsWrongType,
+                None,
+            )),
+        }
+    }
+
+    pub fn list(values: Vec<Value>) -> Self {
+        Value::List(List::new(values))
+    }
+
+    pub fn native<T: 'static>(object: T) -> Self {
+        Value::Native(Native::new(object))
+    }
+
+    // a copy whose lists, also the ones inside other lists and maps, raise
+    // ValueIsFrozen when something tries to change them. maps are copied on
+    // every assignment anyway, changing one never changes it for anyone else
+    pub fn freeze(&self) -> Value {
+        self.frozen(&mut HashMap::new())
+    }
+
+    fn frozen(&self, copies: &mut HashMap<*const (), List>) -> Value {
+        match self {
+            Value::List(list) if list.is_frozen() => self.clone(),
+            Value::List(list) => Value::List(list.frozen(copies)),
+            Value::Map(map) => Value::Map(
+                map.iter()
+                    .map(|(key, value)| (key.clone(), value.frozen(copies)))
+                    .collect(),
+            ),
+            value => value.clone(),
+        }
+    }
+
+    pub fn native_with_finalizer<T: 'static>(
+        object: T,
+        finalizer: impl FnOnce(&T) + 'static,
+    ) -> Self {
+        Value::Native(Native::with_finalizer(object, finalizer))
+    }
+
+    pub fn into_map(&self) -> Result<&BTreeMap<String, Value>, ControlFlowValue> {
+        match self {
+            Value::Map(v) => Ok(v),
+            _ => Err(ControlFlowValue::Exception(
+                Exception::ValueIsWrongType,
+                None,
+            )),
+        }
+    }
+}
+
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Value::Int(v) => serializer.serialize_i64(*v),
+            Value::Float(v) => serializer.serialize_f64(*v),
+            Value::String(v) => serializer.serialize_str(v),
+            Value::Bool(v) => serializer.serialize_bool(*v),
+            // the formats have no way to refer back to a list
+            Value::List(v) if SERIALIZED_LISTS.with_borrow(|lists| lists.contains(&v.id())) => Err(
+                ser::Error::custom("lists containing themselves can not be serialized"),
+            ),
+            Value::List(v) => {
+                SERIALIZED_LISTS.with_borrow_mut(|lists| lists.push(v.id()));
+                let result = v.borrow().serialize(serializer);
+                SERIALIZED_LISTS.with_borrow_mut(|lists| lists.pop());
+                result
+            }
+            Value::Map(v) => v.serialize(serializer),
+            Value::Null => serializer.serialize_unit(),
+            Value::Function(_) => Err(ser::Error::custom("functions can not be serialized")),
+            Value::Native(_) => Err(ser::Error::custom("native objects can not be serialized")),
+        }
+    }
+}
+
+// values read from other formats, binary data becomes a list of the bytes as
+// ints since olang has no type of its own for it
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a value olang can represent")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<Value, E> {
+        Ok(Value::Bool(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Value, E> {
+        Ok(Value::Int(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Value, E> {
+        i64::try_from(v)
+            .map(Value::Int)
+            .map_err(|_| E::custom(format!("the integer {} is too large", v)))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Value, E> {
+        Ok(Value::Float(v))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Value, E> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Value, E> {
+        Ok(Value::List(
+            v.iter().map(|&b| Value::Int(b as i64)).collect(),
+        ))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        Value::deserialize(deserializer)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut list = vec![];
+        while let Some(v) = seq.next_element()? {
+            list.push(v);
+        }
+        Ok(Value::list(list))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        let mut entries = BTreeMap::new();
+        while let Some((key, v)) = map.next_entry::<String, Value>()? {
+            entries.insert(key, v);
         }
+        Ok(Value::Map(entries))
     }
 }
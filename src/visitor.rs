@@ -0,0 +1,341 @@
+use crate::{
+    interpreter::grow_stack,
+    parser::{Block, DefinedFunction, Expression, ExpressionValue, IfClause},
+};
+
+// a read-only traversal over the AST, override the methods for the nodes you are
+// interested in and call the free function of the same name to keep walking the
+// children of that node
+pub trait Visitor {
+    fn visit_expression(&mut self, expression: &Expression) {
+        visit_expression(self, expression)
+    }
+
+    fn visit_block(&mut self, block: &Block) {
+        visit_block(self, block)
+    }
+
+    fn visit_function(&mut self, function: &DefinedFunction) {
+        visit_function(self, function)
+    }
+}
+
+pub fn visit_expression<V: Visitor + ?Sized>(visitor: &mut V, expression: &Expression) {
+    grow_stack(|| visit_children(visitor, expression))
+}
+
+fn visit_children<V: Visitor + ?Sized>(visitor: &mut V, expression: &Expression) {
+    match &expression.value {
+        ExpressionValue::Int(_)
+        | ExpressionValue::Float(_)
+        | ExpressionValue::String(_)
+        | ExpressionValue::Bool(_)
+        | ExpressionValue::Null
+        | ExpressionValue::Identifier(..)
+        | ExpressionValue::Update { .. }
+        | ExpressionValue::Continue
+        | ExpressionValue::Break
+        | ExpressionValue::Import(_) => {}
+        ExpressionValue::List(expressions) => {
+            for expression in expressions {
+                visitor.visit_expression(expression);
+            }
+        }
+        ExpressionValue::Map(entries) => {
+            for (key, value) in entries {
+                visitor.visit_expression(key);
+                visitor.visit_expression(value);
+            }
+        }
+        ExpressionValue::Block(block) => visitor.visit_block(block),
+        ExpressionValue::Binary { left, right, .. } => {
+            visitor.visit_expression(left);
+            visitor.visit_expression(right);
+        }
+        ExpressionValue::Unary {
+            operand: expression,
+            ..
+        }
+        | ExpressionValue::VariableDeclaration { expression, .. }
+        | ExpressionValue::Assign { expression, .. }
+        | ExpressionValue::Member { expression, .. }
+        | ExpressionValue::Discard(expression)
+        | ExpressionValue::Throw(expression) => visitor.visit_expression(expression),
+        ExpressionValue::IndexAssign {
+            indices,
+            expression,
+            ..
+        } => {
+            for index in indices {
+                visitor.visit_expression(index);
+            }
+            visitor.visit_expression(expression);
+        }
+        ExpressionValue::Function(function) => visitor.visit_function(function),
+        ExpressionValue::Call { arguments, .. } => {
+            for argument in arguments {
+                visitor.visit_expression(argument);
+            }
+        }
+        ExpressionValue::Index { expression, index } => {
+            visitor.visit_expression(expression);
+            visitor.visit_expression(index);
+        }
+        ExpressionValue::MemberCall {
+            expression,
+            arguments,
+            ..
+        } => {
+            visitor.visit_expression(expression);
+            for argument in arguments {
+                visitor.visit_expression(argument);
+            }
+        }
+        ExpressionValue::If {
+            clauses,
+            else_block,
+        } => {
+            for clause in clauses {
+                visitor.visit_expression(&clause.test);
+                visitor.visit_block(&clause.body);
+            }
+            if let Some(block) = else_block {
+                visitor.visit_block(block);
+            }
+        }
+        ExpressionValue::Loop {
+            init,
+            test,
+            update,
+            body,
+        } => {
+            for expression in [init, test, update].into_iter().flatten() {
+                visitor.visit_expression(expression);
+            }
+            visitor.visit_block(body);
+        }
+        ExpressionValue::ForIn { iterable, body, .. } => {
+            visitor.visit_expression(iterable);
+            visitor.visit_block(body);
+        }
+    }
+}
+
+pub fn visit_block<V: Visitor + ?Sized>(visitor: &mut V, block: &Block) {
+    for expression in block {
+        visitor.visit_expression(expression);
+    }
+}
+
+pub fn visit_function<V: Visitor + ?Sized>(visitor: &mut V, function: &DefinedFunction) {
+    visitor.visit_block(&function.body);
+}
+
+// a traversal that takes the AST by value and rebuilds it, override the methods
+// for the nodes you want to transform and call the free function of the same
+// name to fold the children of that node
+pub trait Fold {
+    fn fold_expression(&mut self, expression: Expression) -> Expression {
+        fold_expression(self, expression)
+    }
+
+    fn fold_block(&mut self, block: Block) -> Block {
+        fold_block(self, block)
+    }
+
+    fn fold_function(&mut self, function: DefinedFunction) -> DefinedFunction {
+        fold_function(self, function)
+    }
+}
+
+// reuses the allocation of the boxed expression
+fn fold_boxed<F: Fold + ?Sized>(
+    folder: &mut F,
+    mut expression: Box<Expression>,
+) -> Box<Expression> {
+    *expression = folder.fold_expression(*expression);
+    expression
+}
+
+pub fn fold_expression<F: Fold + ?Sized>(folder: &mut F, expression: Expression) -> Expression {
+    grow_stack(|| fold_children(folder, expression))
+}
+
+fn fold_children<F: Fold + ?Sized>(folder: &mut F, expression: Expression) -> Expression {
+    let value = match expression.value {
+        ExpressionValue::List(expressions) => ExpressionValue::List(
+            expressions
+                .into_iter()
+                .map(|expression| folder.fold_expression(expression))
+                .collect(),
+        ),
+        ExpressionValue::Map(entries) => ExpressionValue::Map(
+            entries
+                .into_iter()
+                .map(|(key, value)| (folder.fold_expression(key), folder.fold_expression(value)))
+                .collect(),
+        ),
+        ExpressionValue::Block(block) => ExpressionValue::Block(folder.fold_block(block)),
+        ExpressionValue::Binary {
+            left,
+            operator,
+            right,
+        } => ExpressionValue::Binary {
+            left: fold_boxed(folder, left),
+            operator,
+            right: fold_boxed(folder, right),
+        },
+        ExpressionValue::Unary { operator, operand } => ExpressionValue::Unary {
+            operator,
+            operand: fold_boxed(folder, operand),
+        },
+        ExpressionValue::VariableDeclaration {
+            identifier,
+            slot,
+            expression,
+        } => ExpressionValue::VariableDeclaration {
+            identifier,
+            slot,
+            expression: fold_boxed(folder, expression),
+        },
+        ExpressionValue::Assign {
+            identifier,
+            slot,
+            operator,
+            expression,
+        } => ExpressionValue::Assign {
+            identifier,
+            slot,
+            operator,
+            expression: fold_boxed(folder, expression),
+        },
+        ExpressionValue::IndexAssign {
+            identifier,
+            slot,
+            indices,
+            operator,
+            expression,
+        } => ExpressionValue::IndexAssign {
+            identifier,
+            slot,
+            indices: indices
+                .into_iter()
+                .map(|index| folder.fold_expression(index))
+                .collect(),
+            operator,
+            expression: fold_boxed(folder, expression),
+        },
+        ExpressionValue::Function(function) => {
+            ExpressionValue::Function(folder.fold_function(function))
+        }
+        ExpressionValue::Discard(expression) => {
+            ExpressionValue::Discard(fold_boxed(folder, expression))
+        }
+        ExpressionValue::Throw(expression) => {
+            ExpressionValue::Throw(fold_boxed(folder, expression))
+        }
+        ExpressionValue::Call {
+            identifier,
+            slot,
+            arguments,
+            invariant,
+        } => ExpressionValue::Call {
+            identifier,
+            slot,
+            arguments: arguments
+                .into_iter()
+                .map(|argument| folder.fold_expression(argument))
+                .collect(),
+            invariant,
+        },
+        ExpressionValue::Index { expression, index } => ExpressionValue::Index {
+            expression: fold_boxed(folder, expression),
+            index: fold_boxed(folder, index),
+        },
+        ExpressionValue::Member { expression, member } => ExpressionValue::Member {
+            expression: fold_boxed(folder, expression),
+            member,
+        },
+        ExpressionValue::MemberCall {
+            expression,
+            member,
+            arguments,
+            invariant,
+        } => ExpressionValue::MemberCall {
+            expression: fold_boxed(folder, expression),
+            member,
+            arguments: arguments
+                .into_iter()
+                .map(|argument| folder.fold_expression(argument))
+                .collect(),
+            invariant,
+        },
+        ExpressionValue::If {
+            clauses,
+            else_block,
+        } => ExpressionValue::If {
+            clauses: clauses
+                .into_iter()
+                .map(|clause| IfClause {
+                    test: fold_boxed(folder, clause.test),
+                    body: folder.fold_block(clause.body),
+                })
+                .collect(),
+            else_block: else_block.map(|block| folder.fold_block(block)),
+        },
+        ExpressionValue::Loop {
+            init,
+            test,
+            update,
+            body,
+        } => ExpressionValue::Loop {
+            init: init.map(|expression| fold_boxed(folder, expression)),
+            test: test.map(|expression| fold_boxed(folder, expression)),
+            update: update.map(|expression| fold_boxed(folder, expression)),
+            body: folder.fold_block(body),
+        },
+        ExpressionValue::ForIn {
+            identifier,
+            iterable,
+            body,
+        } => ExpressionValue::ForIn {
+            identifier,
+            iterable: fold_boxed(folder, iterable),
+            body: folder.fold_block(body),
+        },
+        value @ (ExpressionValue::Int(_)
+        | ExpressionValue::Float(_)
+        | ExpressionValue::String(_)
+        | ExpressionValue::Bool(_)
+        | ExpressionValue::Null
+        | ExpressionValue::Identifier(..)
+        | ExpressionValue::Update { .. }
+        | ExpressionValue::Continue
+        | ExpressionValue::Break
+        | ExpressionValue::Import(_)) => value,
+    };
+
+    Expression {
+        region: expression.region,
+        value,
+    }
+}
+
+pub fn fold_block<F: Fold + ?Sized>(folder: &mut F, block: Block) -> Block {
+    block
+        .into_iter()
+        .map(|expression| folder.fold_expression(expression))
+        .collect()
+}
+
+pub fn fold_function<F: Fold + ?Sized>(
+    folder: &mut F,
+    function: DefinedFunction,
+) -> DefinedFunction {
+    DefinedFunction {
+        parameters: function.parameters,
+        body: folder.fold_block(function.body),
+        source: function.source,
+        pure: function.pure,
+    }
+}
@@ -0,0 +1,142 @@
+use crate::{
+    lexer::Region,
+    parser::{BinaryOperationOperator, Block, Expression, ExpressionValue, Program},
+    printer,
+    visitor::{self, Visitor},
+};
+use serde::Serialize;
+use std::fmt;
+
+#[derive(Debug, Serialize)]
+pub struct Warning {
+    pub region: Region,
+    pub message: String,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.region, self.message)
+    }
+}
+
+fn is_comparison(expression: &Expression) -> bool {
+    matches!(
+        expression.value,
+        ExpressionValue::Binary {
+            operator: BinaryOperationOperator::IsEqual
+                | BinaryOperationOperator::IsNotEqual
+                | BinaryOperationOperator::IsLessThan
+                | BinaryOperationOperator::IsLessThanOrEqual
+                | BinaryOperationOperator::IsGreaterThan
+                | BinaryOperationOperator::IsGreaterThanOrEqual,
+            ..
+        }
+    )
+}
+
+struct UnusedResults {
+    warnings: Vec<Warning>,
+}
+
+impl Visitor for UnusedResults {
+    fn visit_block(&mut self, block: &Block) {
+        // the last expression of a block is its value, every other value is dropped
+        if let Some((_, discarded)) = block.split_last() {
+            for expression in discarded.iter().filter(|v| is_comparison(v)) {
+                self.warnings.push(Warning {
+                    region: expression.region.clone(),
+                    message: "the result of this comparison is unused, use `discard` if this is intentional"
+                        .to_string(),
+                });
+            }
+        }
+        visitor::visit_block(self, block)
+    }
+}
+
+// code after a break or continue in the same block never runs
+struct UnreachableCode {
+    warnings: Vec<Warning>,
+}
+
+impl Visitor for UnreachableCode {
+    fn visit_block(&mut self, block: &Block) {
+        let jump = block.iter().position(|expression| {
+            matches!(
+                expression.value,
+                ExpressionValue::Break | ExpressionValue::Continue
+            )
+        });
+        if let Some(unreachable) = jump.and_then(|i| block.get(i + 1)) {
+            self.warnings.push(Warning {
+                region: unreachable.region.clone(),
+                message: format!(
+                    "this code is unreachable, the loop is left by the {} before it",
+                    printer::expression(&block[jump.unwrap()])
+                ),
+            });
+        }
+        visitor::visit_block(self, block)
+    }
+}
+
+// `if x = 5` assigns and tests the assigned value, which is most likely meant as `x == 5`
+struct AssignmentConditions {
+    warnings: Vec<Warning>,
+}
+
+impl AssignmentConditions {
+    fn check(&mut self, condition: &Expression) {
+        if let ExpressionValue::Assign { .. } | ExpressionValue::IndexAssign { .. } =
+            condition.value
+        {
+            self.warnings.push(Warning {
+                region: condition.region.clone(),
+                message: "this assignment is used as a condition, did you mean `==`?".to_string(),
+            });
+        }
+    }
+}
+
+impl Visitor for AssignmentConditions {
+    fn visit_expression(&mut self, expression: &Expression) {
+        match &expression.value {
+            ExpressionValue::If { clauses, .. } => {
+                for clause in clauses {
+                    self.check(&clause.test);
+                }
+            }
+            ExpressionValue::Loop {
+                test: Some(test), ..
+            } => self.check(test),
+            _ => {}
+        }
+        visitor::visit_expression(self, expression)
+    }
+}
+
+// sorts the warnings by where they start in the source
+pub(crate) fn sorted(mut warnings: Vec<Warning>) -> Vec<Warning> {
+    warnings.sort_by_key(|warning| (warning.region.start.row, warning.region.start.col));
+    warnings
+}
+
+// find values which are silently dropped although they are most likely a mistake,
+// like writing `x == 5` instead of `x = 5`, and code which can't do what it looks like
+pub fn check(program: &Program) -> Vec<Warning> {
+    let mut unused_results = UnusedResults { warnings: vec![] };
+    unused_results.visit_block(&program.ast);
+    let mut unreachable = UnreachableCode { warnings: vec![] };
+    unreachable.visit_block(&program.ast);
+    let mut conditions = AssignmentConditions { warnings: vec![] };
+    conditions.visit_block(&program.ast);
+
+    sorted(
+        unused_results
+            .warnings
+            .into_iter()
+            .chain(unreachable.warnings)
+            .chain(conditions.warnings)
+            .collect(),
+    )
+}